@@ -12,5 +12,6 @@ fn main() {
         "A-S-g",
         "M-S-C-h",
         "M-A-S-C-i",
+        "XF86AudioMute",
     )());
 }
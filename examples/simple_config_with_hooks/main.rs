@@ -70,6 +70,7 @@ fn main() -> Result<()> {
         gapless: true,
         follow_focus: true,
         allow_wrapping: false,
+        ..Default::default()
     };
 
     // Default number of clients in the main layout area
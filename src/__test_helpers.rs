@@ -7,22 +7,30 @@
 //! internal tests and reducing boilerplate.
 pub use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, KeyEventHandler, MouseBindings},
+        bindings::{KeyBindings, KeyCode, KeyEventHandler, KeyGrabReport, MouseBindings},
         client::Client,
         config::Config,
-        data_types::{Region, ResizeAction},
+        data_types::{Point, Region, ResizeAction},
         helpers::index_selectors,
         layout::{Layout, LayoutConf},
         ring::{InsertPoint, Selector},
         screen::Screen,
         workspace::Workspace,
-        xconnection::{ClientMessage, Prop, Result, XConn, XEvent, Xid},
+        xconnection::{
+            Atom, ClientAttr, ClientConfig, ClientMessage, Prop, Result, XConn, XError, XEvent,
+            XState, Xid,
+        },
     },
     draw::Color,
     logging_error_handler, Backward, Forward, Less, More, PenroseError, WindowManager,
 };
 
-pub use std::{cell::Cell, collections::HashMap, fmt};
+pub use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt, thread,
+    time::Duration,
+};
 
 pub type TestWM = WindowManager<TestXConn>;
 pub type TestKeyBindings = KeyBindings<TestXConn>;
@@ -153,6 +161,12 @@ impl TestXConn {
         remaining
     }
 
+    pub fn push_events(&self, events: impl IntoIterator<Item = XEvent>) {
+        let mut remaining = self.events.replace(vec![]);
+        remaining.extend(events);
+        self.events.set(remaining);
+    }
+
     pub fn set_screen_count(&mut self, n: u32) {
         self.n_screens.set(n);
     }
@@ -213,8 +227,22 @@ __impl_stub_xcon! {
     }
 }
 
-// A helper for checking that calls to the X server are triggered correctly
-pub struct RecordingXConn(Cell<Vec<(String, Vec<String>)>>);
+// A helper for checking that calls to the X server are triggered correctly.
+//
+// Along with recording each call, properties that are set can be read back, sent client messages
+// and event timeouts are kept for inspecting and the outputs, active clients, keys that are
+// already grabbed by another program and reconnection failures can all be set up by tests.
+pub struct RecordingXConn {
+    calls: Cell<Vec<RecordedCall>>,
+    pub outputs: RefCell<Vec<(Screen, Option<String>)>>,
+    pub props: RefCell<HashMap<(Xid, String), Prop>>,
+    pub sent: RefCell<Vec<ClientMessage>>,
+    pub timeouts: Cell<Vec<Duration>>,
+    pub active_clients: Vec<Xid>,
+    pub taken_keys: Vec<KeyCode>,
+    // None if reconnecting is not supported
+    pub reconnect_failures: Option<u32>,
+}
 
 // Tag for a call through to the X server: maps method -> stringified args
 pub type RecordedCall = (String, Vec<String>);
@@ -225,17 +253,76 @@ impl fmt::Debug for RecordingXConn {
     }
 }
 
+impl Default for RecordingXConn {
+    fn default() -> Self {
+        Self {
+            calls: Cell::new(Vec::new()),
+            outputs: RefCell::new(vec![
+                (Screen::new(Region::new(0, 0, 1366, 768), 0), None),
+                (Screen::new(Region::new(1366, 0, 1366, 768), 0), None),
+            ]),
+            props: RefCell::new(HashMap::new()),
+            sent: RefCell::new(Vec::new()),
+            timeouts: Cell::new(Vec::new()),
+            active_clients: Vec::new(),
+            taken_keys: Vec::new(),
+            reconnect_failures: None,
+        }
+    }
+}
+
 impl RecordingXConn {
     pub fn init() -> Self {
-        Self(Cell::new(Vec::new()))
+        Self::default()
+    }
+
+    pub fn with_screens(self, screens: Vec<Screen>) -> Self {
+        self.set_outputs(screens.into_iter().map(|s| (s, None)).collect());
+        self
+    }
+
+    pub fn with_active_clients(mut self, ids: Vec<Xid>) -> Self {
+        self.active_clients = ids;
+        self
+    }
+
+    pub fn with_taken_keys(mut self, keys: Vec<KeyCode>) -> Self {
+        self.taken_keys = keys;
+        self
+    }
+
+    pub fn with_reconnect_failures(mut self, n: u32) -> Self {
+        self.reconnect_failures = Some(n);
+        self
+    }
+
+    pub fn set_outputs(&self, outputs: Vec<(Screen, Option<String>)>) {
+        *self.outputs.borrow_mut() = outputs;
+    }
+
+    pub fn set_prop(&self, id: Xid, name: &str, val: Prop) {
+        self.props.borrow_mut().insert((id, name.to_string()), val);
+    }
+
+    // The sorted ids in the current _NET_CLIENT_LIST
+    pub fn client_list(&self) -> Vec<Xid> {
+        let key = (XState::root(self), Atom::NetClientList.as_ref().to_string());
+        match self.props.borrow().get(&key) {
+            Some(Prop::Window(ids)) => {
+                let mut ids = ids.clone();
+                ids.sort_unstable();
+                ids
+            }
+            _ => vec![],
+        }
     }
 
     pub fn clear(&self) {
-        self.0.take();
+        self.calls.take();
     }
 
     pub fn calls(&self) -> Vec<(String, Vec<String>)> {
-        self.0.take()
+        self.calls.take()
     }
 
     pub fn was_called(&self, method: &str) -> bool {
@@ -248,9 +335,17 @@ impl RecordingXConn {
 
     // Returns a result to neaten up the code below
     fn add_call(&self, method: &str, args: Vec<String>) {
-        let mut inner = self.0.take();
+        let mut inner = self.calls.take();
         inner.push((method.to_string(), args));
-        self.0.set(inner);
+        self.calls.set(inner);
+    }
+
+    fn grab_result(&self, key: KeyCode) -> Result<()> {
+        if self.taken_keys.contains(&key) {
+            Err(XError::Raw("BadAccess".into()))
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -265,22 +360,42 @@ __impl_stub_xcon! {
         }
     }
     client_properties: {
+        fn mock_get_prop(&self, id: Xid, name: &str) -> Result<Prop> {
+            self.props
+                .borrow()
+                .get(&(id, name.to_string()))
+                .cloned()
+                .ok_or_else(|| XError::MissingProperty(name.into(), id))
+        }
+
         fn mock_change_prop(&self, id: Xid, name: &str, val: Prop) -> Result<()> {
             self.add_call("change_prop", strings!(id, name, val));
+            self.set_prop(id, name, val);
             Ok(())
         }
 
         fn mock_delete_prop(&self, id: Xid, name: &str) -> Result<()> {
             self.add_call("delete_prop", strings!(id, name));
+            self.props.borrow_mut().remove(&(id, name.to_string()));
             Ok(())
         }
     }
     client_handler: {
+        fn mock_kill_client(&self, id: Xid) -> Result<()> {
+            self.add_call("kill_client", strings!(id));
+            Ok(())
+        }
+
         fn mock_focus_client(&self, id: Xid) -> Result<()> {
             self.add_call("focus_client", strings!(id));
             Ok(())
         }
 
+        fn mock_focus_root(&self) -> Result<()> {
+            self.add_call("focus_root", vec![]);
+            Ok(())
+        }
+
         fn mock_map_client_if_needed(&self, win: Option<&mut Client>) -> Result<()> {
             self.add_call("map_client_if_needed", strings!(win));
             Ok(())
@@ -292,6 +407,26 @@ __impl_stub_xcon! {
         }
     }
     client_config: {
+        fn mock_configure_client(&self, id: Xid, data: &[ClientConfig]) -> Result<()> {
+            self.add_call("configure_client", strings!(id, data));
+            Ok(())
+        }
+
+        fn mock_set_client_attributes(&self, id: Xid, data: &[ClientAttr]) -> Result<()> {
+            self.add_call("set_client_attributes", strings!(id, data));
+            Ok(())
+        }
+
+        fn mock_clear_client(&self, id: Xid) -> Result<()> {
+            self.add_call("clear_client", strings!(id));
+            Ok(())
+        }
+
+        fn mock_send_configure_notify(&self, id: Xid, r: Region, border: u32) -> Result<()> {
+            self.add_call("send_configure_notify", strings!(id, r, border));
+            Ok(())
+        }
+
         fn mock_set_client_border_color(&self, id: Xid, color: Color) -> Result<()> {
             self.add_call("set_client_border_color", strings!(id, color));
             Ok(())
@@ -308,18 +443,37 @@ __impl_stub_xcon! {
         }
     }
     event_handler: {
+        fn mock_wait_for_event(&self) -> Result<XEvent> {
+            Ok(XEvent::KeyPress(EXIT_CODE))
+        }
+
+        fn mock_wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>> {
+            let mut timeouts = self.timeouts.take();
+            timeouts.push(timeout);
+            self.timeouts.set(timeouts);
+            thread::sleep(timeout);
+            Ok(None)
+        }
+
         fn mock_send_client_event(&self, msg: ClientMessage) -> Result<()> {
             self.add_call("send_client_event", strings!(msg));
+            self.sent.borrow_mut().push(msg);
             Ok(())
         }
     }
     state: {
         fn mock_current_screens(&self) -> Result<Vec<Screen>> {
             self.add_call("current_screens", vec![]);
-            Ok(vec![
-                Screen::new(Region::new(0, 0, 1366, 768), 0),
-                Screen::new(Region::new(1366, 0, 1366, 768), 0),
-            ])
+            Ok(self.outputs.borrow().iter().map(|(s, _)| *s).collect())
+        }
+
+        fn mock_current_outputs(&self) -> Result<Vec<(Screen, Option<String>)>> {
+            self.add_call("current_outputs", vec![]);
+            Ok(self.outputs.borrow().clone())
+        }
+
+        fn mock_active_clients(&self) -> Result<Vec<Xid>> {
+            Ok(self.active_clients.clone())
         }
     }
     conn: {
@@ -327,5 +481,124 @@ __impl_stub_xcon! {
             self.add_call("is_managed_client", strings!(c.id()));
             true
         }
+
+        fn mock_reconnect(&mut self) -> Result<()> {
+            match self.reconnect_failures.as_mut() {
+                None => Err(XError::Raw("reconnecting is not supported".into())),
+                Some(0) => Ok(()),
+                Some(n) => {
+                    *n -= 1;
+                    Err(XError::ConnectionClosed)
+                }
+            }
+        }
+
+        fn mock_grab_keys(
+            &self,
+            k: &KeyBindings<Self>,
+            m: &MouseBindings<Self>,
+        ) -> Result<KeyGrabReport> {
+            self.add_call("grab_keys", strings!(k.len(), m.len()));
+            let mut report = KeyGrabReport::default();
+            for &key in k.keys() {
+                report.record(key, self.grab_result(key));
+            }
+            Ok(report)
+        }
+
+        fn mock_grab_key(&self, key: KeyCode) -> Result<()> {
+            self.add_call("grab_key", strings!(key));
+            self.grab_result(key)
+        }
+
+        // Report that NumLock has moved so that the bindings get re-grabbed
+        fn mock_refresh_num_lock_mask(&self) -> Result<bool> {
+            self.add_call("refresh_num_lock_mask", vec![]);
+            Ok(true)
+        }
+    }
+}
+
+// A helper that tracks the position of clients and the pointer along with pointer confinement
+pub struct PointerXConn {
+    pub cursor: Cell<Point>,
+    pub positions: Cell<HashMap<Xid, Region>>,
+    pub screens: Vec<Screen>,
+    pub confined: Cell<Vec<Option<Region>>>,
+}
+
+impl fmt::Debug for PointerXConn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerXConn").finish()
+    }
+}
+
+impl Default for PointerXConn {
+    fn default() -> Self {
+        Self {
+            cursor: Cell::new(Point::default()),
+            positions: Cell::new(HashMap::new()),
+            screens: vec![Screen::new(Region::new(0, 0, 1000, 600), 0)],
+            confined: Cell::new(vec![]),
+        }
+    }
+}
+
+impl PointerXConn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cursor(self, p: Point) -> Self {
+        self.cursor.set(p);
+        self
+    }
+
+    // A second, larger screen to the right of the default one
+    pub fn with_two_screens(mut self) -> Self {
+        self.screens
+            .push(Screen::new(Region::new(1000, 0, 2000, 900), 1));
+        self
+    }
+}
+
+__impl_stub_xcon! {
+    for PointerXConn;
+
+    atom_queries: {}
+    client_properties: {}
+    client_handler: {}
+    client_config: {
+        fn mock_position_client(&self, id: Xid, r: Region, _: u32, _: bool) -> Result<()> {
+            let mut positions = self.positions.take();
+            positions.insert(id, r);
+            self.positions.set(positions);
+            Ok(())
+        }
+    }
+    event_handler: {}
+    state: {
+        fn mock_current_screens(&self) -> Result<Vec<Screen>> {
+            Ok(self.screens.clone())
+        }
+
+        fn mock_cursor_position(&self) -> Result<Point> {
+            Ok(self.cursor.get())
+        }
+
+        fn mock_client_geometry(&self, id: Xid) -> Result<Region> {
+            let positions = self.positions.take();
+            let r = positions.get(&id).copied();
+            self.positions.set(positions);
+            r.ok_or_else(|| XError::Raw("unknown client".into()))
+        }
+    }
+    conn: {
+        fn mock_confine_pointer(&self, region: Option<Region>) -> Result<()> {
+            let mut confined = self.confined.take();
+            confined.push(region);
+            self.confined.set(confined);
+            Ok(())
+        }
     }
 }
@@ -11,6 +11,7 @@ use crate::{
         workspace::Workspace,
         xconnection::XConn,
     },
+    draw::{KeyPressDraw, PMenu, PMenuConfig, PMenuMatch},
     Result,
 };
 
@@ -42,6 +43,47 @@ pub fn create_or_switch_to_workspace<X: XConn>(
     })
 }
 
+/**
+ * Pick a [Client] to focus from a [PMenu] listing all known clients.
+ *
+ * Each line shows the name of the [Workspace] the client is on followed by its title. Selecting a
+ * line brings the client's workspace into view and focuses the client.
+ */
+pub fn focus_client_from_menu<X: XConn, D: KeyPressDraw + 'static>(
+    drw: D,
+    config: PMenuConfig,
+) -> KeyEventHandler<X> {
+    let mut menu = PMenu::new(drw, config);
+
+    Box::new(move |wm: &mut WindowManager<X>| {
+        let mut clients: Vec<(usize, u32, String)> = wm
+            .all_clients(&Selector::Any)
+            .iter()
+            .map(|c| (c.workspace(), c.id(), c.wm_name().to_string()))
+            .collect();
+        clients.sort();
+
+        let lines: Vec<String> = clients
+            .iter()
+            .map(|(wix, _, name)| {
+                let ws = wm
+                    .workspace(&Selector::Index(*wix))
+                    .map_or("", |ws| ws.name());
+                format!("{}: {}", ws, name)
+            })
+            .collect();
+
+        let screen_index = wm.active_screen_index();
+        if let PMenuMatch::Line(i, _) = menu.get_selection_from_input("> ", lines, screen_index)? {
+            let (wix, id, _) = clients[i];
+            wm.focus_workspace(&Selector::Index(wix))?;
+            wm.focus_client(&Selector::WinId(id))?;
+        }
+
+        Ok(())
+    })
+}
+
 /**
  * Focus a [Client] with the given class as `WM_CLASS` or spawn the program with the given command
  * if no such Client exists.
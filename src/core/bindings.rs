@@ -20,10 +20,19 @@ pub type MouseEventHandler<X> = Box<dyn FnMut(&mut WindowManager<X>, &MouseEvent
 /// User defined key bindings
 pub type KeyBindings<X> = HashMap<KeyCode, KeyEventHandler<X>>;
 
+/// Descriptions of user defined key bindings
+///
+/// See [gen_keybindings][crate::gen_keybindings] and
+/// [describe_key_bindings][crate::core::manager::WindowManager::describe_key_bindings].
+pub type KeyBindingDescriptions = HashMap<KeyCode, KeyBindingDescription>;
+
 /// User defined mouse bindings
 pub type MouseBindings<X> = HashMap<(MouseEventKind, MouseState), MouseEventHandler<X>>;
 
-pub(crate) type CodeMap = HashMap<String, u8>;
+/// A map of X key names to the key codes that they are currently bound to.
+///
+/// See [keycodes_from_xmodmap][crate::core::helpers::keycodes_from_xmodmap].
+pub type CodeMap = HashMap<String, KeyCodeValue>;
 
 /// Abstraction layer for working with key presses
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -82,6 +91,75 @@ pub type KeyCodeMask = u16;
 /// A u8 X key-code enum value
 pub type KeyCodeValue = u8;
 
+/// The bits of a key event modifier mask that hold the core X modifiers (Shift, Lock, Control
+/// and Mod1 - Mod5)
+pub const CORE_MODIFIER_MASK: KeyCodeMask = 0xff;
+
+/// The bits of a key event modifier mask that hold the active keyboard group (layout)
+pub const GROUP_MASK: KeyCodeMask = 0x6000;
+
+/// The modifier mask bit that is set while CapsLock is active
+pub const CAPS_LOCK_MASK: KeyCodeMask = 1 << 1;
+
+/// The modifier that NumLock is bound to on most systems (Mod2). This is used when the current
+/// modifier mapping does not bind NumLock to a modifier.
+pub const DEFAULT_NUM_LOCK_MASK: KeyCodeMask = 1 << 4;
+
+/// The X keysym for the NumLock key
+pub const XK_NUM_LOCK: u32 = 0xff7f;
+
+/// Every combination of the lock modifiers (CapsLock and NumLock) that a binding needs to be
+/// grabbed with in order for it to fire regardless of which locks are active.
+///
+/// ```
+/// # use penrose::core::bindings::lock_modifier_combinations;
+/// assert_eq!(lock_modifier_combinations(0x10), vec![0, 0x02, 0x10, 0x12]);
+/// ```
+pub fn lock_modifier_combinations(num_lock_mask: KeyCodeMask) -> Vec<KeyCodeMask> {
+    let mut combinations = vec![
+        0,
+        CAPS_LOCK_MASK,
+        num_lock_mask,
+        CAPS_LOCK_MASK | num_lock_mask,
+    ];
+    combinations.sort_unstable();
+    combinations.dedup();
+
+    combinations
+}
+
+/// Determine the modifier mask that NumLock is bound to from the keyboard mapping and modifier
+/// mapping reported by the X server, falling back to [DEFAULT_NUM_LOCK_MASK] if NumLock is not
+/// bound to a modifier.
+///
+/// `keysyms` holds `keysyms_per_keycode` keysyms for each key code starting from `min_keycode`
+/// and `modifier_keycodes` holds `keycodes_per_modifier` key codes for each of the eight core
+/// modifiers in order (Shift, Lock, Control, Mod1 - Mod5).
+pub fn num_lock_mask(
+    min_keycode: KeyCodeValue,
+    keysyms_per_keycode: usize,
+    keysyms: &[u32],
+    keycodes_per_modifier: usize,
+    modifier_keycodes: &[KeyCodeValue],
+) -> KeyCodeMask {
+    if keysyms_per_keycode == 0 || keycodes_per_modifier == 0 {
+        return DEFAULT_NUM_LOCK_MASK;
+    }
+
+    let num_lock_codes: Vec<KeyCodeValue> = keysyms
+        .chunks(keysyms_per_keycode)
+        .enumerate()
+        .filter(|(_, syms)| syms.contains(&XK_NUM_LOCK))
+        .map(|(i, _)| min_keycode.wrapping_add(i as u8))
+        .collect();
+
+    modifier_keycodes
+        .chunks(keycodes_per_modifier)
+        .position(|codes| codes.iter().any(|c| *c != 0 && num_lock_codes.contains(c)))
+        .map(|ix| 1 << ix)
+        .unwrap_or(DEFAULT_NUM_LOCK_MASK)
+}
+
 /// A key press and held modifiers
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -100,6 +178,89 @@ impl KeyCode {
             code: self.code,
         }
     }
+
+    /// Create a new [KeyCode] from this one that only retains the core X modifiers, minus those
+    /// in `lock_mask`.
+    ///
+    /// The keyboard group and any held pointer buttons are dropped from the mask so that key
+    /// bindings fire whichever keyboard layout is currently active. Modifiers that select a shift
+    /// level of the key (such as [AltGr][ModifierKey::AltGr]) are kept, allowing bindings to
+    /// target the third and fourth levels of a key.
+    ///
+    /// ```
+    /// # use penrose::core::bindings::KeyCode;
+    /// // Meta + AltGr with NumLock on while the second keyboard group is active
+    /// let k = KeyCode { mask: 0x2000 | 0x80 | 0x40 | 0x10, code: 26 };
+    ///
+    /// assert_eq!(k.normalized(0x10), KeyCode { mask: 0x80 | 0x40, code: 26 });
+    /// ```
+    pub fn normalized(&self, lock_mask: KeyCodeMask) -> KeyCode {
+        KeyCode {
+            mask: self.mask & CORE_MODIFIER_MASK & !lock_mask,
+            code: self.code,
+        }
+    }
+}
+
+/// How key bindings should be tracked when the keyboard layout changes.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyBindingMode {
+    /// Bindings are tied to the physical key they were resolved to on startup: switching from
+    /// QWERTY to Dvorak leaves `M-j` bound to the same physical key.
+    KeyCode,
+    /// Bindings are tied to the key name they were bound with: switching from QWERTY to Dvorak
+    /// moves `M-j` to whichever physical key now produces `j`.
+    ///
+    /// Bindings are re-resolved each time the X server reports that the keyboard mapping has
+    /// changed.
+    KeySym,
+}
+
+/// A human readable description of what a key binding does, for displaying things like a
+/// cheatsheet of the current key bindings.
+///
+/// See [describe_key_binding][crate::core::manager::WindowManager::describe_key_binding].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyBindingDescription {
+    /// The key binding as it was written when it was bound or described, e.g. `M-S-q`
+    pub spec: String,
+    /// What the key binding does (if it has been described)
+    pub description: Option<String>,
+}
+
+/// The outcome of attempting to grab a set of key bindings.
+///
+/// Grabs most commonly fail when another program (or a previous window manager) has already
+/// grabbed the same key combination.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KeyGrabReport {
+    /// Key bindings that were successfully grabbed
+    pub grabbed: Vec<KeyCode>,
+    /// Key bindings that could not be grabbed along with the error that was returned
+    pub failed: Vec<(KeyCode, String)>,
+}
+
+impl KeyGrabReport {
+    /// Record the outcome of attempting to grab `key`, replacing any previous outcome.
+    pub fn record<E: std::fmt::Display>(&mut self, key: KeyCode, res: std::result::Result<(), E>) {
+        self.forget(key);
+        match res {
+            Ok(()) => self.grabbed.push(key),
+            Err(e) => self.failed.push((key, e.to_string())),
+        }
+    }
+
+    /// Remove any recorded outcome for `key`.
+    pub fn forget(&mut self, key: KeyCode) {
+        self.grabbed.retain(|&k| k != key);
+        self.failed.retain(|&(k, _)| k != key);
+    }
+
+    /// Whether or not every key binding was successfully grabbed.
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
 }
 
 /// Known mouse buttons for binding actions
@@ -142,6 +303,8 @@ pub enum ModifierKey {
     Shift,
     /// Meta / super / windows
     Meta,
+    /// AltGr / ISO_Level3_Shift (Mod5): used to reach the third and fourth levels of a key
+    AltGr,
 }
 
 impl TryFrom<&str> for ModifierKey {
@@ -149,15 +312,124 @@ impl TryFrom<&str> for ModifierKey {
 
     fn try_from(s: &str) -> Result<Self> {
         match s {
-            "C" => Ok(Self::Ctrl),
-            "A" => Ok(Self::Alt),
-            "S" => Ok(Self::Shift),
-            "M" => Ok(Self::Meta),
+            "C" | "Ctrl" | "Control" => Ok(Self::Ctrl),
+            "A" | "Alt" | "Mod1" => Ok(Self::Alt),
+            "S" | "Shift" => Ok(Self::Shift),
+            "M" | "Mod" | "Mod4" | "Meta" | "Super" | "Win" => Ok(Self::Meta),
+            "G" | "AltGr" | "Mod5" => Ok(Self::AltGr),
             _ => Err(PenroseError::UnknownModifier(s.into())),
         }
     }
 }
 
+// These are the modifier masks defined by the core X11 protocol
+impl From<ModifierKey> for KeyCodeMask {
+    fn from(m: ModifierKey) -> KeyCodeMask {
+        match m {
+            ModifierKey::Shift => 1 << 0,
+            ModifierKey::Ctrl => 1 << 2,
+            ModifierKey::Alt => 1 << 3,
+            ModifierKey::Meta => 1 << 6,
+            ModifierKey::AltGr => 1 << 7,
+        }
+    }
+}
+
+/// Parse a human readable key binding into the [KeyCode] it currently corresponds to.
+///
+/// Bindings are a sequence of modifiers followed by a key name, separated by either `-` or `+`:
+/// for example `M-S-q` or `Mod4+Shift+q`. Key names are those found in the output of
+/// `xmodmap -pke` and modifiers may be given using either the short form used by
+/// [gen_keybindings][crate::gen_keybindings] or one of the following aliases:
+///
+///   - Ctrl: `C`, `Ctrl`, `Control`
+///   - Alt: `A`, `Alt`, `Mod1`
+///   - Shift: `S`, `Shift`
+///   - Meta: `M`, `Mod`, `Mod4`, `Meta`, `Super`, `Win`
+///   - AltGr: `G`, `AltGr`, `Mod5`
+///
+/// The modifiers are optional: a bare key name such as `XF86AudioMute` is bound with an empty
+/// modifier mask and will fire regardless of the state of NumLock and CapsLock.
+///
+/// Key names on the third and fourth levels of a key (those reached by holding AltGr) should be
+/// bound along with the AltGr modifier, e.g. `M-G-eacute`.
+///
+/// Key codes depend on the current keymap so bindings should be parsed again if the keymap
+/// changes.
+///
+/// # Example
+/// ```
+/// use penrose::core::bindings::{parse_key_binding, CodeMap, KeyCode};
+///
+/// let codes: CodeMap = vec![("q".to_string(), 24)].into_iter().collect();
+///
+/// assert_eq!(parse_key_binding("Mod4+Shift+q", &codes).unwrap(), KeyCode { mask: 65, code: 24 });
+/// assert_eq!(parse_key_binding("M-S-q", &codes).unwrap(), KeyCode { mask: 65, code: 24 });
+/// assert!(parse_key_binding("M-w", &codes).is_err());
+/// ```
+pub fn parse_key_binding(spec: &str, known_codes: &CodeMap) -> Result<KeyCode> {
+    let mut parts: Vec<&str> = spec.split(['-', '+']).collect();
+    let name = parts.remove(parts.len() - 1);
+    let code = *known_codes
+        .get(name)
+        .ok_or_else(|| PenroseError::UnknownKeyName(name.into()))?;
+
+    let mask = parts.into_iter().try_fold(0, |mask, s| {
+        ModifierKey::try_from(s).map(|m| mask | KeyCodeMask::from(m))
+    })?;
+
+    Ok(KeyCode { mask, code })
+}
+
+/// Format a [KeyCode] as a key binding in the short form accepted by [parse_key_binding].
+///
+/// Modifiers are written in the order `M`, `A`, `C`, `S`, `G`. If more than one key name maps
+/// to the key code then the first name in alphabetical order is used. Returns `None` if the key
+/// code has no name in `known_codes` or its mask contains anything other than known modifiers.
+///
+/// # Example
+/// ```
+/// use penrose::core::bindings::{key_binding_spec, CodeMap, KeyCode};
+///
+/// let codes: CodeMap = vec![("q".to_string(), 24)].into_iter().collect();
+///
+/// assert_eq!(key_binding_spec(KeyCode { mask: 65, code: 24 }, &codes), Some("M-S-q".into()));
+/// assert_eq!(key_binding_spec(KeyCode { mask: 0, code: 25 }, &codes), None);
+/// ```
+pub fn key_binding_spec(key: KeyCode, known_codes: &CodeMap) -> Option<String> {
+    let name = known_codes
+        .iter()
+        .filter(|(_, &code)| code == key.code)
+        .map(|(name, _)| name)
+        .min()?;
+
+    let modifiers = [
+        (ModifierKey::Meta, "M"),
+        (ModifierKey::Alt, "A"),
+        (ModifierKey::Ctrl, "C"),
+        (ModifierKey::Shift, "S"),
+        (ModifierKey::AltGr, "G"),
+    ];
+
+    let mut mask = key.mask;
+    let mut spec = String::new();
+    for (m, prefix) in modifiers.iter() {
+        let bit = KeyCodeMask::from(*m);
+        if mask & bit != 0 {
+            mask &= !bit;
+            spec.push_str(prefix);
+            spec.push('-');
+        }
+    }
+
+    if mask != 0 {
+        return None;
+    }
+    spec.push_str(name);
+
+    Some(spec)
+}
+
 /// A mouse state specification indicating the button and modifiers held
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -186,6 +458,11 @@ pub enum MouseEventKind {
     Release,
     /// The mouse was moved while a button was held
     Motion,
+    /// A button was pressed for a second time within
+    /// [double_click_ms][crate::core::config::Config::double_click_ms] of the previous press at
+    /// the same target window. This replaces the second `Press` event when there is a
+    /// `DoubleClick` binding for the same [MouseState].
+    DoubleClick,
 }
 
 /// A mouse movement or button event
@@ -194,6 +471,10 @@ pub enum MouseEventKind {
 pub struct MouseEvent {
     /// The ID of the window that was contained the click
     pub id: Xid,
+    /// The child of `id` that contained the pointer, if there was one. Mouse bindings are grabbed
+    /// on the root window so this will be the client window under the pointer.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub child: Option<Xid>,
     /// Absolute coordinate of the event
     pub rpt: Point,
     /// Coordinate of the event relative to top-left of the window itself
@@ -202,6 +483,9 @@ pub struct MouseEvent {
     pub state: MouseState,
     /// Was this press, release or motion?
     pub kind: MouseEventKind,
+    /// The X server timestamp of the event in milliseconds
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time: u32,
 }
 
 impl MouseEvent {
@@ -217,10 +501,156 @@ impl MouseEvent {
     ) -> Self {
         MouseEvent {
             id,
+            child: None,
             rpt: Point::new(rx as u32, ry as u32),
             wpt: Point::new(ex as u32, ey as u32),
             state,
             kind,
+            time: 0,
+        }
+    }
+
+    /// Set the X server timestamp of this event
+    pub fn with_time(mut self, time: u32) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Set the child window that contained the pointer. A value of 0 (`None` in X11 terms)
+    /// means that there was no child window.
+    pub fn with_child(mut self, child: Xid) -> Self {
+        self.child = if child == 0 { None } else { Some(child) };
+        self
+    }
+
+    /// The window targeted by this event: the child window under the pointer if there was one,
+    /// otherwise the window that received the event.
+    pub fn target(&self) -> Xid {
+        self.child.unwrap_or(self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        mouse_event_target;
+        args: (child: Xid, expected_child: Option<Xid>, expected_target: Xid);
+
+        case: no_child => (0, None, 1);
+        case: child => (42, Some(42), 42);
+
+        body: {
+            let state = MouseState::new(MouseButton::Left, vec![ModifierKey::Meta]);
+            let e = MouseEvent::new(1, 0, 0, 0, 0, state, MouseEventKind::Press).with_child(child);
+
+            assert_eq!(e.child, expected_child);
+            assert_eq!(e.target(), expected_target);
+        }
+    }
+
+    test_cases! {
+        parse_key_binding;
+        args: (spec: &str, expected: Option<KeyCode>);
+
+        case: key_only => ("q", Some(KeyCode { mask: 0, code: 24 }));
+        case: media_key_only => ("XF86AudioMute", Some(KeyCode { mask: 0, code: 121 }));
+        case: short_modifiers => ("M-S-q", Some(KeyCode { mask: 65, code: 24 }));
+        case: long_modifiers => ("Mod4+Shift+q", Some(KeyCode { mask: 65, code: 24 }));
+        case: aliases_match => ("Win+Control+Alt+q", Some(KeyCode { mask: 76, code: 24 }));
+        case: mixed_separators => ("C+A-Return", Some(KeyCode { mask: 12, code: 36 }));
+        case: alt_gr => ("M-G-q", Some(KeyCode { mask: 192, code: 24 }));
+        case: unknown_key => ("M-w", None);
+        case: unknown_modifier => ("Hyper+q", None);
+        case: missing_key => ("M-", None);
+
+        body: {
+            let codes: CodeMap = vec![
+                ("q".to_string(), 24),
+                ("Return".to_string(), 36),
+                ("XF86AudioMute".to_string(), 121),
+            ]
+            .into_iter()
+            .collect();
+
+            assert_eq!(parse_key_binding(spec, &codes).ok(), expected);
+        }
+    }
+
+    test_cases! {
+        key_binding_spec;
+        args: (key: KeyCode, expected: Option<&str>);
+
+        case: key_only => (KeyCode { mask: 0, code: 24 }, Some("q"));
+        case: modifiers_in_order => (KeyCode { mask: 0xcd, code: 24 }, Some("M-A-C-S-G-q"));
+        case: first_name_is_used => (KeyCode { mask: 64, code: 36 }, Some("M-KP_Enter"));
+        case: unknown_key => (KeyCode { mask: 0, code: 25 }, None);
+        case: unknown_modifier => (KeyCode { mask: 0x10, code: 24 }, None);
+
+        body: {
+            let codes: CodeMap = vec![
+                ("q".to_string(), 24),
+                ("Return".to_string(), 36),
+                ("KP_Enter".to_string(), 36),
+            ]
+            .into_iter()
+            .collect();
+
+            assert_eq!(key_binding_spec(key, &codes).as_deref(), expected);
+            if let Some(spec) = expected {
+                assert_eq!(parse_key_binding(spec, &codes).unwrap(), key);
+            }
+        }
+    }
+
+    test_cases! {
+        num_lock_mask;
+        args: (num_lock_code: KeyCodeValue, modifier_keycodes: Vec<KeyCodeValue>, expected: KeyCodeMask);
+
+        case: mod2 => (9, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0], 1 << 4);
+        case: mod3 => (10, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0], 1 << 5);
+        case: unbound => (9, vec![0; 16], DEFAULT_NUM_LOCK_MASK);
+        case: unknown_key => (0, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0], DEFAULT_NUM_LOCK_MASK);
+
+        body: {
+            // key codes 8, 9 and 10 with two keysyms each
+            let mut keysyms = vec![0x61, 0x41, 0x62, 0x42, 0x63, 0x43];
+            if num_lock_code > 0 {
+                keysyms[2 * (num_lock_code - 8) as usize] = XK_NUM_LOCK;
+            }
+
+            assert_eq!(num_lock_mask(8, 2, &keysyms, 2, &modifier_keycodes), expected);
+        }
+    }
+
+    test_cases! {
+        lock_modifier_combinations;
+        args: (num_lock: KeyCodeMask, expected: Vec<KeyCodeMask>);
+
+        case: mod2 => (1 << 4, vec![0, 0x02, 0x10, 0x12]);
+        case: mod3 => (1 << 5, vec![0, 0x02, 0x20, 0x22]);
+        case: unbound => (0, vec![0, 0x02]);
+
+        body: {
+            assert_eq!(lock_modifier_combinations(num_lock), expected);
+        }
+    }
+
+    test_cases! {
+        normalized_key_code;
+        args: (mask: KeyCodeMask, expected: KeyCodeMask);
+
+        case: unmodified => (0x40, 0x40);
+        case: strips_lock_mask => (0x40 | 0x10, 0x40);
+        case: lock_mask_only => (0x10, 0);
+        case: strips_group => (0x40 | 0x2000, 0x40);
+        case: strips_pointer_buttons => (0x40 | 0x100, 0x40);
+        case: keeps_alt_gr => (0x40 | 0x80 | 0x4000, 0x40 | 0x80);
+
+        body: {
+            let k = KeyCode { mask, code: 24 };
+            assert_eq!(k.normalized(0x10), KeyCode { mask: expected, code: 24 });
         }
     }
 }
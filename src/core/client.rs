@@ -1,5 +1,15 @@
 //! Metadata around X clients and manipulating them
-use crate::core::xconnection::{Atom, Prop, WmHints, WmNormalHints, XClientProperties, Xid};
+use crate::core::{
+    data_types::{AspectRatio, Region},
+    xconnection::{Atom, Prop, WmHints, WmNormalHints, XClientProperties, Xid},
+};
+use std::collections::BTreeSet;
+
+/// A user defined tag that can be attached to a [Client].
+///
+/// See [WindowManager::view_tags][crate::core::manager::WindowManager::view_tags] for details of
+/// how tags are used to control which clients are displayed.
+pub type Tag = String;
 
 /**
  * Meta-data around a client window that we are handling.
@@ -18,11 +28,29 @@ pub struct Client {
     pub(crate) wm_protocols: Vec<String>, // Can't use Atom as it could be something arbitrary
     pub(crate) wm_hints: Option<WmHints>,
     pub(crate) wm_normal_hints: Option<WmNormalHints>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) tags: BTreeSet<Tag>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) border_px: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) float_region: Option<Region>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) tiled_region: Option<Region>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) aspect_ratio: Option<AspectRatio>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) transient_for: Option<Xid>,
     // state flags
     pub(crate) accepts_focus: bool,
     pub(crate) floating: bool,
     pub(crate) fullscreen: bool,
     pub(crate) mapped: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) minimized: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) skip_pager: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) skip_taskbar: bool,
     pub(crate) urgent: bool,
     pub(crate) wm_managed: bool,
 }
@@ -43,14 +71,12 @@ impl Client {
         let accepts_focus = conn.client_accepts_focus(id);
         let wm_name = conn.client_name(id).unwrap_or("unknown".into());
 
-        let wm_class = match conn.get_prop(id, Atom::WmClass.as_ref()) {
-            Ok(Prop::UTF8String(strs)) => strs,
-            _ => vec![],
-        };
-        let wm_type = match conn.get_prop(id, Atom::NetWmWindowType.as_ref()) {
-            Ok(Prop::Atom(atoms)) => atoms,
-            _ => vec![Atom::NetWindowTypeNormal.as_ref().to_string()],
-        };
+        let wm_class = conn
+            .get_string_prop(id, Atom::WmClass.as_ref())
+            .unwrap_or_default();
+        let wm_type = conn
+            .get_atom_list_prop(id, Atom::NetWmWindowType.as_ref())
+            .unwrap_or_else(|_| vec![Atom::NetWindowTypeNormal.as_ref().to_string()]);
         let wm_hints = match conn.get_prop(id, Atom::WmHints.as_ref()) {
             Ok(Prop::WmHints(hints)) => Some(hints),
             _ => None,
@@ -59,10 +85,17 @@ impl Client {
             Ok(Prop::WmNormalHints(hints)) => Some(hints),
             _ => None,
         };
-        let wm_protocols = match conn.get_prop(id, Atom::WmProtocols.as_ref()) {
-            Ok(Prop::Atom(protocols)) => protocols,
-            _ => vec![],
+        let wm_protocols = conn
+            .get_atom_list_prop(id, Atom::WmProtocols.as_ref())
+            .unwrap_or_default();
+        let transient_for = match conn.get_prop(id, Atom::WmTransientFor.as_ref()) {
+            Ok(Prop::Window(ids)) => ids.first().copied(),
+            _ => None,
         };
+        let wm_state = conn
+            .get_atom_list_prop(id, Atom::NetWmState.as_ref())
+            .unwrap_or_default();
+        let has_state = |a: Atom| wm_state.iter().any(|s| s == a.as_ref());
 
         Self {
             id,
@@ -73,10 +106,19 @@ impl Client {
             wm_protocols,
             wm_hints,
             wm_normal_hints,
+            tags: BTreeSet::new(),
+            border_px: None,
+            float_region: None,
+            tiled_region: None,
+            aspect_ratio: None,
+            transient_for,
             floating,
             accepts_focus,
             fullscreen: false,
             mapped: false,
+            minimized: false,
+            skip_pager: has_state(Atom::NetWmStateSkipPager),
+            skip_taskbar: has_state(Atom::NetWmStateSkipTaskbar),
             urgent: false,
             wm_managed: true,
         }
@@ -102,6 +144,115 @@ impl Client {
         self.fullscreen
     }
 
+    /// The most recently read WM_NORMAL_HINTS for this client (if it has set any)
+    pub fn wm_normal_hints(&self) -> Option<&WmNormalHints> {
+        self.wm_normal_hints.as_ref()
+    }
+
+    /// The border width override for this client (if one has been set)
+    pub fn border_px(&self) -> Option<u32> {
+        self.border_px
+    }
+
+    /// How the aspect ratio of this client is preserved when it is tiled (if it is)
+    pub fn aspect_ratio(&self) -> Option<AspectRatio> {
+        self.aspect_ratio
+    }
+
+    // The width:height ratio this client should be shown at if it needs to change from the
+    // ratio of `r`. Clients that do not preserve their aspect ratio never need to be adjusted.
+    pub(crate) fn constrained_aspect(&self, r: &Region) -> Option<(u32, u32)> {
+        let (w, h) = (r.w as u64, r.h as u64);
+        match self.aspect_ratio? {
+            AspectRatio::Fixed(n, d) => Some((n, d)),
+            AspectRatio::FromHints => {
+                let hints = self.wm_normal_hints.as_ref()?;
+                match (hints.min_aspect, hints.max_aspect) {
+                    (Some((n, d)), _) if w * (d as u64) < h * (n as u64) => Some((n, d)),
+                    (_, Some((n, d))) if w * (d as u64) > h * (n as u64) => Some((n, d)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// The tags currently attached to this client
+    pub fn tags(&self) -> &BTreeSet<Tag> {
+        &self.tags
+    }
+
+    /// Check whether or not this client has the given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Attach a tag to this client.
+    ///
+    /// NOTE: the [WindowManager][crate::core::manager::WindowManager] does not update which
+    /// clients are displayed when tags are modified directly. Use
+    /// [WindowManager::add_client_tag][crate::core::manager::WindowManager::add_client_tag] if you
+    /// want tag views to be updated.
+    pub fn add_tag(&mut self, tag: impl Into<Tag>) {
+        self.tags.insert(tag.into());
+    }
+
+    /// Remove a tag from this client, returning whether or not the tag was present
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        self.tags.remove(tag)
+    }
+
+    /// Whether or not this client is currently minimized
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// The `_NET_WM_ALLOWED_ACTIONS` that penrose supports for this client in its current state.
+    ///
+    /// Only clients that are not fullscreen and are either floating or on a workspace with a
+    /// floating layout (`floating_layout`) can be moved or resized: tiled clients are positioned
+    /// by the current layout.
+    pub fn allowed_actions(&self, floating_layout: bool) -> Vec<Atom> {
+        let mut actions = vec![
+            Atom::NetWmActionChangeDesktop,
+            Atom::NetWmActionClose,
+            Atom::NetWmActionFullscreen,
+            Atom::NetWmActionMinimize,
+        ];
+        if (self.floating || floating_layout) && !self.fullscreen {
+            actions.extend([Atom::NetWmActionMove, Atom::NetWmActionResize]);
+        }
+
+        actions
+    }
+
+    /// The client that this client is transient for (set via `WM_TRANSIENT_FOR`), if any
+    pub fn transient_for(&self) -> Option<Xid> {
+        self.transient_for
+    }
+
+    /// Whether or not this client has asked to be left out of pagers
+    /// (`_NET_WM_STATE_SKIP_PAGER`)
+    pub fn skip_pager(&self) -> bool {
+        self.skip_pager
+    }
+
+    /// Whether or not this client has asked to be left out of taskbars
+    /// (`_NET_WM_STATE_SKIP_TASKBAR`)
+    pub fn skip_taskbar(&self) -> bool {
+        self.skip_taskbar
+    }
+
+    // Update the skip pager / taskbar flags from the contents of _NET_WM_STATE, returning
+    // whether or not either of them has changed.
+    pub(crate) fn update_skip_state(&mut self, wm_state: &[String]) -> bool {
+        let has_state = |a: Atom| wm_state.iter().any(|s| s == a.as_ref());
+        let prev = (self.skip_pager, self.skip_taskbar);
+        self.skip_pager = has_state(Atom::NetWmStateSkipPager);
+        self.skip_taskbar = has_state(Atom::NetWmStateSkipTaskbar);
+
+        prev != (self.skip_pager, self.skip_taskbar)
+    }
+
     /// The current workspace index that this client is showing on
     pub fn workspace(&self) -> usize {
         self.workspace
@@ -117,6 +268,16 @@ impl Client {
         self.floating = floating
     }
 
+    /// The last known position of this client while it was floating (if any)
+    pub fn float_region(&self) -> Option<Region> {
+        self.float_region
+    }
+
+    /// The region that this client was last placed in by a layout (if it is currently tiled)
+    pub fn tiled_region(&self) -> Option<Region> {
+        self.tiled_region
+    }
+
     pub(crate) fn set_name(&mut self, name: impl Into<String>) {
         self.wm_name = name.into()
     }
@@ -1,10 +1,22 @@
 //! User facing configuration of the penrose [WindowManager][crate::core::manager::WindowManager].
 use crate::{
-    core::layout::{side_stack, Layout, LayoutConf},
+    core::{
+        bindings::{parse_key_binding, CodeMap, KeyBindingMode, ModifierKey},
+        data_types::{
+            Corner, EmptiedWorkspacePolicy, ErrorPolicy, FloatingFocusPrecedence, FocusNewPolicy,
+            Overflow, Region, RootBackground, TransientPolicy, VisibleWorkspacePolicy,
+            WorkspaceFocus,
+        },
+        layout::{side_stack, Layout, LayoutConf},
+        screen::Screen,
+    },
     draw::{Color, DrawError},
 };
 
-use std::convert::TryInto;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+};
 
 __with_builder_and_getters! {
     /// The main user facing configuration details.
@@ -68,6 +80,27 @@ __with_builder_and_getters! {
     /// the window classes that will always be considered floating
     VecImplInto floating_classes: String; => vec!["dmenu", "dunst"];
 
+    /// the window classes that will have the aspect ratio requested in their WM_NORMAL_HINTS
+    /// preserved when they are tiled
+    VecImplInto aspect_ratio_classes: String; => Vec::<String>::new();
+
+    /// the window classes that are never focused when they are first mapped, regardless of
+    /// `focus_new_windows`
+    VecImplInto no_focus_classes: String; => Vec::<String>::new();
+
+    /// the window classes that are mapped when requested but otherwise ignored: they are never
+    /// tiled, given a border or focused and are not added to a workspace or the client lists
+    VecImplInto ignored_classes: String; => Vec::<String>::new();
+
+    /// the window types (such as `_NET_WM_WINDOW_TYPE_DESKTOP`) that are ignored in the same way
+    /// as `ignored_classes`
+    VecImplInto ignored_window_types: String; => Vec::<String>::new();
+
+    /// the window types that are left floating by
+    /// [sink_all_floats][crate::core::manager::WindowManager::sink_all_floats] and
+    /// `sink_floats_on_tiling`
+    VecImplInto sink_exempt_window_types: String; => vec!["_NET_WM_WINDOW_TYPE_DIALOG"];
+
     /// the [Layout] functions to be used by each [Workspace][crate::core::workspace::Workspace]
     ///
     /// # Constraints
@@ -78,29 +111,324 @@ __with_builder_and_getters! {
             Layout::floating("[----]"),
         ];
 
+    /// per [Workspace][crate::core::workspace::Workspace] overrides for the available layouts,
+    /// keyed by workspace name. Workspaces without an entry use `layouts`.
+    ///
+    /// # Constraints
+    /// Each override must contain at least one layout function
+    Concrete workspace_layouts: HashMap<String, Vec<Layout>>; => HashMap::new();
+    /// commands to spawn when a workspace is focused while it has no clients, keyed by workspace
+    /// name. A command is not run again for the same workspace until a client on that workspace
+    /// is closed, leaving it empty.
+    Concrete workspace_spawns: HashMap<String, String>; => HashMap::new();
+
     /// the focused border color as a hex literal
     ImplTry DrawError; focused_border: Color; => "#cc241d";
     /// the unfocused border color as a hex literal
     ImplTry DrawError; unfocused_border: Color; => "#3c3836";
+    /// the border color used for the focused client of workspaces on screens other than the
+    /// active screen. If not set these clients use the unfocused border color.
+    Concrete inactive_screen_border: Option<Color>; => None;
     /// the border width of each window in pixels
     Concrete border_px: u32; => 2;
     /// the gap between tiled windows in pixels
     Concrete gap_px: u32; => 5;
+    /// the smallest (width, height) in pixels that a layout may give to a tiled window before
+    /// gaps and borders are applied. Windows that would be given a smaller region are stacked
+    /// in a single region covering all of them (skipping any that would cause it to overlap
+    /// other windows), with only the focused one (or the first if none of them are focused)
+    /// shown. A value of (0, 0) disables this.
+    Concrete min_tile_size: (u32, u32); => (0, 0);
+    /// the smallest (width, height) in pixels that a floating window may request for itself.
+    /// Smaller requests (including zero sized ones) are grown to this size.
+    Concrete min_configure_size: (u32, u32); => (1, 1);
+    /// whether or not floating windows requesting a new size or position should be kept within
+    /// the bounds of the screen that they are on
+    Concrete clamp_configure_requests: bool; => true;
+    /// the corner of the screen that clients are moved to by
+    /// [toggle_pip][crate::core::manager::WindowManager::toggle_pip]
+    Concrete pip_corner: Corner; => Corner::BottomRight;
+    /// the (width, height) of picture-in-picture clients as a fraction of the usable area of
+    /// their screen
+    Concrete pip_size: (f64, f64); => (0.25, 0.25);
+    /// the fraction of each grid cell that is used by a window in the workspace overview
+    Concrete overview_scale: f64; => 0.9;
+    /// whether or not the gap around tiled windows should be dropped on screen edges that are
+    /// shared with another screen, leaving gaps only on the outer edges of the full display area
+    Concrete suppress_shared_edge_gaps: bool; => false;
+    /// whether or not border_px and gap_px should be treated as being relative to a 96 DPI
+    /// display and scaled for each screen based on its reported DPI. Screens with an unknown DPI
+    /// use the raw pixel values.
+    Concrete dpi_scaling: bool; => false;
+    /// the maximum number of clients that can be placed in the main area of a layout
+    Concrete max_main_limit: Option<u32>; => None;
+    /// whether or not the number of clients on a workspace is also used as an upper limit for the
+    /// number of clients in the main area of its layout
+    Concrete max_main_limited_by_clients: bool; => false;
+    /// whether changing the number of main clients past its limits clamps or wraps around
+    Concrete max_main_overflow: Overflow; => Overflow::Clamp;
+    /// whether cycling workspaces and clients past the first or last entry wraps around or
+    /// stops at the end. This only applies to `cycle_workspace` and `cycle_client`: cycling
+    /// screens always stops at the end and cycling layouts always wraps around.
+    Concrete cycle_overflow: Overflow; => Overflow::Wrap;
+    /// the distance in pixels at which a floating client being moved with the mouse will snap to
+    /// the edges of screens and other clients. A value of 0 disables snapping.
+    Concrete snap_distance: u32; => 0;
+    /// the maximum time in milliseconds between two presses of the same mouse button for them to
+    /// be treated as a double click. A value of 0 disables double click detection.
+    Concrete double_click_ms: u32; => 300;
+    /// holding this modifier while moving a floating client temporarily disables snapping
+    Concrete snap_disable_modifier: ModifierKey; => ModifierKey::Shift;
+    /// how long in milliseconds to wait for further RandR events after the first one is received
+    /// before re-detecting the connected screens. A value of 0 disables debouncing.
+    Concrete randr_debounce_ms: u64; => 100;
+    /// how long in milliseconds the event loop must go without receiving any events after
+    /// startup before the `startup_settled` hook is run. The default of 0 disables the hook.
+    Concrete startup_settle_ms: u64; => 0;
+    /// how often in milliseconds the `tick` hook should be run while waiting for events. The
+    /// default of 0 disables the hook.
+    Concrete tick_interval_ms: u64; => 0;
+    /// if set, unfocused clients have their opacity set to this value (between 0.0 and 1.0) and
+    /// the focused client is made fully opaque. This requires a running compositor.
+    Concrete unfocused_opacity: Option<f64>; => None;
+    /// whether clients that set `_NET_WM_STATE_SKIP_TASKBAR` or `_NET_WM_STATE_SKIP_PAGER` are
+    /// left out of `_NET_CLIENT_LIST`
+    Concrete hide_skipped_clients: bool; => true;
+    /// how the root window should be painted, making empty workspaces show a solid color or
+    /// wallpaper rather than whatever was previously drawn there. Image wallpapers are set by
+    /// running an external command such as `feh`
+    Concrete root_background: Option<RootBackground>; => None;
+    /// backgrounds to set for the root window when a workspace is focused, keyed by workspace
+    /// name. Workspaces without an entry use `root_background`. Commands are run each time the
+    /// background changes rather than only on startup.
+    Concrete workspace_backgrounds: HashMap<String, RootBackground>; => HashMap::new();
+    /// how long in milliseconds workspace focus must stay unchanged before the background of the
+    /// new workspace is set, so that rapidly switching workspaces does not repeatedly run
+    /// background commands. A value of 0 sets the background immediately.
+    Concrete workspace_background_debounce_ms: u64; => 200;
+    /// which errors raised while handling events cause the main event loop to exit
+    Concrete error_policy: ErrorPolicy; => ErrorPolicy::ExitOnFatal;
+    /// how many times to try re-opening the connection to the X server after it has been lost
+    /// before exiting the main event loop. A value of 0 disables reconnecting.
+    Concrete reconnect_attempts: u32; => 0;
+    /// how long in milliseconds to wait before the first reconnect attempt. The wait is doubled
+    /// after each failed attempt.
+    Concrete reconnect_backoff_ms: u64; => 500;
+    /// the minimum time in milliseconds between two processes being started by
+    /// [WindowManager::spawn][crate::core::manager::WindowManager::spawn]. Requests made before
+    /// the interval has passed are dropped. A value of 0 disables rate limiting.
+    Concrete spawn_min_interval_ms: u64; => 0;
+    /// how long in milliseconds after starting a command with
+    /// [WindowManager::spawn][crate::core::manager::WindowManager::spawn] that requests to run
+    /// the exact same command are dropped. A value of 0 disables de-duplication.
+    Concrete spawn_dedup_ms: u64; => 0;
     /// the percentage of the screen to grow the main region by when incrementing
     Concrete main_ratio_step: f32; => 0.05;
+    /// whether key bindings follow the physical key or the key name when the keyboard layout
+    /// changes
+    Concrete key_binding_mode: KeyBindingMode; => KeyBindingMode::KeyCode;
+    /// whether focusing a client by direction can move focus to another screen when there is no
+    /// client in that direction on the active screen
+    Concrete directional_focus_crosses_screens: bool; => true;
+    /// which client should gain focus when switching to a workspace
+    Concrete workspace_focus: WorkspaceFocus; => WorkspaceFocus::LastFocused;
+    /// what should happen when the last client on the focused workspace is moved to another
+    /// workspace or screen
+    Concrete emptied_workspace_policy: EmptiedWorkspacePolicy; => EmptiedWorkspacePolicy::Stay;
+    /// what should happen when focusing a workspace that is already displayed on another screen
+    Concrete visible_workspace_policy: VisibleWorkspacePolicy; => VisibleWorkspacePolicy::Swap;
+    /// whether floating clients take precedence over tiled clients when working out which
+    /// client is under the pointer
+    Concrete floating_focus_precedence: FloatingFocusPrecedence; => FloatingFocusPrecedence::Stacking;
+    /// whether the focused client should be raised above its peers when it gains focus. Floating
+    /// clients are kept above a focused tiled client.
+    Concrete raise_on_focus: bool; => false;
+    /// whether newly mapped clients should be given focus
+    Concrete focus_new_windows: FocusNewPolicy; => FocusNewPolicy::CurrentWorkspace;
+    /// what should happen to transient windows when the client they are transient for is
+    /// destroyed
+    Concrete transient_policy: TransientPolicy; => TransientPolicy::Keep;
+    /// whether floating clients on a workspace should be returned to the layout when the
+    /// workspace changes from floating mode or a floating layout to a tiling layout
+    Concrete sink_floats_on_tiling: bool; => false;
+    /// whether clients with `_NET_WM_STATE_STICKY` set are left floating when sinking floating
+    /// clients back into the layout
+    Concrete sink_exempt_sticky: bool; => true;
     /// whether or not space should be reserved for a status bar
     Concrete show_bar: bool; => true;
     /// whether or not the reserved space for a status bar is at the top of the sceen
     Concrete top_bar: bool; => true;
     /// the height of the space to be reserved for a status bar in pixels
     Concrete bar_height: u32; => 18;
+    /// extra space in pixels to leave between the status bar and tiled clients on top of the
+    /// reserved bar height (set this to `gap_px` for the bar edge to match the gaps between
+    /// clients)
+    Concrete bar_gap: u32; => 0;
+    /// whether toggling the status bar should act on every screen or only the focused one
+    Concrete toggle_bar_on_all_screens: bool; => true;
+    /// the names of workspaces that should hide the status bar while they are on screen
+    VecImplInto hidden_bar_workspaces: String; => Vec::<String>::new();
+    /// the names of workspaces that should show a single client borderless and covering the
+    /// entire screen until a second client is added
+    VecImplInto auto_fullscreen_workspaces: String; => Vec::<String>::new();
+}
+
+/// The combined border and gap padding of a tiled client may take up at most this fraction of
+/// the smallest dimension of a screen before [Config::validate_against] reports it as an error.
+const MAX_PADDING_FRACTION: u32 = 4;
+
+/// A problem found when validating a [Config].
+///
+/// See [Config::validate] and [Config::validate_against] for details.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// No workspace names were provided
+    #[error("Must supply at least one workspace name")]
+    NoWorkspaces,
+
+    /// No layout functions were provided
+    #[error("Must supply at least one layout function")]
+    NoLayouts,
+
+    /// A per workspace layout override was provided without any layout functions
+    #[error("Must supply at least one layout function for workspace '{0}'")]
+    NoLayoutsForWorkspace(String),
+
+    /// The same layout symbol was used for more than one layout in a single set of layouts
+    #[error("Layout symbol '{0}' is used more than once")]
+    DuplicateLayoutSymbol(String),
+
+    /// main_ratio_step was outside of the range 0.0 -> 1.0
+    #[error("main_ratio_step must be in the range 0.0 -> 1.0: got {0}")]
+    InvalidMainRatioStep(f32),
+
+    /// The border and gap padding around tiled clients is too large for the given screen
+    #[error("border and gap padding of {padding}px is too large for screen {screen:?}")]
+    PaddingTooLarge {
+        /// The total padding applied around each tiled client in raw pixels for this screen
+        padding: u32,
+        /// The region of the screen that the padding is too large for
+        screen: Region,
+    },
+
+    /// A key binding could not be parsed into a known key code
+    #[error("invalid key binding: {0}")]
+    InvalidKeyBinding(String),
 }
 
 impl Config {
+    /// Check this config for common mistakes, returning all problems that were found.
+    ///
+    /// This runs all of the checks that do not depend on the X server: see
+    /// [validate_against][Config::validate_against] for also checking screen sizes and key
+    /// bindings.
+    ///
+    /// # Example
+    /// ```
+    /// use penrose::core::{config::{Config, ConfigError}, layout::Layout};
+    ///
+    /// assert!(Config::default().validate().is_ok());
+    ///
+    /// let config = Config::default()
+    ///     .builder()
+    ///     .layouts(vec![Layout::floating("[--]"), Layout::floating("[--]")])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     config.validate(),
+    ///     Err(vec![ConfigError::DuplicateLayoutSymbol("[--]".into())])
+    /// );
+    /// ```
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        self.validate_against(&[], &[], &HashMap::new())
+    }
+
+    /// Check this config for common mistakes against the screens and key bindings that it will
+    /// be used with, returning all problems that were found.
+    ///
+    /// In addition to the checks run by [validate][Config::validate] this will ensure that the
+    /// padding applied around tiled clients does not take up more than a quarter of any of the
+    /// provided screens and that each of the provided key binding patterns parses using the
+    /// given key codes (see [parse_key_binding][crate::core::bindings::parse_key_binding]).
+    /// If `dpi_scaling` is enabled then the padding is scaled for each screen before it is
+    /// checked.
+    pub fn validate_against(
+        &self,
+        screens: &[Screen],
+        key_bindings: &[&str],
+        known_codes: &CodeMap,
+    ) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if self.workspaces.is_empty() {
+            errors.push(ConfigError::NoWorkspaces);
+        }
+
+        if self.layouts.is_empty() {
+            errors.push(ConfigError::NoLayouts);
+        }
+
+        let mut overrides: Vec<_> = self.workspace_layouts.iter().collect();
+        overrides.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, layouts) in overrides.iter() {
+            if layouts.is_empty() {
+                errors.push(ConfigError::NoLayoutsForWorkspace(name.to_string()));
+            }
+        }
+
+        for layouts in Some(&self.layouts)
+            .into_iter()
+            .chain(overrides.iter().map(|(_, l)| *l))
+        {
+            let mut seen = HashSet::new();
+            for l in layouts.iter() {
+                if !seen.insert(&l.symbol) {
+                    errors.push(ConfigError::DuplicateLayoutSymbol(l.symbol.clone()));
+                }
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.main_ratio_step) {
+            errors.push(ConfigError::InvalidMainRatioStep(self.main_ratio_step));
+        }
+
+        for s in screens.iter() {
+            let mut padding = 2 * (self.border_px + self.gap_px);
+            if self.dpi_scaling {
+                padding = 2 * (s.scale_px(self.border_px) + s.scale_px(self.gap_px));
+            }
+            let screen = s.region(false);
+            if padding * MAX_PADDING_FRACTION > screen.w.min(screen.h) {
+                errors.push(ConfigError::PaddingTooLarge { padding, screen });
+            }
+        }
+
+        errors.extend(
+            key_bindings
+                .iter()
+                .filter(|&&pattern| parse_key_binding(pattern, known_codes).is_err())
+                .map(|pattern| ConfigError::InvalidKeyBinding(pattern.to_string())),
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Create a range from 1 -> n_workspaces for use in keybindings
     pub fn ws_range(&self) -> std::ops::Range<usize> {
         1..(self.workspaces.len() + 1)
     }
+
+    /// The layouts that should be available on the workspace with the given name.
+    ///
+    /// This is the entry in `workspace_layouts` for `name` if there is one, otherwise `layouts`.
+    pub fn layouts_for_workspace(&self, name: &str) -> &Vec<Layout> {
+        self.workspace_layouts.get(name).unwrap_or(&self.layouts)
+    }
 }
 
 impl ConfigBuilder {
@@ -113,6 +441,18 @@ impl ConfigBuilder {
             return Err("Must supply at least one layout function".into());
         }
 
+        if let Some((name, _)) = self
+            .inner
+            .workspace_layouts
+            .iter()
+            .find(|(_, layouts)| layouts.is_empty())
+        {
+            return Err(format!(
+                "Must supply at least one layout function for workspace '{}'",
+                name
+            ));
+        }
+
         if !(0.0..=1.0).contains(&self.inner.main_ratio_step) {
             return Err("main_ratio_step must be in the range 0.0 -> 1.0".into());
         }
@@ -120,3 +460,102 @@ impl ConfigBuilder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::layout::Layout;
+
+    fn codes() -> CodeMap {
+        vec![("a".to_string(), 38), ("Return".to_string(), 36)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(Config::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn all_problems_are_reported() {
+        let config = Config {
+            workspaces: vec![],
+            layouts: vec![],
+            main_ratio_step: 2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                ConfigError::NoWorkspaces,
+                ConfigError::NoLayouts,
+                ConfigError::InvalidMainRatioStep(2.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn duplicate_symbols_in_workspace_overrides_are_reported() {
+        let mut config = Config::default();
+        config.workspace_layouts.insert(
+            "1".into(),
+            vec![Layout::floating("[--]"), Layout::floating("[--]")],
+        );
+        config.workspace_layouts.insert("2".into(), vec![]);
+
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                ConfigError::NoLayoutsForWorkspace("2".into()),
+                ConfigError::DuplicateLayoutSymbol("[--]".into()),
+            ])
+        );
+    }
+
+    test_cases! {
+        padding;
+        args: (border_px: u32, gap_px: u32, dpi_scaling: bool, ok: bool);
+
+        case: default => (2, 5, false, true);
+        case: at_limit => (10, 15, false, true);
+        case: too_large => (10, 16, false, false);
+        case: too_large_once_scaled => (5, 10, true, false);
+        case: scaled_at_limit => (4, 8, true, true);
+
+        body: {
+            let config = Config {
+                border_px,
+                gap_px,
+                dpi_scaling,
+                ..Default::default()
+            };
+            // The second screen is twice the base DPI
+            let screens = &[
+                Screen::new(Region::new(0, 0, 1920, 1080), 0),
+                Screen::new(Region::new(1920, 0, 400, 200), 1).with_physical_width(53),
+            ];
+
+            let res = config.validate_against(screens, &[], &codes());
+            assert_eq!(res.is_ok(), ok, "{:?}", res);
+        }
+    }
+
+    test_cases! {
+        key_bindings;
+        args: (pattern: &str, ok: bool);
+
+        case: plain_key => ("a", true);
+        case: single_modifier => ("M-a", true);
+        case: multiple_modifiers => ("M-S-Return", true);
+        case: unknown_key => ("M-b", false);
+        case: unknown_modifier => ("X-a", false);
+        case: modifier_aliases => ("Super+Shift+Return", true);
+
+        body: {
+            let res = Config::default().validate_against(&[], &[pattern], &codes());
+            assert_eq!(res.is_ok(), ok, "{:?}", res);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Simple data types and enums
 use crate::{
     core::xconnection::{Atom, Xid},
+    draw::Color,
     Result,
 };
 
@@ -72,6 +73,246 @@ pub enum Change {
     Less,
 }
 
+/// What should happen when a bounded value is changed past one of its limits
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// stop at the limit
+    Clamp,
+    /// jump to the opposite limit
+    Wrap,
+}
+
+/// Which client should gain focus when switching to a workspace
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkspaceFocus {
+    /// The client that last had focus on the workspace
+    LastFocused,
+    /// The first client in the main area of the workspace
+    Main,
+    /// The client under the pointer (falling back to the last focused client)
+    UnderPointer,
+}
+
+/// What should happen when moving the last client off of the focused workspace
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmptiedWorkspacePolicy {
+    /// Stay on the now empty workspace
+    Stay,
+    /// Switch to the previously focused workspace
+    PreviousWorkspace,
+    /// Follow the client to the workspace it was moved to
+    FollowClient,
+}
+
+/// What should happen when focusing a workspace that is already displayed on another screen
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VisibleWorkspacePolicy {
+    /// Pull the workspace to the focused screen, placing the workspace that was on the focused
+    /// screen where the target was displayed
+    Swap,
+    /// Leave both workspaces where they are and move focus to the screen showing the target
+    FocusScreen,
+}
+
+/// How floating clients are treated when deciding which client is under the pointer
+///
+/// This is used when the pointer enters a client window, by
+/// [client_at_point][crate::core::manager::WindowManager::client_at_point] and when picking
+/// candidates for
+/// [focus_in_direction][crate::core::manager::WindowManager::focus_in_direction].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatingFocusPrecedence {
+    /// The client that is highest in the stacking order wins, whether or not it is floating.
+    /// Entering a window always focuses that window.
+    Stacking,
+    /// Floating clients always win over tiled clients, with the stacking order only used to
+    /// choose between clients of the same kind. Entering part of a tiled window that lies
+    /// under a floating client focuses the floating client instead, and tiled clients whose
+    /// centre is covered by a floating client are skipped when focusing by direction.
+    FloatingOnTop,
+}
+
+/// A corner of a [Region]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+    /// The top left corner
+    TopLeft,
+    /// The top right corner
+    TopRight,
+    /// The bottom left corner
+    BottomLeft,
+    /// The bottom right corner
+    BottomRight,
+}
+
+/// Whether a newly mapped client should be given focus
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusNewPolicy {
+    /// Always focus new clients
+    Always,
+    /// Never focus new clients
+    Never,
+    /// Only focus new clients that are placed on the active workspace
+    CurrentWorkspace,
+}
+
+/// What should happen to transient windows (such as dialogs) when the
+/// [Client][crate::core::client::Client] they are transient for is destroyed
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransientPolicy {
+    /// Leave transient windows open as regular floating clients, focusing the first of them if
+    /// their parent had focus
+    Keep,
+    /// Ask transient windows to close in the same way as
+    /// [kill_client][crate::core::manager::WindowManager::kill_client]
+    Close,
+}
+
+/// How the aspect ratio of a [Client][crate::core::client::Client] should be determined when it is
+/// being preserved while tiling.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AspectRatio {
+    /// Use the min and max aspect ratios requested by the client in its WM_NORMAL_HINTS
+    FromHints,
+    /// Always use the given width:height ratio
+    Fixed(u32, u32),
+}
+
+/// How errors raised while handling events in the main
+/// [WindowManager][crate::core::manager::WindowManager] event loop are dealt with.
+///
+/// Errors that do not stop the event loop are passed to the
+/// [ErrorHandler][crate::ErrorHandler] and the next event is processed as normal.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorPolicy {
+    /// Only stop the event loop for [fatal][crate::PenroseError::is_fatal] errors, such as the
+    /// connection to the X server being lost
+    ExitOnFatal,
+    /// Stop the event loop for any error
+    ExitOnAny,
+}
+
+/// How the root window should be painted when penrose starts or a workspace is focused
+///
+/// Image wallpapers are set using a [Command][RootBackground::Command]: painting images directly
+/// through the [draw][crate::draw] backend is not supported as it has no way of loading them.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RootBackground {
+    /// Fill the root window with a solid color. This is repainted whenever an empty workspace
+    /// is focused.
+    Color(Color),
+    /// Run an external command (such as `feh --bg-fill ~/wallpaper.png`) to set the root window
+    /// background. As the `root_background` this is only run once at startup while entries in
+    /// `workspace_backgrounds` are run each time they become the current background.
+    Command(String),
+}
+
+/// The saved position of a single floating client within a [FloatLayout]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FloatLayoutEntry {
+    /// The `WM_CLASS` of the client
+    pub class: String,
+    /// Which client this is out of those with the same class, ordered by window ID
+    pub ordinal: usize,
+    /// The position of the client when the layout was saved
+    pub region: Region,
+}
+
+/// A snapshot of the positions of the floating clients on a workspace that can be reapplied
+/// later.
+///
+/// Clients are matched by their `WM_CLASS` and their position among other clients of the same
+/// class so that a layout can be restored after the windows have been closed and reopened.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FloatLayout {
+    /// The saved client positions
+    pub entries: Vec<FloatLayoutEntry>,
+}
+
+impl FloatLayout {
+    /// The saved position for the given client class and ordinal if there is one
+    pub fn region_for(&self, class: &str, ordinal: usize) -> Option<Region> {
+        self.entries
+            .iter()
+            .find(|e| e.class == class && e.ordinal == ordinal)
+            .map(|e| e.region)
+    }
+}
+
+/// A [Client][crate::core::client::Client] to be placed by a [WorkspaceProfile], matched in the
+/// same way as a [FloatLayoutEntry].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProfileClient {
+    /// The `WM_CLASS` of the client
+    pub class: String,
+    /// Which client this is out of those with the same class, ordered by window ID
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ordinal: usize,
+}
+
+/// The desired arrangement of a single named workspace within a [Profile].
+///
+/// Any settings that are left as `None` are not modified when the profile is applied.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkspaceProfile {
+    /// The name of the workspace
+    pub workspace: String,
+    /// The symbol of the layout to select
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub layout: Option<String>,
+    /// The number of clients to place in the main area of the selected layout
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_main: Option<u32>,
+    /// The ratio of the main area to the secondary area of the selected layout
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub main_ratio: Option<f32>,
+    /// The clients that should be moved to this workspace
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clients: Vec<ProfileClient>,
+}
+
+/// A declarative description of how a set of workspaces should be arranged that can be applied
+/// in one go using [apply_profile][crate::core::manager::WindowManager::apply_profile].
+///
+/// ```
+/// # use penrose::core::data_types::{Profile, ProfileClient, WorkspaceProfile};
+/// let work = Profile {
+///     workspaces: vec![WorkspaceProfile {
+///         workspace: "1".into(),
+///         layout: Some("[side]".into()),
+///         max_main: Some(2),
+///         clients: vec![
+///             ProfileClient { class: "kitty".into(), ordinal: 0 },
+///             ProfileClient { class: "kitty".into(), ordinal: 1 },
+///         ],
+///         ..Default::default()
+///     }],
+/// };
+///
+/// assert_eq!(work.workspaces[0].clients.len(), 2);
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// The arrangement for each workspace that should be modified
+    pub workspaces: Vec<WorkspaceProfile>,
+}
+
 /// X window border kind
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -164,6 +405,60 @@ impl Region {
         }
     }
 
+    /// Create a new [Region] with width and height scaled by `fx` and `fy` respectively
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::Region;
+    ///
+    /// let r = Region::new(10, 20, 30, 40);
+    ///
+    /// assert_eq!(r.scale(2.0, 0.5), Region::new(10, 20, 60, 20));
+    /// ```
+    pub fn scale(&self, fx: f64, fy: f64) -> Self {
+        self.scale_w(fx).scale_h(fy)
+    }
+
+    /// Map this [Region] from its position within `from` to the same relative position and size
+    /// within `to`.
+    ///
+    /// This is used for moving floating windows between screens: a window in the top right
+    /// corner of one screen will be placed in the top right corner of the other, scaled to match
+    /// any difference in resolution. The resulting region is clamped to fit inside `to`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::Region;
+    ///
+    /// let from = Region::new(0, 0, 1000, 600);
+    /// let r = Region::new(700, 0, 300, 200);
+    ///
+    /// // Same size screens only translate the region
+    /// let to = Region::new(1000, 0, 1000, 600);
+    /// assert_eq!(r.translate_to_screen(&from, &to), Region::new(1700, 0, 300, 200));
+    ///
+    /// // Different aspect ratios scale both the position and size on each axis
+    /// let to = Region::new(1000, 0, 2000, 900);
+    /// assert_eq!(r.translate_to_screen(&from, &to), Region::new(2400, 0, 600, 300));
+    /// ```
+    pub fn translate_to_screen(&self, from: &Region, to: &Region) -> Self {
+        let fx = to.w as f64 / from.w.max(1) as f64;
+        let fy = to.h as f64 / from.h.max(1) as f64;
+        let dx = (self.x.saturating_sub(from.x) as f64 * fx).floor() as u32;
+        let dy = (self.y.saturating_sub(from.y) as f64 * fy).floor() as u32;
+        let Region { w, h, .. } = self.scale(fx, fy);
+        let (w, h) = (w.clamp(1, to.w.max(1)), h.clamp(1, to.h.max(1)));
+
+        Region::new(
+            to.x + dx.min(to.w.saturating_sub(w)),
+            to.y + dy.min(to.h.saturating_sub(h)),
+            w,
+            h,
+        )
+    }
+
     /// Check whether this Region contains `other` as a sub-Region
     ///
     /// # Examples
@@ -239,6 +534,83 @@ impl Region {
         })
     }
 
+    /// Move this region into the given [Corner] of `enclosing`.
+    ///
+    /// # Errors
+    /// Fails if this Region can not fit inside of `enclosing`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::{Corner, Region};
+    ///
+    /// let r1 = Region::new(10, 10, 50, 60);
+    /// let r2 = Region::new(0, 0, 100, 100);
+    ///
+    /// assert_eq!(r1.in_corner_of(Corner::TopLeft, &r2).unwrap(), Region::new(0, 0, 50, 60));
+    /// assert_eq!(r1.in_corner_of(Corner::BottomRight, &r2).unwrap(), Region::new(50, 40, 50, 60));
+    ///
+    /// let too_big = r2.in_corner_of(Corner::TopLeft, &r1);
+    /// assert!(too_big.is_err());
+    /// ```
+    pub fn in_corner_of(&self, corner: Corner, enclosing: &Region) -> Result<Self> {
+        if self.w > enclosing.w || self.h > enclosing.h {
+            return Err(perror!(
+                "enclosing can not contain self: {:?} {:?}",
+                enclosing,
+                self
+            ));
+        }
+
+        let (left, top) = (enclosing.x, enclosing.y);
+        let (right, bottom) = (
+            enclosing.x + enclosing.w - self.w,
+            enclosing.y + enclosing.h - self.h,
+        );
+        let (x, y) = match corner {
+            Corner::TopLeft => (left, top),
+            Corner::TopRight => (right, top),
+            Corner::BottomLeft => (left, bottom),
+            Corner::BottomRight => (right, bottom),
+        };
+
+        Ok(Self { x, y, ..*self })
+    }
+
+    /// The largest `Region` with the given width:height ratio that fits inside of this one,
+    /// centered within it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::Region;
+    ///
+    /// let r = Region::new(0, 0, 1600, 900);
+    ///
+    /// assert_eq!(r.fit_aspect_ratio(4, 3), Region::new(200, 0, 1200, 900));
+    /// assert_eq!(r.fit_aspect_ratio(32, 9), Region::new(0, 225, 1600, 450));
+    /// assert_eq!(r.fit_aspect_ratio(0, 3), r);
+    /// ```
+    pub fn fit_aspect_ratio(&self, w: u32, h: u32) -> Self {
+        if w == 0 || h == 0 {
+            return *self;
+        }
+
+        let (sw, sh, w, h) = (self.w as u64, self.h as u64, w as u64, h as u64);
+        let (new_w, new_h) = if sw * h > sh * w {
+            ((sh * w / h) as u32, self.h)
+        } else {
+            (self.w, (sw * h / w) as u32)
+        };
+
+        Self {
+            x: self.x + (self.w - new_w) / 2,
+            y: self.y + (self.h - new_h) / 2,
+            w: new_w,
+            h: new_h,
+        }
+    }
+
     /// Split this `Region` into evenly sized rows.
     ///
     /// # Examples
@@ -368,4 +740,130 @@ impl Region {
             ))
         }
     }
+
+    /// Determine which of the eight [ResizeZone]s of this region `p` falls in, where `p` is
+    /// relative to the top left corner of the region.
+    ///
+    /// The region is split into thirds along each axis: points in the outer thirds select the
+    /// corresponding edge or corner, while points in the center select the nearest corner.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::{Point, Region, ResizeZone};
+    ///
+    /// let r = Region::new(100, 100, 300, 300);
+    ///
+    /// assert_eq!(r.resize_zone(Point::new(10, 10)), ResizeZone::TopLeft);
+    /// assert_eq!(r.resize_zone(Point::new(150, 10)), ResizeZone::Top);
+    /// assert_eq!(r.resize_zone(Point::new(290, 150)), ResizeZone::Right);
+    /// assert_eq!(r.resize_zone(Point::new(160, 160)), ResizeZone::BottomRight);
+    /// ```
+    pub fn resize_zone(&self, p: Point) -> ResizeZone {
+        let third = |pos: u32, len: u32| {
+            if pos < len / 3 {
+                Some(false)
+            } else if pos >= len - len / 3 {
+                Some(true)
+            } else {
+                None
+            }
+        };
+
+        let (right, bottom) = match (third(p.x, self.w), third(p.y, self.h)) {
+            (None, None) => (Some(p.x >= self.w / 2), Some(p.y >= self.h / 2)),
+            other => other,
+        };
+
+        match (right, bottom) {
+            (Some(false), Some(false)) => ResizeZone::TopLeft,
+            (None, Some(false)) => ResizeZone::Top,
+            (Some(true), Some(false)) => ResizeZone::TopRight,
+            (Some(false), None) => ResizeZone::Left,
+            (Some(true), None) => ResizeZone::Right,
+            (Some(false), Some(true)) => ResizeZone::BottomLeft,
+            (None, Some(true)) => ResizeZone::Bottom,
+            (Some(true), Some(true)) | (None, None) => ResizeZone::BottomRight,
+        }
+    }
+}
+
+/// The edge or corner of a [Region] that is being dragged when resizing it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResizeZone {
+    /// The top left corner
+    TopLeft,
+    /// The top edge
+    Top,
+    /// The top right corner
+    TopRight,
+    /// The left edge
+    Left,
+    /// The right edge
+    Right,
+    /// The bottom left corner
+    BottomLeft,
+    /// The bottom edge
+    Bottom,
+    /// The bottom right corner
+    BottomRight,
+}
+
+impl ResizeZone {
+    /// Resize `r` by moving the edges of this zone by `dx` and `dy` pixels, while keeping the
+    /// opposite edges in place. The resulting region will be no smaller than `min_w` x `min_h`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::{Region, ResizeZone};
+    ///
+    /// let r = Region::new(100, 100, 300, 300);
+    ///
+    /// assert_eq!(ResizeZone::Top.resize(&r, 50, -20, 1, 1), Region::new(100, 80, 300, 320));
+    /// assert_eq!(
+    ///     ResizeZone::BottomRight.resize(&r, 50, -20, 1, 1),
+    ///     Region::new(100, 100, 350, 280)
+    /// );
+    /// assert_eq!(ResizeZone::Left.resize(&r, 290, 0, 50, 50), Region::new(350, 100, 50, 300));
+    /// ```
+    pub fn resize(&self, r: &Region, dx: i32, dy: i32, min_w: u32, min_h: u32) -> Region {
+        use ResizeZone::*;
+
+        let (x, y, w, h) = r.values();
+        let (left, right) = match self {
+            TopLeft | Left | BottomLeft => (true, false),
+            TopRight | Right | BottomRight => (false, true),
+            Top | Bottom => (false, false),
+        };
+        let (top, bottom) = match self {
+            TopLeft | Top | TopRight => (true, false),
+            BottomLeft | Bottom | BottomRight => (false, true),
+            Left | Right => (false, false),
+        };
+
+        let (x, w) = resize_axis(x, w, dx, left, right, min_w);
+        let (y, h) = resize_axis(y, h, dy, top, bottom, min_h);
+
+        Region::new(x, y, w, h)
+    }
+}
+
+// Move the start or end of a span along a single axis, keeping the opposite end fixed
+fn resize_axis(pos: u32, len: u32, delta: i32, start: bool, end: bool, min: u32) -> (u32, u32) {
+    let (pos, len, delta) = (pos as i64, len as i64, delta as i64);
+    let min = min.max(1) as i64;
+
+    let (pos, len) = if start {
+        let far = pos + len;
+        let new_len = (len - delta).max(min).min(far);
+        (far - new_len, new_len)
+    } else if end {
+        (pos, (len + delta).max(min))
+    } else {
+        (pos, len)
+    };
+
+    (pos as u32, len as u32)
 }
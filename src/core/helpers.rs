@@ -6,9 +6,14 @@ use crate::{
 
 use std::{
     io::Read,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+static STARTUP_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 /// Run an external command
 ///
 /// This redirects the process stdout and stderr to /dev/null.
@@ -50,6 +55,79 @@ pub fn spawn_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Result<()> {
     }
 }
 
+/// Run an external command with additional environment variables set, returning the process ID
+/// of the spawned child process.
+///
+/// This redirects the process stdout and stderr to /dev/null.
+pub fn spawn_with_env<S: Into<String>>(cmd: S, env: &[(&str, &str)]) -> Result<u32> {
+    let s = cmd.into();
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    if parts.is_empty() {
+        return Err(PenroseError::SpawnProc(s));
+    }
+
+    let child = Command::new(parts[0])
+        .args(&parts[1..])
+        .envs(env.iter().copied())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    Ok(child.id())
+}
+
+/// Run an external command with its working directory set to `dir` (if provided)
+///
+/// This redirects the process stdout and stderr to /dev/null.
+pub fn spawn_in_dir<S: Into<String>>(cmd: S, dir: Option<&Path>) -> Result<()> {
+    let s = cmd.into();
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    if parts.is_empty() {
+        return Err(PenroseError::SpawnProc(s));
+    }
+
+    let mut command = Command::new(parts[0]);
+    command
+        .args(&parts[1..])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    command.spawn()?;
+    Ok(())
+}
+
+/// Determine the current working directory of the process with the given PID
+///
+/// This reads the `/proc/<pid>/cwd` symlink so it is only supported on Linux: on other platforms
+/// this always returns `None`.
+pub fn process_cwd(pid: u32) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Generate a new, unique ID suitable for passing to a spawned process as its
+/// `DESKTOP_STARTUP_ID` as part of the startup notification protocol.
+pub fn new_startup_id() -> String {
+    let n = STARTUP_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    format!("penrose-{}-{}-{}_TIME0", std::process::id(), n, secs)
+}
+
 /// Run an external command and return its output.
 ///
 /// NOTE: std::process::Command::output will not work within penrose due to the
@@ -109,30 +187,42 @@ pub fn spawn_for_output_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Res
 ///
 /// # Panics
 /// This function will panic if it is unable to fetch keycodes using the xmodmap
-/// binary on your system or if the output of `xmodmap -pke` is not valid
+/// binary on your system or if the output of `xmodmap -pke` is not valid. See
+/// [try_keycodes_from_xmodmap] for a version that returns an error instead.
 pub fn keycodes_from_xmodmap() -> CodeMap {
-    match Command::new("xmodmap").arg("-pke").output() {
+    match try_keycodes_from_xmodmap() {
+        Ok(codes) => codes,
         Err(e) => panic!("unable to fetch keycodes via xmodmap: {}", e),
-        Ok(o) => match String::from_utf8(o.stdout) {
-            Err(e) => panic!("invalid utf8 from xmodmap: {}", e),
-            Ok(s) => s
-                .lines()
-                .flat_map(|l| {
-                    let mut words = l.split_whitespace(); // keycode <code> = <names ...>
-                    let key_code: u8 = match words.nth(1) {
-                        Some(word) => match word.parse() {
-                            Ok(val) => val,
-                            Err(e) => panic!("{}", e),
-                        },
-                        None => panic!("unexpected output format from xmodmap -pke"),
-                    };
-                    words.skip(1).map(move |name| (name.into(), key_code))
-                })
-                .collect::<CodeMap>(),
-        },
     }
 }
 
+/// Run the xmodmap command to dump the system keymap table, returning an error rather than
+/// panicking if it fails.
+///
+/// See [keycodes_from_xmodmap] for details.
+///
+/// # Errors
+/// This function will return an error if it is unable to fetch keycodes using the xmodmap
+/// binary on your system or if the output of `xmodmap -pke` is not valid
+pub fn try_keycodes_from_xmodmap() -> Result<CodeMap> {
+    let output = Command::new("xmodmap").arg("-pke").output()?;
+    if !output.status.success() {
+        return Err(perror!("xmodmap -pke exited with {}", output.status));
+    }
+
+    let mut codes = CodeMap::new();
+    for line in String::from_utf8(output.stdout)?.lines() {
+        let mut words = line.split_whitespace(); // keycode <code> = <names ...>
+        let key_code: u8 = words
+            .nth(1)
+            .ok_or_else(|| perror!("unexpected output format from xmodmap -pke"))?
+            .parse()?;
+        codes.extend(words.skip(1).map(|name| (name.into(), key_code)));
+    }
+
+    Ok(codes)
+}
+
 /// Create a Vec of index selectors for the given input slice
 pub fn index_selectors<'a, T>(len: usize) -> Vec<Selector<'a, T>> {
     (0..len).map(Selector::Index).collect()
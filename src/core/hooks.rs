@@ -120,7 +120,7 @@ use crate::{
     core::{
         data_types::Region,
         manager::WindowManager,
-        xconnection::{XConn, Xid},
+        xconnection::{RandrChange, WmNormalHints, XConn, Xid},
     },
     Result,
 };
@@ -139,15 +139,23 @@ pub enum HookName {
     RemoveClient(Xid),
     ClientAddedToWorkspace(Xid, usize),
     ClientNameUpdated(Xid, String, bool),
+    ClientNormalHintsChanged(Xid, WmNormalHints),
     LayoutApplied(usize, usize),
     LayoutChange(usize),
     WorkspaceChange(usize, usize),
     WorkspacesUpdated(Vec<String>, usize),
+    WorkspaceAdded(usize, String, usize),
+    WorkspaceRemoved(usize, String, usize),
     ScreenChange,
     ScreenUpdated,
     RanderNotify,
+    RandrChange(RandrChange),
+    BarVisibilityChanged(usize, bool),
+    ClientGeometryChanged(Xid, Region, Region),
     FocusChange(u32),
     EventHandled,
+    StartupSettled(usize),
+    Tick,
 }
 
 /// Utility type for defining hooks in your penrose configuration.
@@ -329,7 +337,8 @@ pub trait Hook<X: XConn> {
 
     /// # Trigger Point
     ///
-    /// Called after a workspace's [Layout][1] has been updated via [cycle_layout][4].
+    /// Called after a workspace's [Layout][1] has been updated via [cycle_layout][4] (including
+    /// [next_layout][5] and [prev_layout][6]).
     ///
     /// Arguments are indices into the WindowManager workspace and screen rings (internal data
     /// structures that support indexing) which can be used to fetch references to the active [Workspace][2]
@@ -344,6 +353,8 @@ pub trait Hook<X: XConn> {
     /// [2]: crate::core::workspace::Workspace
     /// [3]: crate::core::screen::Screen
     /// [4]: crate::core::manager::WindowManager::cycle_layout
+    /// [5]: crate::core::manager::WindowManager::next_layout
+    /// [6]: crate::core::manager::WindowManager::prev_layout
     #[allow(unused_variables)]
     fn layout_change(
         &mut self,
@@ -398,6 +409,54 @@ pub trait Hook<X: XConn> {
         Ok(())
     }
 
+    /// # Trigger Point
+    ///
+    /// Called after a new [Workspace][1] has been added to the [WindowManager] while it is
+    /// running.
+    ///
+    /// Arguments are the index the workspace was inserted at, its name and the total number of
+    /// workspaces now known to the WindowManager.
+    ///
+    /// # Example Uses
+    ///
+    /// Adding a status bar indicator or updating external desktop counts for the new workspace.
+    ///
+    /// [1]: crate::core::workspace::Workspace
+    #[allow(unused_variables)]
+    fn workspace_added(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        index: usize,
+        name: &str,
+        n_workspaces: usize,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called after a [Workspace][1] has been removed from the [WindowManager] while it is
+    /// running.
+    ///
+    /// Arguments are the index the workspace had before it was removed, its name and the total
+    /// number of workspaces remaining.
+    ///
+    /// # Example Uses
+    ///
+    /// Removing the status bar indicator or any other external state for the workspace.
+    ///
+    /// [1]: crate::core::workspace::Workspace
+    #[allow(unused_variables)]
+    fn workspace_removed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        index: usize,
+        name: &str,
+        n_workspaces: usize,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// # Trigger Point
     ///
     /// Called after focus moves to a new [Screen][1].
@@ -454,10 +513,103 @@ pub trait Hook<X: XConn> {
 
     /// # Trigger Point
     ///
-    /// Called after a [Client][1] gains focus.
+    /// Called when the underlying [XConn] emitted a [RandrNotify][1] event, with the details of
+    /// what changed.
+    ///
+    /// This hook will run _before_ [randr_notify][2] and is run for all RandR changes, including
+    /// those that do not result in the current screens being re-detected.
+    ///
+    /// # Example Uses
+    ///
+    /// Reacting to a specific output being connected or disconnected.
+    ///
+    /// [1]: crate::core::xconnection::XEvent::RandrNotify
+    /// [2]: Hook::randr_notify
+    #[allow(unused_variables)]
+    fn randr_change(&mut self, wm: &mut WindowManager<X>, change: &RandrChange) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when the status bar for a screen is shown or hidden using [toggle_bar][1], before
+    /// the visible workspaces are re-laid out to claim or release the space used by the bar.
+    ///
+    /// Arguments are an index into the WindowManager screen ring and whether or not the bar for
+    /// that screen is now visible.
+    ///
+    /// # Example Uses
+    ///
+    /// Mapping and unmapping the windows used by a status bar, as is done by
+    /// [StatusBar][2].
+    ///
+    /// [1]: crate::core::manager::WindowManager::toggle_bar
+    /// [2]: crate::draw::bar::StatusBar
+    #[allow(unused_variables)]
+    fn bar_visibility_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        screen_index: usize,
+        visible: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when applying a layout moves or resizes a tiled [Client][1], after the client has
+    /// been positioned in its new region.
+    ///
+    /// Arguments are the ID of the client, the region it was previously placed in by a layout
+    /// and the region it has now been placed in. Clients that are being tiled for the first time
+    /// (or for the first time since being hidden) do not trigger this hook.
+    ///
+    /// # Example Uses
+    ///
+    /// Driving an external program that animates windows between their old and new positions
+    /// when the layout changes.
+    ///
+    /// [1]: crate::core::client::Client
+    #[allow(unused_variables)]
+    fn client_geometry_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        from: Region,
+        to: Region,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called whenever a managed [Client][1] updates its WM_NORMAL_HINTS property, after the
+    /// new hints have been stored on the client.
+    ///
+    /// # Example Uses
+    ///
+    /// Reacting to clients that change their minimum or maximum size while running, such as
+    /// re-centering a floating client that has switched to a fixed size.
+    ///
+    /// [1]: crate::core::client::Client
+    #[allow(unused_variables)]
+    fn client_normal_hints_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        hints: &WmNormalHints,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called after a [Client][1] gains focus, or after focus is cleared so that no client has
+    /// focus.
     ///
     /// Argument is the focused Client ID which can be used to fetch the internal Client state if
-    /// needed.
+    /// needed. When focus is cleared (for example by [unfocus_all][3]) the ID of the root window
+    /// is passed instead, which does not match any Client.
     ///
     /// # Example Uses
     ///
@@ -466,6 +618,7 @@ pub trait Hook<X: XConn> {
     ///
     /// [1]: crate::core::client::Client
     /// [2]: crate::draw::widget::bar::ActiveWindowName
+    /// [3]: crate::core::manager::WindowManager::unfocus_all
     #[allow(unused_variables)]
     fn focus_change(&mut self, wm: &mut WindowManager<X>, id: Xid) -> Result<()> {
         Ok(())
@@ -485,4 +638,47 @@ pub trait Hook<X: XConn> {
     fn event_handled(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
         Ok(())
     }
+
+    /// # Trigger Point
+    ///
+    /// Called once after startup, the first time that no new [XEvent][1]s have arrived for
+    /// `startup_settle_ms` milliseconds. `n_mapped` is the number of map requests that were
+    /// received between the [startup][2] hook being run and the event loop becoming idle.
+    ///
+    /// This hook is only run if `startup_settle_ms` has been set to a non-zero value in the
+    /// [Config][3].
+    ///
+    /// # Example Uses
+    ///
+    /// Placing or arranging windows that were adopted or launched during startup only once they
+    /// have all been mapped, rather than reacting to each one as it appears.
+    ///
+    /// [1]: crate::core::xconnection::XEvent
+    /// [2]: Hook::startup
+    /// [3]: crate::core::config::Config
+    #[allow(unused_variables)]
+    fn startup_settled(&mut self, wm: &mut WindowManager<X>, n_mapped: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called every `tick_interval_ms` milliseconds while the main [WindowManager] event loop is
+    /// waiting for the next [XEvent][1]. Ticks are run between events so a tick that falls due
+    /// while an event is being handled is run once that event has been processed.
+    ///
+    /// This hook is only run if `tick_interval_ms` has been set to a non-zero value in the
+    /// [Config][2].
+    ///
+    /// # Example Uses
+    ///
+    /// Periodic work such as refreshing a clock in the status bar without needing to run a
+    /// separate thread.
+    ///
+    /// [1]: crate::core::xconnection::XEvent
+    /// [2]: crate::core::config::Config
+    #[allow(unused_variables)]
+    fn tick(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        Ok(())
+    }
 }
@@ -56,16 +56,83 @@
 //! [5]: crate::core::layout::side_stack
 use crate::core::{
     client::Client,
-    data_types::{Change, Region, ResizeAction},
+    data_types::{Change, Overflow, Region, ResizeAction},
     xconnection::Xid,
 };
 
 use std::{cmp, fmt};
 
+/// Which side of the screen the main area of a [Layout] should be placed on.
+///
+/// Layout functions are written with the main area on the left (as is the case for [side_stack])
+/// and the regions they return are mirrored or rotated by the [Layout] to match the requested
+/// side. For layout functions that place their main area somewhere else (such as [bottom_stack])
+/// the requested side is relative to that: `Top` for a `bottom_stack` layout will place the main
+/// area on the left.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum MainSide {
+    /// Use the regions returned by the layout function unmodified
+    #[default]
+    Left,
+    /// Mirror the layout so that the main area is on the right
+    Right,
+    /// Rotate the layout so that the main area is at the top
+    Top,
+    /// Rotate the layout so that the main area is at the bottom
+    Bottom,
+}
+
+impl MainSide {
+    // Map a region returned by a layout function for 'monitor' to the requested side, keeping
+    // shared edges between regions aligned.
+    fn transform(&self, r: &Region, monitor: &Region) -> Region {
+        let (rx, ry, w, h) = r.values();
+        let (mx, my, mw, mh) = monitor.values();
+        let (x1, y1) = (rx.saturating_sub(mx), ry.saturating_sub(my));
+        let (x2, y2) = (x1 + w, y1 + h);
+
+        // scale a position along one axis of the monitor to the other
+        let scale = |v: u32, from: u32, to: u32| (v as u64 * to as u64 / from.max(1) as u64) as u32;
+
+        let (x1, y1, x2, y2) = match self {
+            Self::Left => (x1, y1, x2, y2),
+            Self::Right => (mw.saturating_sub(x2), y1, mw.saturating_sub(x1), y2),
+            Self::Top => (
+                scale(y1, mh, mw),
+                scale(x1, mw, mh),
+                scale(y2, mh, mw),
+                scale(x2, mw, mh),
+            ),
+            Self::Bottom => (
+                scale(y1, mh, mw),
+                mh.saturating_sub(scale(x2, mw, mh)),
+                scale(y2, mh, mw),
+                mh.saturating_sub(scale(x1, mw, mh)),
+            ),
+        };
+
+        Region::new(mx + x1, my + y1, x2 - x1, y2 - y1)
+    }
+}
+
 /// When and how a Layout should be applied.
 ///
 /// The default layout config that only triggers when clients are added / removed and follows user
 /// defined config options.
+///
+/// New fields may be added to this struct over time (`main_side` is a recent addition) so struct
+/// literals should fill in any fields they do not set using `..Default::default()`.
+///
+/// ```
+/// # use penrose::core::layout::{LayoutConf, MainSide};
+/// let conf = LayoutConf {
+///     follow_focus: true,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(conf.main_side, MainSide::Left);
+/// ```
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct LayoutConf {
@@ -77,6 +144,9 @@ pub struct LayoutConf {
     pub follow_focus: bool,
     /// Should cycling clients wrap at the first and last client?
     pub allow_wrapping: bool,
+    /// Which side of the screen the main area should be placed on
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub main_side: MainSide,
 }
 
 impl Default for LayoutConf {
@@ -86,6 +156,7 @@ impl Default for LayoutConf {
             gapless: false,
             follow_focus: false,
             allow_wrapping: true,
+            main_side: MainSide::Left,
         }
     }
 }
@@ -151,8 +222,31 @@ pub fn floating(_: &[&Client], _: Option<Xid>, _: &Region, _: u32, _: f32) -> Ve
     vec![]
 }
 
+/// A problem found when constructing a [Layout].
+///
+/// See [Layout::try_new] and [LayoutBuilder::build] for details.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum LayoutError {
+    /// The layout symbol was empty or only contained whitespace
+    #[error("Layout symbols must not be empty")]
+    EmptySymbol,
+
+    /// The initial ratio was outside of the range 0.0 -> 1.0
+    #[error("Layout '{symbol}' has a ratio outside of the range 0.0 -> 1.0: got {ratio}")]
+    InvalidRatio {
+        /// The symbol of the layout
+        symbol: String,
+        /// The ratio that was provided
+        ratio: f32,
+    },
+}
+
 impl Layout {
     /// Create a new Layout for a specific monitor
+    ///
+    /// Invalid values are logged as errors rather than rejected: a `ratio` outside of the range
+    /// 0.0 -> 1.0 is clamped to that range. See [try_new][Layout::try_new] if you want to handle
+    /// these errors yourself.
     pub fn new(
         symbol: impl Into<String>,
         conf: LayoutConf,
@@ -160,31 +254,111 @@ impl Layout {
         max_main: u32,
         ratio: f32,
     ) -> Self {
-        Self {
-            symbol: symbol.into(),
+        let symbol = symbol.into();
+
+        match Self::try_new(symbol.clone(), conf, f, max_main, ratio) {
+            Ok(layout) => layout,
+            Err(e) => {
+                error!(%e, "invalid layout");
+                Self {
+                    symbol,
+                    conf,
+                    max_main,
+                    ratio: ratio.clamp(0.0, 1.0),
+                    f: Some(f),
+                }
+            }
+        }
+    }
+
+    /// Create a new Layout for a specific monitor, returning an error if `symbol` is empty or
+    /// `ratio` is outside of the range 0.0 -> 1.0.
+    ///
+    /// Combinations of [LayoutConf] options that have no effect (such as a floating layout that
+    /// is given a tiling function) are allowed but logged as warnings.
+    ///
+    /// # Example
+    /// ```
+    /// use penrose::core::layout::{side_stack, Layout, LayoutConf, LayoutError};
+    ///
+    /// assert!(Layout::try_new("[side]", LayoutConf::default(), side_stack, 1, 0.6).is_ok());
+    /// assert_eq!(
+    ///     Layout::try_new("", LayoutConf::default(), side_stack, 1, 0.6),
+    ///     Err(LayoutError::EmptySymbol)
+    /// );
+    /// ```
+    pub fn try_new(
+        symbol: impl Into<String>,
+        conf: LayoutConf,
+        f: LayoutFunc,
+        max_main: u32,
+        ratio: f32,
+    ) -> std::result::Result<Self, LayoutError> {
+        let symbol = symbol.into();
+
+        if symbol.trim().is_empty() {
+            return Err(LayoutError::EmptySymbol);
+        }
+
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(LayoutError::InvalidRatio { symbol, ratio });
+        }
+
+        if conf.floating && f as usize != floating as LayoutFunc as usize {
+            warn!(%symbol, "layout is floating so its layout function will never be called");
+        }
+
+        if conf.floating && conf.follow_focus {
+            warn!(%symbol, "follow_focus has no effect for a floating layout");
+        }
+
+        Ok(Self {
+            symbol,
             conf,
             max_main,
             ratio,
             f: Some(f),
-        }
+        })
     }
 
-    /// A default floating layout that will not attempt to manage windows
-    pub fn floating(symbol: impl Into<String>) -> Self {
-        Self {
+    /// Begin constructing a new Layout using a [LayoutBuilder].
+    ///
+    /// # Example
+    /// ```
+    /// use penrose::core::layout::{monocle, Layout, MainSide};
+    ///
+    /// let layout = Layout::builder("[mono]", monocle)
+    ///     .gapless(true)
+    ///     .main_side(MainSide::Right)
+    ///     .ratio(0.5)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(layout.ratio(), 0.5);
+    /// ```
+    pub fn builder(symbol: impl Into<String>, f: LayoutFunc) -> LayoutBuilder {
+        LayoutBuilder {
             symbol: symbol.into(),
-            conf: LayoutConf {
-                floating: true,
-                gapless: false,
-                follow_focus: false,
-                allow_wrapping: true,
-            },
-            f: Some(floating),
+            conf: LayoutConf::default(),
+            f,
             max_main: 1,
-            ratio: 1.0,
+            ratio: 0.6,
         }
     }
 
+    /// A default floating layout that will not attempt to manage windows
+    pub fn floating(symbol: impl Into<String>) -> Self {
+        let conf = LayoutConf {
+            floating: true,
+            gapless: false,
+            follow_focus: false,
+            allow_wrapping: true,
+            main_side: MainSide::Left,
+        };
+
+        Self::new(symbol, conf, floating, 1, 1.0)
+    }
+
     // NOTE: Used when rehydrating from serde based deserialization. The layout will panic if
     //       used before setting the LayoutFunc. See [WindowManager::hydrate_and_init]
     #[cfg(feature = "serde")]
@@ -192,14 +366,72 @@ impl Layout {
         self.f = Some(f);
     }
 
-    /// Apply the layout function held by this `Layout` using the current max_main and ratio
+    /// Apply the layout function held by this `Layout` using the current max_main and ratio,
+    /// placing the main area on the side of the screen specified in the [LayoutConf].
     pub fn arrange(
         &self,
         clients: &[&Client],
         focused: Option<Xid>,
         r: &Region,
     ) -> Vec<ResizeAction> {
-        (self.f.expect("missing layout function"))(clients, focused, r, self.max_main, self.ratio)
+        let actions = (self.f.expect("missing layout function"))(
+            clients,
+            focused,
+            r,
+            self.max_main,
+            self.ratio,
+        );
+
+        match self.conf.main_side {
+            MainSide::Left => actions,
+            side => actions
+                .into_iter()
+                .map(|(id, reg)| (id, reg.map(|reg| side.transform(&reg, r))))
+                .collect(),
+        }
+    }
+
+    /// The current maximum number of clients in the main area
+    pub fn max_main(&self) -> u32 {
+        self.max_main
+    }
+
+    /// The current ratio of the main area to the secondary area
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    /// Reset max_main and ratio to the values held by `defaults`
+    pub fn reset_to(&mut self, defaults: &Layout) {
+        self.max_main = defaults.max_main;
+        self.ratio = defaults.ratio;
+    }
+
+    /// Set the maximum number of clients in the main area
+    pub fn set_max_main(&mut self, max_main: u32) {
+        self.max_main = max_main;
+    }
+
+    /// Set the ratio of the main area to the secondary area (clamped to between 0.0 and 1.0)
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.clamp(0.0, 1.0);
+    }
+
+    /// Increase/decrease the number of clients in the main area by 1, keeping it within the range
+    /// `1..=upper`. Changes that would move past either end of that range are handled according
+    /// to `overflow`.
+    pub fn update_max_main_bounded(&mut self, change: Change, upper: u32, overflow: Overflow) {
+        let upper = upper.max(1);
+        let current = self.max_main.clamp(1, upper);
+
+        self.max_main = match (change, overflow) {
+            (Change::More, _) if current < upper => current + 1,
+            (Change::More, Overflow::Clamp) => upper,
+            (Change::More, Overflow::Wrap) => 1,
+            (Change::Less, _) if current > 1 => current - 1,
+            (Change::Less, Overflow::Clamp) => 1,
+            (Change::Less, Overflow::Wrap) => upper,
+        };
     }
 
     /// Increase/decrease the number of clients in the main area by 1
@@ -230,6 +462,85 @@ impl Layout {
     }
 }
 
+/// A builder for [Layout]s that starts from [LayoutConf::default], a `max_main` of 1 and a
+/// `ratio` of 0.6.
+///
+/// See [Layout::builder] for details.
+#[derive(Clone)]
+pub struct LayoutBuilder {
+    symbol: String,
+    conf: LayoutConf,
+    f: LayoutFunc,
+    max_main: u32,
+    ratio: f32,
+}
+
+impl fmt::Debug for LayoutBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayoutBuilder")
+            .field("symbol", &self.symbol)
+            .field("conf", &self.conf)
+            .field("max_main", &self.max_main)
+            .field("ratio", &self.ratio)
+            .finish()
+    }
+}
+
+impl LayoutBuilder {
+    /// Replace the full [LayoutConf] for this layout
+    pub fn conf(mut self, conf: LayoutConf) -> Self {
+        self.conf = conf;
+        self
+    }
+
+    /// Set [LayoutConf::floating]
+    pub fn floating(mut self, floating: bool) -> Self {
+        self.conf.floating = floating;
+        self
+    }
+
+    /// Set [LayoutConf::gapless]
+    pub fn gapless(mut self, gapless: bool) -> Self {
+        self.conf.gapless = gapless;
+        self
+    }
+
+    /// Set [LayoutConf::follow_focus]
+    pub fn follow_focus(mut self, follow_focus: bool) -> Self {
+        self.conf.follow_focus = follow_focus;
+        self
+    }
+
+    /// Set [LayoutConf::allow_wrapping]
+    pub fn allow_wrapping(mut self, allow_wrapping: bool) -> Self {
+        self.conf.allow_wrapping = allow_wrapping;
+        self
+    }
+
+    /// Set [LayoutConf::main_side]
+    pub fn main_side(mut self, main_side: MainSide) -> Self {
+        self.conf.main_side = main_side;
+        self
+    }
+
+    /// Set the initial maximum number of clients in the main area
+    pub fn max_main(mut self, max_main: u32) -> Self {
+        self.max_main = max_main;
+        self
+    }
+
+    /// Set the initial ratio of the main area to the secondary area
+    pub fn ratio(mut self, ratio: f32) -> Self {
+        self.ratio = ratio;
+        self
+    }
+
+    /// Validate and construct the [Layout]. See [Layout::try_new] for the checks that are run.
+    pub fn build(self) -> std::result::Result<Layout, LayoutError> {
+        Layout::try_new(self.symbol, self.conf, self.f, self.max_main, self.ratio)
+    }
+}
+
 /*
  * Utility functions for simplifying writing layouts
  */
@@ -362,3 +673,185 @@ pub fn monocle(
         Vec::new()
     }
 }
+
+/// A layout that places clients in a grid of equally sized regions, filling each row from left
+/// to right. If the number of clients is not a square number then the clients in the final row
+/// share its width between them.
+pub fn grid(
+    clients: &[&Client],
+    _: Option<Xid>,
+    monitor_region: &Region,
+    _: u32,
+    _: f32,
+) -> Vec<ResizeAction> {
+    let n = clients.len();
+    if n == 0 {
+        return vec![];
+    }
+
+    let n_cols = (n as f64).sqrt().ceil() as usize;
+    let n_rows = n.div_ceil(n_cols);
+
+    monitor_region
+        .as_rows(n_rows as u32)
+        .iter()
+        .enumerate()
+        .flat_map(|(i, row)| row.as_columns((n - i * n_cols).min(n_cols) as u32))
+        .zip(clients)
+        .map(|(r, c)| (c.id(), Some(r)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::xconnection::MockXConn;
+
+    fn arranged(side: MainSide, f: LayoutFunc, n: u32) -> Vec<Region> {
+        let conn = MockXConn::new(vec![], vec![], vec![]);
+        let clients: Vec<Client> = (0..n).map(|id| Client::new(&conn, id, 0, &[])).collect();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let conf = LayoutConf {
+            main_side: side,
+            ..Default::default()
+        };
+
+        Layout::new("t", conf, f, 1, 0.6)
+            .arrange(&refs, None, &Region::new(0, 0, 1000, 600))
+            .into_iter()
+            .flat_map(|(_, r)| r)
+            .collect()
+    }
+
+    test_cases! {
+        main_side;
+        args: (side: MainSide, expected: Vec<Region>);
+
+        case: left => (MainSide::Left, vec![
+            Region::new(0, 0, 600, 600),
+            Region::new(600, 0, 400, 300),
+            Region::new(600, 300, 400, 300),
+        ]);
+        case: right => (MainSide::Right, vec![
+            Region::new(400, 0, 600, 600),
+            Region::new(0, 0, 400, 300),
+            Region::new(0, 300, 400, 300),
+        ]);
+        case: top => (MainSide::Top, vec![
+            Region::new(0, 0, 1000, 360),
+            Region::new(0, 360, 500, 240),
+            Region::new(500, 360, 500, 240),
+        ]);
+        case: bottom => (MainSide::Bottom, vec![
+            Region::new(0, 240, 1000, 360),
+            Region::new(0, 0, 500, 240),
+            Region::new(500, 0, 500, 240),
+        ]);
+
+        body: {
+            assert_eq!(arranged(side, side_stack, 3), expected);
+        }
+    }
+
+    test_cases! {
+        update_max_main_bounded;
+        args: (start: u32, change: Change, upper: u32, overflow: Overflow, expected: u32);
+
+        case: more_within_bounds => (1, Change::More, 3, Overflow::Clamp, 2);
+        case: less_within_bounds => (2, Change::Less, 3, Overflow::Clamp, 1);
+        case: more_clamps_at_upper => (3, Change::More, 3, Overflow::Clamp, 3);
+        case: less_clamps_at_one => (1, Change::Less, 3, Overflow::Clamp, 1);
+        case: more_wraps_to_one => (3, Change::More, 3, Overflow::Wrap, 1);
+        case: less_wraps_to_upper => (1, Change::Less, 3, Overflow::Wrap, 3);
+        case: zero_is_raised_to_one => (0, Change::Less, 3, Overflow::Clamp, 1);
+        case: above_upper_is_lowered => (5, Change::Less, 3, Overflow::Clamp, 2);
+        case: zero_upper_treated_as_one => (1, Change::More, 0, Overflow::Clamp, 1);
+
+        body: {
+            let mut l = Layout::new("t", LayoutConf::default(), side_stack, start, 0.6);
+            l.update_max_main_bounded(change, upper, overflow);
+            assert_eq!(l.max_main(), expected);
+        }
+    }
+
+    test_cases! {
+        grid;
+        args: (n: u32, expected: Vec<Region>);
+
+        case: none => (0, vec![]);
+        case: single => (1, vec![Region::new(0, 0, 1000, 600)]);
+        case: square => (4, vec![
+            Region::new(0, 0, 500, 300),
+            Region::new(500, 0, 500, 300),
+            Region::new(0, 300, 500, 300),
+            Region::new(500, 300, 500, 300),
+        ]);
+        case: partial_last_row => (3, vec![
+            Region::new(0, 0, 500, 300),
+            Region::new(500, 0, 500, 300),
+            Region::new(0, 300, 1000, 300),
+        ]);
+
+        body: {
+            assert_eq!(arranged(MainSide::Left, grid, n), expected);
+        }
+    }
+
+    test_cases! {
+        try_new;
+        args: (symbol: &'static str, ratio: f32, expected: std::result::Result<(), LayoutError>);
+
+        case: valid => ("[side]", 0.6, Ok(()));
+        case: empty_symbol => ("", 0.6, Err(LayoutError::EmptySymbol));
+        case: whitespace_symbol => ("  ", 0.6, Err(LayoutError::EmptySymbol));
+        case: ratio_too_large => ("[side]", 1.5, Err(LayoutError::InvalidRatio {
+            symbol: "[side]".into(), ratio: 1.5
+        }));
+        case: negative_ratio => ("[side]", -0.1, Err(LayoutError::InvalidRatio {
+            symbol: "[side]".into(), ratio: -0.1
+        }));
+
+        body: {
+            let res = Layout::try_new(symbol, LayoutConf::default(), side_stack, 1, ratio);
+            assert_eq!(res.map(|_| ()), expected);
+        }
+    }
+
+    #[test]
+    fn new_clamps_invalid_ratios_instead_of_panicking() {
+        let l = Layout::new("[side]", LayoutConf::default(), side_stack, 1, 1.5);
+
+        assert_eq!(l.ratio(), 1.0);
+    }
+
+    #[test]
+    fn builder_sets_conf_options() {
+        let l = Layout::builder("[botm]", bottom_stack)
+            .gapless(true)
+            .follow_focus(true)
+            .allow_wrapping(false)
+            .main_side(MainSide::Top)
+            .max_main(2)
+            .ratio(0.4)
+            .build()
+            .unwrap();
+
+        let conf = LayoutConf {
+            floating: false,
+            gapless: true,
+            follow_focus: true,
+            allow_wrapping: false,
+            main_side: MainSide::Top,
+        };
+
+        assert_eq!(l, Layout::new("[botm]", conf, bottom_stack, 2, 0.4));
+    }
+
+    #[test]
+    fn builder_rejects_invalid_layouts() {
+        assert_eq!(
+            Layout::builder("", side_stack).build(),
+            Err(LayoutError::EmptySymbol)
+        );
+    }
+}
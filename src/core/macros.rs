@@ -4,6 +4,9 @@
 ///
 /// NOTE: this explicitly redirects stderr to /dev/null
 ///
+/// The command is run using [WindowManager::spawn][1] so it is subject to any spawn rate limits
+/// set in the user config.
+///
 /// ```no_run
 /// # #[macro_use] extern crate penrose;
 /// # use penrose::__test_helpers::*;
@@ -12,12 +15,13 @@
 /// run_external!("dmenu_run")
 /// # )}
 /// ```
+///
+/// [1]: crate::core::manager::WindowManager::spawn
 #[macro_export]
 macro_rules! run_external {
     ($cmd:tt) => {{
-        Box::new(move |_: &mut $crate::core::manager::WindowManager<_>| {
-            $crate::core::helpers::spawn($cmd)
-        }) as $crate::core::bindings::KeyEventHandler<_>
+        Box::new(move |wm: &mut $crate::core::manager::WindowManager<_>| wm.spawn($cmd))
+            as $crate::core::bindings::KeyEventHandler<_>
     }};
 }
 
@@ -156,6 +160,10 @@ macro_rules! map {
 /// perform common actions like spawning external programs or triggering methods on the
 /// [WindowManager][2].
 ///
+/// Every binding is listed by [key_binding_descriptions][3] once it has been passed to
+/// [grab_keys_and_run][4]. Use [describe_key_binding][5] before calling `grab_keys_and_run` to
+/// give a binding a human readable description or see [Descriptions](#descriptions) below.
+///
 /// ### Map block
 ///
 /// Bind a common pattern via a template.
@@ -191,19 +199,60 @@ macro_rules! map {
 /// the [WindowManager][2] method can be passed by reference using `REF` or by value using `VAL`.
 /// Any additional arguments can be passed explicitly if they are required by the method.
 ///
+/// ### Descriptions
+///
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::__test_helpers::*;
+/// # use penrose::core::bindings::KeyBindingDescriptions;
+/// # fn example(wm: &mut TestWM) -> TestKeyBindings {
+/// let mut descriptions = KeyBindingDescriptions::new();
+/// let key_bindings = gen_keybindings! {
+///     descriptions: descriptions;
+///
+///     "M-j" => run_internal!(cycle_client, Forward), "focus the next client";
+///     "M-Return" => run_external!("alacritty");
+///
+///     map: { "1", "2", "3" } to index_selectors(3) => {
+///         "M-{}" => focus_workspace (REF), "focus workspace {}";
+///     };
+/// };
+/// wm.describe_key_bindings(descriptions);
+/// # key_bindings }
+/// ```
+///
+/// Bindings can be given a human readable description after the action by first naming a
+/// [KeyBindingDescriptions][6] map to collect them in. Descriptions in a `map` block have `{}`
+/// replaced with the key name in the same way as the binding itself. The collected descriptions
+/// can then be passed to [describe_key_bindings][7] so that they are listed alongside their
+/// bindings by [key_binding_descriptions][3].
+///
 /// [1]: crate::core::bindings::KeyEventHandler
 /// [2]: crate::core::manager::WindowManager
+/// [3]: crate::core::manager::WindowManager::key_binding_descriptions
+/// [4]: crate::core::manager::WindowManager::grab_keys_and_run
+/// [5]: crate::core::manager::WindowManager::describe_key_binding
+/// [6]: crate::core::bindings::KeyBindingDescriptions
+/// [7]: crate::core::manager::WindowManager::describe_key_bindings
 #[macro_export]
 macro_rules! gen_keybindings {
-    { $($tokens:tt)* } => {
+    { descriptions: $descriptions:expr; $($tokens:tt)* } => {
         {
             let mut map = ::std::collections::HashMap::new();
             let codes = $crate::core::helpers::keycodes_from_xmodmap();
             let parse = $crate::xcb::helpers::parse_key_binding;
-            __private!(@parsekey map, codes, parse, [], [], $($tokens)*);
+            __private!(@parsekey map, $descriptions, codes, parse, [], [], $($tokens)*);
             map
         }
     };
+
+    { $($tokens:tt)* } => {
+        {
+            #[allow(unused_mut)]
+            let mut _descriptions = $crate::core::bindings::KeyBindingDescriptions::new();
+            gen_keybindings! { descriptions: _descriptions; $($tokens)* }
+        }
+    };
 }
 
 /// Make creating all of the mouse bindings less verbose
@@ -459,10 +508,10 @@ macro_rules! __private {
      *  @parsekey :: handle each of the valid cases in an invocation of gen_keybindings
      */
 
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $descriptions:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         map: { $($str:expr),+ } to $to:expr => {
-            $( $binding:expr => $method:ident ( $($params:tt)* ); )+
+            $( $binding:expr => $method:ident ( $($params:tt)* ) $(, $description:expr)?; )+
         };
         $($tail:tt)*
     } => {
@@ -473,18 +522,29 @@ macro_rules! __private {
                     let binding = format!($binding, name);
                     match $parse(binding.clone(), &$codes) {
                         None => panic!("invalid key binding: {}", binding),
-                        Some(key_code) => $map.insert(
-                            key_code,
-                            run_internal!(
-                                $method,
-                                __private!(@parsemapparams arg; []; $($params,)*)
+                        Some(key_code) => {
+                            $(
+                                $descriptions.insert(
+                                    key_code,
+                                    $crate::core::bindings::KeyBindingDescription {
+                                        spec: binding.clone(),
+                                        description: Some(format!($description, name)),
+                                    },
+                                );
+                            )?
+                            $map.insert(
+                                key_code,
+                                run_internal!(
+                                    $method,
+                                    __private!(@parsemapparams arg; []; $($params,)*)
+                                )
                             )
-                        ),
+                        }
                     };
                 }
             )+
 
-            __private!(@parsekey $map, $codes, $parse,
+            __private!(@parsekey $map, $descriptions, $codes, $parse,
                 [ $($patt,)* ], [ $(($($template),+; $($name),+),)* ($($binding),+; $($str),+) ],
                 $($tail)*
             );
@@ -492,23 +552,34 @@ macro_rules! __private {
     };
 
     // parse a single simple key binding (validated if $validate is true)
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $descriptions:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
-        $binding:expr => $action:expr;
+        $binding:expr => $action:expr $(, $description:expr)?;
         $($tail:tt)*
     } => {
         match $parse($binding.to_string(), &$codes) {
             None => panic!("invalid key binding: {}", $binding),
-            Some(key_code) => $map.insert(key_code, $action),
+            Some(key_code) => {
+                $(
+                    $descriptions.insert(
+                        key_code,
+                        $crate::core::bindings::KeyBindingDescription {
+                            spec: $binding.to_string(),
+                            description: Some($description.to_string()),
+                        },
+                    );
+                )?
+                $map.insert(key_code, $action)
+            }
         };
-        __private!(@parsekey $map, $codes, $parse,
+        __private!(@parsekey $map, $descriptions, $codes, $parse,
             [ $binding, $($patt,)* ], [ $(($($template),+; $($name),+)),* ],
             $($tail)*
         );
     };
 
     // TODO: remove this target in 0.2.2
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $descriptions:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         $(refmap)? $(map)? [ $from:expr ] in { $($binding:expr => $method:ident [ $to:expr ];)+ };
         $($tail:tt)*
@@ -521,7 +592,7 @@ macro_rules! __private {
     };
 
     // base case (should be out of tokens)
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $descriptions:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         $($tail:tt)*
     } => {
@@ -24,6 +24,8 @@ use tracing::{trace, warn};
 pub(super) struct Clients {
     inner: HashMap<Xid, Client>,
     focused_client_id: Option<Xid>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    minimized: Vec<Xid>,
     focused_border: Color,
     unfocused_border: Color,
 }
@@ -33,6 +35,7 @@ impl Clients {
         Self {
             inner: HashMap::new(),
             focused_client_id: None,
+            minimized: Vec::new(),
             focused_border: focused_border.into(),
             unfocused_border: unfocused_border.into(),
         }
@@ -61,6 +64,7 @@ impl Clients {
             Selector::WinId(id) => self.inner.get(&id),
             Selector::Condition(f) => self.inner.iter().find(|(_, v)| f(v)).map(|(_, v)| v),
             Selector::Index(i) => self.inner.iter().nth(*i).map(|(_, c)| c),
+            Selector::FromFocused(_) => None, // clients have no ordering of their own
         }
     }
 
@@ -70,6 +74,7 @@ impl Clients {
             Selector::WinId(id) => self.inner.get_mut(&id),
             Selector::Condition(f) => self.inner.iter_mut().find(|(_, v)| f(v)).map(|(_, v)| v),
             Selector::Index(i) => self.inner.iter_mut().nth(*i).map(|(_, c)| c),
+            Selector::FromFocused(_) => None, // clients have no ordering of their own
         }
     }
 
@@ -129,9 +134,33 @@ impl Clients {
             self.focused_client_id = None;
         }
 
+        self.minimized.retain(|&m| m != id);
         self.inner.remove(&id)
     }
 
+    pub fn is_minimized(&self, id: Xid) -> bool {
+        self.inner.get(&id).map(|c| c.minimized).unwrap_or(false)
+    }
+
+    // Minimized client IDs, most recently minimized first
+    pub fn minimized_ids(&self) -> Vec<Xid> {
+        self.minimized.iter().rev().copied().collect()
+    }
+
+    pub fn set_minimized(&mut self, id: Xid, minimized: bool) {
+        if let Some(c) = self.inner.get_mut(&id) {
+            c.minimized = minimized;
+            self.minimized.retain(|&m| m != id);
+
+            if minimized {
+                self.minimized.push(id);
+                if self.focused_client_id == Some(id) {
+                    self.focused_client_id = None;
+                }
+            }
+        }
+    }
+
     pub fn get(&self, id: Xid) -> Option<&Client> {
         self.inner.get(&id)
     }
@@ -178,6 +207,15 @@ impl Clients {
         self.inner.keys().copied().collect()
     }
 
+    // The clients that should be listed in _NET_CLIENT_LIST
+    pub fn client_list_ids(&self, hide_skipped: bool) -> Vec<Xid> {
+        self.inner
+            .values()
+            .filter(|c| !(hide_skipped && (c.skip_taskbar || c.skip_pager)))
+            .map(|c| c.id)
+            .collect()
+    }
+
     pub fn modify(&mut self, id: Xid, f: impl Fn(&mut Client)) {
         self.inner.entry(id).and_modify(f);
     }
@@ -270,7 +308,7 @@ impl Clients {
         border_px: u32,
         gap_px: u32,
         conn: &X,
-    ) -> Result<()>
+    ) -> Result<Vec<EventAction>>
     where
         X: XClientHandler + XClientConfig,
     {
@@ -286,12 +324,14 @@ impl Clients {
             }
         }
 
-        for id in actions.floating {
-            debug!(id, "mapping floating client above tiled");
-            conn.raise_client(id)?;
-        }
-
-        Ok(())
+        Ok(actions
+            .floating
+            .into_iter()
+            .map(|id| {
+                debug!(id, "mapping floating client above tiled");
+                EventAction::RaiseClient(id)
+            })
+            .collect())
     }
 
     pub fn toggle_fullscreen<X>(
@@ -439,6 +479,7 @@ mod tests {
                 })
                 .collect(),
                 focused_client_id: None,
+                minimized: vec![],
                 focused_border: 0xffffff.into(),
                 unfocused_border: 0x000000.into(),
             };
@@ -6,8 +6,8 @@ use crate::core::{
     hooks::HookName,
     manager::state::WmState,
     xconnection::{
-        Atom, ClientMessage, ConfigureEvent, PointerChange, PropertyEvent, XAtomQuerier, XEvent,
-        Xid,
+        Atom, ClientMessage, ConfigureEvent, PointerChange, PropertyEvent, RandrChange, StackMode,
+        WmStateAction, XAtomQuerier, XEvent, Xid,
     },
 };
 
@@ -27,8 +27,8 @@ use std::str::FromStr;
 pub enum EventAction {
     /// An X window lost focus
     ClientFocusLost(Xid),
-    /// An X window gained focus
-    ClientFocusGained(Xid),
+    /// The pointer entered an X window at the given absolute position
+    ClientEntered(Xid, Point),
     /// An X window had its WM_NAME or _NET_WM_NAME property changed
     ClientNameChanged(Xid, bool),
     /// Move the given client to the workspace at the given index
@@ -37,6 +37,10 @@ pub enum EventAction {
     DestroyClient(Xid),
     /// Screens should be redetected
     DetectScreens,
+    /// An X window had its _NET_WM_STATE property changed
+    ClientWmStateChanged(Xid),
+    /// An X window had its WM_NORMAL_HINTS property changed
+    ClientNormalHintsChanged(Xid),
     /// A client should have focus
     FocusIn(Xid),
     /// The workspace on each screen should be layed out again
@@ -47,6 +51,12 @@ pub enum EventAction {
     MapWindow(Xid),
     /// A client is requesting to be moved: honoured if the client is floating
     MoveClientIfFloating(Xid, Region),
+    /// A client should be raised above all other windows
+    RaiseClient(Xid),
+    /// The keyboard mapping has changed and key codes need to be re-resolved
+    RefreshKeyCodes,
+    /// A client requested to be restacked relative to an optional sibling
+    RestackClientIfFloating(Xid, Option<Xid>, StackMode),
     /// The named hook should now be run
     RunHook(HookName),
     /// A grabbed keybinding was triggered
@@ -59,6 +69,16 @@ pub enum EventAction {
     SetActiveWorkspace(usize),
     /// The active screen should be set based on point location
     SetScreenFromPoint(Option<Point>),
+    /// An X window should be minimized (true) or restored (false)
+    SetClientMinimized(Xid, bool),
+    /// An X window requested to add (1), remove (0) or toggle (2) the given skip pager and
+    /// skip taskbar states
+    SetClientSkipState(Xid, Vec<Atom>, usize),
+    /// An X window requested a change to its above state, which is handled by putting it into
+    /// or taking it out of picture-in-picture mode
+    SetClientAbove(Xid, WmStateAction),
+    /// An X window requested that its _NET_FRAME_EXTENTS be set
+    SetFrameExtents(Xid),
     /// An X window should be set fullscreen
     ToggleClientFullScreen(Xid, bool),
     /// An unknown property was changed on an X window
@@ -81,8 +101,9 @@ where
             EventAction::ClientFocusLost(p.id),
             EventAction::SetScreenFromPoint(Some(p.abs)),
         ],
+        XEvent::MappingNotify => vec![EventAction::RefreshKeyCodes],
         XEvent::MouseEvent(evt) => vec![EventAction::RunMouseBinding(evt)],
-        XEvent::RandrNotify => vec![EventAction::DetectScreens],
+        XEvent::RandrNotify(change) => process_randr_notify(change),
         XEvent::ScreenChange => vec![EventAction::SetScreenFromPoint(None)],
         XEvent::UnmapNotify(id) => vec![EventAction::Unmap(id)],
 
@@ -98,30 +119,67 @@ where
     }
 }
 
-fn process_client_message<X>(_state: &WmState, conn: &X, msg: ClientMessage) -> Vec<EventAction>
+fn process_client_message<X>(state: &WmState, conn: &X, msg: ClientMessage) -> Vec<EventAction>
 where
     X: XAtomQuerier,
 {
     let data = msg.data();
     trace!(id = msg.id, dtype = ?msg.dtype, ?data, "got client message");
 
-    let is_fullscreen = |data: &[u32]| {
+    let has_state = |data: &[u32], atom: Atom| {
         data.iter()
             .map(|&a| conn.atom_name(a))
             .flatten()
-            .any(|s| s == Atom::NetWmStateFullscreen.as_ref())
+            .any(|s| s == atom.as_ref())
+    };
+    let is_fullscreen = |data: &[u32]| has_state(data, Atom::NetWmStateFullscreen);
+    let is_hidden = |data: &[u32]| has_state(data, Atom::NetWmStateHidden);
+    let is_above = |data: &[u32]| has_state(data, Atom::NetWmStateAbove);
+    let skip_states = |data: &[u32]| -> Vec<Atom> {
+        [Atom::NetWmStateSkipPager, Atom::NetWmStateSkipTaskbar]
+            .iter()
+            .copied()
+            .filter(|&a| has_state(data, a))
+            .collect()
     };
 
     match Atom::from_str(&msg.dtype) {
         Ok(Atom::NetActiveWindow) => vec![EventAction::SetActiveClient(msg.id)],
         Ok(Atom::NetCurrentDesktop) => vec![EventAction::SetActiveWorkspace(data.as_usize()[0])],
         Ok(Atom::NetWmDesktop) => vec![EventAction::ClientToWorkspace(msg.id, data.as_usize()[0])],
+        Ok(Atom::NetRequestFrameExtents) => vec![EventAction::SetFrameExtents(msg.id)],
         Ok(Atom::NetWmState) if is_fullscreen(&data.as_u32()[1..3]) => {
-            // _NET_WM_STATE_ADD == 1, _NET_WM_STATE_TOGGLE == 2
-            let should_fullscreen = [1, 2].contains(&data.as_usize()[0]);
-            vec![EventAction::ToggleClientFullScreen(
+            let currently_fullscreen = matches!(state.clients.get(msg.id), Some(c) if c.fullscreen);
+            match WmStateAction::from_raw(data.as_u32()[0]) {
+                Some(action) => vec![EventAction::ToggleClientFullScreen(
+                    msg.id,
+                    action.apply(currently_fullscreen),
+                )],
+                None => vec![],
+            }
+        }
+        Ok(Atom::NetWmState) if is_hidden(&data.as_u32()[1..3]) => {
+            let currently_minimized = state.clients.is_minimized(msg.id);
+            match WmStateAction::from_raw(data.as_u32()[0]) {
+                Some(action) => vec![EventAction::SetClientMinimized(
+                    msg.id,
+                    action.apply(currently_minimized),
+                )],
+                None => vec![],
+            }
+        }
+        Ok(Atom::NetWmState) if is_above(&data.as_u32()[1..3]) => {
+            match WmStateAction::from_raw(data.as_u32()[0]) {
+                Some(action) => vec![EventAction::SetClientAbove(msg.id, action)],
+                None => vec![],
+            }
+        }
+        Ok(Atom::NetWmState) if !skip_states(&data.as_u32()[1..3]).is_empty() => {
+            let states = skip_states(&data.as_u32()[1..3]);
+            vec![EventAction::SetClientSkipState(
                 msg.id,
-                should_fullscreen,
+                states,
+                data.as_usize()[0],
             )]
         }
 
@@ -137,29 +195,45 @@ fn process_configure_notify(evt: ConfigureEvent) -> Vec<EventAction> {
     }
 }
 
-fn process_configure_request(evt: ConfigureEvent) -> Vec<EventAction> {
-    if !evt.is_root {
-        vec![EventAction::MoveClientIfFloating(evt.id, evt.r)]
-    } else {
-        vec![]
+fn process_randr_notify(change: RandrChange) -> Vec<EventAction> {
+    let detect = change.affects_screens();
+    let mut actions = vec![EventAction::RunHook(HookName::RandrChange(change))];
+    if detect {
+        actions.push(EventAction::DetectScreens);
     }
+
+    actions
 }
 
-fn process_enter_notify(state: &WmState, p: PointerChange) -> Vec<EventAction> {
-    let mut actions = vec![
-        EventAction::ClientFocusGained(p.id),
-        EventAction::SetScreenFromPoint(Some(p.abs)),
-    ];
+// Requested border widths are deliberately ignored: borders are owned by penrose (see
+// Config::border_px and WindowManager::set_border_width) so clients are kept consistent with the
+// rest of their workspace.
+fn process_configure_request(evt: ConfigureEvent) -> Vec<EventAction> {
+    if evt.is_root {
+        return vec![];
+    }
 
-    if let Some(current) = state.clients.focused_client_id() {
-        if current != p.id {
-            actions.insert(0, EventAction::ClientFocusLost(current));
-        }
+    let mut actions = vec![EventAction::MoveClientIfFloating(evt.id, evt.r)];
+    if let Some(mode) = evt.stack_mode {
+        actions.push(EventAction::RestackClientIfFloating(
+            evt.id,
+            evt.sibling,
+            mode,
+        ));
     }
 
     actions
 }
 
+// The client to focus is resolved by the WindowManager as it depends on the
+// floating_focus_precedence config setting.
+fn process_enter_notify(_: &WmState, p: PointerChange) -> Vec<EventAction> {
+    vec![
+        EventAction::ClientEntered(p.id, p.abs),
+        EventAction::SetScreenFromPoint(Some(p.abs)),
+    ]
+}
+
 // Processing around map_request is currently copied from dwm:
 //   - if override_redirect is set we completely ignore the window
 //   - if the client is in the client_map (i.e. we are already managing this client) then ignore
@@ -176,6 +250,10 @@ fn process_property_notify(evt: PropertyEvent) -> Vec<EventAction> {
         Ok(a) if a == Atom::WmName || a == Atom::NetWmName => {
             vec![EventAction::ClientNameChanged(evt.id, evt.is_root)]
         }
+        Ok(Atom::NetWmState) if !evt.is_root => vec![EventAction::ClientWmStateChanged(evt.id)],
+        Ok(Atom::WmNormalHints) if !evt.is_root => {
+            vec![EventAction::ClientNormalHintsChanged(evt.id)]
+        }
         // TODO: handle other property changes and possibly allow users to process
         //       unknown events?
         _ => vec![EventAction::UnknownPropertyChange(
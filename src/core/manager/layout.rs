@@ -1,70 +1,201 @@
 use crate::{
     core::{
-        config::Config,
+        data_types::Region,
         hooks::HookName,
-        manager::{event::EventAction, state::WmState, util::pad_region},
-        xconnection::{XClientConfig, XClientHandler},
+        manager::{
+            clients::Clients,
+            event::EventAction,
+            state::WmState,
+            util::{
+                client_border_px, drop_edge_gaps, pad_region, scaled_border_and_gap, shared_edges,
+                stack_undersized,
+            },
+        },
+        screen::Screen,
+        xconnection::{XClientConfig, XClientHandler, Xid},
     },
     Result,
 };
 
 #[tracing::instrument(level = "trace", err, skip(conn))]
-pub(super) fn layout_visible<X>(state: &mut WmState, conn: &X) -> Result<Vec<EventAction>>
+pub(super) fn layout_visible<X>(
+    state: &mut WmState,
+    conn: &X,
+    raise_last: &[Xid],
+    skip: Option<usize>,
+) -> Result<Vec<EventAction>>
 where
     X: XClientHandler + XClientConfig,
 {
-    state
-        .screens
-        .visible_workspaces()
-        .into_iter()
-        .flat_map(|wix| apply_layout(state, conn, wix).transpose())
-        .collect()
+    let mut actions = vec![];
+    for wix in state.screens.visible_workspaces() {
+        if Some(wix) == skip {
+            continue;
+        }
+        actions.append(&mut apply_layout(state, conn, wix, raise_last)?);
+    }
+
+    Ok(actions)
+}
+
+// The region of a screen that is available to the layout of the workspace it is showing
+fn layout_region(show_bar: bool, s: &Screen) -> Region {
+    pad_region(&s.usable_region(show_bar), false, s.padding(), 0)
+}
+
+// The regions that the current layout of a visible workspace assigns to each of its tiled
+// clients, before gaps and borders are applied.
+pub(super) fn tiled_regions(state: &mut WmState, wix: usize) -> Result<Vec<(Xid, Region)>> {
+    let r = match state.screens.indexed_screen_for_workspace(wix) {
+        Some((_, s)) => layout_region(state.config.show_bar, s),
+        None => return Ok(vec![]),
+    };
+
+    let (_, aa) = state.workspaces.get_arrange_actions(
+        wix,
+        r,
+        &state
+            .clients
+            .clients_for_ids(&state.workspaces[wix].client_ids()),
+    )?;
+
+    let focused = state.workspaces[wix].focused_client();
+
+    Ok(
+        stack_undersized(aa.actions, focused, state.config.min_tile_size)
+            .into_iter()
+            .flat_map(|(id, region)| region.map(|r| (id, r)))
+            .collect(),
+    )
 }
 
+// Floating clients are raised above the tiled clients on the workspace, with any clients in
+// raise_last being raised above the other floating clients.
 #[tracing::instrument(level = "debug", err, skip(conn))]
 pub(super) fn apply_layout<X>(
     state: &mut WmState,
     conn: &X,
     wix: usize,
-) -> Result<Option<EventAction>>
+    raise_last: &[Xid],
+) -> Result<Vec<EventAction>>
 where
     X: XClientHandler + XClientConfig,
 {
     let (i, s) = match state.screens.indexed_screen_for_workspace(wix) {
         Some((i, s)) => (i, s),
-        None => return Ok(None),
+        None => return Ok(vec![]),
     };
+    let mut actions = vec![];
 
-    let Config {
-        show_bar,
-        border_px,
-        gap_px,
-        ..
-    } = state.config;
+    let (_, gap_px) = scaled_border_and_gap(&state.config, s);
+    let area = layout_region(state.config.show_bar, s);
+    let edges = if state.config.suppress_shared_edge_gaps {
+        let others: Vec<Region> = state
+            .screens
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, other)| other.region(false))
+            .collect();
+        shared_edges(&s.region(false), &others)
+    } else {
+        vec![]
+    };
 
     let (lc, aa) = state.workspaces.get_arrange_actions(
         wix,
-        s.region(show_bar),
+        area,
         &state
             .clients
             .clients_for_ids(&state.workspaces[wix].client_ids()),
     )?;
 
-    for (id, region) in aa.actions {
+    let focused = state.workspaces[wix].focused_client();
+    let resize_actions = stack_undersized(aa.actions, focused, state.config.min_tile_size);
+
+    // A lone tiled client on an auto-fullscreen workspace covers the whole screen with no
+    // border or gaps. Clients that have explicitly requested fullscreen are left alone so that
+    // dropping out of fullscreen returns them to their normal position.
+    let solo = match resize_actions.as_slice() {
+        [(id, Some(_))] if state.workspaces[wix].auto_fullscreen_single() => {
+            match state.clients.get(*id) {
+                Some(c) if state.workspaces[wix].len() == 1 && !c.fullscreen => Some(*id),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    if let Some(id) = solo {
+        let reg = s.region(false);
+        trace!(id, ?reg, "auto-fullscreening single client");
+        place_tiled_client(&mut state.clients, conn, id, reg, 0, &mut actions)?;
+        actions.push(EventAction::RaiseClient(id));
+        actions.push(EventAction::RunHook(HookName::LayoutApplied(wix, i)));
+
+        return Ok(actions);
+    }
+
+    for (id, region) in resize_actions {
         trace!(id, ?region, "positioning client");
         if let Some(region) = region {
-            let reg = pad_region(&region, lc.gapless, gap_px, border_px);
-            conn.position_client(id, reg, border_px, false)?;
-            state.clients.map_if_needed(id, conn)?;
+            let bpx = client_border_px(&state.config, s, state.clients.get(id));
+            let mut reg = pad_region(&region, lc.gapless, gap_px, bpx);
+            if !lc.gapless {
+                reg = drop_edge_gaps(reg, &region, &area, &edges, gap_px);
+            }
+            if let Some((w, h)) = state
+                .clients
+                .get(id)
+                .and_then(|c| c.constrained_aspect(&reg))
+            {
+                reg = reg.fit_aspect_ratio(w, h);
+            }
+            place_tiled_client(&mut state.clients, conn, id, reg, bpx, &mut actions)?;
         } else {
+            state.clients.modify(id, |c| c.tiled_region = None);
             state.clients.unmap_if_needed(id, conn)?;
         }
     }
 
-    for id in aa.floating {
+    let (last, first): (Vec<Xid>, Vec<Xid>) = aa
+        .floating
+        .into_iter()
+        .partition(|id| raise_last.contains(id));
+    for id in first.into_iter().chain(last) {
         debug!(id, "mapping floating client above tiled");
-        conn.raise_client(id)?;
+        actions.push(EventAction::RaiseClient(id));
+    }
+
+    actions.push(EventAction::RunHook(HookName::LayoutApplied(wix, i)));
+
+    Ok(actions)
+}
+
+// Move a tiled client into its assigned region, recording the region and reporting the change
+// in geometry if it has moved.
+fn place_tiled_client<X>(
+    clients: &mut Clients,
+    conn: &X,
+    id: Xid,
+    reg: Region,
+    bpx: u32,
+    actions: &mut Vec<EventAction>,
+) -> Result<()>
+where
+    X: XClientHandler + XClientConfig,
+{
+    conn.position_client(id, reg, bpx, false)?;
+    conn.send_configure_notify(id, reg, bpx)?;
+    clients.map_if_needed(id, conn)?;
+
+    let from = clients.get(id).and_then(|c| c.tiled_region);
+    clients.modify(id, |c| c.tiled_region = Some(reg));
+    if let Some(from) = from.filter(|&from| from != reg) {
+        actions.push(EventAction::RunHook(HookName::ClientGeometryChanged(
+            id, from, reg,
+        )));
     }
 
-    Ok(Some(EventAction::RunHook(HookName::LayoutApplied(wix, i))))
+    Ok(())
 }
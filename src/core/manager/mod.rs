@@ -1,27 +1,54 @@
 //! The main user API and control logic for Penrose.
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, MouseBindings, MouseEvent},
-        client::Client,
+        bindings::{
+            key_binding_spec, parse_key_binding, CodeMap, KeyBindingDescription,
+            KeyBindingDescriptions, KeyBindingMode, KeyBindings, KeyCode, KeyEventHandler,
+            KeyGrabReport, MouseBindings, MouseEvent, MouseEventKind,
+        },
+        client::{Client, Tag},
         config::Config,
-        data_types::{Change, Point, Region},
+        data_types::{
+            AspectRatio, Change, EmptiedWorkspacePolicy, ErrorPolicy, FloatLayout,
+            FloatLayoutEntry, FloatingFocusPrecedence, FocusNewPolicy, Point, Profile, Region,
+            RelativePosition, ResizeZone, RootBackground, TransientPolicy, VisibleWorkspacePolicy,
+            WorkspaceFocus,
+        },
+        helpers::{
+            new_startup_id, process_cwd, spawn, spawn_in_dir, spawn_with_env,
+            try_keycodes_from_xmodmap,
+        },
         hooks::{HookName, Hooks},
+        layout::grid,
         ring::{Direction, InsertPoint, Selector},
         screen::Screen,
-        workspace::Workspace,
-        xconnection::{Atom, ClientMessageKind, WindowState, XConn, Xid},
+        workspace::{Workspace, WorkspaceView},
+        xconnection::{
+            self, Atom, ClientAttr, ClientConfig, ClientEventMask, ClientMessage,
+            ClientMessageKind, Prop, StackMode, WindowState, XConn, XEvent, Xid,
+        },
     },
     ErrorHandler, PenroseError, Result,
 };
 use nix::sys::signal::{signal, SigHandler, Signal};
-use std::{cell::Cell, fmt};
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    env, fmt,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
 use tracing::Level;
 
-#[cfg(feature = "serde")]
-use crate::core::{helpers::logging_error_handler, layout::LayoutFunc};
+use crate::core::helpers::logging_error_handler;
 
 #[cfg(feature = "serde")]
-use std::collections::HashMap;
+use crate::core::layout::LayoutFunc;
+
+#[cfg(feature = "startup_notification")]
+use crate::core::startup::{StartupSequence, StartupTracker};
 
 mod clients;
 mod event;
@@ -34,16 +61,64 @@ mod workspaces;
 use clients::Clients;
 use event::process_next_event;
 use event::EventAction;
-use layout::{apply_layout, layout_visible};
+use layout::{apply_layout, layout_visible, tiled_regions};
 use screens::Screens;
 use state::WmState;
 use workspaces::Workspaces;
 
+// An in progress mouse driven move (zone is None) or resize of a floating client, along with the
+// lines it can be snapped to while moving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FloatingDrag {
+    id: Xid,
+    zone: Option<ResizeZone>,
+    start: Point,
+    region: Region,
+    xs: Vec<u32>,
+    ys: Vec<u32>,
+}
+
+// An in progress overview of a workspace, along with the positions of its floating clients from
+// before the overview was started so that they can be restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Overview {
+    wix: usize,
+    floating: Vec<(Xid, Region)>,
+}
+
+// The state of a client from before it was made picture-in-picture so that it can be restored.
+// Tiled clients keep their position in the workspace so only floating clients need a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Pip {
+    floating: bool,
+    region: Option<Region>,
+}
+
+/// A callback run with the ID of the first window mapped by a process started using
+/// [spawn_and_wait_for_window][WindowManager::spawn_and_wait_for_window].
+pub type SpawnCallback<X> = Box<dyn FnOnce(&mut WindowManager<X>, Xid) -> Result<()>>;
+
+// A spawned process that is waiting for its first window to be mapped
+pub(super) struct PendingSpawn<X: XConn> {
+    pid: u32,
+    startup_id: String,
+    deadline: Instant,
+    callback: SpawnCallback<X>,
+}
+
 #[cfg(feature = "serde")]
 fn default_hooks<X: XConn>() -> Cell<Hooks<X>> {
     Cell::new(Vec::new())
 }
 
+#[cfg(feature = "serde")]
+fn default_key_code_loader() -> fn() -> Result<CodeMap> {
+    try_keycodes_from_xmodmap
+}
+
+// The maximum number of debounce intervals to wait during a continuous burst of RandR events
+const RANDR_DEBOUNCE_MAX_INTERVALS: u32 = 10;
+
 /// WindowManager is the primary struct / owner of the event loop for penrose.
 ///
 /// It handles most (if not all) of the communication with the underlying [XConn], responding to
@@ -74,6 +149,63 @@ pub struct WindowManager<X: XConn> {
     pub(super) error_handler: ErrorHandler,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub(super) hydrated: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) key_codes: Option<CodeMap>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "Vec::new"))]
+    pub(super) pending_key_bindings: Vec<(KeyCode, Option<KeyEventHandler<X>>)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) grab_report: KeyGrabReport,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) key_names: HashMap<KeyCode, String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) key_descriptions: HashMap<KeyCode, KeyBindingDescription>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) bound_keys: HashSet<KeyCode>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_key_code_loader"))]
+    pub(super) load_key_codes: fn() -> Result<CodeMap>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) floating_drag: Option<FloatingDrag>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) overview: Option<Overview>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) pending_events: VecDeque<XEvent>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) settling: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "Vec::new"))]
+    pub(super) pending_spawns: Vec<PendingSpawn<X>>,
+    #[cfg(feature = "startup_notification")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) startups: StartupTracker,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) focus_history: Vec<Xid>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) global_cycle: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) cycling_globally: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) float_layouts: HashMap<String, FloatLayout>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) last_press: Option<MouseEvent>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) auto_spawned: HashSet<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) pointer_confined: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) stacking: Vec<Xid>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) last_spawn: Option<Instant>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) recent_spawns: HashMap<String, Instant>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) current_background: Option<RootBackground>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) pending_background: Option<(RootBackground, Instant)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) next_tick: Option<Instant>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cursor: Cell<Option<Point>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pip_clients: HashMap<Xid, Pip>,
 }
 
 impl<X: XConn> fmt::Debug for WindowManager<X> {
@@ -92,19 +224,26 @@ impl<X: XConn> WindowManager<X> {
     /// Construct a new window manager instance using a chosen [XConn] backed to communicate
     /// with the X server.
     pub fn new(config: Config, conn: X, hooks: Hooks<X>, error_handler: ErrorHandler) -> Self {
-        let layouts = config.layouts.clone();
-
         trace!("building initial workspaces");
         let workspaces = Workspaces::new(
             config
                 .workspaces
                 .iter()
-                .map(|name| Workspace::new(name, layouts.to_vec()))
+                .map(|name| {
+                    let mut ws = Workspace::new(name, config.layouts_for_workspace(name).to_vec());
+                    if config.hidden_bar_workspaces.contains(name) {
+                        ws.set_show_bar(Some(false));
+                    }
+                    if config.auto_fullscreen_workspaces.contains(name) {
+                        ws.set_auto_fullscreen_single(true);
+                    }
+                    ws
+                })
                 .collect(),
             config.main_ratio_step,
         );
 
-        let screens = Screens::new(config.bar_height, config.top_bar);
+        let screens = Screens::new(config.bar_height + config.bar_gap, config.top_bar);
         let clients = Clients::new(config.focused_border, config.unfocused_border);
 
         let state = WmState {
@@ -122,6 +261,35 @@ impl<X: XConn> WindowManager<X> {
             running: false,
             hydrated: true,
             error_handler,
+            key_codes: None,
+            pending_key_bindings: vec![],
+            grab_report: KeyGrabReport::default(),
+            key_names: HashMap::new(),
+            key_descriptions: HashMap::new(),
+            bound_keys: HashSet::new(),
+            load_key_codes: try_keycodes_from_xmodmap,
+            floating_drag: None,
+            overview: None,
+            pending_events: VecDeque::new(),
+            settling: None,
+            pending_spawns: vec![],
+            #[cfg(feature = "startup_notification")]
+            startups: StartupTracker::default(),
+            focus_history: vec![],
+            global_cycle: None,
+            cycling_globally: false,
+            float_layouts: HashMap::new(),
+            last_press: None,
+            auto_spawned: HashSet::new(),
+            pointer_confined: false,
+            stacking: vec![],
+            last_spawn: None,
+            recent_spawns: HashMap::new(),
+            current_background: None,
+            pending_background: None,
+            next_tick: None,
+            cursor: Cell::new(None),
+            pip_clients: HashMap::new(),
         }
     }
 
@@ -209,8 +377,61 @@ impl<X: XConn> WindowManager<X> {
         trace!("Setting EWMH properties");
         self.conn.set_wm_properties(&self.config.workspaces)?;
 
+        trace!("Painting root window");
+        self.paint_root(true);
+        self.current_background = self.config.root_background.clone();
+        self.queue_workspace_background(self.screens.active_ws_index());
+
         trace!("Forcing cursor to first screen");
-        Ok(self.conn.warp_cursor(None, &self.screens.inner[0])?)
+        self.warp_cursor(None, &self.screens.inner[0])
+    }
+
+    /// Construct and initialise a new [WindowManager] that is ready to process events without
+    /// entering the main event loop or grabbing any key bindings.
+    ///
+    /// This is intended for driving the [WindowManager] from tests using a stub [XConn] in place
+    /// of a running X server: events are passed in one at a time using
+    /// [WindowManager::handle_event]. As with [WindowManager::new], errors are passed to
+    /// `error_handler`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::{Result, map, logging_error_handler};
+    /// # use penrose::__test_helpers::TestXConn as StubXConn;
+    /// use penrose::core::{
+    ///     bindings::{KeyBindings, MouseBindings},
+    ///     config::Config,
+    ///     manager::WindowManager,
+    ///     xconnection::XEvent,
+    /// };
+    ///
+    /// # fn example() -> Result<()> {
+    /// let conn = StubXConn::new(1, vec![], vec![]);
+    /// let mut wm =
+    ///     WindowManager::new_headless(Config::default(), conn, vec![], logging_error_handler())?;
+    ///
+    /// let mut key_bindings: KeyBindings<StubXConn> = map! {};
+    /// let mut mouse_bindings: MouseBindings<StubXConn> = map! {};
+    /// wm.handle_event(XEvent::MapRequest(1, false), &mut key_bindings, &mut mouse_bindings)?;
+    ///
+    /// assert_eq!(wm.focused_client_id(), Some(1));
+    /// # Ok(())
+    /// # }
+    /// # example().unwrap();
+    /// ```
+    pub fn new_headless(
+        config: Config,
+        conn: X,
+        hooks: Hooks<X>,
+        error_handler: ErrorHandler,
+    ) -> Result<Self> {
+        let mut wm = Self::new(config, conn, hooks, error_handler);
+        wm.init()?;
+        wm.focus_workspace(&Selector::Index(0))?;
+        wm.run_hook(HookName::Startup);
+
+        Ok(wm)
     }
 
     #[tracing::instrument(level = "debug", err, skip(self))]
@@ -218,9 +439,14 @@ impl<X: XConn> WindowManager<X> {
         let classes = str_slice!(self.config.floating_classes);
         for mut c in self.conn.active_managed_clients(classes)?.into_iter() {
             let id = c.id();
+            if self.clients.is_known(id) || self.is_ignored(&c) {
+                continue;
+            }
             self.add_client_to_workspace(c.workspace(), id)?;
             self.conn.unmap_client_if_needed(Some(&mut c))?;
             self.clients.insert(id, c);
+            self.stacking.push(id);
+            self.update_allowed_actions(id)?;
             self.conn.mark_new_client(id)?;
         }
 
@@ -260,6 +486,9 @@ impl<X: XConn> WindowManager<X> {
             ClientNameUpdated(id, name, is_root) => {
                 run_hooks!(client_name_updated, self, id, &name, is_root);
             }
+            ClientNormalHintsChanged(id, hints) => {
+                run_hooks!(client_normal_hints_changed, self, id, &hints)
+            }
             LayoutApplied(wix, i) => run_hooks!(layout_applied, self, wix, i),
             LayoutChange(wix) => {
                 let i = self.active_screen_index();
@@ -269,6 +498,8 @@ impl<X: XConn> WindowManager<X> {
             WorkspacesUpdated(names, wix) => {
                 run_hooks!(workspaces_updated, self, str_slice!(names), wix)
             }
+            WorkspaceAdded(wix, name, n) => run_hooks!(workspace_added, self, wix, &name, n),
+            WorkspaceRemoved(wix, name, n) => run_hooks!(workspace_removed, self, wix, &name, n),
             ScreenChange => {
                 let i = self.screens.focused_index();
                 run_hooks!(screen_change, self, i);
@@ -278,16 +509,41 @@ impl<X: XConn> WindowManager<X> {
                 run_hooks!(screens_updated, self, &regions);
             }
             RanderNotify => run_hooks!(randr_notify, self,),
+            RandrChange(change) => run_hooks!(randr_change, self, &change),
+            BarVisibilityChanged(i, visible) => {
+                run_hooks!(bar_visibility_changed, self, i, visible)
+            }
+            ClientGeometryChanged(id, from, to) => {
+                run_hooks!(client_geometry_changed, self, id, from, to)
+            }
             FocusChange(root) => run_hooks!(focus_change, self, root),
             EventHandled => run_hooks!(event_handled, self,),
+            StartupSettled(n_mapped) => run_hooks!(startup_settled, self, n_mapped),
+            Tick => run_hooks!(tick, self,),
         }
     }
 
+    // Layouts raise each of their floating clients in turn so _NET_CLIENT_LIST_STACKING is only
+    // written once a run of raises is complete rather than after every client.
     fn handle_event_actions(&mut self, actions: Vec<EventAction>) -> Result<()> {
+        let mut restacked = false;
         for a in actions {
+            if let EventAction::RaiseClient(id) = a {
+                self.raise(id)?;
+                restacked = true;
+                continue;
+            }
+            if restacked {
+                self.update_x_stacking()?;
+                restacked = false;
+            }
             self.handle_event_action(a, None, None)?;
         }
 
+        if restacked {
+            self.update_x_stacking()?;
+        }
+
         Ok(())
     }
 
@@ -303,7 +559,15 @@ impl<X: XConn> WindowManager<X> {
         use EventAction::*;
 
         match action {
-            ClientFocusGained(id) => self.update_focus(id)?,
+            ClientEntered(id, p) => {
+                let target = self.entered_client_focus_target(id, p)?;
+                if let Some(current) = self.clients.focused_client_id() {
+                    if current != target {
+                        self.state.clients.client_lost_focus(current, &self.conn);
+                    }
+                }
+                self.update_focus(target)?
+            }
             ClientFocusLost(id) => self.state.clients.client_lost_focus(id, &self.conn),
             ClientNameChanged(id, is_root) => {
                 let action = self
@@ -313,17 +577,38 @@ impl<X: XConn> WindowManager<X> {
                 self.handle_event_action(action, None, None)?
             }
             ClientToWorkspace(id, wix) => self.move_client_to_workspace(id, wix)?,
+            ClientWmStateChanged(id) => self.handle_wm_state_change(id)?,
+            ClientNormalHintsChanged(id) => self.handle_normal_hints_change(id)?,
             DestroyClient(id) => self.remove_client(id)?,
             DetectScreens => {
+                self.debounce_randr_events()?;
                 self.run_hook(HookName::RanderNotify);
                 self.detect_screens()?
             }
             FocusIn(id) => self.clients.focus_in(id, &self.conn)?,
             LayoutVisible => self.layout_visible()?,
             LayoutWorkspace(wix) => self.apply_layout(wix)?,
-            MapWindow(id) => self.handle_map_request(id)?,
+            MapWindow(id) => {
+                self.handle_map_request(id)?;
+                #[cfg(feature = "startup_notification")]
+                self.complete_startup(id);
+                self.resolve_pending_spawn(id)?
+            }
             MoveClientIfFloating(id, r) => self.handle_move_if_floating(id, r)?,
-            RunHook(hook_name) => self.run_hook(hook_name),
+            RaiseClient(id) => {
+                self.raise(id)?;
+                self.update_x_stacking()?
+            }
+            RefreshKeyCodes => self.refresh_key_codes(key_bindings, mouse_bindings)?,
+            RestackClientIfFloating(id, sibling, mode) => {
+                self.handle_restack_if_floating(id, sibling, mode)?
+            }
+            RunHook(hook_name) => {
+                if matches!(hook_name, HookName::ScreenChange | HookName::ScreenUpdated) {
+                    self.update_pointer_confinement()?;
+                }
+                self.run_hook(hook_name)
+            }
             RunKeyBinding(e) => match key_bindings {
                 Some(kb) => self.run_key_binding(e, kb),
                 None => return Err(perror!("keybindings can only be triggered from X events")),
@@ -335,6 +620,14 @@ impl<X: XConn> WindowManager<X> {
             SetActiveClient(id) => self.set_active_client(id)?,
             SetActiveWorkspace(wix) => self.focus_workspace(&Selector::Index(wix))?,
             SetScreenFromPoint(p) => self.set_screen_from_point(p)?,
+            SetClientSkipState(id, states, action) => {
+                self.set_client_skip_state(id, &states, action)?
+            }
+            SetClientMinimized(id, should_minimize) => {
+                self.set_minimized(id, should_minimize)?;
+            }
+            SetClientAbove(id, action) => self.set_pip(id, action.apply(self.is_pip(id)))?,
+            SetFrameExtents(id) => self.set_frame_extents(id)?,
             ToggleClientFullScreen(id, should_fullscreen) => {
                 self.set_fullscreen(id, should_fullscreen)?;
             }
@@ -379,44 +672,307 @@ impl<X: XConn> WindowManager<X> {
         }
 
         trace!("grabbing key and mouse bindings");
-        self.conn.grab_keys(&key_bindings, &mouse_bindings)?;
+        for (code, handler) in self.pending_key_bindings.drain(..) {
+            match handler {
+                Some(h) => key_bindings.insert(code, h),
+                None => key_bindings.remove(&code),
+            };
+        }
+        if self.config.key_binding_mode == KeyBindingMode::KeySym {
+            self.record_key_names(&key_bindings);
+        }
+        self.bound_keys = key_bindings.keys().copied().collect();
+        self.record_key_binding_specs();
+        self.grab_report = self.conn.grab_keys(&key_bindings, &mouse_bindings)?;
+        for (key, error) in self.grab_report.failed.iter() {
+            warn!(?key, %error, "unable to grab key binding: it may already be grabbed by another program");
+        }
 
         trace!("forcing focus to first workspace");
         self.focus_workspace(&Selector::Index(0))?;
 
         self.run_hook(HookName::Startup);
         self.running = true;
+        if self.config.startup_settle_ms > 0 {
+            self.settling = Some(0);
+        }
 
         trace!("entering main event loop");
         while self.running {
-            match self.conn.wait_for_event() {
-                Ok(event) => {
-                    let span = span!(target: "penrose", Level::DEBUG, "XEvent", %event);
-                    let _enter = span.enter();
-                    trace!(details = ?event, "event details");
-
-                    let actions = process_next_event(event, &self.state, &self.conn);
-                    for action in actions {
-                        if let Err(e) = self.handle_event_action(
-                            action,
-                            Some(&mut key_bindings),
-                            Some(&mut mouse_bindings),
-                        ) {
-                            (self.error_handler)(e);
-                        }
-                    }
+            match self.next_event() {
+                Ok(event) => self.handle_event(event, &mut key_bindings, &mut mouse_bindings)?,
+                Err(e) => self.handle_error(PenroseError::X(e), &key_bindings, &mouse_bindings)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Process a single [XEvent] in the same way as the main event loop, running any bindings
+    /// that it triggers and the [EventHandled][crate::core::hooks::Hook::event_handled] hooks.
+    ///
+    /// Errors are handled according to the current
+    /// [ErrorPolicy][crate::core::data_types::ErrorPolicy] so an error is only returned if it
+    /// would have stopped the main event loop. See
+    /// [WindowManager::new_headless] for driving a [WindowManager] without an X server.
+    pub fn handle_event(
+        &mut self,
+        event: XEvent,
+        key_bindings: &mut KeyBindings<X>,
+        mouse_bindings: &mut MouseBindings<X>,
+    ) -> Result<()> {
+        let span = span!(target: "penrose", Level::DEBUG, "XEvent", %event);
+        let _enter = span.enter();
+        trace!(details = ?event, "event details");
+        self.cursor.set(None);
+
+        let actions = process_next_event(event, &self.state, &self.conn);
+        for action in actions {
+            if let Err(e) =
+                self.handle_event_action(action, Some(key_bindings), Some(mouse_bindings))
+            {
+                self.handle_error(e, key_bindings, mouse_bindings)?;
+            }
+
+            if let Err(e) = self.apply_pending_key_bindings(key_bindings) {
+                self.handle_error(e, key_bindings, mouse_bindings)?;
+            }
+        }
+
+        self.run_hook(HookName::EventHandled);
+        self.conn.flush();
+        self.cursor.set(None);
+
+        Ok(())
+    }
+
+    // Errors raised in the main event loop are passed to the error handler unless the current
+    // ErrorPolicy says that they should stop the event loop, in which case they are returned.
+    // Losing the connection to the X server is only fatal if reconnecting is disabled or fails.
+    fn handle_error(
+        &mut self,
+        e: PenroseError,
+        key_bindings: &KeyBindings<X>,
+        mouse_bindings: &MouseBindings<X>,
+    ) -> Result<()> {
+        if e.is_fatal() && self.config.reconnect_attempts > 0 {
+            error!(%e, "lost connection to the X server: attempting to reconnect");
+            if let Err(reconnect_err) = self.reconnect(key_bindings, mouse_bindings) {
+                error!(%reconnect_err, "exiting main event loop");
+                return Err(e);
+            }
+            return Ok(());
+        }
 
-                    self.run_hook(HookName::EventHandled);
-                    self.conn.flush();
+        let exit = match self.config.error_policy {
+            ErrorPolicy::ExitOnFatal => e.is_fatal(),
+            ErrorPolicy::ExitOnAny => true,
+        };
+
+        if exit {
+            error!(%e, "exiting main event loop");
+            return Err(e);
+        }
+
+        (self.error_handler)(e);
+        Ok(())
+    }
+
+    // Retry re-opening the X connection with an exponential backoff between attempts.
+    fn reconnect(
+        &mut self,
+        key_bindings: &KeyBindings<X>,
+        mouse_bindings: &MouseBindings<X>,
+    ) -> Result<()> {
+        let attempts = self.config.reconnect_attempts;
+        let mut delay = Duration::from_millis(self.config.reconnect_backoff_ms);
+
+        for attempt in 1..=attempts {
+            thread::sleep(delay);
+            match self.reestablish_connection(key_bindings, mouse_bindings) {
+                Ok(()) => {
+                    info!(attempt, "reconnected to the X server");
+                    return Ok(());
                 }
+                Err(e) => {
+                    warn!(attempt, %e, "unable to reconnect to the X server");
+                    delay *= 2;
+                }
+            }
+        }
+
+        Err(perror!("unable to reconnect after {} attempts", attempts))
+    }
+
+    // Everything tied to the previous connection needs to be set up again: X state is held
+    // server side so any clients that were destroyed while we were disconnected are dropped
+    // and any that were created in the meantime are adopted.
+    #[tracing::instrument(level = "debug", err, skip(self, key_bindings, mouse_bindings))]
+    fn reestablish_connection(
+        &mut self,
+        key_bindings: &KeyBindings<X>,
+        mouse_bindings: &MouseBindings<X>,
+    ) -> Result<()> {
+        self.conn.reconnect()?;
+        self.conn.init()?;
+        self.detect_screens()?;
+        self.conn.set_wm_properties(&self.config.workspaces)?;
+        self.grab_report = self.conn.grab_keys(key_bindings, mouse_bindings)?;
 
-                Err(e) => (self.error_handler)(PenroseError::X(e)),
+        let live = self.conn.active_clients()?;
+        for id in self.clients.all_known_ids() {
+            if live.contains(&id) {
+                self.conn.mark_new_client(id)?;
+            } else {
+                self.remove_client(id)?;
             }
         }
 
+        self.try_manage_existing_windows()?;
+        self.paint_root(false);
+
         Ok(())
     }
 
+    // Events buffered while debouncing RandR events are processed before pulling new events
+    // from the X server.
+    fn next_event(&mut self) -> xconnection::Result<XEvent> {
+        if self.pending_events.is_empty() {
+            if self.settling.is_some() {
+                if let Some(event) = self.poll_until_settled()? {
+                    return Ok(event);
+                }
+            }
+            if let Some(event) = self.poll_until_background_due()? {
+                return Ok(event);
+            }
+        }
+
+        if let Some(event) = self.pending_events.pop_front() {
+            return Ok(event);
+        }
+
+        while self.config.tick_interval_ms > 0 {
+            if let Some(event) = self.poll_until_tick()? {
+                return Ok(event);
+            }
+        }
+
+        self.conn.wait_for_event()
+    }
+
+    // Poll for events until the next tick is due, running the tick hook if nothing arrives.
+    // Ticks that are already due are run before polling so that a steady stream of events can
+    // not hold them off indefinitely.
+    fn poll_until_tick(&mut self) -> xconnection::Result<Option<XEvent>> {
+        let interval = Duration::from_millis(self.config.tick_interval_ms);
+        let now = Instant::now();
+        let deadline = *self.next_tick.get_or_insert(now + interval);
+        let event = if now < deadline {
+            self.poll_until(deadline)?
+        } else {
+            None
+        };
+
+        if event.is_none() {
+            self.next_tick = Some((deadline + interval).max(Instant::now()));
+            self.cursor.set(None);
+            self.run_hook(HookName::Tick);
+        }
+
+        Ok(event)
+    }
+
+    // While waiting for things to settle after startup we poll for the next event rather than
+    // blocking so that we can tell when the event loop has been idle for long enough to run the
+    // startup_settled hook.
+    fn poll_until_settled(&mut self) -> xconnection::Result<Option<XEvent>> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.startup_settle_ms);
+        let event = self.poll_until(deadline)?;
+
+        if event.is_none() {
+            if let Some(n_mapped) = self.settling.take() {
+                debug!(n_mapped, "startup has settled");
+                self.run_hook(HookName::StartupSettled(n_mapped));
+            }
+        }
+
+        Ok(event)
+    }
+
+    // A pending workspace background is set once no events have arrived before its deadline
+    fn poll_until_background_due(&mut self) -> xconnection::Result<Option<XEvent>> {
+        let deadline = match self.pending_background {
+            Some((_, deadline)) => deadline,
+            None => return Ok(None),
+        };
+        let event = self.poll_until(deadline)?;
+
+        if event.is_none() {
+            self.apply_pending_background();
+        }
+
+        Ok(event)
+    }
+
+    // Wait for the next event, returning None if nothing arrives before the deadline
+    fn poll_until(&self, deadline: Instant) -> xconnection::Result<Option<XEvent>> {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if let Some(event) = self.conn.wait_for_event_timeout(timeout)? {
+                return Ok(Some(event));
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Whether or not the event loop is still waiting for events to settle after startup before
+    /// running the `startup_settled` hook.
+    pub fn is_settling(&self) -> bool {
+        self.settling.is_some()
+    }
+
+    /// The number of map requests that have been received since startup while waiting for
+    /// events to settle. Returns `None` once the `startup_settled` hook has been run.
+    pub fn settling_map_requests(&self) -> Option<usize> {
+        self.settling
+    }
+
+    // Monitor reconfiguration results in a burst of RandR events so we wait until no new ones
+    // have arrived for the configured interval before re-detecting screens. Any other events
+    // received while waiting are buffered for the main event loop.
+    #[tracing::instrument(level = "trace", err, skip(self))]
+    fn debounce_randr_events(&mut self) -> Result<()> {
+        let interval = Duration::from_millis(self.config.randr_debounce_ms);
+        if interval.is_zero() {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let latest = start + interval * RANDR_DEBOUNCE_MAX_INTERVALS;
+        let mut deadline = start + interval;
+
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+
+            match self.conn.wait_for_event_timeout(deadline - now)? {
+                Some(XEvent::RandrNotify(change)) => {
+                    trace!(?change, "debouncing randr event");
+                    self.run_hook(HookName::RandrChange(change));
+                    deadline = (now + interval).min(latest);
+                }
+                Some(event) => self.pending_events.push_back(event),
+                None => (),
+            }
+        }
+    }
+
     /*
      * Top Level EventAction handlers
      */
@@ -452,6 +1008,13 @@ impl<X: XConn> WindowManager<X> {
         };
 
         let prev = self.state.clients.set_focused(target, &self.conn);
+        self.record_focus(target);
+        if let Some(opacity) = self.config.unfocused_opacity {
+            if let Some(prev) = prev.filter(|&p| p != target) {
+                self.apply_opacity(prev, opacity);
+            }
+            self.apply_opacity(target, 1.0);
+        }
 
         let (wix, accepts_focus) = {
             // Safe to unwrap because we make sure this is a known client above
@@ -460,8 +1023,12 @@ impl<X: XConn> WindowManager<X> {
         };
 
         self.focus_screen(&Selector::Condition(&|s| s.wix == wix));
+        self.reveal_stacked_client(target, wix)?;
         self.clients
             .set_x_focus(target, accepts_focus, &self.conn)?;
+        if self.config.raise_on_focus {
+            self.raise_focused_client(target, wix)?;
+        }
 
         if let Some(ws) = self.workspaces.get_mut(wix) {
             ws.focus_client(target);
@@ -473,16 +1040,162 @@ impl<X: XConn> WindowManager<X> {
             }
         }
 
+        self.apply_inactive_screen_borders();
         self.run_hook(HookName::FocusChange(target));
         Ok(())
     }
 
+    // Mark the focused clients of workspaces on screens other than the active screen
+    fn apply_inactive_screen_borders(&self) {
+        let color = match self.config.inactive_screen_border {
+            Some(color) => color,
+            None => return,
+        };
+
+        let active = self.screens.active_ws_index();
+        for wix in self.screens.visible_workspaces() {
+            let id = match self.workspaces.get(wix).and_then(|ws| ws.focused_client()) {
+                Some(id) if wix != active && self.clients.focused_client_id() != Some(id) => id,
+                _ => continue,
+            };
+            if let Err(e) = self.conn.set_client_border_color(id, color) {
+                warn!("unable to set client border color for {}: {}", id, e);
+            }
+        }
+    }
+
+    // Tiled clients that are hidden because they were stacked to respect min_tile_size need to
+    // be shown by re-applying the layout with them focused before they can take focus.
+    fn reveal_stacked_client(&mut self, id: Xid, wix: usize) -> Result<()> {
+        let hidden = matches!(
+            self.clients.get(id),
+            Some(c) if c.wm_managed && !c.mapped && !c.floating && !c.minimized
+        );
+
+        if self.config.min_tile_size == (0, 0)
+            || !hidden
+            || !self.screens.visible_workspaces().contains(&wix)
+        {
+            return Ok(());
+        }
+
+        self.workspaces[wix].focus_client(id);
+        self.apply_layout(wix)
+    }
+
+    // Move the given client to the front of the focus history unless we are part way through
+    // cycling through it with focus_next_global, in which case the cycle is committed first.
+    fn record_focus(&mut self, id: Xid) {
+        if self.cycling_globally {
+            return;
+        }
+
+        self.commit_global_focus();
+        self.focus_history.retain(|&c| c != id);
+        self.focus_history.insert(0, id);
+    }
+
+    fn forget_focus_history(&mut self, id: Xid) {
+        if let Some(ix) = self.focus_history.iter().position(|&c| c == id) {
+            self.focus_history.remove(ix);
+            self.global_cycle = match self.global_cycle {
+                Some(current) if ix < current => Some(current - 1),
+                Some(current) if ix == current => None,
+                current => current,
+            };
+        }
+    }
+
+    // Tiled clients are never raised above the floating clients on their workspace so any
+    // floating clients need restacking after a tiled client is raised. Picture-in-picture
+    // clients are kept above everything else on their workspace.
+    fn raise_focused_client(&mut self, id: Xid, wix: usize) -> Result<()> {
+        self.raise(id)?;
+        let tiled = matches!(self.clients.get(id), Some(c) if !c.floating);
+
+        if tiled && !self.workspaces.is_floating(wix) {
+            let floating: Vec<Xid> = self
+                .clients
+                .clients_for_ids(&self.workspaces[wix].client_ids())
+                .into_iter()
+                .filter(|c| c.floating && !self.pip_clients.contains_key(&c.id()))
+                .map(|c| c.id())
+                .collect();
+
+            for id in floating {
+                self.raise(id)?;
+            }
+        }
+
+        self.raise_pip_clients(wix, id)?;
+        self.update_x_stacking()
+    }
+
+    fn raise_pip_clients(&mut self, wix: usize, except: Xid) -> Result<()> {
+        let pip: Vec<Xid> = self.workspaces[wix]
+            .client_ids()
+            .into_iter()
+            .filter(|&id| id != except && self.pip_clients.contains_key(&id))
+            .collect();
+
+        for id in pip {
+            self.raise(id)?;
+        }
+
+        Ok(())
+    }
+
+    // Raise a client above all other windows without updating _NET_CLIENT_LIST_STACKING
+    fn raise(&mut self, id: Xid) -> Result<()> {
+        self.conn.raise_client(id)?;
+        self.track_restack(id, StackMode::Above, None);
+
+        Ok(())
+    }
+
+    // Record a change in the stacking order of a client for _NET_CLIENT_LIST_STACKING. Only
+    // unconditional restacks (Above and Below) are tracked.
+    fn track_restack(&mut self, id: Xid, mode: StackMode, sibling: Option<Xid>) {
+        if !matches!(mode, StackMode::Above | StackMode::Below) {
+            return;
+        }
+
+        self.stacking.retain(|&c| c != id);
+        let sibling_ix = sibling.and_then(|s| self.stacking.iter().position(|&c| c == s));
+        let ix = match (mode, sibling_ix) {
+            (StackMode::Above, Some(ix)) => ix + 1,
+            (StackMode::Below, Some(ix)) => ix,
+            (StackMode::Below, None) => 0,
+            _ => self.stacking.len(),
+        };
+        self.stacking.insert(ix, id);
+    }
+
+    // Restack a client relative to an optional sibling, keeping _NET_CLIENT_LIST_STACKING in sync
+    fn restack(&mut self, id: Xid, mode: StackMode, sibling: Option<Xid>) -> Result<()> {
+        self.conn
+            .configure_client(id, &[ClientConfig::Stack(mode, sibling)])?;
+        self.track_restack(id, mode, sibling);
+        self.update_x_stacking()
+    }
+
     // The given window ID has been destroyed so remove our internal state referencing it.
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn remove_client(&mut self, id: Xid) -> Result<()> {
+        let was_focused = self.clients.focused_client_id() == Some(id);
         if let Some(client) = self.clients.remove(id) {
             let wix = client.workspace();
             self.workspaces.remove_client(wix, id);
+            self.forget_focus_history(id);
+            self.stacking.retain(|&c| c != id);
+            self.pip_clients.remove(&id);
+
+            if let Ok(ws) = self.workspaces.get_workspace(wix) {
+                if ws.is_empty() {
+                    let name = ws.name().to_string();
+                    self.auto_spawned.remove(&name);
+                }
+            }
 
             if self.screens.visible_workspaces().contains(&wix) {
                 self.apply_layout(wix)?;
@@ -490,6 +1203,7 @@ impl<X: XConn> WindowManager<X> {
 
             self.update_known_x_clients()?;
             self.run_hook(HookName::RemoveClient(id));
+            self.handle_orphaned_transients(id, was_focused)?;
         } else {
             debug!(id, "attempt to remove unknown client");
         }
@@ -497,6 +1211,40 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
+    // Apply the configured TransientPolicy to any clients that were transient for `parent`
+    fn handle_orphaned_transients(&mut self, parent: Xid, parent_was_focused: bool) -> Result<()> {
+        let orphans: Vec<Xid> = self
+            .clients
+            .all_known_ids()
+            .into_iter()
+            .filter(
+                |&id| matches!(self.clients.get(id), Some(c) if c.transient_for == Some(parent)),
+            )
+            .collect();
+
+        if orphans.is_empty() {
+            return Ok(());
+        }
+
+        debug!(parent, ?orphans, policy = ?self.config.transient_policy, "parent of transients removed");
+        match self.config.transient_policy {
+            TransientPolicy::Close => orphans.iter().try_for_each(|&id| self.close_client(id)),
+            TransientPolicy::Keep => {
+                for &id in orphans.iter() {
+                    self.clients.modify(id, |c| c.transient_for = None);
+                }
+                let wix = self.screens.active_ws_index();
+                let visible = orphans
+                    .into_iter()
+                    .find(|&id| self.clients.workspace_index_for_client(id) == Some(wix));
+                match visible {
+                    Some(id) if parent_was_focused => self.update_focus(id),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn move_client_to_workspace(&mut self, id: Xid, wix: usize) -> Result<()> {
         let current_wix = match self.clients.workspace_index_for_client(id) {
@@ -504,14 +1252,23 @@ impl<X: XConn> WindowManager<X> {
             None => return Err(PenroseError::UnknownClient(id)),
         };
 
+        if self.clients.is_minimized(id) {
+            // Minimized clients are added to their new workspace when they are restored
+            self.clients.set_client_workspace(id, wix);
+            return Ok(());
+        }
+
         if current_wix != wix {
             self.workspaces.remove_client(current_wix, id);
             self.add_client_to_workspace(wix, id)?;
             self.clients.set_client_workspace(id, wix);
+            if self.layout_is_floating(current_wix) != self.layout_is_floating(wix) {
+                self.update_allowed_actions(id)?;
+            }
 
             if self.screens.visible_workspaces().contains(&wix) {
                 let s = self.screens.focused();
-                self.conn.warp_cursor(Some(id), s)?;
+                self.warp_cursor(Some(id), s)?;
             } else {
                 self.state.clients.unmap_if_needed(id, &self.conn)?;
             }
@@ -524,27 +1281,127 @@ impl<X: XConn> WindowManager<X> {
 
     /// Query the [XConn] for the current connected [Screen] list and reposition displayed
     /// [Workspace] instances if needed.
+    ///
+    /// The workspace and layout shown on each named RandR output are remembered so that an
+    /// output that is disconnected and later reconnected (e.g. when undocking and redocking a
+    /// laptop) is given back the workspace it was showing, with the layout it was using. Outputs
+    /// that have not been seen before are assigned workspaces as normal.
     #[tracing::instrument(level = "trace", err, skip(self))]
     pub fn detect_screens(&mut self) -> Result<()> {
+        let workspaces = &self.state.workspaces;
+        self.state.screens.remember_outputs(|wix| {
+            workspaces
+                .get_workspace(wix)
+                .ok()
+                .map(|ws| ws.layout_symbol().to_string())
+        });
+
         let actions = self
             .state
             .screens
             .update_known_screens(&self.conn, self.workspaces.len())?;
 
-        self.handle_event_actions(actions)
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        self.restore_output_layouts()?;
+        self.handle_event_actions(actions)?;
+
+        // Newly detected screens always start with their bar shown
+        let mut changed = false;
+        for index in 0..self.screens.n_screens() {
+            changed |= self.sync_bar_visibility(index);
+        }
+
+        if changed {
+            self.layout_visible()?;
+        }
+
+        Ok(())
+    }
+
+    fn restore_output_layouts(&mut self) -> Result<()> {
+        let restored: Vec<(usize, String)> = self
+            .screens
+            .restored_outputs()
+            .into_iter()
+            .map(|o| (o.wix, o.layout.clone()))
+            .collect();
+
+        for (wix, layout) in restored {
+            let current = self
+                .workspaces
+                .get_workspace(wix)
+                .map(|ws| ws.layout_symbol());
+            if matches!(current, Ok(symbol) if symbol == layout) {
+                continue;
+            }
+
+            let was_floating = self.layout_is_floating(wix);
+            match self.workspaces.set_layout(wix, &layout) {
+                Ok(()) => {
+                    self.sink_floats_if_now_tiling(wix, was_floating)?;
+                    self.run_hook(HookName::LayoutChange(wix));
+                }
+                Err(error) => debug!(wix, %layout, %error, "unable to restore output layout"),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Penrose only draws borders rather than full frames so the extents are the border width,
+    // scaled for the screen showing the client (or the focused screen if it is not visible).
+    fn set_frame_extents(&self, id: Xid) -> Result<()> {
+        let client = self.clients.get(id);
+        let screen = client
+            .and_then(|c| self.screens.indexed_screen_for_workspace(c.workspace()))
+            .map_or(self.screens.focused(), |(_, s)| s);
+        let bpx = util::client_border_px(&self.config, screen, client);
+        Ok(self.conn.set_frame_extents(id, bpx)?)
+    }
+
+    // Advertise what the client can currently request: this needs re-running whenever the
+    // client is floated or made fullscreen and when its workspace changes to or from a floating
+    // layout.
+    fn update_allowed_actions(&self, id: Xid) -> Result<()> {
+        match self.clients.get(id) {
+            Some(c) => {
+                let actions = c.allowed_actions(self.layout_is_floating(c.workspace()));
+                Ok(self.conn.set_allowed_actions(id, &actions)?)
+            }
+            None => Ok(()),
+        }
     }
 
     // Map a new client window.
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn handle_map_request(&mut self, id: Xid) -> Result<()> {
         trace!(id, "handling map request");
+        if let Some(n) = self.settling.as_mut() {
+            *n += 1;
+        }
         let classes = str_slice!(self.config.floating_classes);
-        let client = Client::new(&self.conn, id, self.screens.active_ws_index(), classes);
+        let mut client = Client::new(&self.conn, id, self.screens.active_ws_index(), classes);
+        if client
+            .wm_class
+            .iter()
+            .any(|c| self.config.aspect_ratio_classes.contains(c))
+        {
+            client.aspect_ratio = Some(AspectRatio::FromHints);
+        }
         let is_managed_type = self.conn.is_managed_client(&client);
         trace!(id, ?client.wm_name, ?client.wm_class, ?client.wm_type, "client details");
 
+        if self.is_ignored(&client) {
+            debug!(id, ?client.wm_class, ?client.wm_type, "mapping ignored client");
+            return Ok(self.conn.map_client(id)?);
+        }
+
         // Run hooks to allow them to modify the client
         self.clients.insert(id, client);
+        self.stacking.push(id);
         self.run_hook(HookName::NewClient(id));
 
         let details = self
@@ -559,6 +1416,15 @@ impl<X: XConn> WindowManager<X> {
 
         let (wix, wm_hints, wm_managed, floating) = details.unwrap();
 
+        if let Some(tags) = self.workspaces.get(wix).and_then(|ws| ws.viewed_tags()) {
+            let tags = tags.clone();
+            self.clients.modify(id, |c| {
+                if c.tags.is_empty() {
+                    c.tags = tags.clone();
+                }
+            });
+        }
+
         if let Some(ref wmh) = wm_hints {
             if wmh.initial_state == WindowState::Withdrawn {
                 self.clients.remove(id);
@@ -570,41 +1436,129 @@ impl<X: XConn> WindowManager<X> {
             return Ok(self.conn.map_client(id)?);
         }
 
+        if let Err(e) = self.set_frame_extents(id) {
+            warn!(id, %e, "unable to set _NET_FRAME_EXTENTS for new client");
+        }
+        self.update_allowed_actions(id)?;
+        let focus_new = self.should_focus_new_client(id, wix);
+        let prev_focus = self.workspaces.focused_client(wix);
+
         if wm_managed {
             self.add_client_to_workspace(wix, id)?;
         }
 
-        if floating {
+        if floating || self.workspaces.is_floating(wix) {
             if let Some((_, s)) = self.screens.indexed_screen_for_workspace(wix) {
+                let bpx = util::client_border_px(&self.config, s, self.clients.get(id));
                 util::position_floating_client(
                     &self.conn,
                     id,
-                    s.region(self.config.show_bar),
-                    self.config.border_px,
+                    s.usable_region(self.config.show_bar),
+                    bpx,
                 )?
             }
         }
 
         self.conn.mark_new_client(id)?;
-        self.update_focus(id)?;
+        if focus_new {
+            self.update_focus(id)?;
+        } else {
+            // Adding the client to its workspace will have placed it in focus there so we need
+            // to restore the previous focus to prevent it being picked up later.
+            if let (Some(prev), Some(ws)) = (prev_focus, self.workspaces.get_mut(wix)) {
+                ws.focus_client(prev);
+            }
+            self.conn
+                .set_client_border_color(id, self.config.unfocused_border)?;
+        }
         self.update_known_x_clients()?;
 
         if wix == self.screens.active_ws_index() {
             self.apply_layout(wix)?;
             self.state.clients.map_if_needed(id, &self.conn)?;
-            let s = self.screens.focused();
-            self.conn.warp_cursor(Some(id), s)?;
+            if focus_new {
+                let s = self.screens.focused();
+                self.warp_cursor(Some(id), s)?;
+            }
         }
 
         Ok(())
     }
 
+    // Clients matching one of the ignore rules in the config are mapped but never tracked
+    fn is_ignored(&self, client: &Client) -> bool {
+        client
+            .wm_class
+            .iter()
+            .any(|c| self.config.ignored_classes.contains(c))
+            || client
+                .wm_type
+                .iter()
+                .any(|t| self.config.ignored_window_types.contains(t))
+    }
+
+    fn should_focus_new_client(&self, id: Xid, wix: usize) -> bool {
+        if let Some(c) = self.clients.get(id) {
+            if c.wm_class
+                .iter()
+                .any(|c| self.config.no_focus_classes.contains(c))
+            {
+                return false;
+            }
+        }
+
+        match self.config.focus_new_windows {
+            FocusNewPolicy::Always => true,
+            FocusNewPolicy::Never => false,
+            FocusNewPolicy::CurrentWorkspace => wix == self.screens.active_ws_index(),
+        }
+    }
+
+    // Requested geometries are sanitized before being passed on to the X server so that
+    // misbehaving clients can not make themselves invisible or trigger BadValue errors.
     fn handle_move_if_floating(&mut self, id: Xid, r: Region) -> Result<()> {
         if let Some(client) = self.clients.get(id) {
             if client.floating {
-                debug!(id, region = ?r, "repositioning floating window");
-                let bpx = self.config.border_px;
-                self.conn.position_client(id, r, bpx, true)?;
+                let (bpx, bounds) =
+                    match self.screens.indexed_screen_for_workspace(client.workspace) {
+                        Some((_, s)) => (
+                            util::client_border_px(&self.config, s, Some(client)),
+                            Some(s.region(false)),
+                        ),
+                        None => (client.border_px.unwrap_or(self.config.border_px), None),
+                    };
+                let bounds = bounds.filter(|_| self.config.clamp_configure_requests);
+                let min = self.config.min_configure_size;
+
+                match util::sanitize_configure_region(r, bounds.as_ref(), min, bpx) {
+                    Some(reg) => {
+                        debug!(id, region = ?reg, requested = ?r, "repositioning floating window");
+                        self.conn.position_client(id, reg, bpx, true)?;
+                    }
+                    None => warn!(id, region = ?r, "ignoring invalid configure request"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Tiled clients have their stacking order managed by penrose so only floating clients are
+    // permitted to restack themselves, and never above picture-in-picture clients.
+    fn handle_restack_if_floating(
+        &mut self,
+        id: Xid,
+        sibling: Option<Xid>,
+        mode: StackMode,
+    ) -> Result<()> {
+        if let Some(client) = self.clients.get(id) {
+            if client.floating {
+                debug!(id, ?sibling, ?mode, "restacking floating window");
+                let wix = client.workspace();
+                self.conn
+                    .configure_client(id, &[ClientConfig::Stack(mode, sibling)])?;
+                self.track_restack(id, mode, sibling);
+                self.raise_pip_clients(wix, id)?;
+                self.update_x_stacking()?;
             }
         }
         Ok(())
@@ -616,6 +1570,9 @@ impl<X: XConn> WindowManager<X> {
     }
 
     fn handle_unmap_notify(&mut self, id: Xid) -> Result<()> {
+        if self.clients.is_minimized(id) {
+            return Ok(()); // WM_STATE is set to Iconic when minimizing
+        }
         Ok(self.conn.set_client_state(id, WindowState::Withdrawn)?)
     }
 
@@ -632,11 +1589,223 @@ impl<X: XConn> WindowManager<X> {
         }
     }
 
+    // Changes to key bindings are queued up by rebind / unbind so that key binding actions are able
+    // to modify the bindings that are currently being run. Grabs are only updated for keys that
+    // were added or removed: replacing the action of an existing binding leaves its grab alone.
+    fn apply_pending_key_bindings(&mut self, bindings: &mut KeyBindings<X>) -> Result<()> {
+        for (code, handler) in self.pending_key_bindings.drain(..) {
+            match handler {
+                Some(h) => {
+                    self.bound_keys.insert(code);
+                    if bindings.insert(code, h).is_none() {
+                        let res = self.conn.grab_key(code);
+                        if let Err(ref error) = res {
+                            warn!(key = ?code, %error, "unable to grab key binding: it may already be grabbed by another program");
+                        }
+                        self.grab_report.record(code, res);
+                    }
+                }
+                None => {
+                    self.bound_keys.remove(&code);
+                    if bindings.remove(&code).is_some() {
+                        self.grab_report.forget(code);
+                        self.conn.ungrab_key(code)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Remember the key name for each binding that we don't already have a name for so that the
+    // binding can follow that name to a new key code if the keymap changes.
+    fn record_key_names(&mut self, bindings: &KeyBindings<X>) {
+        let missing: Vec<KeyCode> = bindings
+            .keys()
+            .filter(|k| !self.key_names.contains_key(k))
+            .copied()
+            .collect();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let codes = match self.key_codes() {
+            Ok(codes) => codes,
+            Err(e) => {
+                error!(%e, "unable to load key codes: key bindings will not follow keymap changes");
+                return;
+            }
+        };
+
+        let mut names = Vec::with_capacity(missing.len());
+        for k in missing {
+            let name = codes
+                .iter()
+                .filter(|(_, &code)| code == k.code)
+                .map(|(name, _)| name)
+                .min()
+                .cloned();
+
+            match name {
+                Some(name) => names.push((k, name)),
+                None => warn!(key = ?k, "no key name found for key binding"),
+            }
+        }
+        self.key_names.extend(names);
+    }
+
+    // Record the spec of each binding that has not been bound using rebind or described so that
+    // every key binding is listed by key_binding_descriptions.
+    fn record_key_binding_specs(&mut self) {
+        let missing: Vec<KeyCode> = self
+            .bound_keys
+            .iter()
+            .filter(|k| !self.key_descriptions.contains_key(k))
+            .copied()
+            .collect();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let codes = match self.key_codes() {
+            Ok(codes) => codes,
+            Err(e) => {
+                error!(%e, "unable to load key codes: key bindings will not be listed in key_binding_descriptions");
+                return;
+            }
+        };
+
+        let specs: Vec<(KeyCode, KeyBindingDescription)> = missing
+            .into_iter()
+            .flat_map(|k| {
+                let spec = key_binding_spec(k, codes);
+                if spec.is_none() {
+                    warn!(key = ?k, "unable to format key binding spec");
+                }
+                spec.map(|spec| {
+                    let description = None;
+                    (k, KeyBindingDescription { spec, description })
+                })
+            })
+            .collect();
+        self.key_descriptions.extend(specs);
+    }
+
+    // Whether or not `code` is bound once any pending changes to the key bindings are applied
+    fn key_is_bound(&self, code: KeyCode) -> bool {
+        match self
+            .pending_key_bindings
+            .iter()
+            .rev()
+            .find(|(c, _)| *c == code)
+        {
+            Some((_, handler)) => handler.is_some(),
+            None => self.bound_keys.contains(&code),
+        }
+    }
+
+    // The keymap has changed: NumLock may now be bound to a different modifier (in which case the
+    // connection drops all of our grabs and we need to grab everything again) and if we are
+    // tracking bindings by key name then each binding and its grab is moved over to the key code
+    // that the name now maps to.
+    fn refresh_key_codes(
+        &mut self,
+        key_bindings: Option<&mut KeyBindings<X>>,
+        mouse_bindings: Option<&mut MouseBindings<X>>,
+    ) -> Result<()> {
+        self.key_codes = None;
+        let (key_bindings, mouse_bindings) = match (key_bindings, mouse_bindings) {
+            (Some(k), Some(m)) => (k, m),
+            _ => return Ok(()),
+        };
+
+        let regrab = self.conn.refresh_num_lock_mask()?;
+        if self.config.key_binding_mode == KeyBindingMode::KeySym {
+            self.move_key_bindings(key_bindings)?;
+        }
+
+        if regrab {
+            debug!("NumLock modifier has changed: re-grabbing key and mouse bindings");
+            self.grab_report = self.conn.grab_keys(key_bindings, mouse_bindings)?;
+            for (key, error) in self.grab_report.failed.iter() {
+                warn!(?key, %error, "unable to grab key binding: it may already be grabbed by another program");
+            }
+        }
+
+        Ok(())
+    }
+
+    // Move each binding and its grab over to the key code that its key name now maps to
+    fn move_key_bindings(&mut self, bindings: &mut KeyBindings<X>) -> Result<()> {
+        let codes = match (self.load_key_codes)() {
+            Ok(codes) => codes,
+            Err(e) => {
+                error!(%e, "unable to reload key codes after keymap change");
+                return Ok(());
+            }
+        };
+        let moved: Vec<(KeyCode, KeyCode)> = self
+            .key_names
+            .iter()
+            .flat_map(|(&old, name)| {
+                codes
+                    .get(name)
+                    .map(|&code| (old, KeyCode { code, ..old }))
+                    .filter(|(old, new)| old != new)
+            })
+            .collect();
+
+        // Remove everything first so that bindings that swap key codes don't clobber each other
+        let mut to_grab = Vec::with_capacity(moved.len());
+        for (old, new) in moved {
+            if let Some(name) = self.key_names.remove(&old) {
+                let description = self.key_descriptions.remove(&old);
+                to_grab.push((new, name, bindings.remove(&old), description));
+            }
+            self.bound_keys.remove(&old);
+            self.grab_report.forget(old);
+            self.conn.ungrab_key(old)?;
+        }
+
+        for (new, name, handler, description) in to_grab {
+            debug!(key = ?new, %name, "moving key binding to new key code");
+            self.key_names.insert(new, name);
+            if let Some(d) = description {
+                self.key_descriptions.insert(new, d);
+            }
+            if let Some(h) = handler {
+                self.bound_keys.insert(new);
+                bindings.insert(new, h);
+                let res = self.conn.grab_key(new);
+                self.grab_report.record(new, res);
+            }
+        }
+
+        self.key_codes = Some(codes);
+        Ok(())
+    }
+
     // NOTE: This defers control of the [WindowManager] to the user's mouse-binding action
     //       which can lead to arbitrary calls to public methods on the [WindowManager]
     //       including mutable methods.
     #[tracing::instrument(level = "debug", skip(self, e, bindings), fields(?e.state, ?e.kind))]
     fn run_mouse_binding(&mut self, e: MouseEvent, bindings: &mut MouseBindings<X>) {
+        // The pointer is grabbed while an overview is active so every click is for selecting a
+        // client rather than running a binding.
+        if self.overview.is_some() {
+            if e.kind == MouseEventKind::Press {
+                let selected = self.clients.get(e.target()).map(|c| c.id());
+                if let Err(e) = self.end_overview(selected) {
+                    (self.error_handler)(e);
+                }
+            }
+            return;
+        }
+
+        let e = self.detect_double_click(e, bindings);
         if let Some(action) = bindings.get_mut(&(e.kind, e.state.clone())) {
             // ignoring Child handlers and SIGCHILD
             if let Err(e) = action(self, &e) {
@@ -645,24 +1814,191 @@ impl<X: XConn> WindowManager<X> {
         }
     }
 
+    // X has no notion of a double click so we track the previous press ourselves. Presses are
+    // only converted if there is a DoubleClick binding for the same state, leaving the Press
+    // binding to run as normal otherwise. A third press starts a new click.
+    fn detect_double_click(
+        &mut self,
+        mut e: MouseEvent,
+        bindings: &MouseBindings<X>,
+    ) -> MouseEvent {
+        let interval = self.config.double_click_ms;
+        if e.kind != MouseEventKind::Press
+            || interval == 0
+            || !bindings.contains_key(&(MouseEventKind::DoubleClick, e.state.clone()))
+        {
+            return e;
+        }
+
+        match self.last_press.take() {
+            Some(prev)
+                if prev.state == e.state
+                    && prev.target() == e.target()
+                    && e.time.wrapping_sub(prev.time) <= interval =>
+            {
+                e.kind = MouseEventKind::DoubleClick;
+            }
+            _ => self.last_press = Some(e.clone()),
+        }
+
+        e
+    }
+
     fn set_active_client(&mut self, id: Xid) -> Result<()> {
         self.focus_client(&Selector::WinId(id))
             .map_err(|_| PenroseError::UnknownClient(id))
             .map(|_| ())
     }
 
+    // Confine the pointer to the focused screen if confinement is enabled, otherwise release it
+    fn update_pointer_confinement(&self) -> Result<()> {
+        let region = if self.pointer_confined {
+            Some(self.screens.focused().region(false))
+        } else {
+            None
+        };
+
+        Ok(self.conn.confine_pointer(region)?)
+    }
+
     // Set the active [Screen] based on an (x, y) [Point]. If point is None then we set
     // based on the current cursor position instead.
     fn set_screen_from_point(&mut self, point: Option<Point>) -> Result<()> {
         let point = match point {
             Some(p) => p,
-            None => self.conn.cursor_position()?,
+            None => self.cursor_position()?,
         };
 
         self.focus_screen(&Selector::Condition(&|s: &Screen| s.contains(point)));
         Ok(())
     }
 
+    // Re-read the skip pager / taskbar state of a client after its _NET_WM_STATE changed
+    fn handle_wm_state_change(&mut self, id: Xid) -> Result<()> {
+        if !self.clients.is_known(id) {
+            return Ok(());
+        }
+
+        let wm_state = self
+            .conn
+            .get_atom_list_prop(id, Atom::NetWmState.as_ref())
+            .unwrap_or_default();
+        let changed = match self.clients.get_mut(id) {
+            Some(c) => c.update_skip_state(&wm_state),
+            None => false,
+        };
+
+        if changed {
+            self.update_known_x_clients()?;
+        }
+
+        Ok(())
+    }
+
+    // Apply a request from a client to change its skip pager / taskbar state, updating
+    // _NET_WM_STATE to match.
+    fn set_client_skip_state(&mut self, id: Xid, states: &[Atom], action: usize) -> Result<()> {
+        if !self.clients.is_known(id) {
+            return Ok(());
+        }
+
+        for &a in states {
+            let present = match self.clients.get(id) {
+                Some(c) if a == Atom::NetWmStateSkipPager => c.skip_pager,
+                Some(c) => c.skip_taskbar,
+                None => false,
+            };
+            // _NET_WM_STATE_REMOVE == 0, _NET_WM_STATE_ADD == 1, _NET_WM_STATE_TOGGLE == 2
+            let wanted = match action {
+                1 => true,
+                2 => !present,
+                _ => false,
+            };
+            self.conn.set_client_wm_state(id, a, wanted)?;
+        }
+
+        self.handle_wm_state_change(id)
+    }
+
+    // The startup notification ID set on a window by the client that created it, if any.
+    fn window_startup_id(&self, id: Xid) -> Option<String> {
+        [Atom::NetStartupId, Atom::NetWmStartupId]
+            .iter()
+            .find_map(|a| self.conn.get_utf8_prop(id, a.as_ref()).ok())
+    }
+
+    // Mark any pending launch matching the newly mapped window as complete, dropping those that
+    // have timed out.
+    #[cfg(feature = "startup_notification")]
+    fn complete_startup(&mut self, id: Xid) {
+        for s in self.startups.remove_expired() {
+            debug!(startup_id = %s.id, command = %s.command, "startup sequence timed out");
+        }
+
+        if let Some(s) = self
+            .window_startup_id(id)
+            .and_then(|startup_id| self.startups.complete(&startup_id))
+        {
+            debug!(id, startup_id = %s.id, command = %s.command, "startup sequence complete");
+        }
+    }
+
+    // Re-read the WM_NORMAL_HINTS of a managed client and let hooks know about the new values.
+    fn handle_normal_hints_change(&mut self, id: Xid) -> Result<()> {
+        if !self.clients.is_known(id) {
+            return Ok(());
+        }
+
+        let hints = match self.conn.get_prop(id, Atom::WmNormalHints.as_ref()) {
+            Ok(Prop::WmNormalHints(hints)) => hints,
+            _ => return Ok(()),
+        };
+
+        if let Some(c) = self.clients.get_mut(id) {
+            c.wm_normal_hints = Some(hints.clone());
+        }
+        self.run_hook(HookName::ClientNormalHintsChanged(id, hints));
+
+        Ok(())
+    }
+
+    // Run the callback for a spawned process if the newly mapped window belongs to it. Windows
+    // with a matching startup ID take priority over those with a matching PID.
+    fn resolve_pending_spawn(&mut self, id: Xid) -> Result<()> {
+        let now = Instant::now();
+        self.pending_spawns.retain(|p| {
+            if p.deadline <= now {
+                debug!(
+                    pid = p.pid,
+                    "timed out waiting for a window from spawned process"
+                );
+            }
+            p.deadline > now
+        });
+
+        if self.pending_spawns.is_empty() {
+            return Ok(());
+        }
+
+        let startup_id = self.window_startup_id(id);
+        let pid = self.conn.get_cardinal(id, Atom::NetWmPid.as_ref()).ok();
+
+        let matched = self
+            .pending_spawns
+            .iter()
+            .position(|p| startup_id.as_deref() == Some(p.startup_id.as_str()))
+            .or_else(|| self.pending_spawns.iter().position(|p| pid == Some(p.pid)));
+
+        match matched {
+            Some(ix) => {
+                let pending = self.pending_spawns.remove(ix);
+                debug!(id, pid = pending.pid, "window mapped for spawned process");
+                (pending.callback)(self, id)
+            }
+            None => Ok(()),
+        }
+    }
+
     // Toggle the given client fullscreen. This has knock on effects for other windows and can
     // be triggered by user key bindings as well as applications requesting full screen as well.
     // TODO: should something going fullscreen also hide unmaged windows?
@@ -687,25 +2023,113 @@ impl<X: XConn> WindowManager<X> {
             .state
             .clients
             .toggle_fullscreen(id, wix, &client_ids, r, &self.conn)?;
+        self.update_allowed_actions(id)?;
 
         self.handle_event_actions(actions)
     }
 
+    fn set_minimized(&mut self, id: Xid, should_minimize: bool) -> Result<()> {
+        let (currently_minimized, fullscreen, wix) = self
+            .clients
+            .get(id)
+            .map(|c| (c.minimized, c.fullscreen, c.workspace()))
+            .ok_or(PenroseError::UnknownClient(id))?;
+
+        if currently_minimized == should_minimize {
+            return Ok(()); // Client is already in the correct state
+        }
+
+        let visible = self.screens.visible_workspaces().contains(&wix);
+
+        if should_minimize {
+            if fullscreen {
+                self.set_fullscreen(id, false)?;
+            }
+            self.workspaces.remove_client(wix, id);
+            self.clients.set_minimized(id, true);
+            self.state.clients.unmap_if_needed(id, &self.conn)?;
+            self.conn.set_client_state(id, WindowState::Iconic)?;
+            self.conn.set_client_hidden(id, true)?;
+
+            if visible {
+                self.apply_layout(wix)?;
+                if let Some(next) = self.workspaces[wix].focused_client() {
+                    self.update_focus(next)?;
+                }
+            }
+        } else {
+            self.clients.set_minimized(id, false);
+            self.add_client_to_workspace(wix, id)?;
+            self.conn.set_client_state(id, WindowState::Normal)?;
+            self.conn.set_client_hidden(id, false)?;
+
+            if visible {
+                self.apply_layout(wix)?;
+                self.state.clients.map_if_needed(id, &self.conn)?;
+                self.update_focus(id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Make sure that the clients on the given workspace match its current tag view
+    fn refresh_tag_view(&mut self, wix: usize) -> Result<()> {
+        let ws = match self.workspaces.get(wix) {
+            Some(ws) => ws,
+            None => return Ok(()),
+        };
+
+        let ids = ws.client_ids();
+        let (to_show, to_hide): (Vec<&Client>, Vec<&Client>) = self
+            .clients
+            .clients_for_workspace(wix)
+            .into_iter()
+            .filter(|c| c.wm_managed && !c.minimized)
+            .partition(|c| ws.shows_client(c));
+        let (to_show, to_hide) = (
+            to_show.iter().map(|c| c.id()).collect::<Vec<_>>(),
+            to_hide.iter().map(|c| c.id()).collect::<Vec<_>>(),
+        );
+
+        for id in to_hide.into_iter().filter(|id| ids.contains(id)) {
+            self.workspaces.remove_client(wix, id);
+            self.state.clients.unmap_if_needed(id, &self.conn)?;
+        }
+
+        for id in to_show.into_iter().filter(|id| !ids.contains(id)) {
+            self.add_client_to_workspace(wix, id)?;
+        }
+
+        if self.screens.visible_workspaces().contains(&wix) {
+            self.apply_layout(wix)?;
+            if let Some(id) = self.workspaces[wix].focused_client() {
+                self.update_focus(id)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /*
      * Common mid level actions that make up larger event response handlers.
      */
 
     fn apply_layout(&mut self, wix: usize) -> Result<()> {
-        if let Some(action) = apply_layout(&mut self.state, &self.conn, wix)? {
-            self.handle_event_action(action, None, None)
-        } else {
-            Ok(())
+        // The overview positions its clients itself and lays the workspace out again when it ends
+        if matches!(&self.overview, Some(o) if o.wix == wix) {
+            return Ok(());
         }
+        let pip: Vec<Xid> = self.pip_clients.keys().copied().collect();
+        let actions = apply_layout(&mut self.state, &self.conn, wix, &pip)?;
+        self.handle_event_actions(actions)
     }
 
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn layout_visible(&mut self) -> Result<()> {
-        let actions = layout_visible(&mut self.state, &self.conn)?;
+        let pip: Vec<Xid> = self.pip_clients.keys().copied().collect();
+        let overview_wix = self.overview.as_ref().map(|o| o.wix);
+        let actions = layout_visible(&mut self.state, &self.conn, &pip, overview_wix)?;
         self.handle_event_actions(actions)
     }
 
@@ -721,17 +2145,73 @@ impl<X: XConn> WindowManager<X> {
     }
 
     fn update_known_x_clients(&self) -> Result<()> {
-        let ids = self.clients.all_known_ids();
-        Ok(self.conn.update_known_clients(&ids)?)
+        let ids = self
+            .clients
+            .client_list_ids(self.config.hide_skipped_clients);
+        self.conn.update_known_clients(&ids)?;
+        self.update_x_stacking()
     }
 
-    fn focus_screen(&mut self, sel: &Selector<'_, Screen>) -> &Screen {
-        let prev_wix = self.screens.focused().wix;
+    // Clients without a known stacking position (e.g. following a restart) are listed first.
+    fn update_x_stacking(&self) -> Result<()> {
+        let ids = self
+            .clients
+            .client_list_ids(self.config.hide_skipped_clients);
+        let mut stacking: Vec<Xid> = ids
+            .iter()
+            .filter(|id| !self.stacking.contains(id))
+            .copied()
+            .collect();
+        stacking.extend(self.stacking.iter().filter(|id| ids.contains(id)));
+
+        Ok(self.conn.update_client_stacking(&stacking)?)
+    }
 
-        let actions = self.screens.focus_screen(sel);
-        if let Err(e) = self.handle_event_actions(actions) {
-            (self.error_handler)(e);
-        }
+    /// The IDs of known [clients][Client] in their current stacking order, from bottom to top.
+    ///
+    /// This reflects restacking performed by penrose itself: either through
+    /// [raise_client][WindowManager::raise_client] and friends, raising clients on focus or
+    /// floating clients requesting to be restacked.
+    pub fn stacking_order(&self) -> &[Xid] {
+        &self.stacking
+    }
+
+    /// Raise the [Client] matching the given [Selector] above all other windows.
+    pub fn raise_client(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        match self.client(selector).map(|c| c.id()) {
+            Some(id) => self.restack(id, StackMode::Above, None),
+            None => Ok(()),
+        }
+    }
+
+    /// Lower the [Client] matching the given [Selector] below all other windows.
+    pub fn lower_client(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        match self.client(selector).map(|c| c.id()) {
+            Some(id) => self.restack(id, StackMode::Below, None),
+            None => Ok(()),
+        }
+    }
+
+    /// Restack the [Client] `id` so that it is directly above `sibling`.
+    ///
+    /// Returns an error if either client is not known to penrose.
+    pub fn restack_above(&mut self, id: Xid, sibling: Xid) -> Result<()> {
+        for c in [id, sibling] {
+            if !self.clients.is_known(c) {
+                return Err(PenroseError::UnknownClient(c));
+            }
+        }
+
+        self.restack(id, StackMode::Above, Some(sibling))
+    }
+
+    fn focus_screen(&mut self, sel: &Selector<'_, Screen>) -> &Screen {
+        let prev_wix = self.screens.focused().wix;
+
+        let actions = self.screens.focus_screen(sel);
+        if let Err(e) = self.handle_event_actions(actions) {
+            (self.error_handler)(e);
+        }
 
         // update current workspace if it is different
         let wix = self.screens.focused().wix;
@@ -744,9 +2224,34 @@ impl<X: XConn> WindowManager<X> {
         self.screens.focused()
     }
 
+    // The client that should be focused when focusing the given workspace based on the user's
+    // workspace_focus config setting.
+    fn client_to_focus_on_workspace(&self, wix: usize) -> Result<Option<Xid>> {
+        let ws = self.workspaces.get_workspace(wix)?;
+        let last_focused = ws.focused_client();
+
+        Ok(match self.config.workspace_focus {
+            WorkspaceFocus::LastFocused => last_focused,
+            WorkspaceFocus::Main => ws.iter().next().copied(),
+            WorkspaceFocus::UnderPointer => {
+                let p = self.cursor_position()?;
+                ws.iter()
+                    .find(|&&id| match self.conn.client_geometry(id) {
+                        Ok(r) => r.contains_point(&p),
+                        Err(_) => false,
+                    })
+                    .copied()
+                    .or(last_focused)
+            }
+        })
+    }
+
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn add_client_to_workspace(&mut self, wix: usize, id: Xid) -> Result<()> {
         self.clients.modify(id, |c| c.set_workspace(wix));
+        if !self.focus_history.contains(&id) {
+            self.focus_history.push(id);
+        }
         if let Some(action) = self.workspaces.add_client(wix, id)? {
             self.conn.set_client_workspace(id, wix)?;
             self.handle_event_action(action, None, None)?;
@@ -789,10 +2294,32 @@ impl<X: XConn> WindowManager<X> {
         self.clients.focused_client_id()
     }
 
+    /// Whether or not the given [Client] is currently visible.
+    ///
+    /// A client is visible if its [Workspace] is displayed on one of the connected
+    /// [screens][Screen], it is not minimized and it is not hidden by a tag view on that
+    /// workspace. Unknown clients are never visible.
+    pub fn client_is_visible(&self, id: Xid) -> bool {
+        let client = match self.clients.get(id) {
+            Some(c) => c,
+            None => return false,
+        };
+        let wix = client.workspace();
+
+        !self.clients.is_minimized(id)
+            && self.screens.visible_workspaces().contains(&wix)
+            && self
+                .workspaces
+                .get_workspace(wix)
+                .map(|ws| ws.shows_client(client))
+                .unwrap_or(false)
+    }
+
     /// Cycle between known [screens][Screen]. Does not wrap from first to last
     pub fn cycle_screen(&mut self, direction: Direction) -> Result<()> {
         let old_wix = self.screens.focused().wix;
         let actions = self.state.screens.cycle_screen(direction, &self.conn)?;
+        self.cursor.set(None);
 
         let wix = self.screens.active_ws_index();
         if old_wix != wix {
@@ -805,33 +2332,39 @@ impl<X: XConn> WindowManager<X> {
     /// Cycle between [workspaces][1] on the current [screen][2].
     ///
     /// This method will pull workspaces to the active screen if they are currently displayed on
-    /// another screen.
+    /// another screen, regardless of `visible_workspace_policy`. Whether cycling past the first
+    /// or last workspace wraps around is set by `cycle_overflow` in the current [Config].
     ///
     /// [1]: Workspace
     /// [2]: Screen
     pub fn cycle_workspace(&mut self, direction: Direction) -> Result<()> {
-        let i = self.workspaces.cycle_workspace(direction);
-        self.focus_workspace(&Selector::Index(i))
+        let overflow = self.config.cycle_overflow;
+        let i = self.workspaces.cycle_workspace(direction, overflow);
+        self.pull_workspace(&Selector::Index(i))
     }
 
     /// Move the currently focused [Workspace] to the next [Screen] in 'direction'
     pub fn drag_workspace(&mut self, direction: Direction) -> Result<()> {
         let wix = self.screens.active_ws_index();
         self.cycle_screen(direction)?;
-        self.focus_workspace(&Selector::Index(wix)) // focus_workspace will pull it to the new screen
+        self.pull_workspace(&Selector::Index(wix))
     }
 
     /// Cycle focus between [clients][1] for the active [Workspace]
     ///
+    /// Whether cycling past the first or last client wraps around is set by `cycle_overflow` in
+    /// the current [Config].
+    ///
     /// [1]: Client
     pub fn cycle_client(&mut self, direction: Direction) -> Result<()> {
         let wix = self.screens.active_ws_index();
-        let res = self.workspaces.cycle_client(wix, direction);
+        let overflow = self.config.cycle_overflow;
+        let res = self.workspaces.cycle_client(wix, direction, overflow);
         // 1st parameter is not needed because self.state.clients.focused_client_id has not been updated
         if let Some((_, new)) = res {
             self.update_focus(new)?;
             let screen = self.screens.focused();
-            self.conn.warp_cursor(Some(new), screen)?;
+            self.warp_cursor(Some(new), screen)?;
         }
 
         Ok(())
@@ -845,7 +2378,7 @@ impl<X: XConn> WindowManager<X> {
         };
 
         if let Some(wid) = self.active_workspace().focused_client() {
-            if wid == id {
+            if wid == id && self.clients.focused_client_id() == Some(id) {
                 return Ok(id);
             }
         }
@@ -853,757 +2386,4019 @@ impl<X: XConn> WindowManager<X> {
         // update focused client if there is a new client that is in focus
         self.update_focus(id)?;
         let screen = self.screens.focused();
-        self.conn.warp_cursor(Some(id), screen)?;
+        self.warp_cursor(Some(id), screen)?;
 
         Ok(id)
     }
 
-    /// Rotate the [Client] stack on the active [Workspace].
+    /// Move X input focus to the root window so that no [Client] receives key events.
     ///
-    /// This maintains the current window layout but permutes the positions of each window within
-    /// that layout.
-    pub fn rotate_clients(&mut self, direction: Direction) -> Result<()> {
-        let wix = self.screens.active_ws_index();
-        self.workspaces.rotate_clients(wix, direction);
-        self.apply_layout(wix)
-    }
-
-    /// Move the focused [Client] through the stack of clients on the active [Workspace].
-    pub fn drag_client(&mut self, direction: Direction) -> Result<()> {
+    /// The focused client is cleared, `_NET_ACTIVE_WINDOW` is removed from the root window and
+    /// [focus_change][2] hooks are run with the ID of the root window. Each [Workspace] keeps
+    /// track of the client it had focused so normal focus is restored by the pointer entering a
+    /// client window or by calling [focus_client][1] or any of the other focus methods.
+    ///
+    /// [1]: WindowManager::focus_client
+    /// [2]: crate::core::hooks::Hook::focus_change
+    pub fn unfocus_all(&mut self) -> Result<()> {
         if let Some(id) = self.clients.focused_client_id() {
-            let wix = self.screens.active_ws_index();
-            self.workspaces.drag_client(wix, direction);
-            self.apply_layout(wix)?;
-            self.update_focus(id)?;
-            self.conn.warp_cursor(Some(id), self.screens.focused())?;
+            self.state.clients.client_lost_focus(id, &self.conn);
+            if let Some(opacity) = self.config.unfocused_opacity {
+                self.apply_opacity(id, opacity);
+            }
         }
 
+        let root = self.conn.root();
+        self.conn.focus_root()?;
+        self.conn
+            .delete_prop(root, Atom::NetActiveWindow.as_ref())?;
+        self.run_hook(HookName::FocusChange(root));
+
         Ok(())
     }
 
-    /// Cycle between [layouts][1] for the active [Workspace]
+    /// Focus the next [Client] in most recently used order across all [Workspace]s, bringing its
+    /// workspace into view if needed.
     ///
-    /// [1]: crate::core::layout::Layout
-    pub fn cycle_layout(&mut self, direction: Direction) -> Result<()> {
-        let wix = self.screens.active_ws_index();
-        self.workspaces.cycle_layout(wix, direction);
-        self.run_hook(HookName::LayoutChange(wix));
-        self.apply_layout(wix)
+    /// Repeated calls step further back through the focus history without reordering it, in the
+    /// same way as holding down the modifier key in a traditional alt-tab switcher. Penrose does
+    /// not receive key release events so the selection is committed (moving the selected client
+    /// to the front of the focus history) either by calling [commit_global_focus][1] or by the
+    /// next change of focus that is not from this method.
+    ///
+    /// [1]: WindowManager::commit_global_focus
+    pub fn focus_next_global(&mut self) -> Result<()> {
+        let n = self.focus_history.len();
+        let start = self.global_cycle.unwrap_or(0);
+        let ix = match (1..n)
+            .map(|i| (start + i) % n)
+            .find(|&i| !self.clients.is_minimized(self.focus_history[i]))
+        {
+            Some(ix) => ix,
+            None => return Ok(()),
+        };
+
+        let id = self.focus_history[ix];
+        self.global_cycle = Some(ix);
+        self.cycling_globally = true;
+        let res = self.focus_client_on_any_workspace(id);
+        self.cycling_globally = false;
+
+        res
     }
 
-    /// Increase or decrease the number of clients in the main area by 1.
-    ///
-    /// The change is applied to the active [layout][1] on the [Workspace] that currently holds
-    /// focus.
+    /// Finish cycling through clients with [focus_next_global][1], moving the currently selected
+    /// client to the front of the focus history.
     ///
-    /// [1]: crate::core::layout::Layout
-    pub fn update_max_main(&mut self, change: Change) -> Result<()> {
-        let wix = self.screens.active_ws_index();
-        self.workspaces.update_max_main(wix, change);
-        self.apply_layout(wix)
+    /// [1]: WindowManager::focus_next_global
+    pub fn commit_global_focus(&mut self) {
+        if let Some(ix) = self.global_cycle.take() {
+            let id = self.focus_history.remove(ix);
+            self.focus_history.insert(0, id);
+        }
     }
 
-    /// Increase or decrease the current [layout][1] main_ratio by `main_ratio_step`
-    ///
-    /// The change is applied to the active [layout][1] on the [Workspace] that currently holds
-    /// focus.
-    ///
-    /// [1]: crate::core::layout::Layout
-    pub fn update_main_ratio(&mut self, change: Change) -> Result<()> {
-        let wix = self.screens.active_ws_index();
-        self.workspaces.update_main_ratio(wix, change);
-        self.apply_layout(wix)
+    /// The IDs of all known clients in most recently focused order.
+    pub fn focus_history(&self) -> &[Xid] {
+        &self.focus_history
     }
 
-    /// Shut down the WindowManager, running any required cleanup and exiting penrose
-    ///
-    /// **NOTE**: any registered hooks on the `WindowManager` will still run following calling this
-    /// method, with the actual exit condition being checked and handled at the end.
-    pub fn exit(&mut self) -> Result<()> {
-        self.conn.cleanup()?;
-        self.conn.flush();
-        self.running = false;
+    fn focus_client_on_any_workspace(&mut self, id: Xid) -> Result<()> {
+        let wix = match self.clients.workspace_index_for_client(id) {
+            Some(wix) => wix,
+            None => return Err(PenroseError::UnknownClient(id)),
+        };
 
-        Ok(())
-    }
+        if !self.screens.visible_workspaces().contains(&wix) {
+            self.focus_workspace(&Selector::Index(wix))?;
+        }
 
-    /// The layout symbol for the [layout][1] currently being used on the
-    /// active workspace
-    ///
-    /// [1]: crate::core::layout::Layout
-    pub fn current_layout_symbol(&self) -> &str {
-        let wix = self.screens.active_ws_index();
-        self.workspaces.current_layout_symbol(wix)
-    }
+        self.update_focus(id)?;
+        let screen = self.screens.focused();
+        self.warp_cursor(Some(id), screen)?;
 
-    /// Set the root X window name. Useful for exposing information to external programs
-    pub fn set_root_window_name(&self, s: impl AsRef<str>) -> Result<()> {
-        Ok(self.conn.set_root_window_name(s.as_ref())?)
+        Ok(())
     }
 
-    /// Set the insert point for new clients. Default is to insert at index 0.
-    pub fn set_client_insert_point(&mut self, cip: InsertPoint) -> Result<()> {
-        self.workspaces.set_client_insert_point(cip);
+    /// Save the current positions of the floating [Client]s on the active [Workspace] as a named
+    /// [FloatLayout], replacing any existing layout with the same name.
+    pub fn save_float_layout(&mut self, name: impl Into<String>) -> Result<()> {
+        let mut entries = vec![];
+        for (id, class, ordinal) in self.floating_clients_by_class(self.screens.active_ws_index()) {
+            let region = self.conn.client_geometry(id)?;
+            entries.push(FloatLayoutEntry {
+                class,
+                ordinal,
+                region,
+            });
+        }
 
+        self.float_layouts
+            .insert(name.into(), FloatLayout { entries });
         Ok(())
     }
 
-    /// Set the displayed workspace for the focused screen to be `index` in the list of
-    /// workspaces passed at `init`.
-    ///
-    /// A common way to use this method is in a `refMap` section when generating your keybindings
-    /// and using the [index_selectors][1] helper method to make the required [selectors][2].
+    /// Move the floating [Client]s on the active [Workspace] back to the positions recorded in
+    /// the named [FloatLayout].
     ///
-    /// [1]: crate::core::helpers::index_selectors
-    /// [2]: crate::core::ring::Selector
-    pub fn focus_workspace(&mut self, selector: &Selector<'_, Workspace>) -> Result<()> {
-        let ix = self.screens.focused().wix;
-        if self.workspaces.would_focus(ix, selector) {
-            return Ok(());
-        }
+    /// Saved positions that do not match a current client are skipped and clients without a saved
+    /// position are left where they are.
+    pub fn restore_float_layout(&mut self, name: &str) -> Result<()> {
+        let layout = match self.float_layouts.get(name) {
+            Some(layout) => layout,
+            None => return Err(perror!("unknown float layout: {}", name)),
+        };
 
-        if let Some(index) = self.workspaces.index(selector) {
-            let active = self.screens.active_ws_index();
-            self.previous_workspace = active;
+        let wix = self.screens.active_ws_index();
+        let regions: Vec<(Xid, Region)> = self
+            .floating_clients_by_class(wix)
+            .into_iter()
+            .flat_map(|(id, class, ordinal)| layout.region_for(&class, ordinal).map(|r| (id, r)))
+            .collect();
+
+        for (id, r) in regions {
+            let bpx =
+                util::client_border_px(&self.config, self.screens.focused(), self.clients.get(id));
+            self.conn.position_client(id, r, bpx, true)?;
+            self.clients.modify(id, |c| c.float_region = Some(r));
+        }
 
-            for i in 0..self.screens.n_screens() {
-                if self.screens.inner[i].wix == index {
-                    // The workspace we want is currently displayed on another screen so
-                    // pull the target workspace to the focused screen, and place the
-                    // workspace we had on the screen where the target was
-                    self.screens.inner[i].wix = self.screens.focused().wix;
-                    self.screens.focused_mut().wix = index;
+        Ok(())
+    }
 
-                    // re-apply layouts as screen dimensions may differ
-                    self.apply_layout(active)?;
-                    self.apply_layout(index)?;
+    /// The named [FloatLayout] if it has been saved
+    pub fn float_layout(&self, name: &str) -> Option<&FloatLayout> {
+        self.float_layouts.get(name)
+    }
 
-                    // update xproperty _NET_CURRENT_DESKTOP
-                    self.conn.set_current_workspace(index)?;
+    /// Store a [FloatLayout] under the given name (e.g. one that was previously saved to disk)
+    /// so that it can be restored using [restore_float_layout][1].
+    ///
+    /// [1]: WindowManager::restore_float_layout
+    pub fn set_float_layout(&mut self, name: impl Into<String>, layout: FloatLayout) {
+        self.float_layouts.insert(name.into(), layout);
+    }
 
-                    let ws = self.workspaces.get_workspace(index)?;
-                    if let Some(id) = ws.focused_client() {
-                        self.update_focus(id)?;
-                    };
+    /// Arrange workspaces as described by the given [Profile].
+    ///
+    /// For each [WorkspaceProfile][crate::core::data_types::WorkspaceProfile] the named layout
+    /// is selected and has its `max_main` and `main_ratio` set before any matching [Client]s are
+    /// moved to the workspace. Clients are matched by their `WM_CLASS` and their ordinal among
+    /// all managed clients of the same class (ordered by ID), with entries that do not match a
+    /// current client being skipped.
+    ///
+    /// Returns an error if the profile names a workspace or layout that does not exist, leaving
+    /// any preceding workspaces in the profile arranged.
+    pub fn apply_profile(&mut self, profile: &Profile) -> Result<()> {
+        let by_class = self.clients_by_class(self.clients.all_known_ids());
 
-                    self.workspaces.focus(&Selector::Index(index));
-                    self.run_hook(HookName::WorkspaceChange(active, index));
-                    return Ok(());
-                }
+        for p in profile.workspaces.iter() {
+            let wix = self
+                .workspaces
+                .index(&Selector::Condition(&|ws: &Workspace| {
+                    ws.name() == p.workspace
+                }))
+                .ok_or_else(|| perror!("unknown workspace: {}", p.workspace))?;
+
+            if let Some(symbol) = &p.layout {
+                self.workspaces.set_layout(wix, symbol)?;
+                self.run_hook(HookName::LayoutChange(wix));
             }
-
-            // target not currently displayed so unmap what we currently have
-            // displayed and replace it with the target workspace
-            let ws = self.workspaces.get_workspace(active)?;
-            for id in ws.client_ids().iter() {
-                self.state.clients.unmap_if_needed(*id, &self.conn)?;
+            if let Some(max_main) = p.max_main {
+                self.workspaces.set_max_main(wix, max_main);
+            }
+            if let Some(ratio) = p.main_ratio {
+                self.workspaces.set_main_ratio(wix, ratio);
             }
 
-            let ws = self.workspaces.get_workspace(index)?;
-            for id in ws.client_ids().iter() {
-                self.state.clients.map_if_needed(*id, &self.conn)?;
+            for c in p.clients.iter() {
+                let matched = by_class
+                    .iter()
+                    .find(|(_, class, ordinal)| *class == c.class && *ordinal == c.ordinal);
+                if let Some(&(id, _, _)) = matched {
+                    self.move_client_to_workspace(id, wix)?;
+                }
             }
+        }
 
-            self.screens.focused_mut().wix = index;
-            self.apply_layout(index)?;
-            self.conn.set_current_workspace(index)?;
+        self.layout_visible()
+    }
 
-            let ws = self.workspaces.get_workspace(index)?;
-            if let Some(id) = ws.focused_client() {
-                self.update_focus(id)?;
-            };
+    // The floating clients on the given workspace along with their class and their position
+    // among clients of the same class (ordered by ID) for matching against a FloatLayout.
+    fn floating_clients_by_class(&self, wix: usize) -> Vec<(Xid, String, usize)> {
+        let ws_floating = self.workspaces.is_floating(wix);
+        let ids: Vec<Xid> = self
+            .clients
+            .clients_for_ids(&self.workspaces[wix].client_ids())
+            .into_iter()
+            .filter(|c| ws_floating || c.floating)
+            .map(|c| c.id())
+            .collect();
 
-            self.workspaces.focus(&Selector::Index(index));
-            self.run_hook(HookName::WorkspaceChange(active, index));
-        }
+        self.clients_by_class(ids)
+    }
 
-        Ok(())
+    // The given clients along with their class and their position among the other given clients
+    // of the same class (ordered by ID).
+    fn clients_by_class(&self, ids: Vec<Xid>) -> Vec<(Xid, String, usize)> {
+        let mut clients = self.clients.clients_for_ids(&ids);
+        clients.sort_unstable_by_key(|c| c.id());
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        clients
+            .into_iter()
+            .map(|c| {
+                let class = c.wm_class.first().cloned().unwrap_or_default();
+                let ordinal = seen.entry(class.clone()).or_insert(0);
+                *ordinal += 1;
+                (c.id(), class, *ordinal - 1)
+            })
+            .collect()
     }
 
-    /// Switch focus back to the last workspace that had focus.
-    pub fn toggle_workspace(&mut self) -> Result<()> {
-        self.focus_workspace(&Selector::Index(self.previous_workspace))
+    /// Rotate the [Client] stack on the active [Workspace].
+    ///
+    /// This maintains the current window layout but permutes the positions of each window within
+    /// that layout.
+    pub fn rotate_clients(&mut self, direction: Direction) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        self.workspaces.rotate_clients(wix, direction);
+        self.apply_layout(wix)
     }
 
-    /// Move the focused client to the workspace matching 'selector'.
-    pub fn client_to_workspace(&mut self, selector: &Selector<'_, Workspace>) -> Result<()> {
+    /// Move the focused [Client] through the stack of clients on the active [Workspace].
+    pub fn drag_client(&mut self, direction: Direction) -> Result<()> {
         if let Some(id) = self.clients.focused_client_id() {
-            if let Some(wix) = self.workspaces.index(selector) {
-                self.move_client_to_workspace(id, wix)?;
-                if let Some(now_focused) = self.active_workspace().focused_client() {
-                    self.state.clients.set_focused(now_focused, &self.conn);
-                }
-            }
+            let wix = self.screens.active_ws_index();
+            self.workspaces.drag_client(wix, direction);
+            self.apply_layout(wix)?;
+            self.update_focus(id)?;
+            self.warp_cursor(Some(id), self.screens.focused())?;
         }
 
         Ok(())
     }
 
-    /// Move the focused client to the active workspace on the screen matching 'selector'.
-    pub fn client_to_screen(&mut self, selector: &Selector<'_, Screen>) -> Result<()> {
-        let i = match self.screen(selector) {
-            Some(s) => s.wix,
+    /// Swap the focused [Client] with the tiled client next to it on screen in the given
+    /// direction, based on the regions assigned by the current layout.
+    ///
+    /// This is a no-op if the focused client is not tiled or there is no tiled client in that
+    /// direction.
+    pub fn swap_in_direction(&mut self, direction: RelativePosition) -> Result<()> {
+        let id = match self.clients.focused_client_id() {
+            Some(id) => id,
             None => return Ok(()),
         };
-        self.client_to_workspace(&Selector::Index(i))
-    }
 
-    /// Toggle the fullscreen state of the [Client] matching the given [Selector]
-    pub fn toggle_client_fullscreen(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
-        let (id, client_is_fullscreen) = match self.client(selector) {
-            None => return Ok(()), // unknown client
-            Some(c) => (c.id(), c.fullscreen),
+        let wix = self.screens.active_ws_index();
+        let regions = tiled_regions(&mut self.state, wix)?;
+        let focused = match regions.iter().find(|&&(i, _)| i == id) {
+            Some(&(_, r)) => r,
+            None => return Ok(()),
         };
-        self.set_fullscreen(id, !client_is_fullscreen)
-    }
 
-    /// Kill the focused client window.
-    #[tracing::instrument(level = "debug", err, skip(self))]
-    pub fn kill_client(&mut self) -> Result<()> {
-        if let Some(id) = self.clients.focused_client_id() {
-            let msg = ClientMessageKind::DeleteWindow(id).as_message(&self.conn)?;
-            self.conn.send_client_event(msg)?;
-            self.conn.flush();
+        if let Some(other) = util::neighbour_in_direction(&focused, &regions, direction) {
+            self.workspaces.swap_focused_with(wix, other);
+            self.apply_layout(wix)?;
+            self.update_focus(id)?;
+            self.warp_cursor(Some(id), self.screens.focused())?;
         }
 
         Ok(())
     }
 
-    /// Get a reference to the first Screen satisfying 'selector'. Xid selectors will return
-    /// the screen containing that Client if the client is known.
-    /// NOTE: It is not possible to get a mutable reference to a Screen.
-    pub fn screen(&self, selector: &Selector<'_, Screen>) -> Option<&Screen> {
-        if let Selector::WinId(id) = selector {
-            let wix = self.clients.workspace_index_for_client(*id)?;
-            self.screens.screen(&Selector::Condition(&|s| s.wix == wix))
-        } else {
-            self.screens.screen(&selector)
+    /// Swap the focused [Client] with the client matching the given [Selector] in the client
+    /// order of the active [Workspace], keeping focus on the currently focused client.
+    ///
+    /// This is a no-op if the selected client is the focused client and an error if the selected
+    /// client is not on the active workspace.
+    pub fn swap_focused_with(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let focused = match self.workspaces[wix].focused_client() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let other = match self.client(selector) {
+            Some(c) if c.workspace() == wix => c.id(),
+            _ => return Err(PenroseError::NoMatchingElement),
+        };
+
+        if other != focused {
+            self.workspaces.swap_focused_with(wix, other);
+            self.apply_layout(wix)?;
+            self.update_focus(focused)?;
+            self.warp_cursor(Some(focused), self.screens.focused())?;
         }
-    }
 
-    /// An immutable reference to the current active [Workspace]
-    pub fn active_workspace(&self) -> &Workspace {
-        self.workspaces
-            .workspace(&Selector::Index(self.screens.active_ws_index()))
-            .expect("no active workspace")
+        Ok(())
     }
 
-    /// A mutable reference to the current active [Workspace]
-    pub fn active_workspace_mut(&mut self) -> &mut Workspace {
-        self.state
-            .workspaces
-            .workspace_mut(&Selector::Index(self.screens.active_ws_index()))
-            .expect("no active workspace")
-    }
+    /// Move focus to the nearest [Client] in the given direction from the focused client, based
+    /// on the regions they are occupying on screen.
+    ///
+    /// Both tiled and floating clients are considered, with ties being broken as follows:
+    ///   - only clients whose centre lies in the requested direction are candidates
+    ///   - clients that overlap the focused client along the other axis are preferred
+    ///   - then the client whose centre is closest to that of the focused client
+    ///   - then the client that comes first in the workspace client order
+    ///
+    /// If there is no candidate on the active screen and
+    /// [directional_focus_crosses_screens][1] is set, the nearest screen in that direction is
+    /// selected using the same rules and then the nearest client on that screen. If none of the
+    /// clients on that screen lie in the requested direction, its focused client is used
+    /// instead. When the active workspace is empty the active screen itself is used as the
+    /// starting point.
+    ///
+    /// If [floating_focus_precedence][2] is set to `FloatingOnTop` then tiled clients whose centre
+    /// is covered by a floating client are not candidates.
+    ///
+    /// [1]: crate::core::config::Config::directional_focus_crosses_screens
+    /// [2]: crate::core::config::Config::floating_focus_precedence
+    pub fn focus_in_direction(&mut self, direction: RelativePosition) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let regions = self.client_regions(wix)?;
+        let focused = self.active_workspace().focused_client();
 
-    /// The currently focused workspace indices being shown on each screen
-    pub fn focused_workspaces(&self) -> Vec<usize> {
-        self.screens.visible_workspaces()
-    }
+        let origin = match focused.and_then(|id| regions.iter().find(|&&(i, _)| i == id)) {
+            Some(&(_, r)) => r,
+            None => self.screens.focused().region(false),
+        };
 
-    /// Add a new workspace at `index`, shifting all workspaces with indices greater to the right.
-    pub fn add_workspace(&mut self, index: usize, ws: Workspace) -> Result<()> {
-        self.workspaces.add_workspace(index, ws);
-        self.update_x_workspace_details()
+        let covered = self.covered_by_floating_clients(&regions);
+        let others: Vec<_> = regions
+            .into_iter()
+            .filter(|&(id, _)| Some(id) != focused && !covered.contains(&id))
+            .collect();
+        let mut target = util::focus_target_in_direction(&origin, &others, direction);
+
+        if target.is_none() && self.config.directional_focus_crosses_screens {
+            let screens: Vec<_> = self
+                .screens
+                .iter()
+                .filter(|s| s.wix != wix)
+                .map(|s| (s.wix, s.region(false)))
+                .collect();
+            let current = self.screens.focused().region(false);
+
+            if let Some(w) = util::focus_target_in_direction(&current, &screens, direction) {
+                let regions = self.client_regions(w)?;
+                target = util::focus_target_in_direction(&origin, &regions, direction)
+                    .or_else(|| self.workspaces.get(w).and_then(|ws| ws.focused_client()));
+            }
+        }
+
+        match target {
+            Some(id) => self.focus_client(&Selector::WinId(id)).map(|_| ()),
+            None => Ok(()),
+        }
     }
 
-    /// Add a new workspace at the end of the current workspace list
-    pub fn push_workspace(&mut self, ws: Workspace) -> Result<()> {
-        self.workspaces.push_workspace(ws);
-        self.update_x_workspace_details()
+    /// The client on a visible workspace that is under the given point, taking the
+    /// [floating_focus_precedence][1] config setting into account.
+    ///
+    /// Clients that have not been restacked by penrose since they were mapped are treated as
+    /// being below those that have.
+    ///
+    /// [1]: crate::core::config::Config::floating_focus_precedence
+    pub fn client_at_point(&mut self, p: Point) -> Result<Option<Xid>> {
+        let wix = match self.screens.iter().find(|s| s.contains(p)) {
+            Some(s) => s.wix,
+            None => return Ok(None),
+        };
+        let floating_on_top =
+            self.config.floating_focus_precedence == FloatingFocusPrecedence::FloatingOnTop;
+
+        Ok(self
+            .client_regions(wix)?
+            .into_iter()
+            .filter(|(_, r)| r.contains_point(&p))
+            .max_by_key(|&(id, _)| {
+                let floating = floating_on_top && self.is_floating(id);
+                let stacked = self.stacking.iter().position(|&s| s == id);
+                (floating, stacked)
+            })
+            .map(|(id, _)| id))
     }
 
-    /// Remove a Workspace from the WindowManager. All clients that were present on the removed
-    /// workspace will be destroyed. Xid selectors will be ignored.
-    pub fn remove_workspace(
-        &mut self,
-        selector: &Selector<'_, Workspace>,
-    ) -> Result<Option<Workspace>> {
-        if self.workspaces.len() == self.screens.n_screens() {
-            return Err(perror!("must have at least one workspace per screen"));
+    /// The current position of the cursor relative to the root window.
+    ///
+    /// The position is only queried from the X server once while handling each event or running
+    /// the [tick][crate::core::hooks::Hook::tick] hooks: repeated calls return the same position
+    /// unless penrose has moved the cursor itself.
+    pub fn cursor_position(&self) -> Result<Point> {
+        if let Some(p) = self.cursor.get() {
+            return Ok(p);
         }
+        let p = self.conn.cursor_position()?;
+        self.cursor.set(Some(p));
 
-        let ws = self.workspaces.remove_workspace(&selector)?;
-        ws.iter().try_for_each(|c| self.remove_client(*c))?;
+        Ok(p)
+    }
 
-        // Focus the workspace before the one we just removed. There is always at least one
-        // workspace before this one due to the guard above.
-        let ix = self.screens.focused().wix.saturating_sub(1);
-        self.focus_workspace(&Selector::Index(ix))?;
-        self.update_x_workspace_details()?;
+    /// The client on a visible workspace that is currently under the cursor.
+    ///
+    /// Overlapping clients are resolved in the same way as [client_at_point][1].
+    ///
+    /// [1]: WindowManager::client_at_point
+    pub fn client_under_cursor(&mut self) -> Result<Option<Xid>> {
+        let p = self.cursor_position()?;
+        self.client_at_point(p)
+    }
 
-        Ok(Some(ws))
+    fn warp_cursor(&self, id: Option<Xid>, screen: &Screen) -> Result<()> {
+        self.cursor.set(None);
+        Ok(self.conn.warp_cursor(id, screen)?)
     }
 
-    /// Get a reference to the first Workspace satisfying 'selector'. Xid selectors will return
-    /// the workspace containing that Client if the client is known.
-    pub fn workspace(&self, selector: &Selector<'_, Workspace>) -> Option<&Workspace> {
-        self.workspaces.workspace(selector)
+    fn is_floating(&self, id: Xid) -> bool {
+        matches!(self.clients.get(id), Some(c) if c.floating)
     }
 
-    /// Get a mutable reference to the first Workspace satisfying 'selector'. Xid selectors will
-    /// return the workspace containing that Client if the client is known.
-    pub fn workspace_mut(&mut self, selector: &Selector<'_, Workspace>) -> Option<&mut Workspace> {
-        self.workspaces.workspace_mut(selector)
+    // Entering a tiled client focuses the floating client above that point instead if floating
+    // clients take precedence.
+    fn entered_client_focus_target(&mut self, id: Xid, p: Point) -> Result<Xid> {
+        if self.config.floating_focus_precedence == FloatingFocusPrecedence::Stacking
+            || !self.clients.is_known(id)
+            || self.is_floating(id)
+        {
+            return Ok(id);
+        }
+
+        Ok(match self.client_at_point(p)? {
+            Some(target) if self.is_floating(target) => target,
+            _ => id,
+        })
     }
 
-    /// Get a vector of immutable references to _all_ workspaces that match the provided [Selector].
-    pub fn all_workspaces(&self, selector: &Selector<'_, Workspace>) -> Vec<&Workspace> {
-        self.workspaces.matching_workspaces(selector)
+    // Tiled clients whose centre lies under a floating client if floating clients take
+    // precedence.
+    fn covered_by_floating_clients(&self, regions: &[(Xid, Region)]) -> Vec<Xid> {
+        if self.config.floating_focus_precedence == FloatingFocusPrecedence::Stacking {
+            return vec![];
+        }
+
+        let (floating, tiled): (Vec<_>, Vec<_>) =
+            regions.iter().partition(|(id, _)| self.is_floating(*id));
+
+        tiled
+            .into_iter()
+            .filter(|(_, r)| {
+                let centre = Point::new(r.x + r.w / 2, r.y + r.h / 2);
+                floating.iter().any(|(_, f)| f.contains_point(&centre))
+            })
+            .map(|(id, _)| id)
+            .collect()
     }
 
-    /// Get a vector of mutable references to _all_ workspaces that match the provided [Selector].
-    pub fn all_workspaces_mut(
-        &mut self,
-        selector: &Selector<'_, Workspace>,
-    ) -> Vec<&mut Workspace> {
-        self.workspaces.matching_workspaces_mut(selector)
+    // The on screen regions of the tiled and floating clients on a visible workspace, in
+    // workspace client order.
+    fn client_regions(&mut self, wix: usize) -> Result<Vec<(Xid, Region)>> {
+        let tiled: HashMap<Xid, Region> =
+            tiled_regions(&mut self.state, wix)?.into_iter().collect();
+        let ws = match self.workspaces.get(wix) {
+            Some(ws) => ws,
+            None => return Ok(vec![]),
+        };
+        let floating_layout = ws.layout_conf().floating;
+
+        Ok(ws
+            .iter()
+            .flat_map(|&id| match tiled.get(&id) {
+                Some(&r) => Some((id, r)),
+                None if floating_layout
+                    || matches!(self.clients.get(id), Some(c) if c.floating) =>
+                {
+                    self.conn.client_geometry(id).ok().map(|r| (id, r))
+                }
+                None => None,
+            })
+            .collect())
     }
 
-    /// Set the name of the selected Workspace
-    pub fn set_workspace_name(
-        &mut self,
-        name: impl Into<String>,
-        selector: &Selector<'_, Workspace>,
+    /// Set the opacity of the selected [Client] from 0.0 (fully transparent) to 1.0 (fully
+    /// opaque) by setting its `_NET_WM_WINDOW_OPACITY` property.
+    ///
+    /// This has no visible effect unless a compositor is running. If
+    /// [unfocused_opacity][1] is set then the opacity will be reset the next time that the
+    /// client gains or loses focus.
+    ///
+    /// [1]: crate::core::config::Config::unfocused_opacity
+    pub fn set_opacity(&mut self, selector: &Selector<'_, Client>, opacity: f64) -> Result<()> {
+        let id = self
+            .client(selector)
+            .ok_or(PenroseError::NoMatchingElement)?
+            .id();
+        let val = util::opacity_cardinal(opacity);
+
+        Ok(self
+            .conn
+            .set_cardinal(id, Atom::NetWmWindowOpacity.as_ref(), val)?)
+    }
+
+    /// Send a client message of type `dtype` to the window `id`, for integrating with custom
+    /// protocols supported by individual programs.
+    ///
+    /// `format` is the number of bits in each data value (8, 16 or 32) and `data` must contain
+    /// exactly 20, 10 or 5 values respectively. `dtype` may be the name of any atom, with unknown
+    /// names being interned with the X server.
+    pub fn send_client_message(
+        &self,
+        id: Xid,
+        dtype: &str,
+        format: u8,
+        data: &[u32],
     ) -> Result<()> {
-        self.workspaces.set_workspace_name(name, selector);
-        self.update_x_workspace_details()
+        let msg =
+            ClientMessage::try_from_data(id, ClientEventMask::NoEventMask, dtype, format, data)?;
+
+        Ok(self.conn.send_client_event(msg)?)
     }
 
-    /// Take a reference to the first Client found matching 'selector'
-    pub fn client(&self, selector: &Selector<'_, Client>) -> Option<&Client> {
-        match selector {
-            Selector::Index(i) => self
-                .workspaces
-                .get(self.screens.active_ws_index())
-                .and_then(|ws| ws.iter().nth(*i))
-                .and_then(|id| self.clients.get(*id)),
-            _ => self.clients.client(selector),
+    // Failing to update opacity is purely cosmetic so we log rather than returning an error
+    fn apply_opacity(&mut self, id: Xid, opacity: f64) {
+        if let Err(e) = self.set_opacity(&Selector::WinId(id), opacity) {
+            warn!(id, %e, "unable to set client opacity");
         }
     }
 
-    /// Take a mutable reference to the first Client found matching 'selector'
-    pub fn client_mut(&mut self, selector: &Selector<'_, Client>) -> Option<&mut Client> {
-        match selector {
-            Selector::Index(i) => match self
-                .state
-                .workspaces
-                .get(self.screens.active_ws_index())
-                .and_then(|ws| ws.iter().nth(*i))
-            {
-                Some(id) => self.state.clients.get_mut(*id),
-                None => None,
-            },
-            _ => self.clients.client_mut(selector),
+    // Paint the root window using the configured background. Commands are only run on startup
+    // as tools such as `feh` set a root pixmap that persists until it is replaced.
+    fn paint_root(&self, startup: bool) {
+        match &self.config.root_background {
+            Some(RootBackground::Command(_)) if !startup => (),
+            Some(bg) => self.set_root_background(bg),
+            None => (),
         }
     }
 
-    /// Get a vector of references to the Clients found matching 'selector'.
-    /// The resulting vector is sorted by Client id.
-    pub fn all_clients(&self, selector: &Selector<'_, Client>) -> Vec<&Client> {
-        let mut clients: Vec<&Client> = match selector {
-            Selector::Index(i) => self
-                .workspaces
-                .get(self.screens.active_ws_index())
-                .and_then(|ws| ws.iter().nth(*i))
-                .and_then(|id| self.clients.get(*id))
-                .into_iter()
-                .collect(),
-            _ => self.clients.matching_clients(selector),
+    fn set_root_background(&self, bg: &RootBackground) {
+        let root = self.conn.root();
+        let res = match bg {
+            RootBackground::Color(c) => self
+                .conn
+                .set_client_attributes(root, &[ClientAttr::BackgroundColor(c.rgb_u32())])
+                .and_then(|_| self.conn.clear_client(root))
+                .map_err(PenroseError::from),
+            RootBackground::Command(cmd) => spawn(cmd),
         };
 
-        clients.sort_unstable_by_key(|c| c.id());
-        clients
+        if let Err(e) = res {
+            warn!(%e, "unable to paint the root window");
+        }
     }
 
-    /// Get a vector of mutable references to the Clients found matching 'selector'.
-    ///
-    /// The resulting vector is sorted by Client id.
-    pub fn all_clients_mut(&mut self, selector: &Selector<'_, Client>) -> Vec<&mut Client> {
-        let mut clients: Vec<&mut Client> = match selector {
-            Selector::Index(i) => {
-                match self
-                    .state
-                    .workspaces
-                    .get(self.screens.active_ws_index())
-                    .and_then(|ws| ws.iter().nth(*i))
-                {
-                    Some(id) => self.state.clients.get_mut(*id).into_iter().collect(),
-                    None => vec![],
-                }
-            }
-            _ => self.clients.matching_clients_mut(selector),
+    // Only the background that is currently set is repainted: changes of background are left
+    // to queue_workspace_background.
+    fn paint_root_if_empty(&self, wix: usize) {
+        let bg = match self.workspaces.get_workspace(wix) {
+            Ok(ws) if ws.is_empty() => self.background_for(ws.name()),
+            _ => return,
         };
 
-        clients.sort_unstable_by_key(|c| c.id());
-        clients
+        if let Some(bg @ RootBackground::Color(_)) = bg {
+            if Some(bg) == self.current_background.as_ref() {
+                self.set_root_background(bg);
+            }
+        }
     }
 
-    /// The number of detected screens currently being tracked by the WindowManager.
-    pub fn n_screens(&self) -> usize {
-        self.screens.n_screens()
+    fn background_for(&self, workspace: &str) -> Option<&RootBackground> {
+        self.config
+            .workspace_backgrounds
+            .get(workspace)
+            .or_else(|| self.config.root_background.as_ref())
     }
 
-    /// The current effective screen size of the target screen. Effective screen size is the
-    /// physical screen size minus any space reserved for a status bar.
-    pub fn screen_size(&self, index: usize) -> Option<Region> {
-        self.screens.screen_size(index, self.config.show_bar)
-    }
+    // Queue setting the background for a newly focused workspace if it differs from the one
+    // that was last set. Nothing is queued if workspace_backgrounds is empty so that the
+    // root_background command is still only run on startup.
+    fn queue_workspace_background(&mut self, wix: usize) {
+        if self.config.workspace_backgrounds.is_empty() {
+            return;
+        }
 
-    /// Position an individual client on the display. (x,y) coordinates are absolute (i.e. relative
-    /// to the root window not any individual screen).
-    pub fn position_client(&self, id: Xid, region: Region, stack_above: bool) -> Result<()> {
-        let bpx = self.config.border_px;
-        self.conn
-            .position_client(id, region, bpx, stack_above)
-            .map_err(|e| e.into())
-    }
+        let name = match self.workspaces.get_workspace(wix) {
+            Ok(ws) => ws.name(),
+            Err(_) => return,
+        };
+        let target = match self.background_for(name) {
+            Some(bg) if Some(bg) != self.current_background.as_ref() => bg.clone(),
+            _ => {
+                self.pending_background = None;
+                return;
+            }
+        };
 
-    /// Make the Client with ID 'id' visible at its last known position.
-    pub fn show_client(&mut self, id: Xid) -> Result<()> {
-        self.state.clients.map_if_needed(id, &self.conn)?;
-        // TODO: is this right? Need to double check...
-        self.conn
-            .set_client_workspace(id, self.screens.active_ws_index())
-            .map_err(|e| e.into())
+        let delay = Duration::from_millis(self.config.workspace_background_debounce_ms);
+        self.pending_background = Some((target, Instant::now() + delay));
+        if delay.is_zero() {
+            self.apply_pending_background();
+        }
     }
 
-    /// Hide the Client with ID 'id'.
-    pub fn hide_client(&mut self, id: Xid) -> Result<()> {
-        self.state.clients.unmap_if_needed(id, &self.conn)
+    fn apply_pending_background(&mut self) {
+        if let Some((bg, _)) = self.pending_background.take() {
+            debug!(?bg, "setting workspace background");
+            self.set_root_background(&bg);
+            self.current_background = Some(bg);
+        }
     }
 
-    /// Layout the workspace currently shown on the given screen index.
-    pub fn layout_screen(&mut self, screen_index: usize) -> Result<()> {
-        if let Some(wix) = self.screens.get(screen_index).map(|s| s.wix) {
-            self.apply_layout(wix)?;
+    // Run the configured workspace_spawns command for an empty workspace unless it has already
+    // been run since a client on the workspace last closed and left it empty.
+    fn spawn_if_empty(&mut self, wix: usize) {
+        let name = match self.workspaces.get_workspace(wix) {
+            Ok(ws) if ws.is_empty() => ws.name().to_string(),
+            _ => return,
+        };
+
+        if self.auto_spawned.contains(&name) {
+            return;
         }
 
-        Ok(())
+        if let Some(cmd) = self.config.workspace_spawns.get(&name) {
+            debug!(%name, %cmd, "spawning default program for empty workspace");
+            match spawn(cmd) {
+                Ok(_) => {
+                    self.auto_spawned.insert(name);
+                }
+                Err(e) => warn!(%name, %e, "unable to spawn default program for workspace"),
+            }
+        }
     }
 
-    /// An index into the WindowManager known screens for the screen that is currently focused
-    pub fn active_screen_index(&self) -> usize {
-        self.screens.active_screen_index()
+    /// Spawn an external command, subject to the `spawn_min_interval_ms` and `spawn_dedup_ms`
+    /// limits set in the user [Config].
+    ///
+    /// Requests that fall inside either limit are dropped (returning `Ok`) so that a held down
+    /// key binding can not start a flood of processes. Both limits are disabled by default and
+    /// [spawn][crate::core::helpers::spawn] can be used directly for deliberately rapid spawning.
+    /// This is what key bindings created with [run_external!] use.
+    pub fn spawn(&mut self, cmd: impl Into<String>) -> Result<()> {
+        let cmd = cmd.into();
+        if !self.spawn_allowed(&cmd, Instant::now()) {
+            debug!(%cmd, "dropping rate limited spawn request");
+            return Ok(());
+        }
+
+        spawn(cmd)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        __test_helpers::{
-            n_clients, test_key_bindings, test_mouse_bindings, test_windowmanager, RecordedCall,
-            RecordingXConn,
-        },
-        core::{
-            data_types::*,
-            helpers::logging_error_handler,
-            layout::*,
-            ring::Direction::*,
-            screen::*,
-            xconnection::{MockXConn, Prop, XEvent},
-        },
-        draw::Color,
-    };
+    // Check a spawn request against the configured limits, recording it if it is allowed
+    fn spawn_allowed(&mut self, cmd: &str, now: Instant) -> bool {
+        let min_interval = Duration::from_millis(self.config.spawn_min_interval_ms);
+        let dedup = Duration::from_millis(self.config.spawn_dedup_ms);
+        let within = |t: &Instant, limit: Duration| now.saturating_duration_since(*t) < limit;
 
-    use std::{cell::Cell, collections::HashMap, convert::TryFrom};
+        if self.last_spawn.iter().any(|t| within(t, min_interval)) {
+            return false;
+        }
+        self.recent_spawns.retain(|_, t| within(t, dedup));
+        if self.recent_spawns.contains_key(cmd) {
+            return false;
+        }
 
-    fn wm_with_mock_conn(events: Vec<XEvent>, unmanaged_ids: Vec<Xid>) -> WindowManager<MockXConn> {
-        let conn = MockXConn::new(test_screens(), events, unmanaged_ids);
-        let conf = Config {
-            layouts: focus_test_layouts(false),
-            ..Default::default()
-        };
-        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
-        wm.init().unwrap();
+        if !dedup.is_zero() {
+            self.recent_spawns.insert(cmd.to_string(), now);
+        }
+        self.last_spawn = Some(now);
 
-        wm
+        true
     }
 
-    fn focus_test_layouts(follow_focus: bool) -> Vec<Layout> {
-        let conf = LayoutConf {
-            follow_focus,
-            ..Default::default()
-        };
-        vec![Layout::new("t", conf, mock_layout, 1, 0.6)]
+    /// Spawn an external command in the working directory of the focused [Client]'s process.
+    ///
+    /// The process is found using the `_NET_WM_PID` of the focused client and its working
+    /// directory is read from `/proc/<pid>/cwd`, which is only available on Linux. If no client
+    /// is focused, the client has not set its PID or the directory can not be read then the
+    /// command is run in `$HOME` instead. This is most useful for opening a new terminal in the
+    /// same directory as the focused one.
+    pub fn spawn_in_focused_dir(&self, cmd: impl Into<String>) -> Result<()> {
+        spawn_in_dir(cmd, self.focused_client_dir().as_deref())
     }
 
-    fn test_screens() -> Vec<Screen> {
-        vec![
-            Screen::new(Region::new(0, 0, 1366, 768), 0),
-            Screen::new(Region::new(1366, 0, 1366, 768), 0),
-        ]
+    fn focused_client_dir(&self) -> Option<PathBuf> {
+        self.focused_client_id()
+            .and_then(|id| self.conn.get_cardinal(id, Atom::NetWmPid.as_ref()).ok())
+            .and_then(process_cwd)
+            .or_else(|| env::var_os("HOME").map(PathBuf::from))
     }
 
-    fn add_n_clients<X: XConn>(wm: &mut WindowManager<X>, n: usize, offset: usize) {
-        for i in 0..n {
-            wm.handle_map_request(10 * (i + offset + 1) as u32).unwrap();
-        }
+    /// Spawn an external command and run `callback` with the ID of the first window that it maps.
+    ///
+    /// The command is started with `DESKTOP_STARTUP_ID` set in its environment and new windows
+    /// are matched against it using their `_NET_STARTUP_ID` property, falling back to their
+    /// `_NET_WM_PID`. Clients are not required to set either of these so if no matching window
+    /// is mapped within `timeout` then `callback` is dropped without being run.
+    pub fn spawn_and_wait_for_window<S, F>(
+        &mut self,
+        cmd: S,
+        timeout: Duration,
+        callback: F,
+    ) -> Result<()>
+    where
+        S: Into<String>,
+        F: FnOnce(&mut WindowManager<X>, Xid) -> Result<()> + 'static,
+    {
+        let startup_id = new_startup_id();
+        let pid = spawn_with_env(cmd, &[("DESKTOP_STARTUP_ID", &startup_id)])?;
+        self.wait_for_window(pid, startup_id, timeout, Box::new(callback));
+
+        Ok(())
     }
 
-    #[test]
-    fn workspace_switching_with_active_clients() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
+    /// Spawn an external command using the startup notification protocol, returning the startup
+    /// ID that was assigned to it.
+    ///
+    /// The launch is tracked until a window with a matching `_NET_STARTUP_ID` is mapped or the
+    /// launch times out, allowing the set of in progress launches to be queried using
+    /// [pending_startups][WindowManager::pending_startups] (for example to show a "launching"
+    /// indicator in a status bar).
+    #[cfg(feature = "startup_notification")]
+    pub fn spawn_with_startup_notification<S: Into<String>>(&mut self, cmd: S) -> Result<String> {
+        let cmd = cmd.into();
+        let startup_id = self.startups.begin(cmd.clone());
+        if let Err(e) = spawn_with_env(cmd, &[("DESKTOP_STARTUP_ID", &startup_id)]) {
+            self.startups.complete(&startup_id);
+            return Err(e);
+        }
 
-        // add clients to the first workspace: final client should have focus
-        add_n_clients(&mut wm, 3, 0);
-        assert_eq!(wm.workspaces[0].len(), 3);
-        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+        Ok(startup_id)
+    }
 
-        // switch and add to the second workspace: final client should have focus
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        add_n_clients(&mut wm, 2, 3);
-        assert_eq!(wm.workspaces[1].len(), 2);
-        assert_eq!(wm.workspaces[1].focused_client(), Some(50));
+    /// The launches started by
+    /// [spawn_with_startup_notification][WindowManager::spawn_with_startup_notification] that
+    /// are still waiting for a window to be mapped.
+    #[cfg(feature = "startup_notification")]
+    pub fn pending_startups(&self) -> Vec<&StartupSequence> {
+        self.startups.pending()
+    }
 
-        // switch back: clients should be the same, same client should have focus
-        wm.focus_workspace(&Selector::Index(0)).unwrap();
-        assert_eq!(wm.workspaces[0].len(), 3);
-        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+    /// Whether or not there are any launches that are still waiting for a window to be mapped.
+    #[cfg(feature = "startup_notification")]
+    pub fn is_launching(&self) -> bool {
+        self.startups.is_launching()
     }
 
-    #[test]
-    fn killing_a_client_does_not_remove_it_from_the_workspace() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0);
-        // Should trigger the kill but we wait for DestroyNotify before removing the
-        // client state
-        wm.kill_client().unwrap();
+    /// Set how long launches started by
+    /// [spawn_with_startup_notification][WindowManager::spawn_with_startup_notification] are
+    /// tracked for before being considered to have failed.
+    #[cfg(feature = "startup_notification")]
+    pub fn set_startup_timeout(&mut self, timeout: Duration) {
+        self.startups.set_timeout(timeout);
+    }
 
-        assert_eq!(wm.workspaces[0].len(), 1);
+    fn wait_for_window(
+        &mut self,
+        pid: u32,
+        startup_id: String,
+        timeout: Duration,
+        callback: SpawnCallback<X>,
+    ) {
+        self.pending_spawns.push(PendingSpawn {
+            pid,
+            startup_id,
+            deadline: Instant::now() + timeout,
+            callback,
+        });
     }
 
-    #[test]
-    fn client_to_workspace_inserts_at_head() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 2, 0); // [20, 10]
-        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 20 -> ws::1
-        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 10 -> ws::1, [10, 20]
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-
-        assert_eq!(
-            wm.workspaces[1].iter().collect::<Vec<&Xid>>(),
-            vec![&10, &20]
-        );
+    /// Cycle between [layouts][1] for the active [Workspace]
+    ///
+    /// Changing layout only changes where clients are placed: the order of clients on the
+    /// workspace and which client is focused are left as they were.
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn cycle_layout(&mut self, direction: Direction) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let was_floating = self.layout_is_floating(wix);
+        self.workspaces.cycle_layout(wix, direction);
+        self.sink_floats_if_now_tiling(wix, was_floating)?;
+        self.run_hook(HookName::LayoutChange(wix));
+        self.apply_layout(wix)
     }
 
-    #[test]
-    fn client_to_workspace_sets_focus() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 2, 0); // [20, 10]
-        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 20 -> ws::1
-        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 10 -> ws::1, [10, 20]
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-
-        assert_eq!(wm.workspaces[1].focused_client(), Some(10));
+    /// Move to the next [layout][1] for the active [Workspace], wrapping from the last layout
+    /// back to the first.
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn next_layout(&mut self) -> Result<()> {
+        self.cycle_layout(Direction::Forward)
     }
 
-    #[test]
-    fn client_to_invalid_workspace_is_noop() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0); // [20, 10]
-
-        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
-        wm.client_to_workspace(&Selector::Index(42)).unwrap();
-        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
+    /// Move to the previous [layout][1] for the active [Workspace], wrapping from the first
+    /// layout back to the last.
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn prev_layout(&mut self) -> Result<()> {
+        self.cycle_layout(Direction::Backward)
     }
 
-    #[test]
-    fn client_to_screen_sets_correct_workspace() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0); // [20, 10]
-
-        wm.client_to_screen(&Selector::Index(1)).unwrap();
-        assert_eq!(wm.clients.workspace_index_for_client(10), Some(1));
+    /// Set the [layout][1] for the active [Workspace] to be the one with the given symbol.
+    ///
+    /// Returns an error if the active [Workspace] has no layout with a matching symbol.
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn set_layout(&mut self, symbol: &str) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let was_floating = self.layout_is_floating(wix);
+        self.workspaces.set_layout(wix, symbol)?;
+        self.sink_floats_if_now_tiling(wix, was_floating)?;
+        self.run_hook(HookName::LayoutChange(wix));
+        self.apply_layout(wix)
     }
 
-    #[test]
-    fn client_to_invalid_screen_is_noop() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0); // [20, 10]
-
-        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
-        wm.client_to_screen(&Selector::Index(5)).unwrap();
-        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
+    /// Toggle floating mode for the active [Workspace].
+    ///
+    /// While a workspace is in floating mode its layout is not applied and all clients are left
+    /// at their requested positions.
+    pub fn toggle_workspace_floating(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let was_floating = self.layout_is_floating(wix);
+        self.workspaces.toggle_floating(wix);
+        self.sink_floats_if_now_tiling(wix, was_floating)?;
+        self.run_hook(HookName::LayoutChange(wix));
+        self.apply_layout(wix)
     }
 
-    #[test]
-    fn x_focus_events_set_workspace_focus() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 5, 0); // focus on last client: 50
-        wm.update_focus(10).unwrap();
-
-        assert_eq!(wm.workspaces[0].focused_client(), Some(10));
+    /// Return all floating [Client]s on the active [Workspace] to its layout and re-tile it.
+    ///
+    /// Fullscreen clients, clients with a window type in `sink_exempt_window_types` and (if
+    /// `sink_exempt_sticky` is set) sticky clients are left floating. As with
+    /// [toggle_float][Self::toggle_float], the current position of each client is remembered
+    /// for when it is next floated.
+    pub fn sink_all_floats(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        self.sink_floats(wix)?;
+        self.apply_layout(wix)
     }
 
-    #[test]
-    fn focus_workspace_sets_focus_in_ring() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        assert_eq!(wm.workspaces.focused_index(), 0);
-        assert_eq!(wm.workspaces.focused_index(), wm.screens.active_ws_index());
-        wm.focus_workspace(&Selector::Index(3)).unwrap();
-        assert_eq!(wm.workspaces.focused_index(), 3);
-        assert_eq!(wm.workspaces.focused_index(), wm.screens.active_ws_index());
+    fn layout_is_floating(&self, wix: usize) -> bool {
+        matches!(self.workspaces.get(wix), Some(ws) if ws.is_floating() || ws.layout_conf().floating)
     }
 
-    #[test]
-    fn dragging_clients_forward_from_index_0() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 5, 0); // focus on last client (50) ix == 0
+    // Called after the layout of a workspace has changed: clients may need sinking and can no
+    // longer be moved or resized (or now can be) if the layout has changed to or from floating.
+    fn sink_floats_if_now_tiling(&mut self, wix: usize, was_floating: bool) -> Result<()> {
+        let now_floating = self.layout_is_floating(wix);
+        if self.config.sink_floats_on_tiling && was_floating && !now_floating {
+            self.sink_floats(wix)?;
+        }
 
-        let clients = |w: &mut WindowManager<_>| {
-            w.workspaces[w.screens.get(0).unwrap().wix]
-                .iter()
-                .cloned()
-                .collect::<Vec<_>>()
-        };
+        if was_floating != now_floating {
+            for id in self.workspaces.client_ids(wix)? {
+                self.update_allowed_actions(id)?;
+            }
+        }
 
-        wm.drag_client(Forward).unwrap();
-        assert_eq!(wm.clients.focused_client_id(), Some(50));
-        assert_eq!(clients(&mut wm), vec![40, 50, 30, 20, 10]);
+        Ok(())
+    }
 
-        wm.drag_client(Forward).unwrap();
-        assert_eq!(wm.clients.focused_client_id(), Some(50));
-        assert_eq!(clients(&mut wm), vec![40, 30, 50, 20, 10]);
+    fn sink_floats(&mut self, wix: usize) -> Result<()> {
+        let sticky = Atom::NetWmStateSticky.as_ref();
+        let ids: Vec<Xid> = self
+            .workspaces
+            .client_ids(wix)?
+            .into_iter()
+            .filter(|&id| match self.clients.get(id) {
+                Some(c) => {
+                    c.floating
+                        && !c.fullscreen
+                        && !self.pip_clients.contains_key(&id)
+                        && !c
+                            .wm_type
+                            .iter()
+                            .any(|t| self.config.sink_exempt_window_types.contains(t))
+                }
+                None => false,
+            })
+            .filter(|&id| {
+                !self.config.sink_exempt_sticky
+                    || !matches!(
+                        self.conn.get_atom_list_prop(id, Atom::NetWmState.as_ref()),
+                        Ok(states) if states.iter().any(|s| s == sticky)
+                    )
+            })
+            .collect();
+
+        for id in ids {
+            let r = self.conn.client_geometry(id).ok();
+            self.clients.modify(id, |c| {
+                c.float_region = r.or(c.float_region);
+                c.floating = false;
+            });
+            self.update_allowed_actions(id)?;
+        }
 
-        wm.update_focus(20).unwrap();
-        wm.drag_client(Forward).unwrap();
-        assert_eq!(wm.clients.focused_client_id(), Some(20));
-        assert_eq!(clients(&mut wm), vec![40, 30, 50, 10, 20]);
+        Ok(())
     }
 
-    #[test]
-    fn getting_all_clients_on_workspace() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
+    /// Toggle the [Client] matching the given [Selector] between being tiled and floating.
+    ///
+    /// The position of a floating client is remembered when it is tiled so that floating it again
+    /// restores it. The first time a client is floated it is centered on its screen.
+    pub fn toggle_float(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let (id, wix, floating) = match self.clients.client_mut(selector) {
+            Some(c) => (c.id(), c.workspace(), c.floating),
+            None => return Ok(()), // unknown client
+        };
 
-        add_n_clients(&mut wm, 3, 0);
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        add_n_clients(&mut wm, 2, 3);
+        if floating {
+            let r = self.conn.client_geometry(id)?;
+            if self.pip_clients.remove(&id).is_some() {
+                self.conn
+                    .set_client_wm_state(id, Atom::NetWmStateAbove, false)?;
+            }
+            self.clients.modify(id, |c| {
+                c.float_region = Some(r);
+                c.floating = false;
+            });
+            self.update_allowed_actions(id)?;
+            return self.apply_layout(wix);
+        }
 
-        let ws_0 = Selector::Condition(&|c: &Client| c.workspace() == 0);
-        let ws_1 = Selector::Condition(&|c: &Client| c.workspace() == 1);
+        self.clients.modify(id, |c| c.floating = true);
+        self.update_allowed_actions(id)?;
+        if let Some((_, s)) = self.screens.indexed_screen_for_workspace(wix) {
+            let bpx = util::client_border_px(&self.config, s, self.clients.get(id));
+            let screen_region = s.usable_region(self.config.show_bar);
+            let r = match self.clients.get(id).and_then(|c| c.float_region) {
+                Some(r) => r,
+                None => screen_region
+                    .scale_w(0.5)
+                    .scale_h(0.5)
+                    .centered_in(&screen_region)?,
+            };
+            self.conn.position_client(id, r, bpx, true)?;
+        }
 
-        assert_eq!(wm.all_clients(&ws_0).len(), 3);
-        assert_eq!(wm.all_clients_mut(&ws_1).len(), 2);
+        self.apply_layout(wix)
     }
 
-    #[test]
-    fn selector_screen() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0);
-
-        assert_eq!(wm.screen(&Selector::Focused), Some(wm.screens.focused()));
-        assert_eq!(wm.screen(&Selector::Index(1)), wm.screens.get(1));
-        assert_eq!(wm.screen(&Selector::WinId(10)), wm.screens.get(0));
-        assert_eq!(
-            wm.screen(&Selector::Condition(&|s| s.wix == 1)),
-            wm.screens.get(1)
-        );
+    /// Toggle the focused [Client] in and out of picture-in-picture mode.
+    ///
+    /// Entering picture-in-picture floats the client at `pip_size` in the `pip_corner` of its
+    /// screen and keeps it above the other clients on its workspace, marking it with
+    /// `_NET_WM_STATE_ABOVE`. Toggling it off again returns the client to how it was before:
+    /// tiled clients go back to their previous place in the layout and floating clients to their
+    /// previous position. Fullscreen clients are left alone.
+    ///
+    /// Clients can also request this for themselves by asking to add or remove
+    /// `_NET_WM_STATE_ABOVE`.
+    pub fn toggle_pip(&mut self) -> Result<()> {
+        match self.clients.focused_client_id() {
+            Some(id) => self.set_pip(id, !self.is_pip(id)),
+            None => Ok(()),
+        }
     }
 
-    #[test]
-    fn selector_workspace() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0);
+    fn set_pip(&mut self, id: Xid, should_pip: bool) -> Result<()> {
+        let (wix, floating, fullscreen) = match self.clients.get(id) {
+            Some(c) => (c.workspace(), c.floating, c.fullscreen),
+            None => return Ok(()),
+        };
 
-        assert_eq!(wm.workspace(&Selector::Focused), wm.workspaces.focused());
-        assert_eq!(wm.workspace(&Selector::Index(1)), wm.workspaces.get(1));
-        assert_eq!(wm.workspace(&Selector::WinId(10)), wm.workspaces.get(0));
-        assert_eq!(
-            wm.workspace(&Selector::Condition(&|w| w.name() == "3")),
-            wm.workspaces.get(2)
-        );
-    }
+        if should_pip == self.is_pip(id) || (should_pip && fullscreen) {
+            return Ok(());
+        }
 
-    #[test]
-    fn selector_client() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 4, 0);
+        if let Some(pip) = self.pip_clients.remove(&id) {
+            self.clients.modify(id, |c| c.floating = pip.floating);
+            self.conn
+                .set_client_wm_state(id, Atom::NetWmStateAbove, false)?;
+            self.update_allowed_actions(id)?;
+            if let Some(r) = pip.region {
+                self.position_client(id, r, true)?;
+            }
+            return self.apply_layout(wix);
+        }
 
-        assert_eq!(wm.client(&Selector::Focused), wm.clients.get(40));
-        assert_eq!(wm.client(&Selector::Index(2)), wm.clients.get(20));
-        assert_eq!(wm.client(&Selector::WinId(30)), wm.clients.get(30));
-        assert_eq!(
-            wm.client(&Selector::Condition(&|c| c.id() == 10)),
-            wm.clients.get(10)
+        let (bpx, screen_region) = match self.screens.indexed_screen_for_workspace(wix) {
+            Some((_, s)) => (
+                util::client_border_px(&self.config, s, self.clients.get(id)),
+                s.usable_region(self.config.show_bar),
+            ),
+            None => return Ok(()),
+        };
+        let (fw, fh) = self.config.pip_size;
+        let outer = screen_region
+            .scale_w(fw.clamp(0.0, 1.0))
+            .scale_h(fh.clamp(0.0, 1.0))
+            .in_corner_of(self.config.pip_corner, &screen_region)?;
+        let (x, y, w, h) = outer.values();
+        let r = Region::new(
+            x,
+            y,
+            w.saturating_sub(2 * bpx).max(1),
+            h.saturating_sub(2 * bpx).max(1),
         );
-    }
 
-    #[test]
-    fn unmanaged_window_types_are_not_added_to_workspaces() {
-        // Setting the unmanaged window IDs here sets the return of
-        // MockXConn.is_managed_window to false for those IDs
-        let mut wm = wm_with_mock_conn(vec![], vec![10]);
+        let region = if floating {
+            Some(self.conn.client_geometry(id)?)
+        } else {
+            None
+        };
+        self.pip_clients.insert(id, Pip { floating, region });
+        self.clients.modify(id, |c| c.floating = true);
+        self.conn
+            .set_client_wm_state(id, Atom::NetWmStateAbove, true)?;
+        self.update_allowed_actions(id)?;
+        self.conn.position_client(id, r, bpx, true)?;
 
-        wm.handle_map_request(10).unwrap(); // should not be tiled
-        assert!(wm.clients.get(10).is_some());
-        assert!(wm.workspaces[0].is_empty());
+        // The layout raises the client above everything else on the workspace
+        self.apply_layout(wix)
+    }
 
-        wm.handle_map_request(20).unwrap(); // should be tiled
-        assert!(wm.clients.get(20).is_some());
-        assert!(wm.workspaces[0].len() == 1);
+    /// Whether or not the given client is currently in picture-in-picture mode.
+    ///
+    /// See [toggle_pip][Self::toggle_pip].
+    pub fn is_pip(&self, id: Xid) -> bool {
+        self.pip_clients.contains_key(&id)
     }
 
-    struct ScreenChangingXConn {
-        num_screens: Cell<usize>,
+    /// Begin moving the floating [Client] under the pointer using the mouse.
+    ///
+    /// This is intended to be bound to a mouse button press, with
+    /// [update_floating_drag][Self::update_floating_drag] bound to motion and
+    /// [end_floating_drag][Self::end_floating_drag] bound to the release of the same button.
+    /// Tiled and unknown clients are ignored.
+    ///
+    /// While moving, the client will snap to the edges of screens and other visible clients that
+    /// are within `snap_distance` pixels. Snapping is disabled for motion events where
+    /// `snap_disable_modifier` is held, so motion needs to also be bound with that modifier for it
+    /// to take effect.
+    pub fn start_floating_move(&mut self, e: &MouseEvent) -> Result<()> {
+        self.start_floating_drag(e, None)
     }
 
-    impl ScreenChangingXConn {
-        fn set_num_screens(&mut self, n: usize) {
-            self.num_screens.set(n);
-        }
+    /// Begin resizing the floating [Client] under the pointer using the mouse.
+    ///
+    /// The edge or corner being dragged is determined from where in the client the [MouseEvent]
+    /// occurred (see [Region::resize_zone]). See [start_floating_move][Self::start_floating_move]
+    /// for details of how to bind the rest of the drag.
+    pub fn start_floating_resize(&mut self, e: &MouseEvent) -> Result<()> {
+        let zone = match self.conn.client_geometry(e.target()) {
+            // Events for bindings grabbed on the root window are relative to the root rather
+            // than the client under the pointer.
+            Ok(r) if e.child.is_some() => {
+                let (x, y, _, _) = r.values();
+                r.resize_zone(Point::new(
+                    e.rpt.x.saturating_sub(x),
+                    e.rpt.y.saturating_sub(y),
+                ))
+            }
+            Ok(r) => r.resize_zone(e.wpt),
+            Err(_) => return Ok(()),
+        };
+        self.start_floating_drag(e, Some(zone))
     }
 
-    __impl_stub_xcon! {
-        for ScreenChangingXConn;
+    fn start_floating_drag(&mut self, e: &MouseEvent, zone: Option<ResizeZone>) -> Result<()> {
+        let target = e.target();
+        match self.clients.get(target) {
+            Some(c) if c.floating => (),
+            _ => return Ok(()),
+        }
 
-        atom_queries: {}
-        client_properties: {}
-        client_handler: {}
-        client_config: {}
-        event_handler: {}
-        state: {
-            fn mock_current_screens(&self) -> crate::core::xconnection::Result<Vec<Screen>> {
-                let num_screens = self.num_screens.get();
-                Ok((0..(num_screens))
-                    .map(|n| Screen::new(Region::new(800 * n as u32, 600 * n as u32, 800, 600), n))
-                    .collect())
+        let region = self.conn.client_geometry(target)?;
+        let show_bar = self.config.show_bar;
+        let mut snap_regions: Vec<Region> = self
+            .screens
+            .iter()
+            .map(|s| s.usable_region(show_bar))
+            .collect();
+        for wix in self.screens.visible_workspaces() {
+            for id in self.workspaces[wix].client_ids() {
+                let mapped = self.clients.get(id).map(|c| c.mapped).unwrap_or(false);
+                if id != target && mapped {
+                    if let Ok(r) = self.conn.client_geometry(id) {
+                        snap_regions.push(r);
+                    }
+                }
             }
         }
-        conn: {}
-    }
 
-    impl WindowManager<ScreenChangingXConn> {
-        fn set_num_screens(&mut self, n: usize) {
-            self.conn_mut().set_num_screens(n);
+        let (mut xs, mut ys) = (vec![], vec![]);
+        for (x, y, w, h) in snap_regions.iter().map(|r| r.values()) {
+            xs.extend([x, x + w]);
+            ys.extend([y, y + h]);
         }
+
+        debug!(id = target, ?zone, "starting floating drag");
+        self.floating_drag = Some(FloatingDrag {
+            id: target,
+            zone,
+            start: e.rpt,
+            region,
+            xs,
+            ys,
+        });
+
+        Ok(())
     }
 
-    // TODO: rewrite and move out to screens.rs
-    #[test]
-    fn updating_screens_retains_focused_workspaces() {
-        let conn = ScreenChangingXConn {
-            num_screens: Cell::new(1),
+    /// Move or resize the floating [Client] that is currently being dragged based on the position
+    /// of the given [MouseEvent]. Resized clients will not be shrunk below their requested minimum
+    /// size.
+    pub fn update_floating_drag(&mut self, e: &MouseEvent) -> Result<()> {
+        let drag = match self.floating_drag.as_ref() {
+            Some(drag) => drag,
+            None => return Ok(()),
         };
-        let conf = Config::default();
-        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
-        wm.init().unwrap();
-
-        // detect_screens is called on init so should have one screen
-        assert_eq!(wm.screens.n_screens(), 1);
-        assert_eq!(wm.screens.focused().wix, 0);
 
-        // Focus workspace 1 the redetect screens: should have 1 and 0
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        assert_eq!(wm.screens.focused().wix, 1);
-        wm.set_num_screens(2);
-        wm.detect_screens().unwrap();
-        assert_eq!(wm.screens.n_screens(), 2);
-        assert_eq!(wm.screens.get(0).unwrap().wix, 1);
-        assert_eq!(wm.screens.get(1).unwrap().wix, 0);
+        let client = match self.clients.get(drag.id) {
+            Some(c) => c,
+            None => {
+                self.floating_drag = None;
+                return Ok(());
+            }
+        };
 
-        // Adding another screen should now have WS 2 as 1 is taken
+        let dx = e.rpt.x as i32 - drag.start.x as i32;
+        let dy = e.rpt.y as i32 - drag.start.y as i32;
+        let r = match drag.zone {
+            Some(zone) => {
+                let (min_w, min_h) = match client.wm_normal_hints.as_ref().and_then(|h| h.min) {
+                    Some(min) => {
+                        let (_, _, w, h) = min.values();
+                        (w, h)
+                    }
+                    None => (1, 1),
+                };
+                zone.resize(&drag.region, dx, dy, min_w, min_h)
+            }
+            None => {
+                let (x, y, w, h) = drag.region.values();
+                let moved = Region::new(
+                    (x as i32 + dx).max(0) as u32,
+                    (y as i32 + dy).max(0) as u32,
+                    w,
+                    h,
+                );
+                if e.state
+                    .modifiers
+                    .contains(&self.config.snap_disable_modifier)
+                {
+                    moved
+                } else {
+                    util::snap_region(moved, &drag.xs, &drag.ys, self.config.snap_distance)
+                }
+            }
+        };
+
+        let bpx = match self.screens.indexed_screen_for_workspace(client.workspace) {
+            Some((_, s)) => util::client_border_px(&self.config, s, Some(client)),
+            None => client.border_px.unwrap_or(self.config.border_px),
+        };
+        let id = drag.id;
+        self.conn.position_client(id, r, bpx, true)?;
+        self.clients.modify(id, |c| c.float_region = Some(r));
+
+        Ok(())
+    }
+
+    /// Finish moving or resizing the floating [Client] that is currently being dragged (if any).
+    pub fn end_floating_drag(&mut self) -> Result<()> {
+        if let Some(FloatingDrag { id, .. }) = self.floating_drag.take() {
+            debug!(id, "finished floating drag");
+        }
+        Ok(())
+    }
+
+    /// Increase or decrease the number of clients in the main area by 1.
+    ///
+    /// The change is applied to the active [layout][1] on the [Workspace] that currently holds
+    /// focus. The number of main clients is kept between 1 and `max_main_limit` (and the number
+    /// of clients on the workspace if `max_main_limited_by_clients` is set), with changes past
+    /// either end being handled according to `max_main_overflow` in the current [Config].
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn update_max_main(&mut self, change: Change) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let limit = self.config.max_main_limit;
+        let limit_to_clients = self.config.max_main_limited_by_clients;
+        let overflow = self.config.max_main_overflow;
+        self.workspaces
+            .update_max_main(wix, change, limit, limit_to_clients, overflow);
+        self.apply_layout(wix)
+    }
+
+    /// Increase or decrease the current [layout][1] main_ratio by `main_ratio_step`
+    ///
+    /// The change is applied to the active [layout][1] on the [Workspace] that currently holds
+    /// focus.
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn update_main_ratio(&mut self, change: Change) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        self.workspaces.update_main_ratio(wix, change);
+        self.apply_layout(wix)
+    }
+
+    /// Reset max_main and main_ratio for the active [layout][1] on the focused [Workspace] back
+    /// to the values given in the current [Config] and re-apply the layout.
+    ///
+    /// If `clear_client_overrides` is true then any border width overrides set on clients on
+    /// the workspace using [set_border_width][WindowManager::set_border_width] are also removed.
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn reset_layout(&mut self, clear_client_overrides: bool) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let ws = self.workspaces.get_workspace(wix)?;
+        let defaults = self.config.layouts_for_workspace(ws.name()).clone();
+        let ids = ws.client_ids();
+
+        if !self.workspaces.reset_layout(wix, &defaults) {
+            warn!(
+                layout = self.workspaces.current_layout_symbol(wix),
+                "no configured layout to reset to"
+            );
+        }
+
+        if clear_client_overrides {
+            for id in ids {
+                let floating = match self.clients.get_mut(id) {
+                    Some(c) if c.border_px.is_some() => {
+                        c.border_px = None;
+                        c.floating
+                    }
+                    _ => continue,
+                };
+
+                if let (true, Some((_, s))) =
+                    (floating, self.screens.indexed_screen_for_workspace(wix))
+                {
+                    let bpx = util::client_border_px(&self.config, s, self.clients.get(id));
+                    self.conn
+                        .configure_client(id, &[ClientConfig::BorderPx(bpx)])?;
+                }
+            }
+        }
+
+        self.apply_layout(wix)
+    }
+
+    /// Prevent the pointer from leaving the focused [Screen], or release it if `confine` is false.
+    ///
+    /// While enabled the pointer is confined to whichever screen is focused, following the
+    /// focus as it moves between screens via key bindings such as
+    /// [cycle_screen][WindowManager::cycle_screen]. Clients continue to receive pointer events
+    /// as normal and the pointer is released when penrose exits.
+    pub fn confine_pointer_to_screen(&mut self, confine: bool) -> Result<()> {
+        self.pointer_confined = confine;
+        self.update_pointer_confinement()
+    }
+
+    /// Toggle whether or not the pointer is confined to the focused [Screen]. See
+    /// [confine_pointer_to_screen][WindowManager::confine_pointer_to_screen] for details.
+    pub fn toggle_pointer_confinement(&mut self) -> Result<()> {
+        self.confine_pointer_to_screen(!self.pointer_confined)
+    }
+
+    /// Whether or not the pointer is currently confined to the focused [Screen]
+    pub fn pointer_is_confined(&self) -> bool {
+        self.pointer_confined
+    }
+
+    /// Shut down the WindowManager, running any required cleanup and exiting penrose
+    ///
+    /// **NOTE**: any registered hooks on the `WindowManager` will still run following calling this
+    /// method, with the actual exit condition being checked and handled at the end.
+    pub fn exit(&mut self) -> Result<()> {
+        self.conn.cleanup()?;
+        self.conn.flush();
+        self.running = false;
+
+        Ok(())
+    }
+
+    /// The layout symbol for the [layout][1] currently being used on the
+    /// active workspace
+    ///
+    /// [1]: crate::core::layout::Layout
+    pub fn current_layout_symbol(&self) -> &str {
+        let wix = self.screens.active_ws_index();
+        self.workspaces.current_layout_symbol(wix)
+    }
+
+    /// The name of the [layout][1] currently being used on the active workspace.
+    ///
+    /// Layouts are identified by their symbol so this is equivalent to [current_layout_symbol][2].
+    ///
+    /// [1]: crate::core::layout::Layout
+    /// [2]: WindowManager::current_layout_symbol
+    pub fn current_layout_name(&self) -> &str {
+        self.current_layout_symbol()
+    }
+
+    /// Parse a human readable key binding such as `Mod4+Shift+q` into the [KeyCode] that it
+    /// corresponds to under the current keymap.
+    ///
+    /// See [parse_key_binding] for the accepted format. The keymap is loaded using
+    /// [try_keycodes_from_xmodmap] the first time it is needed (unless one has been provided using
+    /// [set_key_codes][WindowManager::set_key_codes]) and is reloaded after the X server reports
+    /// that the keyboard mapping has changed.
+    pub fn parse_key_binding(&mut self, spec: &str) -> Result<KeyCode> {
+        parse_key_binding(spec, self.key_codes()?)
+    }
+
+    /// Bind `handler` to the key binding described by `spec`, replacing any existing binding for
+    /// the same key combination.
+    ///
+    /// `spec` is parsed using [parse_key_binding][WindowManager::parse_key_binding]. The new key
+    /// combination is grabbed once the current event has finished being processed so it is safe
+    /// to call this from within a key binding action (including the action being replaced). If
+    /// the [WindowManager] is not yet running then the binding is merged with those passed to
+    /// [grab_keys_and_run][WindowManager::grab_keys_and_run] on startup.
+    ///
+    /// Any existing [description][WindowManager::describe_key_binding] of the key combination is
+    /// kept and listed using `spec`.
+    pub fn rebind(&mut self, spec: &str, handler: KeyEventHandler<X>) -> Result<()> {
+        let code = self.parse_key_binding(spec)?;
+        if let Some(name) = spec.rsplit(['-', '+']).next() {
+            self.key_names.insert(code, name.to_string());
+        }
+        let description = self
+            .key_descriptions
+            .remove(&code)
+            .and_then(|d| d.description);
+        self.key_descriptions.insert(
+            code,
+            KeyBindingDescription {
+                spec: spec.to_string(),
+                description,
+            },
+        );
+        self.pending_key_bindings.push((code, Some(handler)));
+        Ok(())
+    }
+
+    /// Set a human readable description for the key binding described by `spec`, replacing any
+    /// existing description of the same key combination. Passing `None` clears the description.
+    ///
+    /// `spec` is parsed using [parse_key_binding][WindowManager::parse_key_binding] and is
+    /// recorded as given for displaying alongside the description. Key combinations can be
+    /// described before they are bound (for example before calling
+    /// [grab_keys_and_run][WindowManager::grab_keys_and_run]) but descriptions are only listed
+    /// by [key_binding_descriptions][WindowManager::key_binding_descriptions] while the key
+    /// combination is bound.
+    pub fn describe_key_binding(&mut self, spec: &str, description: Option<&str>) -> Result<()> {
+        let code = self.parse_key_binding(spec)?;
+        self.key_descriptions.insert(
+            code,
+            KeyBindingDescription {
+                spec: spec.to_string(),
+                description: description.map(|d| d.to_string()),
+            },
+        );
+        Ok(())
+    }
+
+    /// Set the descriptions of multiple key bindings at once, replacing any existing descriptions
+    /// of the same key combinations.
+    ///
+    /// This is intended for use with the descriptions collected by
+    /// [gen_keybindings][crate::gen_keybindings] and, as with
+    /// [describe_key_binding][WindowManager::describe_key_binding], descriptions are only listed
+    /// while the key combination is bound.
+    pub fn describe_key_bindings(&mut self, descriptions: KeyBindingDescriptions) {
+        self.key_descriptions.extend(descriptions);
+    }
+
+    /// The specs and descriptions of all current key bindings, sorted by spec.
+    ///
+    /// Bindings passed to [grab_keys_and_run][WindowManager::grab_keys_and_run] that have not
+    /// been [described][WindowManager::describe_key_binding] are listed using a spec generated
+    /// from their key code by [key_binding_spec]. Bindings added using
+    /// [rebind][WindowManager::rebind] are listed using the spec that they were bound with.
+    ///
+    /// This is intended for building things like a cheatsheet of the current key bindings.
+    pub fn key_binding_descriptions(&self) -> Vec<&KeyBindingDescription> {
+        let mut descriptions: Vec<_> = self
+            .key_descriptions
+            .iter()
+            .filter(|(&code, _)| self.key_is_bound(code))
+            .map(|(_, d)| d)
+            .collect();
+        descriptions.sort();
+        descriptions
+    }
+
+    /// Remove the key binding described by `spec`, releasing the grab on that key combination.
+    ///
+    /// As with [rebind][WindowManager::rebind], the change is applied once the current event has
+    /// finished being processed. Unbinding a key combination that is not bound is a no-op.
+    pub fn unbind(&mut self, spec: &str) -> Result<()> {
+        let code = self.parse_key_binding(spec)?;
+        self.key_names.remove(&code);
+        self.key_descriptions.remove(&code);
+        self.pending_key_bindings.push((code, None));
+        Ok(())
+    }
+
+    /// The outcome of grabbing each of the current key bindings.
+    ///
+    /// This is populated when [grab_keys_and_run][WindowManager::grab_keys_and_run] is called and
+    /// updated as bindings are added and removed using [rebind][WindowManager::rebind] and
+    /// [unbind][WindowManager::unbind]. Key bindings that could not be grabbed are also logged as
+    /// warnings.
+    pub fn grab_report(&self) -> &KeyGrabReport {
+        &self.grab_report
+    }
+
+    /// Set the keymap used to resolve key names when parsing key bindings.
+    ///
+    /// This will be replaced by the output of [try_keycodes_from_xmodmap] if the X server reports
+    /// that the keyboard mapping has changed.
+    pub fn set_key_codes(&mut self, codes: CodeMap) {
+        self.key_codes = Some(codes);
+    }
+
+    fn key_codes(&mut self) -> Result<&CodeMap> {
+        let codes = match self.key_codes.take() {
+            Some(codes) => codes,
+            None => (self.load_key_codes)()?,
+        };
+
+        Ok(self.key_codes.insert(codes))
+    }
+
+    /// Set the root X window name. Useful for exposing information to external programs
+    pub fn set_root_window_name(&self, s: impl AsRef<str>) -> Result<()> {
+        Ok(self.conn.set_root_window_name(s.as_ref())?)
+    }
+
+    /// Set the insert point for new clients. Default is to insert at index 0.
+    pub fn set_client_insert_point(&mut self, cip: InsertPoint) -> Result<()> {
+        self.workspaces.set_client_insert_point(cip);
+
+        Ok(())
+    }
+
+    /// Set the displayed workspace for the focused screen to be `index` in the list of
+    /// workspaces passed at `init`.
+    ///
+    /// If the workspace is already displayed on another screen then what happens is determined
+    /// by `visible_workspace_policy` in the current [Config]: either the two screens swap
+    /// workspaces or focus moves to the screen already showing the target. Focusing the
+    /// workspace that is already on the focused screen is a no-op.
+    ///
+    /// A common way to use this method is in a `refMap` section when generating your keybindings
+    /// and using the [index_selectors][1] helper method to make the required [selectors][2].
+    ///
+    /// [1]: crate::core::helpers::index_selectors
+    /// [2]: crate::core::ring::Selector
+    pub fn focus_workspace(&mut self, selector: &Selector<'_, Workspace>) -> Result<()> {
+        self.change_workspace(selector, self.config.visible_workspace_policy)
+    }
+
+    // Display the selected workspace on the focused screen, swapping it with the workspace on
+    // the focused screen if it is currently displayed elsewhere.
+    fn pull_workspace(&mut self, selector: &Selector<'_, Workspace>) -> Result<()> {
+        self.change_workspace(selector, VisibleWorkspacePolicy::Swap)
+    }
+
+    fn change_workspace(
+        &mut self,
+        selector: &Selector<'_, Workspace>,
+        policy: VisibleWorkspacePolicy,
+    ) -> Result<()> {
+        let ix = self.screens.focused().wix;
+        if self.workspaces.would_focus(ix, selector) {
+            return Ok(());
+        }
+
+        if let Some(index) = self.workspaces.index(selector) {
+            let active = self.screens.active_ws_index();
+            self.previous_workspace = active;
+
+            for i in 0..self.screens.n_screens() {
+                if self.screens.inner[i].wix == index
+                    && policy == VisibleWorkspacePolicy::FocusScreen
+                {
+                    // The workspace we want is currently displayed on another screen so move
+                    // focus to that screen and leave both workspaces where they are
+                    self.focus_screen(&Selector::Index(i));
+                    let id = self.client_to_focus_on_workspace(index)?;
+                    if let Some(id) = id {
+                        self.update_focus(id)?;
+                    };
+                    self.warp_cursor(id, self.screens.focused())?;
+
+                    self.workspaces.focus(&Selector::Index(index));
+                    self.paint_root_if_empty(index);
+                    self.spawn_if_empty(index);
+                    self.queue_workspace_background(index);
+                    self.run_hook(HookName::WorkspaceChange(active, index));
+                    return Ok(());
+                }
+
+                if self.screens.inner[i].wix == index {
+                    // The workspace we want is currently displayed on another screen so
+                    // pull the target workspace to the focused screen, and place the
+                    // workspace we had on the screen where the target was
+                    self.screens.inner[i].wix = self.screens.focused().wix;
+                    self.screens.focused_mut().wix = index;
+                    self.sync_bar_visibility(i);
+                    self.sync_bar_visibility(self.screens.focused_index());
+
+                    // re-apply layouts as screen dimensions may differ
+                    self.apply_layout(active)?;
+                    self.apply_layout(index)?;
+
+                    // update xproperty _NET_CURRENT_DESKTOP
+                    self.conn.set_current_workspace(index)?;
+
+                    if let Some(id) = self.client_to_focus_on_workspace(index)? {
+                        self.update_focus(id)?;
+                    };
+
+                    self.workspaces.focus(&Selector::Index(index));
+                    self.paint_root_if_empty(index);
+                    self.spawn_if_empty(index);
+                    self.queue_workspace_background(index);
+                    self.run_hook(HookName::WorkspaceChange(active, index));
+                    return Ok(());
+                }
+            }
+
+            // target not currently displayed so unmap what we currently have
+            // displayed and replace it with the target workspace
+            let ws = self.workspaces.get_workspace(active)?;
+            for id in ws.client_ids().iter() {
+                self.state.clients.unmap_if_needed(*id, &self.conn)?;
+            }
+
+            let ws = self.workspaces.get_workspace(index)?;
+            for id in ws.client_ids().iter() {
+                self.state.clients.map_if_needed(*id, &self.conn)?;
+            }
+
+            self.screens.focused_mut().wix = index;
+            self.sync_bar_visibility(self.screens.focused_index());
+            self.apply_layout(index)?;
+            self.conn.set_current_workspace(index)?;
+
+            if let Some(id) = self.client_to_focus_on_workspace(index)? {
+                self.update_focus(id)?;
+            };
+
+            self.workspaces.focus(&Selector::Index(index));
+            self.paint_root_if_empty(index);
+            self.spawn_if_empty(index);
+            self.queue_workspace_background(index);
+            self.run_hook(HookName::WorkspaceChange(active, index));
+        }
+
+        Ok(())
+    }
+
+    /// Switch focus back to the last workspace that had focus.
+    pub fn toggle_workspace(&mut self) -> Result<()> {
+        self.focus_workspace(&Selector::Index(self.previous_workspace))
+    }
+
+    /// Move the focused client to the workspace matching 'selector'.
+    ///
+    /// If this leaves the focused workspace empty then focus is updated according to the
+    /// `emptied_workspace_policy` config setting.
+    pub fn client_to_workspace(&mut self, selector: &Selector<'_, Workspace>) -> Result<()> {
+        if let Some(wix) = self.workspaces.index(selector) {
+            if let Some((id, from)) = self.send_focused_client_to(wix)? {
+                self.apply_emptied_workspace_policy(from, id, wix)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Move the focused client to the given workspace, returning its ID and the index of the
+    // workspace it was moved from so that the emptied workspace policy can be applied.
+    fn send_focused_client_to(&mut self, wix: usize) -> Result<Option<(Xid, usize)>> {
+        let id = match self.clients.focused_client_id() {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let from = self.screens.active_ws_index();
+        self.move_client_to_workspace(id, wix)?;
+        if let Some(now_focused) = self.active_workspace().focused_client() {
+            self.state.clients.set_focused(now_focused, &self.conn);
+        }
+
+        Ok(Some((id, from)))
+    }
+
+    fn apply_emptied_workspace_policy(&mut self, from: usize, id: Xid, to: usize) -> Result<()> {
+        let emptied = from != to
+            && self.screens.active_ws_index() == from
+            && matches!(self.workspaces.get(from), Some(ws) if ws.is_empty());
+        if !emptied || self.clients.workspace_index_for_client(id) != Some(to) {
+            return Ok(());
+        }
+
+        match self.config.emptied_workspace_policy {
+            EmptiedWorkspacePolicy::Stay => Ok(()),
+            EmptiedWorkspacePolicy::PreviousWorkspace if self.previous_workspace != from => {
+                self.focus_workspace(&Selector::Index(self.previous_workspace))
+            }
+            EmptiedWorkspacePolicy::PreviousWorkspace => Ok(()),
+            // Clients moved to a workspace on another screen are followed by focusing the screen
+            // rather than pulling their workspace on to the current one.
+            EmptiedWorkspacePolicy::FollowClient
+                if self.screens.visible_workspaces().contains(&to) =>
+            {
+                self.update_focus(id)
+            }
+            EmptiedWorkspacePolicy::FollowClient => self.focus_workspace(&Selector::Index(to)),
+        }
+    }
+
+    /// Move the focused client to the active workspace on the screen matching 'selector'.
+    ///
+    /// Floating clients keep the same relative position on the new screen, scaling their size if
+    /// the screens have different resolutions. As with [client_to_workspace][1], focus is updated
+    /// according to `emptied_workspace_policy` if this leaves the focused workspace empty.
+    ///
+    /// [1]: WindowManager::client_to_workspace
+    pub fn client_to_screen(&mut self, selector: &Selector<'_, Screen>) -> Result<()> {
+        let show_bar = self.config.show_bar;
+        let (i, to) = match self.screen(selector) {
+            Some(s) => (s.wix, s.usable_region(show_bar)),
+            None => return Ok(()),
+        };
+        let from = self.screens.focused().usable_region(show_bar);
+        let float = match self.clients.focused_client() {
+            Some(c) if c.floating && from != to => {
+                self.conn.client_geometry(c.id()).ok().map(|r| (c.id(), r))
+            }
+            _ => None,
+        };
+
+        let moved = self.send_focused_client_to(i)?;
+
+        if let Some((id, r)) = float {
+            let r = r.translate_to_screen(&from, &to);
+            let bpx = match self.screens.indexed_screen_for_workspace(i) {
+                Some((_, s)) => util::client_border_px(&self.config, s, self.clients.get(id)),
+                None => self.config.border_px,
+            };
+            self.conn.position_client(id, r, bpx, true)?;
+            self.clients.modify(id, |c| c.float_region = Some(r));
+        }
+
+        match moved {
+            Some((id, from)) => self.apply_emptied_workspace_policy(from, id, i),
+            None => Ok(()),
+        }
+    }
+
+    /// Move the focused client to the active workspace on the next [Screen], wrapping from the
+    /// last screen back to the first. If `follow` is true then focus moves with the client,
+    /// otherwise focus is updated as for [client_to_screen][Self::client_to_screen].
+    ///
+    /// This is a no-op if there is only a single screen.
+    pub fn move_client_to_next_screen(&mut self, follow: bool) -> Result<()> {
+        self.move_client_to_adjacent_screen(Direction::Forward, follow)
+    }
+
+    /// Move the focused client to the active workspace on the previous [Screen], wrapping from
+    /// the first screen back to the last. If `follow` is true then focus moves with the client,
+    /// otherwise focus is updated as for [client_to_screen][Self::client_to_screen].
+    ///
+    /// This is a no-op if there is only a single screen.
+    pub fn move_client_to_prev_screen(&mut self, follow: bool) -> Result<()> {
+        self.move_client_to_adjacent_screen(Direction::Backward, follow)
+    }
+
+    fn move_client_to_adjacent_screen(&mut self, direction: Direction, follow: bool) -> Result<()> {
+        let n = self.screens.n_screens();
+        let id = match self.clients.focused_client_id() {
+            Some(id) if n > 1 => id,
+            _ => return Ok(()),
+        };
+
+        let current = self.screens.focused_index();
+        let target = match direction {
+            Direction::Forward => (current + 1) % n,
+            Direction::Backward => (current + n - 1) % n,
+        };
+
+        self.client_to_screen(&Selector::Index(target))?;
+        if follow {
+            self.update_focus(id)?;
+            self.warp_cursor(Some(id), self.screens.focused())?;
+        }
+
+        Ok(())
+    }
+
+    /// Toggle the fullscreen state of the [Client] matching the given [Selector]
+    pub fn toggle_client_fullscreen(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let (id, client_is_fullscreen) = match self.client(selector) {
+            None => return Ok(()), // unknown client
+            Some(c) => (c.id(), c.fullscreen),
+        };
+        self.set_fullscreen(id, !client_is_fullscreen)
+    }
+
+    /// Minimize the [Client] matching the given [Selector].
+    ///
+    /// Minimized clients are unmapped and removed from their [Workspace] until they are restored
+    /// using [restore_client][1]. They can be listed using [minimized_clients][2].
+    ///
+    /// [1]: WindowManager::restore_client
+    /// [2]: WindowManager::minimized_clients
+    pub fn minimize_client(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        match self.client(selector) {
+            Some(c) => self.set_minimized(c.id(), true),
+            None => Ok(()), // unknown client
+        }
+    }
+
+    /// Restore the minimized [Client] matching the given [Selector] to its [Workspace].
+    ///
+    /// Clients that are not minimized are left unchanged.
+    pub fn restore_client(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        match self.client(selector) {
+            Some(c) => self.set_minimized(c.id(), false),
+            None => Ok(()), // unknown client
+        }
+    }
+
+    /// Restore the most recently minimized [Client] if there is one.
+    pub fn restore_last_minimized(&mut self) -> Result<()> {
+        match self.clients.minimized_ids().first() {
+            Some(&id) => self.set_minimized(id, false),
+            None => Ok(()),
+        }
+    }
+
+    /// All currently minimized [clients][Client], most recently minimized first.
+    pub fn minimized_clients(&self) -> Vec<&Client> {
+        self.clients
+            .minimized_ids()
+            .into_iter()
+            .flat_map(|id| self.clients.get(id))
+            .collect()
+    }
+
+    /// Override the border width used for the [Client] matching the given [Selector].
+    ///
+    /// Passing `None` removes any existing override so that the client goes back to using the
+    /// `border_px` value from the current [Config].
+    pub fn set_border_width(
+        &mut self,
+        selector: &Selector<'_, Client>,
+        px: Option<u32>,
+    ) -> Result<()> {
+        let (id, wix, floating) = match self.clients.client_mut(selector) {
+            Some(c) => {
+                c.border_px = px;
+                (c.id(), c.workspace(), c.floating)
+            }
+            None => return Ok(()), // unknown client
+        };
+
+        let screen = match self.screens.indexed_screen_for_workspace(wix) {
+            Some((_, s)) => s,
+            None => return Ok(()), // not currently visible
+        };
+
+        if floating {
+            let bpx = util::client_border_px(&self.config, screen, self.clients.get(id));
+            self.conn
+                .configure_client(id, &[ClientConfig::BorderPx(bpx)])?;
+            Ok(())
+        } else {
+            self.apply_layout(wix)
+        }
+    }
+
+    /// Preserve the aspect ratio of the [Client] matching the given [Selector] when it is tiled,
+    /// or stop doing so if `aspect_ratio` is `None`.
+    ///
+    /// Tiled clients that preserve their aspect ratio are shown at the largest size with the
+    /// correct ratio that fits inside of the region assigned to them by the current layout,
+    /// centered within it. The remainder of the region is left empty so that the root window
+    /// shows through as a letterbox: see `root_background` in [Config] for setting its color.
+    /// Floating clients are unaffected.
+    pub fn set_client_aspect_ratio(
+        &mut self,
+        selector: &Selector<'_, Client>,
+        aspect_ratio: Option<AspectRatio>,
+    ) -> Result<()> {
+        let wix = match self.clients.client_mut(selector) {
+            Some(c) => {
+                c.aspect_ratio = aspect_ratio;
+                c.workspace()
+            }
+            None => return Ok(()), // unknown client
+        };
+
+        self.apply_layout(wix)
+    }
+
+    /// Attach a [Tag] to the [Client] matching the given [Selector], updating the tag view of its
+    /// [Workspace] if needed.
+    pub fn add_client_tag(
+        &mut self,
+        selector: &Selector<'_, Client>,
+        tag: impl Into<Tag>,
+    ) -> Result<()> {
+        let wix = match self.clients.client_mut(selector) {
+            Some(c) => {
+                c.add_tag(tag);
+                c.workspace()
+            }
+            None => return Ok(()), // unknown client
+        };
+        self.refresh_tag_view(wix)
+    }
+
+    /// Remove a [Tag] from the [Client] matching the given [Selector], updating the tag view of
+    /// its [Workspace] if needed.
+    pub fn remove_client_tag(&mut self, selector: &Selector<'_, Client>, tag: &str) -> Result<()> {
+        let wix = match self.clients.client_mut(selector) {
+            Some(c) => {
+                c.remove_tag(tag);
+                c.workspace()
+            }
+            None => return Ok(()), // unknown client
+        };
+        self.refresh_tag_view(wix)
+    }
+
+    /// Set the [tags][Tag] being viewed on the active [Workspace].
+    ///
+    /// Tag views are an opt-in alternative to using multiple workspaces: while viewing tags, only
+    /// clients with at least one of the viewed tags are displayed and new clients are given the
+    /// viewed tags when they are first mapped. Use [clear_tag_view][1] to go back to displaying
+    /// all clients on the workspace.
+    ///
+    /// [1]: WindowManager::clear_tag_view
+    pub fn view_tags<T>(&mut self, tags: impl IntoIterator<Item = T>) -> Result<()>
+    where
+        T: Into<Tag>,
+    {
+        let tags: BTreeSet<Tag> = tags.into_iter().map(|t| t.into()).collect();
+        if tags.is_empty() {
+            return Err(perror!("must view at least one tag"));
+        }
+        let wix = self.screens.active_ws_index();
+        self.active_workspace_mut().set_viewed_tags(Some(tags));
+        self.refresh_tag_view(wix)
+    }
+
+    /// Add or remove the given [Tag] from the set of tags being viewed on the active [Workspace].
+    ///
+    /// If the active workspace is not currently using a tag view then this is equivalent to
+    /// calling [view_tags][1] with just this tag. Removing the last viewed tag is not permitted
+    /// and will be ignored.
+    ///
+    /// [1]: WindowManager::view_tags
+    pub fn toggle_viewed_tag(&mut self, tag: impl Into<Tag>) -> Result<()> {
+        let tag = tag.into();
+        let mut tags = self
+            .active_workspace()
+            .viewed_tags()
+            .cloned()
+            .unwrap_or_default();
+        if !tags.remove(&tag) {
+            tags.insert(tag);
+        }
+
+        if tags.is_empty() {
+            return Ok(());
+        }
+        self.view_tags(tags)
+    }
+
+    /// Stop using a tag view on the active [Workspace] and display all of its clients.
+    pub fn clear_tag_view(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        self.active_workspace_mut().set_viewed_tags(None);
+        self.refresh_tag_view(wix)
+    }
+
+    /// The [tags][Tag] currently being viewed on the active [Workspace] (if any)
+    pub fn viewed_tags(&self) -> Option<&BTreeSet<Tag>> {
+        self.active_workspace().viewed_tags()
+    }
+
+    /// Kill the focused client window.
+    ///
+    /// Clients that list `WM_DELETE_WINDOW` in their `WM_PROTOCOLS` are asked to close
+    /// themselves, all other clients are forcibly killed.
+    #[tracing::instrument(level = "debug", err, skip(self))]
+    pub fn kill_client(&mut self) -> Result<()> {
+        match self.clients.focused_client_id() {
+            Some(id) => self.close_client(id),
+            None => Ok(()),
+        }
+    }
+
+    fn close_client(&self, id: Xid) -> Result<()> {
+        // Fall back to asking politely if we are unable to check the client's protocols
+        let delete = Atom::WmDeleteWindow.as_ref();
+        if self
+            .conn
+            .client_supports_protocol(id, delete)
+            .unwrap_or(true)
+        {
+            let msg = ClientMessageKind::DeleteWindow(id).as_message(&self.conn)?;
+            self.conn.send_client_event(msg)?;
+        } else {
+            debug!(id, "client does not support WM_DELETE_WINDOW: killing it");
+            self.conn.kill_client(id)?;
+        }
+        self.conn.flush();
+
+        Ok(())
+    }
+
+    /// Get a reference to the first Screen satisfying 'selector'. Xid selectors will return
+    /// the screen containing that Client if the client is known.
+    /// NOTE: It is not possible to get a mutable reference to a Screen.
+    pub fn screen(&self, selector: &Selector<'_, Screen>) -> Option<&Screen> {
+        if let Selector::WinId(id) = selector {
+            let wix = self.clients.workspace_index_for_client(*id)?;
+            self.screens.screen(&Selector::Condition(&|s| s.wix == wix))
+        } else {
+            self.screens.screen(&selector)
+        }
+    }
+
+    /// An immutable reference to the current active [Workspace]
+    pub fn active_workspace(&self) -> &Workspace {
+        self.workspaces
+            .workspace(&Selector::Index(self.screens.active_ws_index()))
+            .expect("no active workspace")
+    }
+
+    /// A mutable reference to the current active [Workspace]
+    pub fn active_workspace_mut(&mut self) -> &mut Workspace {
+        self.state
+            .workspaces
+            .workspace_mut(&Selector::Index(self.screens.active_ws_index()))
+            .expect("no active workspace")
+    }
+
+    /// The name of the current active [Workspace]
+    pub fn current_workspace_name(&self) -> &str {
+        self.active_workspace().name()
+    }
+
+    /// The names of all [workspaces][Workspace] in order.
+    pub fn workspace_names(&self) -> Vec<String> {
+        self.workspaces.workspace_names()
+    }
+
+    /// Switch focus to the first [Workspace] with the given name.
+    ///
+    /// Returns an error if there is no workspace with a matching name.
+    pub fn focus_workspace_by_name(&mut self, name: &str) -> Result<()> {
+        let wix = self
+            .workspaces
+            .index(&Selector::Condition(&|ws: &Workspace| ws.name() == name))
+            .ok_or_else(|| perror!("unknown workspace: {}", name))?;
+
+        self.focus_workspace(&Selector::Index(wix))
+    }
+
+    /// The currently focused workspace indices being shown on each screen
+    pub fn focused_workspaces(&self) -> Vec<usize> {
+        self.screens.visible_workspaces()
+    }
+
+    /// Add a new workspace at `index`, shifting all workspaces with indices greater to the right.
+    pub fn add_workspace(&mut self, index: usize, ws: Workspace) -> Result<()> {
+        let name = ws.name().to_string();
+        self.workspaces.add_workspace(index, ws);
+        self.update_x_workspace_details()?;
+        let n = self.workspaces.len();
+        self.run_hook(HookName::WorkspaceAdded(index, name, n));
+
+        Ok(())
+    }
+
+    /// Add a new workspace at the end of the current workspace list
+    pub fn push_workspace(&mut self, ws: Workspace) -> Result<()> {
+        let name = ws.name().to_string();
+        self.workspaces.push_workspace(ws);
+        self.update_x_workspace_details()?;
+        let n = self.workspaces.len();
+        self.run_hook(HookName::WorkspaceAdded(n - 1, name, n));
+
+        Ok(())
+    }
+
+    /// Remove a Workspace from the WindowManager. All clients that were present on the removed
+    /// workspace will be destroyed. Xid selectors will be ignored.
+    pub fn remove_workspace(
+        &mut self,
+        selector: &Selector<'_, Workspace>,
+    ) -> Result<Option<Workspace>> {
+        if self.workspaces.len() == self.screens.n_screens() {
+            return Err(perror!("must have at least one workspace per screen"));
+        }
+
+        let index = self.workspaces.index(selector);
+        let ws = self.workspaces.remove_workspace(&selector)?;
+        ws.iter().try_for_each(|c| self.remove_client(*c))?;
+
+        // Focus the workspace before the one we just removed. There is always at least one
+        // workspace before this one due to the guard above.
+        let ix = self.screens.focused().wix.saturating_sub(1);
+        self.focus_workspace(&Selector::Index(ix))?;
+        self.update_x_workspace_details()?;
+        if let Some(index) = index {
+            let n = self.workspaces.len();
+            self.run_hook(HookName::WorkspaceRemoved(index, ws.name().to_string(), n));
+        }
+
+        Ok(Some(ws))
+    }
+
+    /// Get a reference to the first Workspace satisfying 'selector'. Xid selectors will return
+    /// the workspace containing that Client if the client is known.
+    pub fn workspace(&self, selector: &Selector<'_, Workspace>) -> Option<&Workspace> {
+        self.workspaces.workspace(selector)
+    }
+
+    /// Take a [WorkspaceView] snapshot of the first Workspace satisfying 'selector'. Xid selectors
+    /// will return the workspace containing that Client if the client is known.
+    pub fn workspace_view(&self, selector: &Selector<'_, Workspace>) -> Option<WorkspaceView> {
+        self.workspaces
+            .indexed_workspace(selector)
+            .map(|(ix, ws)| ws.view(ix))
+    }
+
+    /// Get a mutable reference to the first Workspace satisfying 'selector'. Xid selectors will
+    /// return the workspace containing that Client if the client is known.
+    pub fn workspace_mut(&mut self, selector: &Selector<'_, Workspace>) -> Option<&mut Workspace> {
+        self.workspaces.workspace_mut(selector)
+    }
+
+    /// Get a vector of immutable references to _all_ workspaces that match the provided [Selector].
+    pub fn all_workspaces(&self, selector: &Selector<'_, Workspace>) -> Vec<&Workspace> {
+        self.workspaces.matching_workspaces(selector)
+    }
+
+    /// Get a vector of mutable references to _all_ workspaces that match the provided [Selector].
+    pub fn all_workspaces_mut(
+        &mut self,
+        selector: &Selector<'_, Workspace>,
+    ) -> Vec<&mut Workspace> {
+        self.workspaces.matching_workspaces_mut(selector)
+    }
+
+    /// Set the name of the selected Workspace
+    pub fn set_workspace_name(
+        &mut self,
+        name: impl Into<String>,
+        selector: &Selector<'_, Workspace>,
+    ) -> Result<()> {
+        self.workspaces.set_workspace_name(name, selector);
+        self.update_x_workspace_details()
+    }
+
+    // Index and FromFocused selectors for clients are resolved against the client order of the
+    // active workspace rather than the full set of known clients.
+    fn active_workspace_client_id(&self, selector: &Selector<'_, Client>) -> Option<Xid> {
+        let ws = self.workspaces.get(self.screens.active_ws_index())?;
+        match selector {
+            Selector::Index(i) => ws.iter().nth(*i).copied(),
+            Selector::FromFocused(n) => ws.client_from_focused(*n),
+            _ => None,
+        }
+    }
+
+    /// Take a reference to the first Client found matching 'selector'
+    pub fn client(&self, selector: &Selector<'_, Client>) -> Option<&Client> {
+        match selector {
+            Selector::Index(_) | Selector::FromFocused(_) => self
+                .active_workspace_client_id(selector)
+                .and_then(|id| self.clients.get(id)),
+            _ => self.clients.client(selector),
+        }
+    }
+
+    /// Take a mutable reference to the first Client found matching 'selector'
+    pub fn client_mut(&mut self, selector: &Selector<'_, Client>) -> Option<&mut Client> {
+        match selector {
+            Selector::Index(_) | Selector::FromFocused(_) => {
+                match self.active_workspace_client_id(selector) {
+                    Some(id) => self.state.clients.get_mut(id),
+                    None => None,
+                }
+            }
+            _ => self.clients.client_mut(selector),
+        }
+    }
+
+    /// Get a vector of references to the Clients found matching 'selector'.
+    /// The resulting vector is sorted by Client id.
+    pub fn all_clients(&self, selector: &Selector<'_, Client>) -> Vec<&Client> {
+        let mut clients: Vec<&Client> = match selector {
+            Selector::Index(_) | Selector::FromFocused(_) => {
+                self.client(selector).into_iter().collect()
+            }
+            _ => self.clients.matching_clients(selector),
+        };
+
+        clients.sort_unstable_by_key(|c| c.id());
+        clients
+    }
+
+    /// Get a vector of mutable references to the Clients found matching 'selector'.
+    ///
+    /// The resulting vector is sorted by Client id.
+    pub fn all_clients_mut(&mut self, selector: &Selector<'_, Client>) -> Vec<&mut Client> {
+        let mut clients: Vec<&mut Client> = match selector {
+            Selector::Index(_) | Selector::FromFocused(_) => {
+                self.client_mut(selector).into_iter().collect()
+            }
+            _ => self.clients.matching_clients_mut(selector),
+        };
+
+        clients.sort_unstable_by_key(|c| c.id());
+        clients
+    }
+
+    /// The IDs of all Clients found matching 'selector', across all workspaces.
+    ///
+    /// The resulting vector is sorted by Client id. It is a snapshot of the clients matching at
+    /// the time of the call so it can be iterated over while making changes that modify the set
+    /// of known clients: see [for_each_client_matching][Self::for_each_client_matching].
+    pub fn matching_client_ids(&self, selector: &Selector<'_, Client>) -> Vec<Xid> {
+        self.all_clients(selector).iter().map(|c| c.id()).collect()
+    }
+
+    /// Run `action` for each Client found matching 'selector', in order of Client id.
+    ///
+    /// The matching clients are found before any actions are run, so changes made by the action
+    /// do not affect which clients it is run for: clients that start matching part way through
+    /// are not included and clients that stop matching are still included. The one exception is
+    /// that clients which are no longer known to the [WindowManager] (because an earlier action
+    /// removed them) are skipped. Note that [kill_client][Self::kill_client] only asks a client
+    /// to close so it will still be known until the X server reports that it has been destroyed.
+    ///
+    /// Stops at and returns the first error returned by `action`.
+    ///
+    /// ```no_run
+    /// # use penrose::{core::{client::Client, manager::WindowManager, ring::Selector, xconnection::XConn}, Result};
+    /// # fn example<X: XConn>(wm: &mut WindowManager<X>) -> Result<()> {
+    /// // Close all firefox windows
+    /// let firefox = Selector::Condition(&|c: &Client| c.class() == "firefox");
+    /// wm.for_each_client_matching(&firefox, |wm, id| {
+    ///     wm.focus_client(&Selector::WinId(id))?;
+    ///     wm.kill_client()
+    /// })
+    /// # }
+    /// ```
+    pub fn for_each_client_matching<F>(
+        &mut self,
+        selector: &Selector<'_, Client>,
+        mut action: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Self, Xid) -> Result<()>,
+    {
+        for id in self.matching_client_ids(selector) {
+            if self.clients.is_known(id) {
+                action(self, id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of detected screens currently being tracked by the WindowManager.
+    pub fn n_screens(&self) -> usize {
+        self.screens.n_screens()
+    }
+
+    /// All of the detected screens currently being tracked by the WindowManager in index order.
+    pub fn screens(&self) -> Vec<&Screen> {
+        self.screens.iter().collect()
+    }
+
+    /// The currently focused [Screen]. Its index is available via
+    /// [active_screen_index][WindowManager::active_screen_index].
+    pub fn focused_screen(&self) -> &Screen {
+        self.screens.focused()
+    }
+
+    /// Set extra padding in pixels to be applied around the edge of the screen with the given
+    /// index when laying out tiled clients. This is applied on top of the normal gaps and is
+    /// preserved when screens are re-detected.
+    ///
+    /// Setting the padding to `0` removes it.
+    pub fn set_screen_padding(&mut self, index: usize, px: u32) -> Result<()> {
+        match self.state.screens.set_padding(index, px) {
+            Some(wix) => self.apply_layout(wix),
+            None => Err(perror!("{} is not a known screen index", index)),
+        }
+    }
+
+    /// Show every client on the focused workspace in a [grid] so that one can be selected by
+    /// clicking on it, after which the workspace returns to its normal layout with the selected
+    /// client focused.
+    ///
+    /// Each client is scaled down to `overview_scale` of its grid cell. The pointer is grabbed
+    /// while the overview is active and clicking anywhere other than on a client ends the
+    /// overview without changing focus, as does calling [end_overview][Self::end_overview].
+    /// The layout of the workspace is not reapplied until the overview ends.
+    pub fn start_overview(&mut self) -> Result<()> {
+        if self.overview.is_some() {
+            return Ok(());
+        }
+
+        let wix = self.screens.active_ws_index();
+        let s = *self.screens.focused();
+        let ids = self.workspaces.get_workspace(wix)?.client_ids();
+        let clients: Vec<&Client> = self
+            .clients
+            .clients_for_ids(&ids)
+            .into_iter()
+            .filter(|c| !c.minimized)
+            .collect();
+        if clients.is_empty() {
+            return Ok(());
+        }
+
+        let floating = clients
+            .iter()
+            .filter(|c| c.floating || self.workspaces.is_floating(wix))
+            .map(|c| Ok((c.id(), self.conn.client_geometry(c.id())?)))
+            .collect::<Result<Vec<_>>>()?;
+        let borders: Vec<u32> = clients
+            .iter()
+            .map(|&c| util::client_border_px(&self.config, &s, Some(c)))
+            .collect();
+
+        let scale = self.config.overview_scale.clamp(0.1, 1.0);
+        let cells = grid(
+            &clients,
+            None,
+            &s.usable_region(self.config.show_bar),
+            1,
+            1.0,
+        );
+        for ((id, cell), bpx) in cells.into_iter().zip(borders) {
+            if let Some(cell) = cell {
+                let r = cell.scale_w(scale).scale_h(scale).centered_in(&cell)?;
+                self.conn.position_client(id, r, bpx, true)?;
+                self.state.clients.map_if_needed(id, &self.conn)?;
+            }
+        }
+
+        self.conn.grab_pointer()?;
+        self.overview = Some(Overview { wix, floating });
+
+        Ok(())
+    }
+
+    /// End an overview started using [start_overview][Self::start_overview], restoring the
+    /// normal layout of the workspace and focusing `selected` if it is one of its clients.
+    pub fn end_overview(&mut self, selected: Option<Xid>) -> Result<()> {
+        let overview = match self.overview.take() {
+            Some(overview) => overview,
+            None => return Ok(()),
+        };
+
+        self.conn.ungrab_pointer()?;
+        if let Some((_, s)) = self.screens.indexed_screen_for_workspace(overview.wix) {
+            for (id, r) in overview.floating {
+                let bpx = util::client_border_px(&self.config, s, self.clients.get(id));
+                self.conn.position_client(id, r, bpx, true)?;
+            }
+        }
+        self.apply_layout(overview.wix)?;
+
+        let on_workspace =
+            |id: &Xid| matches!(self.clients.get(*id), Some(c) if c.workspace() == overview.wix);
+        match selected.filter(on_workspace) {
+            Some(id) => self.update_focus(id),
+            None => Ok(()),
+        }
+    }
+
+    /// Start an overview of the focused workspace if there is not one active, otherwise end the
+    /// current overview without changing focus. See [start_overview][Self::start_overview] for
+    /// details.
+    pub fn toggle_overview(&mut self) -> Result<()> {
+        if self.overview.is_some() {
+            self.end_overview(None)
+        } else {
+            self.start_overview()
+        }
+    }
+
+    /// Whether or not there is currently an active overview started using
+    /// [start_overview][Self::start_overview].
+    pub fn overview_active(&self) -> bool {
+        self.overview.is_some()
+    }
+
+    /// Show or hide the status bar, re-laying out the visible workspaces so that tiled clients
+    /// claim or release the space used by the bar.
+    ///
+    /// If `toggle_bar_on_all_screens` is set in the [Config] then every screen is set to the
+    /// opposite of the current state of the focused screen, otherwise only the bar on the focused
+    /// screen is toggled. The windows of the bar itself are shown and hidden by the
+    /// [bar_visibility_changed][crate::core::hooks::Hook::bar_visibility_changed] hook.
+    ///
+    /// The new state is remembered by the workspaces on the affected screens and restored
+    /// whenever they are shown again. See
+    /// [set_workspace_bar_visible][WindowManager::set_workspace_bar_visible] for details.
+    pub fn toggle_bar(&mut self) -> Result<()> {
+        let visible = !self.screens.focused().bar_visible();
+        let indices: Vec<usize> = if self.config.toggle_bar_on_all_screens {
+            (0..self.screens.n_screens()).collect()
+        } else {
+            vec![self.screens.focused_index()]
+        };
+
+        for index in indices {
+            if let Some(wix) = self.update_bar_visibility(index, visible) {
+                self.workspaces[wix].set_show_bar(Some(visible));
+            }
+        }
+
+        self.layout_visible()
+    }
+
+    /// Show or hide the status bar on the screen with the given index, remembering the new state
+    /// for the workspace currently on that screen. See [toggle_bar][WindowManager::toggle_bar]
+    /// for details.
+    pub fn set_bar_visible(&mut self, index: usize, visible: bool) -> Result<()> {
+        match self.update_bar_visibility(index, visible) {
+            Some(wix) => {
+                self.workspaces[wix].set_show_bar(Some(visible));
+                self.apply_layout(wix)
+            }
+            None => Err(perror!("{} is not a known screen index", index)),
+        }
+    }
+
+    /// Set whether or not the status bar should be shown while the selected workspace is on
+    /// screen, updating the bar immediately if it is currently visible. `None` shows the bar as
+    /// normal.
+    ///
+    /// The initial value for each workspace is taken from `hidden_bar_workspaces` in the
+    /// [Config].
+    pub fn set_workspace_bar_visible(
+        &mut self,
+        selector: &Selector<'_, Workspace>,
+        show_bar: Option<bool>,
+    ) -> Result<()> {
+        let wix = self
+            .workspaces
+            .index(selector)
+            .ok_or(PenroseError::NoMatchingElement)?;
+        self.workspaces[wix].set_show_bar(show_bar);
+
+        let index = self
+            .screens
+            .indexed_screen_for_workspace(wix)
+            .map(|(i, _)| i);
+        match index {
+            Some(index) if self.sync_bar_visibility(index) => self.apply_layout(wix),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set whether or not a lone client on the selected workspace should be shown borderless and
+    /// covering the entire screen, re-applying the layout if the workspace is currently visible.
+    ///
+    /// Clients that have explicitly been made fullscreen take precedence over this setting. The
+    /// initial value for each workspace is taken from `auto_fullscreen_workspaces` in the
+    /// [Config].
+    pub fn set_workspace_auto_fullscreen(
+        &mut self,
+        selector: &Selector<'_, Workspace>,
+        auto_fullscreen_single: bool,
+    ) -> Result<()> {
+        let wix = self
+            .workspaces
+            .index(selector)
+            .ok_or(PenroseError::NoMatchingElement)?;
+        self.workspaces[wix].set_auto_fullscreen_single(auto_fullscreen_single);
+
+        if self.screens.visible_workspaces().contains(&wix) {
+            self.apply_layout(wix)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stable sort the clients of the selected [Workspace] using `cmp`, keeping focus on the
+    /// currently focused client and re-applying the layout if the workspace is visible.
+    pub fn sort_workspace_clients_by<F>(
+        &mut self,
+        selector: &Selector<'_, Workspace>,
+        mut cmp: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&Client, &Client) -> Ordering,
+    {
+        let wix = self
+            .workspaces
+            .index(selector)
+            .ok_or(PenroseError::NoMatchingElement)?;
+
+        let clients = &self.state.clients;
+        self.state.workspaces[wix].sort_clients_by(|&a, &b| {
+            match (clients.get(a), clients.get(b)) {
+                (Some(a), Some(b)) => cmp(a, b),
+                _ => Ordering::Equal,
+            }
+        });
+
+        if self.screens.visible_workspaces().contains(&wix) {
+            self.apply_layout(wix)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Show or hide the bar on the given screen to match the workspace it is showing, returning
+    // whether or not the visibility of the bar changed.
+    fn sync_bar_visibility(&mut self, index: usize) -> bool {
+        let (was_visible, wix) = match self.screens.get(index) {
+            Some(s) => (s.bar_visible(), s.wix),
+            None => return false,
+        };
+        let visible = self.workspaces[wix].show_bar().unwrap_or(true);
+        self.update_bar_visibility(index, visible);
+
+        was_visible != visible
+    }
+
+    // Returns the workspace shown on the screen if it exists, only running hooks when the
+    // visibility of its bar actually changes.
+    fn update_bar_visibility(&mut self, index: usize, visible: bool) -> Option<usize> {
+        let was_visible = self.screens.get(index)?.bar_visible();
+        let wix = self.state.screens.set_bar_visible(index, visible)?;
+        if was_visible != visible {
+            self.run_hook(HookName::BarVisibilityChanged(index, visible));
+        }
+
+        Some(wix)
+    }
+
+    /// The [Workspace] currently being shown on the screen with the given index.
+    pub fn workspace_on_screen(&self, index: usize) -> Option<&Workspace> {
+        self.screens
+            .get(index)
+            .and_then(|s| self.workspaces.get(s.wix))
+    }
+
+    /// The current effective screen size of the target screen. Effective screen size is the
+    /// physical screen size minus any space reserved for a status bar.
+    pub fn screen_size(&self, index: usize) -> Option<Region> {
+        self.screens.screen_size(index, self.config.show_bar)
+    }
+
+    /// Position an individual client on the display. (x,y) coordinates are absolute (i.e. relative
+    /// to the root window not any individual screen).
+    ///
+    /// The border width is scaled for the screen showing the client's workspace, falling back to
+    /// the focused screen for clients that are not currently visible.
+    pub fn position_client(&self, id: Xid, region: Region, stack_above: bool) -> Result<()> {
+        let client = self.clients.get(id);
+        let screen = client
+            .and_then(|c| self.screens.indexed_screen_for_workspace(c.workspace()))
+            .map_or_else(|| self.screens.focused(), |(_, s)| s);
+        let bpx = util::client_border_px(&self.config, screen, client);
+        self.conn
+            .position_client(id, region, bpx, stack_above)
+            .map_err(|e| e.into())
+    }
+
+    /// Make the Client with ID 'id' visible at its last known position.
+    pub fn show_client(&mut self, id: Xid) -> Result<()> {
+        self.state.clients.map_if_needed(id, &self.conn)?;
+        // TODO: is this right? Need to double check...
+        self.conn
+            .set_client_workspace(id, self.screens.active_ws_index())
+            .map_err(|e| e.into())
+    }
+
+    /// Hide the Client with ID 'id'.
+    pub fn hide_client(&mut self, id: Xid) -> Result<()> {
+        self.state.clients.unmap_if_needed(id, &self.conn)
+    }
+
+    /// Layout the workspace currently shown on the given screen index.
+    pub fn layout_screen(&mut self, screen_index: usize) -> Result<()> {
+        if let Some(wix) = self.screens.get(screen_index).map(|s| s.wix) {
+            self.apply_layout(wix)?;
+        }
+
+        Ok(())
+    }
+
+    /// An index into the WindowManager known screens for the screen that is currently focused
+    pub fn active_screen_index(&self) -> usize {
+        self.screens.active_screen_index()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        __test_helpers::{
+            n_clients, test_key_bindings, test_mouse_bindings, test_windowmanager, PointerXConn,
+            RecordedCall, RecordingXConn, TestWM, EXIT_CODE,
+        },
+        core::{
+            bindings::{ModifierKey, MouseButton, MouseEventKind, MouseState},
+            data_types::*,
+            helpers::logging_error_handler,
+            layout::*,
+            ring::Direction::*,
+            screen::*,
+            xconnection::{
+                ClientMessageData, ConfigureEvent, MockXConn, PointerChange, Prop, RandrChange,
+                WmNormalHints, WmNormalHintsFlags, WmStateAction, XClientConfig, XClientProperties,
+                XError, XEvent, XState,
+            },
+        },
+        draw::Color,
+    };
+
+    use std::{
+        cell::{Cell, RefCell},
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    fn wm_with_mock_conn(events: Vec<XEvent>, unmanaged_ids: Vec<Xid>) -> WindowManager<MockXConn> {
+        let conn = MockXConn::new(test_screens(), events, unmanaged_ids);
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm
+    }
+
+    fn focus_test_layouts(follow_focus: bool) -> Vec<Layout> {
+        let conf = LayoutConf {
+            follow_focus,
+            ..Default::default()
+        };
+        vec![Layout::new("t", conf, mock_layout, 1, 0.6)]
+    }
+
+    fn test_screens() -> Vec<Screen> {
+        vec![
+            Screen::new(Region::new(0, 0, 1366, 768), 0),
+            Screen::new(Region::new(1366, 0, 1366, 768), 0),
+        ]
+    }
+
+    fn add_n_clients<X: XConn>(wm: &mut WindowManager<X>, n: usize, offset: usize) {
+        for i in 0..n {
+            wm.handle_map_request(10 * (i + offset + 1) as u32).unwrap();
+        }
+    }
+
+    #[test]
+    fn workspace_switching_with_active_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+
+        // add clients to the first workspace: final client should have focus
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(wm.workspaces[0].len(), 3);
+        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+
+        // switch and add to the second workspace: final client should have focus
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 2, 3);
+        assert_eq!(wm.workspaces[1].len(), 2);
+        assert_eq!(wm.workspaces[1].focused_client(), Some(50));
+
+        // switch back: clients should be the same, same client should have focus
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(wm.workspaces[0].len(), 3);
+        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+    }
+
+    #[test]
+    fn killing_a_client_does_not_remove_it_from_the_workspace() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+        // Should trigger the kill but we wait for DestroyNotify before removing the
+        // client state
+        wm.kill_client().unwrap();
+
+        assert_eq!(wm.workspaces[0].len(), 1);
+    }
+
+    test_cases! {
+        orphaned_transients;
+        args: (policy: TransientPolicy, expected_sent: Vec<Xid>, focused: Option<Xid>, transient_for: Option<Xid>);
+
+        case: keep => (TransientPolicy::Keep, vec![], Some(20), None);
+        case: close => (TransientPolicy::Close, vec![20], None, Some(10));
+
+        body: {
+            let conn = RecordingXConn::init();
+            let conf = Config {
+                transient_policy: policy,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            for id in [10, 20, 30] {
+                wm.handle_map_request(id).unwrap();
+            }
+            wm.clients.modify(20, |c| c.transient_for = Some(10));
+            let delete = vec![Atom::WmDeleteWindow.as_ref().to_string()];
+            wm.conn.set_prop(20, Atom::WmProtocols.as_ref(), Prop::Atom(delete));
+            wm.focus_client(&Selector::WinId(10)).unwrap();
+
+            wm.handle_event_action(EventAction::DestroyClient(10), None, None)
+                .unwrap();
+
+            let sent: Vec<Xid> = wm.conn.sent.borrow().iter().map(|m| m.id).collect();
+            assert_eq!(sent, expected_sent);
+            assert_eq!(wm.focused_client_id(), focused);
+            assert_eq!(wm.clients.get(20).and_then(|c| c.transient_for()), transient_for);
+        }
+    }
+
+    #[test]
+    fn send_client_message_validates_and_sends_the_message() {
+        let conn = RecordingXConn::init();
+        let wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+
+        wm.send_client_message(42, "_MY_PROTOCOL", 16, &[1; 10])
+            .unwrap();
+        let res = wm.send_client_message(42, "_MY_PROTOCOL", 32, &[1; 10]);
+
+        assert!(matches!(
+            res,
+            Err(PenroseError::X(XError::InvalidClientMessageLength(
+                32, 5, 10
+            )))
+        ));
+        let sent = wm.conn.sent.borrow();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].id, 42);
+        assert_eq!(sent[0].dtype, "_MY_PROTOCOL");
+        assert_eq!(sent[0].data(), &ClientMessageData::U16([1; 10]));
+    }
+
+    test_cases! {
+        kill_client_honours_wm_protocols;
+        args: (protocols: Option<Vec<&str>>, deleted: bool);
+
+        case: supports_delete => (Some(vec!["WM_TAKE_FOCUS", "WM_DELETE_WINDOW"]), true);
+        case: other_protocols => (Some(vec!["WM_TAKE_FOCUS"]), false);
+        case: no_protocols => (None, false);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            if let Some(ps) = protocols {
+                let ps = ps.iter().map(|p| p.to_string()).collect();
+                wm.conn.set_prop(10, Atom::WmProtocols.as_ref(), Prop::Atom(ps));
+            }
+            wm.conn.sent.borrow_mut().clear();
+            wm.conn.clear();
+            wm.kill_client().unwrap();
+
+            assert_eq!(!wm.conn.sent.borrow().is_empty(), deleted);
+            assert_eq!(wm.conn.was_called("kill_client"), !deleted);
+        }
+    }
+
+    #[test]
+    fn client_to_workspace_inserts_at_head() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0); // [20, 10]
+        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 20 -> ws::1
+        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 10 -> ws::1, [10, 20]
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+
+        assert_eq!(
+            wm.workspaces[1].iter().collect::<Vec<&Xid>>(),
+            vec![&10, &20]
+        );
+    }
+
+    #[test]
+    fn client_to_workspace_sets_focus() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0); // [20, 10]
+        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 20 -> ws::1
+        wm.client_to_workspace(&Selector::Index(1)).unwrap(); // 10 -> ws::1, [10, 20]
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+
+        assert_eq!(wm.workspaces[1].focused_client(), Some(10));
+    }
+
+    #[test]
+    fn client_to_invalid_workspace_is_noop() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0); // [20, 10]
+
+        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
+        wm.client_to_workspace(&Selector::Index(42)).unwrap();
+        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
+    }
+
+    #[test]
+    fn client_to_screen_sets_correct_workspace() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0); // [20, 10]
+
+        wm.client_to_screen(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.clients.workspace_index_for_client(10), Some(1));
+    }
+
+    #[test]
+    fn client_to_invalid_screen_is_noop() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0); // [20, 10]
+
+        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
+        wm.client_to_screen(&Selector::Index(5)).unwrap();
+        assert_eq!(wm.clients.workspace_index_for_client(10), Some(0));
+    }
+
+    #[test]
+    fn x_focus_events_set_workspace_focus() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 5, 0); // focus on last client: 50
+        wm.update_focus(10).unwrap();
+
+        assert_eq!(wm.workspaces[0].focused_client(), Some(10));
+    }
+
+    #[test]
+    fn focus_workspace_sets_focus_in_ring() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        assert_eq!(wm.workspaces.focused_index(), 0);
+        assert_eq!(wm.workspaces.focused_index(), wm.screens.active_ws_index());
+        wm.focus_workspace(&Selector::Index(3)).unwrap();
+        assert_eq!(wm.workspaces.focused_index(), 3);
+        assert_eq!(wm.workspaces.focused_index(), wm.screens.active_ws_index());
+    }
+
+    #[test]
+    fn dragging_clients_forward_from_index_0() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 5, 0); // focus on last client (50) ix == 0
+
+        let clients = |w: &mut WindowManager<_>| {
+            w.workspaces[w.screens.get(0).unwrap().wix]
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        wm.drag_client(Forward).unwrap();
+        assert_eq!(wm.clients.focused_client_id(), Some(50));
+        assert_eq!(clients(&mut wm), vec![40, 50, 30, 20, 10]);
+
+        wm.drag_client(Forward).unwrap();
+        assert_eq!(wm.clients.focused_client_id(), Some(50));
+        assert_eq!(clients(&mut wm), vec![40, 30, 50, 20, 10]);
+
+        wm.update_focus(20).unwrap();
+        wm.drag_client(Forward).unwrap();
+        assert_eq!(wm.clients.focused_client_id(), Some(20));
+        assert_eq!(clients(&mut wm), vec![40, 30, 50, 10, 20]);
+    }
+
+    #[test]
+    fn getting_all_clients_on_workspace() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+
+        add_n_clients(&mut wm, 3, 0);
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 2, 3);
+
+        let ws_0 = Selector::Condition(&|c: &Client| c.workspace() == 0);
+        let ws_1 = Selector::Condition(&|c: &Client| c.workspace() == 1);
+
+        assert_eq!(wm.all_clients(&ws_0).len(), 3);
+        assert_eq!(wm.all_clients_mut(&ws_1).len(), 2);
+    }
+
+    test_cases! {
+        emptied_workspace_policy;
+        args: (
+            policy: EmptiedWorkspacePolicy,
+            n_clients: usize,
+            target: usize,
+            expected: usize,
+            expected_visible: Vec<usize>
+        );
+
+        case: stay => (EmptiedWorkspacePolicy::Stay, 1, 3, 2, vec![2, 1]);
+        case: previous => (EmptiedWorkspacePolicy::PreviousWorkspace, 1, 3, 0, vec![0, 1]);
+        case: follow => (EmptiedWorkspacePolicy::FollowClient, 1, 3, 3, vec![3, 1]);
+        // Following to another screen focuses that screen rather than pulling its workspace
+        case: follow_to_other_screen => (EmptiedWorkspacePolicy::FollowClient, 1, 1, 1, vec![2, 1]);
+        case: previous_not_emptied => (EmptiedWorkspacePolicy::PreviousWorkspace, 2, 3, 2, vec![2, 1]);
+        case: follow_not_emptied => (EmptiedWorkspacePolicy::FollowClient, 2, 3, 2, vec![2, 1]);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.emptied_workspace_policy = policy;
+            wm.focus_workspace(&Selector::Index(2)).unwrap();
+            add_n_clients(&mut wm, n_clients, 0);
+
+            wm.client_to_workspace(&Selector::Index(target)).unwrap();
+
+            assert_eq!(wm.screens.active_ws_index(), expected);
+            assert_eq!(wm.screens.focused().wix, expected);
+            assert_eq!(wm.screens.visible_workspaces(), expected_visible);
+        }
+    }
+
+    test_cases! {
+        emptied_workspace_policy_moving_to_a_screen;
+        args: (
+            policy: EmptiedWorkspacePolicy,
+            action: fn(&mut WindowManager<MockXConn>),
+            expected: usize,
+            expected_visible: Vec<usize>
+        );
+
+        case: client_to_screen_stay => (
+            EmptiedWorkspacePolicy::Stay,
+            |wm| wm.client_to_screen(&Selector::Index(1)).unwrap(),
+            2,
+            vec![2, 1]
+        );
+        case: client_to_screen_previous => (
+            EmptiedWorkspacePolicy::PreviousWorkspace,
+            |wm| wm.client_to_screen(&Selector::Index(1)).unwrap(),
+            0,
+            vec![0, 1]
+        );
+        case: client_to_screen_follow => (
+            EmptiedWorkspacePolicy::FollowClient,
+            |wm| wm.client_to_screen(&Selector::Index(1)).unwrap(),
+            1,
+            vec![2, 1]
+        );
+        case: next_screen_previous => (
+            EmptiedWorkspacePolicy::PreviousWorkspace,
+            |wm| wm.move_client_to_next_screen(false).unwrap(),
+            0,
+            vec![0, 1]
+        );
+        case: next_screen_follow => (
+            EmptiedWorkspacePolicy::FollowClient,
+            |wm| wm.move_client_to_next_screen(false).unwrap(),
+            1,
+            vec![2, 1]
+        );
+        case: prev_screen_previous => (
+            EmptiedWorkspacePolicy::PreviousWorkspace,
+            |wm| wm.move_client_to_prev_screen(false).unwrap(),
+            0,
+            vec![0, 1]
+        );
+        case: prev_screen_follow => (
+            EmptiedWorkspacePolicy::FollowClient,
+            |wm| wm.move_client_to_prev_screen(false).unwrap(),
+            1,
+            vec![2, 1]
+        );
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.emptied_workspace_policy = policy;
+            wm.focus_workspace(&Selector::Index(2)).unwrap();
+            add_n_clients(&mut wm, 1, 0);
+
+            action(&mut wm);
+
+            assert_eq!(wm.workspaces[1].client_ids(), vec![10]);
+            assert_eq!(wm.screens.active_ws_index(), expected);
+            assert_eq!(wm.screens.visible_workspaces(), expected_visible);
+        }
+    }
+
+    #[test]
+    fn acting_on_all_matching_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+
+        add_n_clients(&mut wm, 3, 0);
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 2, 3);
+
+        let selector = Selector::Condition(&|c: &Client| c.id() > 10 && !c.floating);
+        assert_eq!(wm.matching_client_ids(&selector), vec![20, 30, 40, 50]);
+
+        // Clients that stop matching part way through are still acted on but clients that have
+        // been removed are skipped
+        let mut seen = vec![];
+        wm.for_each_client_matching(&selector, |wm, id| {
+            seen.push(id);
+            wm.clients.modify(id, |c| c.floating = true);
+            if id == 20 {
+                wm.remove_client(30)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(seen, vec![20, 40, 50]);
+        assert!(wm.matching_client_ids(&selector).is_empty());
+
+        let res = wm.for_each_client_matching(&Selector::Any, |_, _| Err(perror!("boom")));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn selector_screen() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+
+        assert_eq!(wm.screen(&Selector::Focused), Some(wm.screens.focused()));
+        assert_eq!(wm.screen(&Selector::Index(1)), wm.screens.get(1));
+        assert_eq!(wm.screen(&Selector::WinId(10)), wm.screens.get(0));
+        assert_eq!(
+            wm.screen(&Selector::Condition(&|s| s.wix == 1)),
+            wm.screens.get(1)
+        );
+    }
+
+    #[test]
+    fn selector_workspace() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+
+        assert_eq!(wm.workspace(&Selector::Focused), wm.workspaces.focused());
+        assert_eq!(wm.workspace(&Selector::Index(1)), wm.workspaces.get(1));
+        assert_eq!(wm.workspace(&Selector::WinId(10)), wm.workspaces.get(0));
+        assert_eq!(
+            wm.workspace(&Selector::Condition(&|w| w.name() == "3")),
+            wm.workspaces.get(2)
+        );
+    }
+
+    #[test]
+    fn selector_workspace_view() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 2, 0);
+
+        let view = wm.workspace_view(&Selector::WinId(10)).unwrap();
+        assert_eq!(view.index, 1);
+        assert_eq!(view.name, "2");
+        assert_eq!(view.client_ids, vec![20, 10]);
+        assert_eq!(view.focused_client, Some(20));
+        assert_eq!(wm.workspace_view(&Selector::Index(1)), Some(view.clone()));
+        assert_eq!(wm.workspace_view(&Selector::WinId(99)), None);
+    }
+
+    fn wm_with_global_focus_history() -> WindowManager<MockXConn> {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        add_n_clients(&mut wm, 1, 2);
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(wm.focus_history(), &[20, 30, 10]);
+
+        wm
+    }
+
+    #[test]
+    fn focus_next_global_cycles_in_mru_order_across_workspaces() {
+        let mut wm = wm_with_global_focus_history();
+
+        wm.focus_next_global().unwrap();
+        assert_eq!(wm.clients.focused_client_id(), Some(30));
+        assert_eq!(wm.screens.active_ws_index(), 2);
+        assert_eq!(wm.focus_history(), &[20, 30, 10]);
+
+        wm.focus_next_global().unwrap();
+        assert_eq!(wm.clients.focused_client_id(), Some(10));
+        assert_eq!(wm.screens.active_ws_index(), 0);
+
+        wm.commit_global_focus();
+        assert_eq!(wm.focus_history(), &[10, 20, 30]);
+
+        wm.remove_client(30).unwrap();
+        assert_eq!(wm.focus_history(), &[10, 20]);
+    }
+
+    #[test]
+    fn other_focus_changes_commit_global_focus() {
+        let mut wm = wm_with_global_focus_history();
+
+        wm.focus_next_global().unwrap();
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+
+        assert_eq!(wm.clients.focused_client_id(), Some(20));
+        assert_eq!(wm.focus_history(), &[20, 30, 10]);
+        assert_eq!(wm.global_cycle, None);
+    }
+
+    #[test]
+    fn selector_client() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 4, 0);
+
+        assert_eq!(wm.client(&Selector::Focused), wm.clients.get(40));
+        assert_eq!(wm.client(&Selector::Index(2)), wm.clients.get(20));
+        assert_eq!(wm.client(&Selector::WinId(30)), wm.clients.get(30));
+        assert_eq!(
+            wm.client(&Selector::Condition(&|c| c.id() == 10)),
+            wm.clients.get(10)
+        );
+    }
+
+    #[test]
+    fn unmanaged_window_types_are_not_added_to_workspaces() {
+        // Setting the unmanaged window IDs here sets the return of
+        // MockXConn.is_managed_window to false for those IDs
+        let mut wm = wm_with_mock_conn(vec![], vec![10]);
+
+        wm.handle_map_request(10).unwrap(); // should not be tiled
+        assert!(wm.clients.get(10).is_some());
+        assert!(wm.workspaces[0].is_empty());
+
+        wm.handle_map_request(20).unwrap(); // should be tiled
+        assert!(wm.clients.get(20).is_some());
+        assert!(wm.workspaces[0].len() == 1);
+    }
+
+    struct ScreenChangingXConn {
+        num_screens: Cell<usize>,
+    }
+
+    impl ScreenChangingXConn {
+        fn set_num_screens(&mut self, n: usize) {
+            self.num_screens.set(n);
+        }
+    }
+
+    __impl_stub_xcon! {
+        for ScreenChangingXConn;
+
+        atom_queries: {}
+        client_properties: {}
+        client_handler: {}
+        client_config: {}
+        event_handler: {}
+        state: {
+            fn mock_current_screens(&self) -> crate::core::xconnection::Result<Vec<Screen>> {
+                let num_screens = self.num_screens.get();
+                Ok((0..(num_screens))
+                    .map(|n| Screen::new(Region::new(800 * n as u32, 600 * n as u32, 800, 600), n))
+                    .collect())
+            }
+        }
+        conn: {}
+    }
+
+    impl WindowManager<ScreenChangingXConn> {
+        fn set_num_screens(&mut self, n: usize) {
+            self.conn_mut().set_num_screens(n);
+        }
+    }
+
+    // Side by side screens for each named output
+    fn named_outputs(names: &[&str]) -> Vec<(Screen, Option<String>)> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(n, name)| {
+                let s = Screen::new(Region::new(800 * n as u32, 0, 800, 600), n);
+                (s, Some(name.to_string()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reconnected_outputs_restore_their_workspace_and_layout() {
+        let conn = RecordingXConn::init();
+        conn.set_outputs(named_outputs(&["eDP-1", "DP-2"]));
+        let conf = Config {
+            layouts: ["first", "second"]
+                .iter()
+                .map(|s| Layout::new(*s, LayoutConf::default(), mock_layout, 1, 0.6))
+                .collect(),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let layout = |wm: &WindowManager<_>, wix| {
+            wm.workspace(&Selector::Index(wix))
+                .unwrap()
+                .layout_symbol()
+                .to_string()
+        };
+
+        wm.cycle_screen(Forward).unwrap();
+        wm.focus_workspace(&Selector::Index(4)).unwrap();
+        wm.next_layout().unwrap();
+        assert_eq!(wm.screens.visible_workspaces(), vec![0, 4]);
+
+        // undock and change the layout of workspace 4 while it is shown on the laptop screen
+        wm.conn.set_outputs(named_outputs(&["eDP-1"]));
+        wm.detect_screens().unwrap();
+        assert_eq!(wm.screens.visible_workspaces(), vec![0]);
+        wm.focus_workspace(&Selector::Index(4)).unwrap();
+        wm.prev_layout().unwrap();
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(layout(&wm, 4), "first");
+
+        // redock
+        wm.conn.set_outputs(named_outputs(&["eDP-1", "DP-2"]));
+        wm.detect_screens().unwrap();
+        assert_eq!(wm.screens.visible_workspaces(), vec![0, 4]);
+        assert_eq!(layout(&wm, 4), "second");
+        assert_eq!(layout(&wm, 0), "first");
+    }
+
+    #[test]
+    fn floating_clients_keep_their_relative_position_when_moved_between_screens() {
+        let conn = PointerXConn::new().with_two_screens();
+        let conf = Config {
+            show_bar: false,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+        wm.clients.modify(10, |c| c.floating = true);
+        wm.conn()
+            .position_client(10, Region::new(700, 0, 300, 200), 0, true)
+            .unwrap();
+
+        wm.client_to_screen(&Selector::Index(1)).unwrap();
+        let expected = Region::new(2400, 0, 600, 300);
+
+        assert_eq!(wm.workspaces[1].client_ids(), vec![10]);
+        assert_eq!(wm.conn().client_geometry(10).unwrap(), expected);
+        assert_eq!(wm.clients.get(10).unwrap().float_region(), Some(expected));
+    }
+
+    #[test]
+    fn pointer_confinement_follows_the_focused_screen() {
+        let conn = PointerXConn::new().with_two_screens();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.conn().confined.take();
+
+        wm.confine_pointer_to_screen(true).unwrap();
+        wm.cycle_screen(Forward).unwrap();
+        wm.toggle_pointer_confinement().unwrap();
+        wm.cycle_screen(Backward).unwrap();
+
+        assert!(!wm.pointer_is_confined());
+        assert_eq!(
+            wm.conn().confined.take(),
+            vec![
+                Some(Region::new(0, 0, 1000, 600)),
+                Some(Region::new(1000, 0, 2000, 900)),
+                None,
+                None,
+            ]
+        );
+    }
+
+    test_cases! {
+        focus_on_workspace_switch;
+        args: (workspace_focus: WorkspaceFocus, expected: Xid);
+
+        case: last_focused => (WorkspaceFocus::LastFocused, 20);
+        case: main => (WorkspaceFocus::Main, 30);
+        case: under_pointer => (WorkspaceFocus::UnderPointer, 10);
+
+        body: {
+            let conn = PointerXConn::new();
+            let conf = Config::default()
+                .builder()
+                .workspace_focus(workspace_focus)
+                .build()
+                .unwrap();
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 3, 0);
+            wm.update_focus(20).unwrap();
+
+            let (x, y, w, h) = wm.conn.client_geometry(10).unwrap().values();
+            wm.conn.cursor.set(Point::new(x + w / 2, y + h / 2));
+
+            wm.focus_workspace(&Selector::Index(1)).unwrap();
+            wm.focus_workspace(&Selector::Index(0)).unwrap();
+
+            assert_eq!(wm.focused_client_id(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn float_layouts_restore_floating_clients_by_class_and_ordinal() {
+        let conn = PointerXConn::new();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 5, 0);
+        let set_floating = |wm: &mut WindowManager<PointerXConn>, id: Xid, class: &str| {
+            let c = wm.clients.get_mut(id).unwrap();
+            c.set_floating(true);
+            c.wm_class = vec![class.to_string()];
+        };
+        for &id in &[10, 30, 40] {
+            set_floating(&mut wm, id, "term");
+        }
+
+        let saved = [
+            (10, Region::new(0, 0, 100, 100)),
+            (30, Region::new(100, 0, 100, 100)),
+            (40, Region::new(200, 0, 100, 100)),
+        ];
+        for &(id, r) in &saved {
+            wm.conn.position_client(id, r, 0, false).unwrap();
+        }
+        wm.save_float_layout("edit").unwrap();
+
+        // the second "term" client is closed and "other" only starts floating after saving
+        wm.remove_client(30).unwrap();
+        set_floating(&mut wm, 50, "other");
+        let moved = Region::new(500, 500, 50, 50);
+        for &id in &[10, 40, 50] {
+            wm.conn.position_client(id, moved, 0, false).unwrap();
+        }
+
+        wm.restore_float_layout("edit").unwrap();
+
+        let geometry = |id| wm.conn.client_geometry(id).unwrap();
+        assert_eq!(geometry(10), saved[0].1);
+        assert_eq!(geometry(40), saved[1].1);
+        assert_eq!(geometry(50), moved);
+        let float_region = |id| wm.clients.get(id).unwrap().float_region;
+        assert_eq!(float_region(10), Some(saved[0].1));
+        assert_eq!(float_region(40), Some(saved[1].1));
+        assert!(wm.restore_float_layout("unknown").is_err());
+    }
+
+    #[test]
+    fn apply_profile_arranges_workspaces_and_moves_matching_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.state.config.layouts = vec![
+            Layout::new("a", LayoutConf::default(), mock_layout, 1, 0.6),
+            Layout::new("b", LayoutConf::default(), mock_layout, 1, 0.6),
+        ];
+        wm.workspaces[2] = Workspace::new("3", wm.state.config.layouts.clone());
+        add_n_clients(&mut wm, 4, 0);
+        for &(id, class) in &[(10, "term"), (20, "browser"), (30, "term"), (40, "term")] {
+            wm.clients
+                .modify(id, |c| c.wm_class = vec![class.to_string()]);
+        }
+
+        let profile = Profile {
+            workspaces: vec![WorkspaceProfile {
+                workspace: "3".into(),
+                layout: Some("b".into()),
+                max_main: Some(3),
+                main_ratio: Some(0.3),
+                clients: vec![
+                    ProfileClient {
+                        class: "term".into(),
+                        ordinal: 1,
+                    },
+                    ProfileClient {
+                        class: "browser".into(),
+                        ordinal: 0,
+                    },
+                    ProfileClient {
+                        class: "browser".into(),
+                        ordinal: 1,
+                    },
+                ],
+            }],
+        };
+        wm.apply_profile(&profile).unwrap();
+
+        let ws = &wm.workspaces[2];
+        assert_eq!(ws.layout_symbol(), "b");
+        assert_eq!(ws.max_main(), 3);
+        assert!((ws.main_ratio() - 0.3).abs() < f32::EPSILON);
+        assert_eq!(ws.client_ids(), vec![20, 30]);
+        assert_eq!(wm.workspaces[0].client_ids(), vec![40, 10]);
+    }
+
+    #[test]
+    fn client_is_visible_checks_screens_and_minimized_state() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0);
+        wm.move_client_to_workspace(30, 2).unwrap();
+        wm.minimize_client(&Selector::WinId(20)).unwrap();
+
+        assert!(wm.client_is_visible(10));
+        assert!(!wm.client_is_visible(20));
+        assert!(!wm.client_is_visible(30));
+        assert!(!wm.client_is_visible(99));
+
+        // workspace 2 is pulled on to the focused screen
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert!(!wm.client_is_visible(10));
+        assert!(wm.client_is_visible(30));
+    }
+
+    #[test]
+    fn apply_profile_errors_for_unknown_workspaces() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        let profile = Profile {
+            workspaces: vec![WorkspaceProfile {
+                workspace: "unknown".into(),
+                ..Default::default()
+            }],
+        };
+
+        assert!(wm.apply_profile(&profile).is_err());
+    }
+
+    test_cases! {
+        update_max_main_respects_config;
+        args: (
+            limit: Option<u32>,
+            limit_to_clients: bool,
+            overflow: Overflow,
+            presses: usize,
+            expected: u32
+        );
+
+        case: unbounded_by_default => (None, false, Overflow::Clamp, 5, 6);
+        case: clamps_at_client_count => (None, true, Overflow::Clamp, 5, 3);
+        case: clamps_at_limit => (Some(2), false, Overflow::Clamp, 5, 2);
+        case: wraps_past_client_count => (None, true, Overflow::Wrap, 3, 1);
+        case: wraps_past_limit => (Some(2), false, Overflow::Wrap, 2, 1);
+        case: smaller_bound_wins => (Some(5), true, Overflow::Clamp, 5, 3);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.max_main_limit = limit;
+            wm.state.config.max_main_limited_by_clients = limit_to_clients;
+            wm.state.config.max_main_overflow = overflow;
+            add_n_clients(&mut wm, 3, 0);
+
+            for _ in 0..presses {
+                wm.update_max_main(Change::More).unwrap();
+            }
+
+            assert_eq!(wm.workspaces[0].max_main(), expected);
+        }
+    }
+
+    test_cases! {
+        floating_resize;
+        args: (wx: i16, wy: i16, rx: i16, ry: i16, expected: Region);
+
+        // client starts at (100, 100, 300, 300) with a minimum size of 100x50
+        case: top_left_corner => (10, 10, 50, 60, Region::new(40, 50, 360, 350));
+        case: top_edge => (150, 10, 150, 20, Region::new(100, 10, 300, 390));
+        case: right_edge => (290, 150, 450, 200, Region::new(100, 100, 360, 300));
+        case: bottom_right_corner => (290, 290, 350, 300, Region::new(100, 100, 260, 210));
+        case: honours_min_size => (290, 290, 0, 0, Region::new(100, 100, 100, 50));
+
+        body: {
+            let conn = PointerXConn::new();
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.clients.modify(10, |c| {
+                c.floating = true;
+                c.wm_normal_hints = Some(WmNormalHints::new(
+                    WmNormalHintsFlags::P_MIN_SIZE,
+                    None,
+                    Some(Region::new(0, 0, 100, 50)),
+                    None,
+                    None,
+                ));
+            });
+            let mut positions = wm.conn.positions.take();
+            positions.insert(10, Region::new(100, 100, 300, 300));
+            wm.conn.positions.set(positions);
+
+            let state = MouseState::new(MouseButton::Left, vec![]);
+            let (sx, sy) = (100 + wx, 100 + wy);
+            let press = MouseEvent::new(10, sx, sy, wx, wy, state.clone(), MouseEventKind::Press);
+            let motion = MouseEvent::new(10, rx, ry, 0, 0, state, MouseEventKind::Motion);
+
+            wm.start_floating_resize(&press).unwrap();
+            wm.update_floating_drag(&motion).unwrap();
+            wm.end_floating_drag().unwrap();
+
+            assert_eq!(wm.conn.client_geometry(10).unwrap(), expected);
+            assert_eq!(wm.clients.get(10).unwrap().float_region(), Some(expected));
+            assert!(wm.floating_drag.is_none());
+        }
+    }
+
+    #[test]
+    fn floating_resize_targets_the_child_of_root_window_events() {
+        let conn = PointerXConn::new();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+        wm.clients.modify(10, |c| c.floating = true);
+        let mut positions = wm.conn.positions.take();
+        positions.insert(10, Region::new(100, 100, 300, 300));
+        wm.conn.positions.set(positions);
+
+        // Event coordinates are relative to the root window that the binding was grabbed on
+        let root = wm.conn.root();
+        let state = MouseState::new(MouseButton::Left, vec![]);
+        let press = MouseEvent::new(
+            root,
+            110,
+            110,
+            110,
+            110,
+            state.clone(),
+            MouseEventKind::Press,
+        )
+        .with_child(10);
+        let motion = MouseEvent::new(root, 50, 60, 50, 60, state, MouseEventKind::Motion);
+
+        wm.start_floating_resize(&press).unwrap();
+        wm.update_floating_drag(&motion).unwrap();
+        wm.end_floating_drag().unwrap();
+
+        assert_eq!(
+            wm.conn.client_geometry(10).unwrap(),
+            Region::new(40, 50, 360, 350)
+        );
+    }
+
+    test_cases! {
+        double_click;
+        args: (double_click_ms: u32, bind_double: bool, times: Vec<u32>, expected: Vec<MouseEventKind>);
+
+        case: detected => (300, true, vec![0, 100], vec![MouseEventKind::Press, MouseEventKind::DoubleClick]);
+        case: too_slow => (300, true, vec![0, 400], vec![MouseEventKind::Press, MouseEventKind::Press]);
+        case: third_press_is_single => (300, true, vec![0, 100, 200], vec![MouseEventKind::Press, MouseEventKind::DoubleClick, MouseEventKind::Press]);
+        case: no_double_click_binding => (300, false, vec![0, 100], vec![MouseEventKind::Press, MouseEventKind::Press]);
+        case: disabled => (0, true, vec![0, 100], vec![MouseEventKind::Press, MouseEventKind::Press]);
+
+        body: {
+            let conf = Config {
+                double_click_ms,
+                ..Default::default()
+            };
+            let conn = MockXConn::new(test_screens(), vec![], vec![]);
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+
+            let seen = Rc::new(RefCell::new(vec![]));
+            let state = MouseState::new(MouseButton::Left, vec![ModifierKey::Meta]);
+            let mut bindings: MouseBindings<MockXConn> = HashMap::new();
+            let kinds = if bind_double {
+                vec![MouseEventKind::Press, MouseEventKind::DoubleClick]
+            } else {
+                vec![MouseEventKind::Press]
+            };
+            for kind in kinds {
+                let s = Rc::clone(&seen);
+                bindings.insert(
+                    (kind, state.clone()),
+                    Box::new(move |_: &mut WindowManager<MockXConn>, e: &MouseEvent| {
+                        s.borrow_mut().push(e.kind);
+                        Ok(())
+                    }),
+                );
+            }
+
+            for time in times {
+                let e = MouseEvent::new(42, 0, 0, 0, 0, state.clone(), MouseEventKind::Press)
+                    .with_child(10)
+                    .with_time(time);
+                wm.run_mouse_binding(e, &mut bindings);
+            }
+
+            assert_eq!(*seen.borrow(), expected);
+        }
+    }
+
+    test_cases! {
+        floating_move_snaps_to_screen_edges;
+        args: (snap_distance: u32, modifiers: Vec<ModifierKey>, expected: Region);
+
+        case: snapping_disabled => (0, vec![], Region::new(8, 25, 200, 200));
+        case: snaps_within_distance => (10, vec![], Region::new(0, 18, 200, 200));
+        case: out_of_range => (5, vec![], Region::new(8, 25, 200, 200));
+        case: modifier_disables_snapping => (
+            10, vec![ModifierKey::Shift], Region::new(8, 25, 200, 200)
+        );
+
+        body: {
+            let conn = PointerXConn::new();
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            wm.state.config.snap_distance = snap_distance;
+            add_n_clients(&mut wm, 1, 0);
+            wm.clients.modify(10, |c| c.floating = true);
+            let mut positions = wm.conn.positions.take();
+            positions.insert(10, Region::new(100, 100, 200, 200));
+            wm.conn.positions.set(positions);
+
+            let press_state = MouseState::new(MouseButton::Left, vec![]);
+            let motion_state = MouseState::new(MouseButton::Left, modifiers);
+            let press = MouseEvent::new(10, 150, 150, 50, 50, press_state, MouseEventKind::Press);
+            let motion = MouseEvent::new(10, 58, 75, 0, 0, motion_state, MouseEventKind::Motion);
+
+            wm.start_floating_move(&press).unwrap();
+            wm.update_floating_drag(&motion).unwrap();
+            wm.end_floating_drag().unwrap();
+
+            assert_eq!(wm.conn.client_geometry(10).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn floating_resize_ignores_tiled_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+        let state = MouseState::new(MouseButton::Left, vec![]);
+        let press = MouseEvent::new(10, 0, 0, 0, 0, state, MouseEventKind::Press);
+
+        wm.start_floating_resize(&press).unwrap();
+        assert!(wm.floating_drag.is_none());
+    }
+
+    test_cases! {
+        swap_in_direction;
+        args: (focused: Xid, direction: RelativePosition, expected: Vec<Xid>);
+
+        // side_stack places 30 in the main area with 20 above 10 in the stack
+        case: main_to_stack => (30, RelativePosition::Right, vec![20, 30, 10]);
+        case: stack_to_main => (10, RelativePosition::Left, vec![10, 20, 30]);
+        case: within_stack => (10, RelativePosition::Above, vec![30, 10, 20]);
+        case: no_neighbour => (30, RelativePosition::Left, vec![30, 20, 10]);
+        // unlike directional focus, the stack straddling the main area's edge is not "above" it
+        case: main_up => (30, RelativePosition::Above, vec![30, 20, 10]);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.layouts = vec![Layout::new("t", LayoutConf::default(), side_stack, 1, 0.6)];
+            wm.state.workspaces = Workspaces::new(
+                vec![Workspace::new("1", wm.state.config.layouts.clone())],
+                0.05,
+            );
+            add_n_clients(&mut wm, 3, 0);
+            assert_eq!(wm.workspaces[0].client_ids(), vec![30, 20, 10]);
+
+            wm.update_focus(focused).unwrap();
+            wm.swap_in_direction(direction).unwrap();
+
+            assert_eq!(wm.workspaces[0].client_ids(), expected);
+            assert_eq!(wm.workspaces[0].focused_client(), Some(focused));
+        }
+    }
+
+    test_cases! {
+        focus_in_direction;
+        args: (focused: Xid, direction: RelativePosition, cross_screens: bool, expected: Xid);
+
+        // side_stack places 30 in the main area with 20 above 10 in the stack
+        case: within_screen => (30, RelativePosition::Right, true, 20);
+        case: ties_use_client_order => (20, RelativePosition::Left, true, 30);
+        case: across_screens => (20, RelativePosition::Right, true, 40);
+        case: across_screens_disabled => (20, RelativePosition::Right, false, 20);
+        case: no_candidate => (40, RelativePosition::Above, true, 40);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.directional_focus_crosses_screens = cross_screens;
+            wm.state.config.layouts = vec![Layout::new("t", LayoutConf::default(), side_stack, 1, 0.6)];
+            wm.state.workspaces = Workspaces::new(
+                ["1", "2"].iter().map(|n| Workspace::new(*n, wm.state.config.layouts.clone())).collect(),
+                0.05,
+            );
+            wm.detect_screens().unwrap();
+            add_n_clients(&mut wm, 3, 0);
+            wm.cycle_screen(Forward).unwrap();
+            add_n_clients(&mut wm, 1, 3);
+
+            wm.update_focus(focused).unwrap();
+            wm.focus_in_direction(direction).unwrap();
+
+            assert_eq!(wm.clients.focused_client_id(), Some(expected));
+        }
+    }
+
+    test_cases! {
+        randr_debounce;
+        args: (debounce_ms: u64, expected_pending: Vec<XEvent>, expected_remaining: Vec<XEvent>);
+
+        case: enabled => (
+            10,
+            vec![XEvent::MapRequest(1, false)],
+            vec![]
+        );
+        case: disabled => (
+            0,
+            vec![],
+            vec![XEvent::RandrNotify(RandrChange::Unknown), XEvent::MapRequest(1, false)]
+        );
+
+        body: {
+            let events = vec![XEvent::RandrNotify(RandrChange::Unknown), XEvent::MapRequest(1, false)];
+            let mut wm = wm_with_mock_conn(events, vec![]);
+            wm.state.config.randr_debounce_ms = debounce_ms;
+
+            wm.handle_event_action(EventAction::DetectScreens, None, None).unwrap();
+
+            assert_eq!(wm.pending_events.drain(..).collect::<Vec<_>>(), expected_pending);
+            assert_eq!(wm.conn.remaining_events(), expected_remaining);
+        }
+    }
+
+    #[test]
+    fn toggle_float_restores_the_last_floating_position() {
+        let conn = PointerXConn::new();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+
+        let screen_region = wm.screens.focused().region(true);
+        let centered = screen_region
+            .scale_w(0.5)
+            .scale_h(0.5)
+            .centered_in(&screen_region)
+            .unwrap();
+
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        assert!(wm.clients.get(10).unwrap().floating);
+        assert_eq!(wm.conn.client_geometry(10).unwrap(), centered);
+
+        // simulate the user moving the client while it is floating
+        let moved = Region::new(10, 20, 300, 200);
+        let mut positions = wm.conn.positions.take();
+        positions.insert(10, moved);
+        wm.conn.positions.set(positions);
+
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        assert!(!wm.clients.get(10).unwrap().floating);
+        assert_eq!(wm.clients.get(10).unwrap().float_region(), Some(moved));
+        assert_ne!(wm.conn.client_geometry(10).unwrap(), moved);
+
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        assert!(wm.clients.get(10).unwrap().floating);
+        assert_eq!(wm.conn.client_geometry(10).unwrap(), moved);
+    }
+
+    test_cases! {
+        floating_focus_precedence;
+        args: (precedence: FloatingFocusPrecedence, entered: Xid, p: Point, expected: Xid, on_top: Xid);
+
+        case: stacking_over_float => (FloatingFocusPrecedence::Stacking, 20, Point::new(500, 300), 20, 10);
+        case: stacking_outside_float => (FloatingFocusPrecedence::Stacking, 20, Point::new(50, 50), 20, 20);
+        case: floating_on_top_over_float => (FloatingFocusPrecedence::FloatingOnTop, 20, Point::new(500, 300), 10, 10);
+        case: floating_on_top_outside_float => (FloatingFocusPrecedence::FloatingOnTop, 20, Point::new(50, 50), 20, 20);
+        case: floating_on_top_entering_float => (FloatingFocusPrecedence::FloatingOnTop, 10, Point::new(500, 300), 10, 10);
+
+        body: {
+            let conn = PointerXConn::new();
+            let conf = Config {
+                floating_focus_precedence: precedence,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 2, 0);
+            // 10 floats in the centre of the screen with 20 tiled underneath it
+            wm.toggle_float(&Selector::WinId(10)).unwrap();
+            wm.update_focus(30 - entered).unwrap();
+
+            let actions = process_next_event(
+                XEvent::Enter(PointerChange { id: entered, abs: p, relative: p }),
+                &wm.state,
+                &wm.conn,
+            );
+            wm.handle_event_actions(actions).unwrap();
+
+            assert_eq!(wm.focused_client_id(), Some(expected));
+            // layouts raise floating clients above tiled ones
+            assert_eq!(wm.client_at_point(p).unwrap(), Some(on_top));
+        }
+    }
+
+    test_cases! {
+        client_under_cursor;
+        args: (cursor: Point, expected: Option<Xid>);
+
+        case: over_float => (Point::new(500, 300), Some(10));
+        case: outside_float => (Point::new(50, 50), Some(20));
+        case: off_screen => (Point::new(2000, 2000), None);
+
+        body: {
+            let conn = PointerXConn::new().with_cursor(cursor);
+            let conf = Config {
+                floating_focus_precedence: FloatingFocusPrecedence::FloatingOnTop,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 2, 0);
+            // 10 floats in the centre of the screen with 20 tiled underneath it
+            wm.toggle_float(&Selector::WinId(10)).unwrap();
+
+            assert_eq!(wm.cursor_position().unwrap(), cursor);
+            assert_eq!(wm.client_under_cursor().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn cursor_position_is_cached_per_event() {
+        let conn = PointerXConn::new().with_cursor(Point::new(1, 1));
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let (mut kb, mut mb) = (HashMap::new(), HashMap::new());
+
+        assert_eq!(wm.cursor_position().unwrap(), Point::new(1, 1));
+        wm.conn.cursor.set(Point::new(2, 2));
+        assert_eq!(wm.cursor_position().unwrap(), Point::new(1, 1));
+
+        wm.handle_event(XEvent::ScreenChange, &mut kb, &mut mb)
+            .unwrap();
+        assert_eq!(wm.cursor_position().unwrap(), Point::new(2, 2));
+
+        wm.conn.cursor.set(Point::new(3, 3));
+        wm.warp_cursor(None, &wm.screens.inner[0]).unwrap();
+        assert_eq!(wm.cursor_position().unwrap(), Point::new(3, 3));
+
+        // The cached position is dropped once each event has been handled
+        wm.handle_event(XEvent::ScreenChange, &mut kb, &mut mb)
+            .unwrap();
+        wm.conn.cursor.set(Point::new(4, 4));
+        assert_eq!(wm.cursor_position().unwrap(), Point::new(4, 4));
+    }
+
+    struct TickCursor(Rc<RefCell<Vec<Point>>>);
+
+    impl<X: XConn> crate::core::hooks::Hook<X> for TickCursor {
+        fn tick(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+            self.0.borrow_mut().push(wm.cursor_position()?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tick_hooks_see_the_current_cursor_position() {
+        let conf = Config {
+            tick_interval_ms: 1,
+            ..Default::default()
+        };
+        let conn = PointerXConn::new().with_cursor(Point::new(1, 1));
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let seen = Rc::new(RefCell::new(vec![]));
+        wm.hooks.replace(vec![Box::new(TickCursor(seen.clone()))]);
+
+        assert_eq!(wm.cursor_position().unwrap(), Point::new(1, 1));
+        wm.conn.cursor.set(Point::new(2, 2));
+        wm.next_tick = Some(Instant::now());
+        assert!(wm.poll_until_tick().unwrap().is_none());
+
+        assert_eq!(*seen.borrow(), vec![Point::new(2, 2)]);
+    }
+
+    test_cases! {
+        toggle_pip;
+        args: (was_floating: bool, corner: Corner, expected: Region);
+
+        case: tiled_bottom_right => (false, Corner::BottomRight, Region::new(750, 450, 246, 146));
+        case: tiled_top_left => (false, Corner::TopLeft, Region::new(0, 0, 246, 146));
+        case: floating_top_right => (true, Corner::TopRight, Region::new(750, 0, 246, 146));
+
+        body: {
+            let conn = PointerXConn::new();
+            let conf = Config {
+                border_px: 2,
+                show_bar: false,
+                pip_corner: corner,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 3, 0);
+            wm.focus_client(&Selector::WinId(20)).unwrap();
+            if was_floating {
+                wm.toggle_float(&Selector::WinId(20)).unwrap();
+            }
+            let before = wm.conn.client_geometry(20).unwrap();
+
+            wm.toggle_pip().unwrap();
+            assert!(wm.is_pip(20));
+            assert!(wm.clients.get(20).unwrap().floating);
+            assert_eq!(wm.conn.client_geometry(20).unwrap(), expected);
+            assert_eq!(wm.stacking_order().last(), Some(&20));
+
+            wm.toggle_pip().unwrap();
+            assert!(!wm.is_pip(20));
+            assert_eq!(wm.clients.get(20).unwrap().floating, was_floating);
+            assert_eq!(wm.conn.client_geometry(20).unwrap(), before);
+            assert_eq!(wm.workspaces[0].client_ids(), vec![30, 20, 10]);
+        }
+    }
+
+    #[test]
+    fn pip_clients_are_raised_above_other_floating_clients_in_x() {
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        wm.focus_client(&Selector::WinId(30)).unwrap();
+        wm.conn.clear();
+
+        wm.toggle_pip().unwrap();
+        let calls = wm.conn.calls();
+        let raised: Vec<&str> = calls
+            .iter()
+            .filter(|(m, _)| m == "raise_client")
+            .map(|(_, args)| args[0].as_str())
+            .collect();
+        let above = Atom::NetWmStateAbove.as_ref();
+
+        assert_eq!(raised.last(), Some(&"30"));
+        assert!(calls
+            .iter()
+            .any(|(m, args)| m == "change_prop" && args[0] == "30" && args[2].contains(above)));
+
+        wm.toggle_float(&Selector::WinId(20)).unwrap();
+        assert_eq!(wm.stacking_order().last(), Some(&30));
+    }
+
+    #[test]
+    fn pip_clients_are_marked_above_in_net_wm_state() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        let above = |wm: &WindowManager<RecordingXConn>| {
+            wm.conn
+                .get_atom_list_prop(10, Atom::NetWmState.as_ref())
+                .unwrap_or_default()
+                .contains(&Atom::NetWmStateAbove.as_ref().to_string())
+        };
+
+        let request = EventAction::SetClientAbove(10, WmStateAction::Add);
+        wm.handle_event_action(request, None, None).unwrap();
+        assert!(wm.is_pip(10));
+        assert!(above(&wm));
+
+        let request = EventAction::SetClientAbove(10, WmStateAction::Toggle);
+        wm.handle_event_action(request, None, None).unwrap();
+        assert!(!wm.is_pip(10));
+        assert!(!above(&wm));
+
+        wm.focus_client(&Selector::WinId(10)).unwrap();
+        wm.toggle_pip().unwrap();
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        assert!(!wm.is_pip(10));
+        assert!(!above(&wm));
+    }
+
+    #[test]
+    fn fullscreen_clients_can_not_be_made_pip() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.toggle_client_fullscreen(&Selector::Focused).unwrap();
+        wm.toggle_pip().unwrap();
+
+        let id = wm.focused_client_id().unwrap();
+        assert!(!wm.is_pip(id));
+        assert!(wm.client(&Selector::WinId(id)).unwrap().is_fullscreen());
+    }
+
+    #[test]
+    fn layouts_write_the_stacking_order_once() {
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        for id in [10, 20, 30] {
+            wm.toggle_float(&Selector::WinId(id)).unwrap();
+        }
+        wm.conn.clear();
+
+        wm.apply_layout(0).unwrap();
+        let stacking = Atom::NetClientListStacking.as_ref();
+        let calls = wm.conn.calls();
+        let raised = calls.iter().filter(|(m, _)| m == "raise_client").count();
+        let written = calls
+            .iter()
+            .filter(|(m, args)| m == "change_prop" && args[1].contains(stacking))
+            .count();
+
+        assert_eq!(raised, 3);
+        assert_eq!(written, 1);
+    }
+
+    #[test]
+    fn pip_clients_stay_above_focused_clients() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            raise_on_focus: true,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        wm.focus_client(&Selector::WinId(10)).unwrap();
+        wm.toggle_pip().unwrap();
+        wm.toggle_float(&Selector::WinId(20)).unwrap();
+
+        wm.focus_client(&Selector::WinId(30)).unwrap();
+        assert_eq!(wm.stacking_order(), &[30, 20, 10]);
+
+        wm.focus_client(&Selector::WinId(20)).unwrap();
+        assert_eq!(wm.stacking_order(), &[30, 20, 10]);
+
+        wm.handle_restack_if_floating(20, None, StackMode::Above)
+            .unwrap();
+        assert_eq!(wm.stacking_order(), &[30, 20, 10]);
+
+        wm.sink_all_floats().unwrap();
+        assert!(wm.is_pip(10));
+        assert!(!wm.clients.get(20).unwrap().floating);
+    }
+
+    test_cases! {
+        directional_focus_floating_precedence;
+        args: (precedence: FloatingFocusPrecedence, expected: Xid);
+
+        case: stacking => (FloatingFocusPrecedence::Stacking, 20);
+        case: floating_on_top => (FloatingFocusPrecedence::FloatingOnTop, 10);
+
+        body: {
+            let conn = PointerXConn::new();
+            let conf = Config {
+                floating_focus_precedence: precedence,
+                layouts: vec![Layout::new("t", LayoutConf::default(), side_stack, 1, 0.6)],
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 3, 0);
+            // 30 is in the main area with 20 in the stack. 10 floats over the centre of 20 but
+            // its own centre is further away from 30.
+            wm.toggle_float(&Selector::WinId(10)).unwrap();
+            let r20 = wm.conn.client_geometry(20).unwrap();
+            let (cx, cy) = (r20.x + r20.w / 2, r20.y + r20.h / 2);
+            let mut positions = wm.conn.positions.take();
+            positions.insert(10, Region::new(cx - 10, cy - 10, r20.w / 2, r20.h / 2));
+            wm.conn.positions.set(positions);
+
+            wm.update_focus(30).unwrap();
+            wm.focus_in_direction(RelativePosition::Right).unwrap();
+
+            assert_eq!(wm.focused_client_id(), Some(expected));
+        }
+    }
+
+    // TODO: rewrite and move out to screens.rs
+    #[test]
+    fn updating_screens_retains_focused_workspaces() {
+        let conn = ScreenChangingXConn {
+            num_screens: Cell::new(1),
+        };
+        let conf = Config::default();
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        // detect_screens is called on init so should have one screen
+        assert_eq!(wm.screens.n_screens(), 1);
+        assert_eq!(wm.screens.focused().wix, 0);
+
+        // Focus workspace 1 the redetect screens: should have 1 and 0
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.screens.focused().wix, 1);
+        wm.set_num_screens(2);
+        wm.detect_screens().unwrap();
+        assert_eq!(wm.screens.n_screens(), 2);
+        assert_eq!(wm.screens.get(0).unwrap().wix, 1);
+        assert_eq!(wm.screens.get(1).unwrap().wix, 0);
+
+        // Adding another screen should now have WS 2 as 1 is taken
         wm.set_num_screens(3);
         wm.detect_screens().unwrap();
         assert_eq!(wm.screens.n_screens(), 3);
@@ -1611,530 +6406,2817 @@ mod tests {
         assert_eq!(wm.screens.get(1).unwrap().wix, 0);
         assert_eq!(wm.screens.get(2).unwrap().wix, 2);
 
-        // Focus WS 3 on screen 1, drop down to 1 screen: it should still have WS 3
+        // Focus WS 3 on screen 1, drop down to 1 screen: it should still have WS 3
+        wm.focus_workspace(&Selector::Index(3)).unwrap();
+        wm.set_num_screens(1);
+        wm.detect_screens().unwrap();
+        assert_eq!(wm.screens.n_screens(), 1);
+        assert_eq!(wm.screens.get(0).unwrap().wix, 3);
+    }
+
+    // Check that workspace layout is triggered correctly from public methods
+
+    macro_rules! layout_trigger_test {
+        { $method:ident; $should_layout:expr; $($arg:expr),* } => {
+            paste::paste! {
+                #[test]
+                fn [<layout_trigger_test _ $method>]() {
+                    let conn = RecordingXConn::init();
+                    let conf = Config {
+                        layouts: focus_test_layouts(false),
+                        ..Default::default()
+                    };
+                    let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+                    wm.init().unwrap();
+                    add_n_clients(&mut wm, 3, 0);
+                    wm.focus_workspace(&Selector::Index(1)).unwrap();
+                    add_n_clients(&mut wm, 3, 30);
+                    wm.focus_workspace(&Selector::Index(0)).unwrap();
+                    wm.conn.clear();
+                    wm.$method($($arg),*).unwrap();
+
+                    // Defining "we applied layout" as "position_client" was called
+                    // at least once. Tests around layout application itself being
+                    // correct are handled separately
+                    if $should_layout {
+                        assert!(wm.conn.was_called("position_client"));
+                    } else {
+                        assert!(wm.conn.was_not_called("position_client"));
+                    }
+                }
+            }
+        }
+    }
+
+    layout_trigger_test!(cycle_workspace; true; Forward);
+    layout_trigger_test!(drag_workspace; true; Forward);
+    layout_trigger_test!(cycle_client; false; Forward);
+    layout_trigger_test!(focus_client; false; &Selector::Any);
+    layout_trigger_test!(rotate_clients; true; Forward);
+    layout_trigger_test!(drag_client; true; Forward);
+    layout_trigger_test!(cycle_layout; true; Forward);
+    layout_trigger_test!(next_layout; true;);
+    layout_trigger_test!(prev_layout; true;);
+    layout_trigger_test!(set_layout; true; "t");
+    layout_trigger_test!(toggle_workspace_floating; false;);
+    layout_trigger_test!(set_border_width; true; &Selector::WinId(10), Some(5));
+    layout_trigger_test!(update_max_main; true; Change::More);
+    layout_trigger_test!(update_main_ratio; true; Change::More);
+    layout_trigger_test!(exit; false;);
+    layout_trigger_test!(set_root_window_name; false; "test");
+    layout_trigger_test!(set_client_insert_point; false; InsertPoint::First);
+    layout_trigger_test!(focus_workspace; true; &Selector::Index(1));
+    layout_trigger_test!(toggle_workspace; true;);
+    layout_trigger_test!(client_to_workspace; true; &Selector::Index(1));
+    layout_trigger_test!(client_to_screen; true; &Selector::Index(1));
+    layout_trigger_test!(toggle_client_fullscreen; true; &Selector::WinId(10));
+    layout_trigger_test!(kill_client; false;);
+    layout_trigger_test!(remove_workspace; true; &Selector::Index(0));
+    layout_trigger_test!(position_client; true; 10, Region::default(), true);
+    layout_trigger_test!(layout_screen; true; 0);
+
+    #[test]
+    fn workspace_layouts_override_global_layouts() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config::default()
+            .builder()
+            .workspaces(vec!["1", "2"])
+            .layouts(focus_test_layouts(false))
+            .workspace_layouts(map! {
+                "2".to_string() => vec![
+                    Layout::new("mono", LayoutConf::default(), monocle, 1, 0.6),
+                    Layout::floating("float"),
+                ],
+            })
+            .build()
+            .unwrap();
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        assert_eq!(wm.workspaces[0].layout_symbol(), "t");
+        assert_eq!(wm.workspaces[1].layout_symbol(), "mono");
+
+        wm.workspaces.cycle_layout(1, Forward);
+        assert_eq!(wm.workspaces[1].layout_symbol(), "float");
+        wm.workspaces.cycle_layout(1, Forward);
+        assert_eq!(wm.workspaces[1].layout_symbol(), "mono");
+    }
+
+    #[test]
+    fn floating_workspaces_do_not_tile_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.init().unwrap();
+        wm.toggle_workspace_floating().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+
+        let state = &mut wm.state;
+        let clients = state
+            .clients
+            .clients_for_ids(&state.workspaces[0].client_ids());
+        let (lc, aa) = state
+            .workspaces
+            .get_arrange_actions(0, Region::default(), &clients)
+            .unwrap();
+        assert!(lc.floating);
+        assert!(aa.actions.is_empty());
+        assert_eq!(aa.floating.len(), 3);
+
+        wm.toggle_workspace_floating().unwrap();
+        assert!(!wm.workspaces[0].is_floating());
+    }
+
+    #[test]
+    fn minimized_clients_are_removed_from_their_workspace() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+
+        wm.minimize_client(&Selector::WinId(20)).unwrap();
+        wm.minimize_client(&Selector::WinId(10)).unwrap();
+
+        assert_eq!(wm.workspaces[0].client_ids(), vec![30]);
+        let ids: Vec<Xid> = wm.minimized_clients().iter().map(|c| c.id()).collect();
+        assert_eq!(ids, vec![10, 20]);
+        assert!(wm.client(&Selector::WinId(10)).unwrap().is_minimized());
+
+        // Cycling only moves between non-minimized clients
+        wm.cycle_client(Forward).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(30));
+
+        wm.restore_last_minimized().unwrap();
+        assert!(wm.workspaces[0].client_ids().contains(&10));
+        assert_eq!(wm.focused_client_id(), Some(10));
+
+        wm.restore_client(&Selector::WinId(20)).unwrap();
+        assert!(wm.minimized_clients().is_empty());
+        assert_eq!(wm.workspaces[0].len(), 3);
+    }
+
+    #[test]
+    fn tag_views_show_the_union_of_viewed_tags() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        wm.add_client_tag(&Selector::WinId(10), "a").unwrap();
+        wm.add_client_tag(&Selector::WinId(20), "b").unwrap();
+        wm.add_client_tag(&Selector::WinId(30), "c").unwrap();
+
+        // All clients are shown by default
+        assert_eq!(wm.workspaces[0].len(), 3);
+
+        wm.view_tags(vec!["a"]).unwrap();
+        assert_eq!(wm.workspaces[0].client_ids(), vec![10]);
+
+        wm.toggle_viewed_tag("b").unwrap();
+        let mut ids = wm.workspaces[0].client_ids();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![10, 20]);
+
+        // New clients are given the viewed tags
+        add_n_clients(&mut wm, 1, 3);
+        assert!(wm.client(&Selector::WinId(40)).unwrap().has_tag("a"));
+        assert!(wm.client(&Selector::WinId(40)).unwrap().has_tag("b"));
+
+        wm.remove_client_tag(&Selector::WinId(10), "a").unwrap();
+        assert!(!wm.workspaces[0].client_ids().contains(&10));
+
+        wm.clear_tag_view().unwrap();
+        assert_eq!(wm.workspaces[0].len(), 4);
+        assert_eq!(wm.viewed_tags(), None);
+    }
+
+    #[test]
+    fn set_layout_with_unknown_symbol_is_an_error() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.init().unwrap();
+        let before = wm.current_layout_name().to_string();
+
+        assert!(wm.set_layout("not a layout").is_err());
+        assert_eq!(wm.current_layout_name(), before);
+    }
+
+    #[test]
+    fn layout_trigger_test_cycle_client_follow_focus() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            layouts: focus_test_layouts(true),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 3, 30);
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        wm.conn.clear();
+        wm.cycle_client(Direction::Forward).unwrap();
+
+        assert!(wm.conn.was_called("position_client"));
+    }
+
+    #[test]
+    fn layout_trigger_test_focus_client_follow_focus() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            layouts: focus_test_layouts(true),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.handle_map_request(0).unwrap();
+        wm.handle_map_request(1).unwrap();
+        wm.focus_client(&Selector::WinId(0)).unwrap();
+        wm.conn.clear();
+
+        // shouldn't trigger layout when re-focusing same client
+        wm.focus_client(&Selector::WinId(0)).unwrap();
+        assert!(wm.conn.was_not_called("position_client"));
+
+        wm.conn.clear();
+
+        // focusing any other client should trigger layout stuff
+        wm.focus_client(&Selector::WinId(1)).unwrap();
+        assert!(wm.conn.was_called("position_client"));
+    }
+
+    test_cases! {
+        configure_requests_are_sanitized;
+        args: (r: Region, clamp: bool, expected: Option<Region>);
+
+        case: valid => (Region::new(10, 20, 300, 200), true, Some(Region::new(10, 20, 300, 200)));
+        case: zero_size => (Region::new(10, 20, 0, 0), true, Some(Region::new(10, 20, 50, 50)));
+        case: negative_position => (Region::new(-10i16 as u32, 20, 300, 200), true, Some(Region::new(0, 20, 300, 200)));
+        case: off_screen => (Region::new(5000, 5000, 300, 200), true, Some(Region::new(1062, 564, 300, 200)));
+        case: off_screen_unclamped => (Region::new(5000, 5000, 300, 200), false, Some(Region::new(5000, 5000, 300, 200)));
+        case: nonsensical => (Region::new(1 << 24, 20, 300, 200), true, None);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let conf = Config {
+                border_px: 2,
+                min_configure_size: (50, 50),
+                clamp_configure_requests: clamp,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            wm.handle_map_request(1).unwrap();
+            wm.clients.get_mut(1).unwrap().set_floating(true);
+            wm.conn.clear();
+
+            let evt = ConfigureEvent {
+                id: 1,
+                r,
+                is_root: false,
+                // requested border widths are ignored in favour of the configured border_px
+                border_width: Some(10),
+                sibling: None,
+                stack_mode: None,
+            };
+            let actions = process_next_event(XEvent::ConfigureRequest(evt), &wm.state, &wm.conn);
+            wm.handle_event_actions(actions).unwrap();
+
+            let calls = wm.conn.calls();
+            let expected: Vec<_> = expected
+                .into_iter()
+                .map(|reg| ("position_client".to_string(), strings!(1, reg, 2, true)))
+                .collect();
+            assert_eq!(calls, expected);
+        }
+    }
+
+    #[test]
+    fn only_floating_clients_can_restack_themselves() {
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.handle_map_request(1).unwrap();
+        wm.handle_map_request(2).unwrap();
+        wm.clients.get_mut(1).unwrap().set_floating(true);
+        wm.conn.clear();
+
+        wm.handle_restack_if_floating(2, None, StackMode::Above)
+            .unwrap();
+        assert!(wm.conn.was_not_called("configure_client"));
+
+        wm.handle_restack_if_floating(1, Some(2), StackMode::Below)
+            .unwrap();
+        let calls = wm.conn.calls();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    "configure_client".to_string(),
+                    strings!(1, [ClientConfig::Stack(StackMode::Below, Some(2))])
+                ),
+                (
+                    "change_prop".to_string(),
+                    strings!(42, "_NET_CLIENT_LIST_STACKING", Prop::Window(vec![1, 2]))
+                ),
+            ]
+        );
+    }
+
+    test_cases! {
+        manual_restacking;
+        args: (restack: fn(&mut WindowManager<RecordingXConn>), expected: Vec<Xid>, updated: bool);
+
+        case: raise => (|wm| wm.raise_client(&Selector::WinId(10)).unwrap(), vec![20, 30, 10], true);
+        case: lower => (|wm| wm.lower_client(&Selector::WinId(30)).unwrap(), vec![30, 10, 20], true);
+        case: restack_above => (|wm| wm.restack_above(10, 20).unwrap(), vec![20, 10, 30], true);
+        case: unknown_selector => (|wm| wm.raise_client(&Selector::WinId(99)).unwrap(), vec![10, 20, 30], false);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 3, 0);
+            wm.conn.clear();
+
+            restack(&mut wm);
+
+            assert_eq!(wm.stacking_order(), &expected[..]);
+            let stacking_props: Vec<RecordedCall> = wm
+                .conn
+                .calls()
+                .into_iter()
+                .filter(|(m, args)| m == "change_prop" && args[1].contains("STACKING"))
+                .collect();
+            let expected_props = if updated {
+                vec![(
+                    "change_prop".to_string(),
+                    strings!(42, "_NET_CLIENT_LIST_STACKING", Prop::Window(expected)),
+                )]
+            } else {
+                vec![]
+            };
+            assert_eq!(stacking_props, expected_props);
+        }
+    }
+
+    #[test]
+    fn restack_above_unknown_clients_is_an_error() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+
+        assert!(wm.restack_above(10, 99).is_err());
+        assert!(wm.restack_above(99, 10).is_err());
+    }
+
+    #[test]
+    fn floating_clients_raised_by_layouts_are_tracked_in_the_stacking_order() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0);
+        wm.toggle_float(&Selector::WinId(20)).unwrap();
+        wm.restack_above(30, 20).unwrap();
+        wm.layout_visible().unwrap();
+
+        assert_eq!(wm.stacking_order().last(), Some(&20));
+    }
+
+    #[test]
+    fn removed_clients_leave_the_stacking_order() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0);
+        wm.remove_client(20).unwrap();
+
+        assert_eq!(wm.stacking_order(), &[10, 30]);
+    }
+
+    test_cases! {
+        raise_on_focus;
+        args: (raise_on_focus: bool, target: Xid, expected: Vec<Xid>);
+
+        case: disabled => (false, 2, vec![]);
+        case: tiled_keeps_floating_above => (true, 2, vec![2, 1]);
+        case: floating => (true, 1, vec![1]);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let conf = Config {
+                raise_on_focus,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            wm.handle_map_request(1).unwrap();
+            wm.handle_map_request(2).unwrap();
+            wm.handle_map_request(3).unwrap();
+            wm.clients.get_mut(1).unwrap().set_floating(true);
+            wm.conn.clear();
+
+            wm.focus_client(&Selector::WinId(target)).unwrap();
+            let raised: Vec<_> = wm
+                .conn
+                .calls()
+                .into_iter()
+                .filter(|(m, _)| m == "raise_client")
+                .map(|(_, args)| args)
+                .collect();
+
+            let expected: Vec<_> = expected.into_iter().map(|id| strings!(id)).collect();
+            assert_eq!(raised, expected);
+        }
+    }
+
+    #[test]
+    fn tiled_clients_are_sent_a_synthetic_configure_notify() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        wm.conn.clear();
+
+        wm.layout_screen(0).unwrap();
+        let calls = wm.conn.calls();
+        let positioned: Vec<_> = calls
+            .iter()
+            .filter(|(m, _)| m == "position_client")
+            .map(|(_, args)| args[..3].to_vec())
+            .collect();
+        let notified: Vec<_> = calls
+            .iter()
+            .filter(|(m, _)| m == "send_configure_notify")
+            .map(|(_, args)| args.clone())
+            .collect();
+
+        assert_eq!(positioned.len(), 2);
+        assert_eq!(positioned, notified);
+    }
+
+    test_cases! {
+        aspect_ratio;
+        args: (aspect_ratio: Option<AspectRatio>, expected: Region);
+
+        case: not_preserved => (None, Region::new(5, 23, 1352, 736));
+        case: fixed => (Some(AspectRatio::Fixed(4, 3)), Region::new(190, 23, 981, 736));
+        case: from_missing_hints => (Some(AspectRatio::FromHints), Region::new(5, 23, 1352, 736));
+
+        body: {
+            let mut wm = WindowManager::new(
+                Config::default(),
+                RecordingXConn::init(),
+                vec![],
+                logging_error_handler(),
+            );
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.set_client_aspect_ratio(&Selector::WinId(10), aspect_ratio).unwrap();
+            wm.conn.clear();
+            wm.layout_screen(0).unwrap();
+
+            let positioned: Vec<_> = wm
+                .conn
+                .calls()
+                .into_iter()
+                .filter(|(m, _)| m == "position_client")
+                .map(|(_, args)| args)
+                .collect();
+
+            assert_eq!(positioned, vec![strings!(10, expected, 2, false)]);
+        }
+    }
+
+    test_cases! {
+        unfocused_opacity;
+        args: (unfocused_opacity: Option<f64>, expected: Vec<RecordedCall>);
+
+        case: disabled => (None, vec![]);
+        case: enabled => (
+            Some(0.5),
+            vec![
+                ("change_prop".into(), strings!(20, "_NET_WM_WINDOW_OPACITY", Prop::Cardinal(0x8000_0000))),
+                ("change_prop".into(), strings!(10, "_NET_WM_WINDOW_OPACITY", Prop::Cardinal(u32::MAX))),
+            ]
+        );
+
+        body: {
+            let conn = RecordingXConn::init();
+            let conf = Config {
+                layouts: focus_test_layouts(false),
+                unfocused_opacity,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 2, 0);
+            wm.conn.clear();
+
+            wm.update_focus(10).unwrap();
+            let opacity_calls: Vec<_> = wm
+                .conn
+                .calls()
+                .into_iter()
+                .filter(|(m, args)| m == "change_prop" && args[1] == "\"_NET_WM_WINDOW_OPACITY\"")
+                .collect();
+
+            assert_eq!(opacity_calls, expected);
+        }
+    }
+
+    test_cases! {
+        focus_workspace_by_name;
+        args: (name: &'static str, expected: Option<usize>);
+
+        case: known => ("3", Some(2));
+        case: renamed => ("mail", Some(4));
+        case: unknown => ("nope", None);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.set_workspace_name("mail", &Selector::Index(4)).unwrap();
+            assert_eq!(wm.workspace_names()[4], "mail");
+
+            let res = wm.focus_workspace_by_name(name);
+
+            match expected {
+                Some(wix) => {
+                    assert!(res.is_ok());
+                    assert_eq!(wm.screens.active_ws_index(), wix);
+                    assert_eq!(wm.current_workspace_name(), name);
+                }
+                None => {
+                    assert!(res.is_err());
+                    assert_eq!(wm.current_workspace_name(), "1");
+                }
+            }
+        }
+    }
+
+    // Places every client other than the first on the given workspace
+    struct SendNewClientsTo(usize);
+
+    impl<X: XConn> crate::core::hooks::Hook<X> for SendNewClientsTo {
+        fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> Result<()> {
+            if id != 10 {
+                wm.clients.modify(id, |c| c.set_workspace(self.0));
+            }
+            Ok(())
+        }
+    }
+
+    test_cases! {
+        focus_new_windows;
+        args: (policy: FocusNewPolicy, wix: usize, opted_out: bool, focused: bool);
+
+        case: always_current => (FocusNewPolicy::Always, 0, false, true);
+        case: always_other => (FocusNewPolicy::Always, 1, false, true);
+        case: always_opted_out => (FocusNewPolicy::Always, 0, true, false);
+        case: never => (FocusNewPolicy::Never, 0, false, false);
+        case: current_workspace_current => (FocusNewPolicy::CurrentWorkspace, 0, false, true);
+        case: current_workspace_other => (FocusNewPolicy::CurrentWorkspace, 1, false, false);
+        case: current_workspace_opted_out => (FocusNewPolicy::CurrentWorkspace, 0, true, false);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let class = Prop::UTF8String(vec!["background".into()]);
+            conn.change_prop(20, Atom::WmClass.as_ref(), class).unwrap();
+            let conf = Config {
+                focus_new_windows: policy,
+                no_focus_classes: if opted_out { vec!["background".into()] } else { vec![] },
+                ..Default::default()
+            };
+            let hooks: Hooks<RecordingXConn> = vec![Box::new(SendNewClientsTo(wix))];
+            let mut wm = WindowManager::new(conf, conn, hooks, logging_error_handler());
+            wm.init().unwrap();
+            wm.handle_map_request(10).unwrap();
+            wm.update_focus(10).unwrap();
+            wm.handle_map_request(20).unwrap();
+
+            let expected = if focused { 20 } else { 10 };
+            assert_eq!(wm.clients.focused_client_id(), Some(expected));
+            assert_eq!(wm.workspaces[0].focused_client(), Some(if wix == 0 { expected } else { 10 }));
+        }
+    }
+
+    test_cases! {
+        ignored_clients;
+        args: (prop: &str, val: Prop);
+
+        case: class => (Atom::WmClass.as_ref(), Prop::UTF8String(vec!["icons".into()]));
+        case: window_type => (
+            Atom::NetWmWindowType.as_ref(),
+            Prop::Atom(vec![Atom::NetWindowTypeDesktop.as_ref().into()])
+        );
+
+        body: {
+            let conn = RecordingXConn::init();
+            conn.change_prop(20, prop, val).unwrap();
+            let conf = Config {
+                ignored_classes: vec!["icons".into()],
+                ignored_window_types: vec![Atom::NetWindowTypeDesktop.as_ref().into()],
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            wm.handle_map_request(10).unwrap();
+            wm.handle_map_request(20).unwrap();
+
+            assert!(!wm.clients.is_known(20));
+            assert_eq!(wm.workspaces[0].client_ids(), vec![10]);
+            assert_eq!(wm.clients.focused_client_id(), Some(10));
+        }
+    }
+
+    test_cases! {
+        sink_all_floats;
+        args: (prop: Option<(&str, Prop)>, exempt_sticky: bool, expected_floating: bool);
+
+        case: plain => (None, true, false);
+        case: dialog => (
+            Some((Atom::NetWmWindowType.as_ref(), Prop::Atom(vec![Atom::NetWindowTypeDialog.as_ref().into()]))),
+            true,
+            true
+        );
+        case: sticky => (
+            Some((Atom::NetWmState.as_ref(), Prop::Atom(vec![Atom::NetWmStateSticky.as_ref().into()]))),
+            true,
+            true
+        );
+        case: sticky_not_exempt => (
+            Some((Atom::NetWmState.as_ref(), Prop::Atom(vec![Atom::NetWmStateSticky.as_ref().into()]))),
+            false,
+            false
+        );
+
+        body: {
+            let conn = RecordingXConn::init();
+            if let Some((name, val)) = prop {
+                conn.change_prop(10, name, val).unwrap();
+            }
+            let conf = Config {
+                sink_exempt_sticky: exempt_sticky,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            wm.handle_map_request(10).unwrap();
+            wm.handle_map_request(20).unwrap();
+            wm.clients.modify(10, |c| c.floating = true);
+
+            wm.sink_all_floats().unwrap();
+
+            assert_eq!(wm.clients.get(10).unwrap().floating, expected_floating);
+            assert!(!wm.clients.get(20).unwrap().floating);
+        }
+    }
+
+    test_cases! {
+        sink_floats_on_tiling;
+        args: (enabled: bool, expected_floating: bool);
+
+        case: enabled => (true, false);
+        case: disabled => (false, true);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.sink_floats_on_tiling = enabled;
+            add_n_clients(&mut wm, 2, 0);
+            wm.toggle_workspace_floating().unwrap();
+            wm.clients.modify(10, |c| c.floating = true);
+
+            // Changing layout while still in floating mode leaves floating clients alone
+            wm.cycle_layout(Forward).unwrap();
+            assert!(wm.clients.get(10).unwrap().floating);
+
+            wm.toggle_workspace_floating().unwrap();
+            assert_eq!(wm.clients.get(10).unwrap().floating, expected_floating);
+        }
+    }
+
+    test_cases! {
+        layout_changes_preserve_client_order;
+        args: (change: fn(&mut WindowManager<MockXConn>) -> Result<()>);
+
+        case: cycle_forward => (|wm| wm.cycle_layout(Forward));
+        case: cycle_backward => (|wm| wm.cycle_layout(Backward));
+        case: set_layout => (|wm| wm.set_layout("[grid]"));
+        case: set_floating_layout => (|wm| wm.set_layout("[----]"));
+        case: toggle_floating => (|wm| wm.toggle_workspace_floating());
+
+        body: {
+            let conn = MockXConn::new(test_screens(), vec![], vec![]);
+            let conf = Config {
+                layouts: vec![
+                    Layout::new("[side]", LayoutConf::default(), side_stack, 1, 0.6),
+                    Layout::new("[botm]", LayoutConf::default(), bottom_stack, 1, 0.6),
+                    Layout::new("[mono]", LayoutConf::default(), monocle, 1, 0.6),
+                    Layout::new("[grid]", LayoutConf::default(), grid, 1, 0.6),
+                    Layout::floating("[----]"),
+                ],
+                sink_floats_on_tiling: true,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 4, 0);
+            wm.toggle_float(&Selector::WinId(20)).unwrap();
+            wm.focus_client(&Selector::WinId(30)).unwrap();
+
+            let expected = vec![40, 30, 20, 10];
+            assert_eq!(wm.workspaces[0].client_ids(), expected);
+
+            for _ in 0..6 {
+                change(&mut wm).unwrap();
+                assert_eq!(wm.workspaces[0].client_ids(), expected);
+                assert_eq!(wm.focused_client_id(), Some(30));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    test_cases! {
+        focused_client_dir;
+        args: (pid: Option<u32>, expected: Option<PathBuf>);
+
+        case: known_pid => (
+            Some(std::process::id()), Some(env::current_dir().unwrap())
+        );
+        case: missing_pid => (None, env::var_os("HOME").map(PathBuf::from));
+        case: unknown_pid => (Some(u32::MAX), env::var_os("HOME").map(PathBuf::from));
+
+        body: {
+            let conn = RecordingXConn::init();
+            if let Some(pid) = pid {
+                conn.set_cardinal(10, Atom::NetWmPid.as_ref(), pid).unwrap();
+            }
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            wm.handle_map_request(10).unwrap();
+
+            assert_eq!(wm.focused_client_dir(), expected);
+        }
+    }
+
+    #[test]
+    fn frame_extents_are_set_on_map_and_when_requested() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            border_px: 3,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let extents = |wm: &WindowManager<RecordingXConn>, id: Xid| {
+            wm.conn.get_prop(id, Atom::NetFrameExtents.as_ref()).ok()
+        };
+
+        wm.handle_map_request(10).unwrap();
+        assert_eq!(extents(&wm, 10), Some(Prop::CardinalList(vec![3; 4])));
+
+        assert_eq!(extents(&wm, 20), None);
+        wm.handle_event_action(EventAction::SetFrameExtents(20), None, None)
+            .unwrap();
+        assert_eq!(extents(&wm, 20), Some(Prop::CardinalList(vec![3; 4])));
+    }
+
+    #[test]
+    fn frame_extents_are_scaled_for_the_screen_showing_the_client() {
+        // The second screen is twice the base DPI
+        let conn = RecordingXConn::init().with_screens(vec![
+            Screen::new(Region::new(0, 0, 1366, 768), 0),
+            Screen::new(Region::new(1366, 0, 1366, 768), 1).with_physical_width(181),
+        ]);
+        let conf = Config {
+            border_px: 3,
+            dpi_scaling: true,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+        wm.move_client_to_workspace(10, 1).unwrap();
+        assert_eq!(wm.screens.focused().wix, 0);
+
+        wm.handle_event_action(EventAction::SetFrameExtents(10), None, None)
+            .unwrap();
+        let extents = wm.conn.get_prop(10, Atom::NetFrameExtents.as_ref()).ok();
+        assert_eq!(extents, Some(Prop::CardinalList(vec![6; 4])));
+    }
+
+    #[test]
+    fn position_client_scales_borders_for_the_screen_showing_the_client() {
+        // The second screen is twice the base DPI
+        let conn = RecordingXConn::init().with_screens(vec![
+            Screen::new(Region::new(0, 0, 1366, 768), 0),
+            Screen::new(Region::new(1366, 0, 1366, 768), 1).with_physical_width(181),
+        ]);
+        let conf = Config {
+            border_px: 3,
+            dpi_scaling: true,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+        wm.move_client_to_workspace(10, 1).unwrap();
+        wm.conn.clear();
+
+        let r = Region::new(1400, 10, 200, 100);
+        wm.position_client(10, r, true).unwrap();
+        let calls = wm.conn.calls();
+
+        assert_eq!(
+            calls,
+            vec![("position_client".into(), strings!(10, r, 6, true))]
+        );
+    }
+
+    #[test]
+    fn clients_skipping_the_taskbar_are_left_out_of_the_client_list() {
+        let conn = RecordingXConn::init();
+        let skip = Atom::NetWmStateSkipTaskbar.as_ref();
+        conn.set_atom_list_prop(10, Atom::NetWmState.as_ref(), &[skip])
+            .unwrap();
+
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+
+        assert!(wm.clients.get(10).unwrap().skip_taskbar());
+        assert_eq!(wm.conn.client_list(), vec![20]);
+
+        let remove_skip = EventAction::SetClientSkipState(10, vec![Atom::NetWmStateSkipTaskbar], 0);
+        wm.handle_event_action(remove_skip, None, None).unwrap();
+
+        assert!(!wm.clients.get(10).unwrap().skip_taskbar());
+        assert_eq!(wm.conn.client_list(), vec![10, 20]);
+        assert!(wm
+            .conn
+            .get_atom_list_prop(10, Atom::NetWmState.as_ref())
+            .unwrap()
+            .is_empty());
+
+        // the client setting the property directly is picked up as well
+        wm.conn
+            .set_atom_list_prop(20, Atom::NetWmState.as_ref(), &[skip])
+            .unwrap();
+        wm.handle_event_action(EventAction::ClientWmStateChanged(20), None, None)
+            .unwrap();
+
+        assert_eq!(wm.conn.client_list(), vec![10]);
+    }
+
+    test_cases! {
+        spawn_and_wait_for_window;
+        args: (props: Vec<(&'static str, Prop)>, timeout_ms: u64, expected: Vec<Xid>);
+
+        case: matching_pid => (vec![("_NET_WM_PID", Prop::Cardinal(1234))], 1000, vec![10]);
+        case: matching_startup_id => (
+            vec![("_NET_STARTUP_ID", Prop::UTF8String(vec!["test-id".into()]))],
+            1000,
+            vec![10]
+        );
+        case: no_match => (vec![("_NET_WM_PID", Prop::Cardinal(42))], 1000, vec![]);
+        case: timed_out => (vec![("_NET_WM_PID", Prop::Cardinal(1234))], 0, vec![]);
+
+        body: {
+            let conn = RecordingXConn::init();
+            for (name, prop) in props {
+                conn.change_prop(10, name, prop).unwrap();
+            }
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+
+            let matched = Rc::new(RefCell::new(vec![]));
+            let m = Rc::clone(&matched);
+            let timeout = Duration::from_millis(timeout_ms);
+            wm.wait_for_window(1234, "test-id".into(), timeout, Box::new(move |_, id| {
+                m.borrow_mut().push(id);
+                Ok(())
+            }));
+
+            wm.handle_event_action(EventAction::MapWindow(10), None, None).unwrap();
+            wm.handle_event_action(EventAction::MapWindow(20), None, None).unwrap();
+
+            assert_eq!(*matched.borrow(), expected);
+            assert!(wm.pending_spawns.len() <= 1);
+        }
+    }
+
+    #[cfg(feature = "startup_notification")]
+    test_cases! {
+        startup_notification;
+        args: (prop: &'static str, complete: bool);
+
+        case: net_startup_id => ("_NET_STARTUP_ID", true);
+        case: net_wm_startup_id => ("_NET_WM_STARTUP_ID", true);
+        case: unrelated_prop => ("WM_NAME", false);
+
+        body: {
+            let mut wm = WindowManager::new(
+                Config::default(),
+                RecordingXConn::init(),
+                vec![],
+                logging_error_handler(),
+            );
+            wm.init().unwrap();
+
+            let startup_id = wm.startups.begin("test");
+            wm.conn
+                .change_prop(10, prop, Prop::UTF8String(vec![startup_id.clone()]))
+                .unwrap();
+            assert!(wm.is_launching());
+
+            wm.handle_event_action(EventAction::MapWindow(10), None, None).unwrap();
+
+            assert_eq!(wm.is_launching(), !complete);
+        }
+    }
+
+    test_cases! {
+        root_background;
+        args: (target: usize, n_clients: usize, expected: bool);
+
+        case: empty_workspace => (2, 0, true);
+        case: non_empty_workspace => (2, 2, false);
+        case: empty_workspace_on_other_screen => (1, 0, true);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let conf = Config {
+                root_background: Some(RootBackground::Color(0x282828ff.into())),
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            assert!(wm.conn.was_called("clear_client"));
+
+            if n_clients > 0 {
+                wm.focus_workspace(&Selector::Index(target)).unwrap();
+                add_n_clients(&mut wm, n_clients, 0);
+                wm.focus_workspace(&Selector::Index(0)).unwrap();
+            }
+            wm.conn.clear();
+            wm.focus_workspace(&Selector::Index(target)).unwrap();
+
+            assert_eq!(wm.conn.was_called("clear_client"), expected);
+        }
+    }
+
+    #[test]
+    fn root_background_commands_are_only_run_on_startup() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            root_background: Some(RootBackground::Command("true".into())),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.conn.clear();
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+
+        assert!(wm.conn.was_not_called("set_client_attributes"));
+    }
+
+    test_cases! {
+        workspace_backgrounds;
+        args: (targets: Vec<usize>, root_background: Option<u32>, expected: Vec<u32>);
+
+        case: own_background => (vec![1], None, vec![0x111111]);
+        case: shared_backgrounds_are_not_reapplied => (vec![1, 2], None, vec![0x111111]);
+        case: no_background => (vec![3], None, vec![]);
+        case: fallback_to_root_background => (vec![1, 3], Some(0x333333), vec![0x111111, 0x333333]);
+        case: returning_to_the_initial_background => (vec![2, 0], None, vec![0x111111, 0x000000]);
+
+        body: {
+            let conn = RecordingXConn::init();
+            let backgrounds = vec![("1", 0x000000ff), ("2", 0x111111ff), ("3", 0x111111ff)];
+            let conf = Config {
+                workspace_backgrounds: backgrounds
+                    .into_iter()
+                    .map(|(name, c)| (name.to_string(), RootBackground::Color(c.into())))
+                    .collect(),
+                workspace_background_debounce_ms: 0,
+                root_background: root_background.map(|c| RootBackground::Color((c << 8 | 0xff).into())),
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            // Empty workspaces repaint the current background so give "3" a client
+            wm.focus_workspace(&Selector::Index(2)).unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.focus_workspace(&Selector::Index(0)).unwrap();
+            wm.conn.clear();
+
+            for wix in targets {
+                wm.focus_workspace(&Selector::Index(wix)).unwrap();
+            }
+
+            let painted: Vec<RecordedCall> = wm
+                .conn
+                .calls()
+                .into_iter()
+                .filter(|(m, _)| m == "set_client_attributes")
+                .collect();
+            let expected: Vec<RecordedCall> = expected
+                .into_iter()
+                .map(|c| (
+                    "set_client_attributes".to_string(),
+                    strings!(42, [ClientAttr::BackgroundColor(c)])
+                ))
+                .collect();
+            assert_eq!(painted, expected);
+        }
+    }
+
+    #[test]
+    fn workspace_backgrounds_are_debounced() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            workspace_backgrounds: map! {
+                "2".to_string() => RootBackground::Color(0x111111ff.into()),
+                "3".to_string() => RootBackground::Color(0x222222ff.into()),
+            },
+            workspace_background_debounce_ms: 1,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.conn.clear();
+
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert!(wm.conn.was_not_called("set_client_attributes"));
+
+        assert!(wm.poll_until_background_due().unwrap().is_none());
+        assert_eq!(
+            wm.conn.calls(),
+            vec![
+                (
+                    "set_client_attributes".to_string(),
+                    strings!(42, [ClientAttr::BackgroundColor(0x222222)])
+                ),
+                ("clear_client".to_string(), strings!(42)),
+            ]
+        );
+        assert!(wm.pending_background.is_none());
+    }
+
+    test_cases! {
+        reset_layout;
+        args: (clear_client_overrides: bool, expected_border: Option<u32>);
+
+        case: keep_overrides => (false, Some(5));
+        case: clear_overrides => (true, None);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            add_n_clients(&mut wm, 3, 0);
+            wm.update_max_main(Change::More).unwrap();
+            wm.update_main_ratio(Change::More).unwrap();
+            wm.set_border_width(&Selector::WinId(10), Some(5)).unwrap();
+
+            wm.reset_layout(clear_client_overrides).unwrap();
+
+            let ws = wm.workspaces.get_workspace(0).unwrap();
+            assert_eq!(ws.max_main(), 1);
+            assert_eq!(ws.main_ratio(), 0.6);
+            assert_eq!(wm.clients.get(10).unwrap().border_px(), expected_border);
+        }
+    }
+
+    test_cases! {
+        handle_error;
+        args: (policy: ErrorPolicy, err: PenroseError, exits: bool);
+
+        case: fatal_exits => (ErrorPolicy::ExitOnFatal, XError::ConnectionClosed.into(), true);
+        case: recoverable_is_handled => (ErrorPolicy::ExitOnFatal, XError::UnknownClient(1).into(), false);
+        case: non_x_error_is_handled => (ErrorPolicy::ExitOnFatal, PenroseError::NoMatchingElement, false);
+        case: exit_on_any => (ErrorPolicy::ExitOnAny, XError::UnknownClient(1).into(), true);
+
+        body: {
+            let handled = Rc::new(RefCell::new(0));
+            let h = Rc::clone(&handled);
+            let error_handler: ErrorHandler = Box::new(move |_| *h.borrow_mut() += 1);
+            let conf = Config {
+                error_policy: policy,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, MockXConn::new(test_screens(), vec![], vec![]), vec![], error_handler);
+
+            assert_eq!(
+                wm.handle_error(err, &HashMap::new(), &HashMap::new()).is_err(),
+                exits
+            );
+            assert_eq!(*handled.borrow(), if exits { 0 } else { 1 });
+        }
+    }
+
+    test_cases! {
+        reconnect;
+        args: (attempts: u32, failures: u32, reconnected: bool);
+
+        case: disabled => (0, 0, false);
+        case: first_attempt => (3, 0, true);
+        case: after_failures => (3, 2, true);
+        case: attempts_exhausted => (2, 2, false);
+
+        body: {
+            let conf = Config {
+                reconnect_attempts: attempts,
+                reconnect_backoff_ms: 0,
+                ..Default::default()
+            };
+            let conn = RecordingXConn::init()
+                .with_reconnect_failures(failures)
+                .with_active_clients(vec![10, 30]);
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 3, 0);
+
+            let res = wm.handle_error(XError::ConnectionClosed.into(), &HashMap::new(), &HashMap::new());
+
+            assert_eq!(res.is_ok(), reconnected);
+            if reconnected {
+                let mut ids = wm.clients.all_known_ids();
+                ids.sort_unstable();
+                assert_eq!(ids, vec![10, 30]);
+            }
+        }
+    }
+
+    #[test]
+    fn event_loop_exits_when_the_connection_is_closed() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        let res = wm.grab_keys_and_run(HashMap::new(), HashMap::new());
+
+        assert!(matches!(
+            res,
+            Err(PenroseError::X(XError::ConnectionClosed))
+        ));
+    }
+
+    #[test]
+    fn set_opacity_requires_a_known_client() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        assert!(wm.set_opacity(&Selector::WinId(42), 0.5).is_err());
+    }
+
+    /*
+     * Helpers for specifying expected events with RecordingXConn
+     */
+
+    fn _focus(id: Xid) -> RecordedCall {
+        ("focus_client".into(), strings!(id))
+    }
+
+    fn _take_focus(id: Xid) -> RecordedCall {
+        let conn = RecordingXConn::init();
+        let evt = ClientMessageKind::TakeFocus(id).as_message(&conn).unwrap();
+        ("send_client_event".into(), strings!(evt))
+    }
+
+    fn _id(a: Atom) -> RecordedCall {
+        ("atom_id".into(), strings!(a.as_ref()))
+    }
+
+    fn _border(id: Xid, focused: bool) -> RecordedCall {
+        let color = if focused {
+            Color::try_from("#00ff00").unwrap()
+        } else {
+            Color::try_from("#ff0000").unwrap()
+        };
+        ("set_client_border_color".into(), strings!(id, color))
+    }
+
+    fn _active(id: Xid) -> RecordedCall {
+        let args = strings!(42, "_NET_ACTIVE_WINDOW", Prop::Window(vec![id]));
+        ("change_prop".into(), args)
+    }
+
+    fn _remove_active() -> RecordedCall {
+        ("delete_prop".into(), strings!(42, "_NET_ACTIVE_WINDOW"))
+    }
+
+    test_cases! {
+        update_focus;
+        args: (
+            target: Xid,
+            accepts_focus: bool,
+            current: Option<Xid>,
+            n_clients: usize,
+            follow_focus: bool,
+            expected_focus: Option<Xid>,
+            expected_calls: Vec<RecordedCall>
+        );
+
+        // We should still run focusing logic when the requested target is our current focus
+        case: client_is_current_focus => (
+            10, true, Some(10), 3, false,
+            Some(10), vec![_focus(10), _active(10), _border(10, true)]
+        );
+
+        // We should remove the focused border from the current client first
+        case: client_is_not_current_focus => (
+            20, true, Some(10), 3, false,
+            Some(20), vec![_border(10, false), _focus(20), _active(20), _border(20, true)]
+        );
+
+        // Focus should default to the focused client on the active workspace if the given client
+        // is not in the client_map
+        case: client_is_unknown_workspace_populated => (
+            999, true, Some(10), 3, false,
+            Some(30), vec![_border(10, false), _focus(30), _active(30), _border(30, true)]
+        );
+
+        // If the client is unknown and the workspace is empty, focus should revert to root
+        case: client_is_unknown_workspace_empty => (
+            999, true, None, 0, false,
+            None, vec![_focus(42), _remove_active()]
+        );
+
+        // If the client doesn't accept focus then we should still mark it as focused in the
+        // internal state, but a TakeFocus client message should be sent instead of forcing
+        // focus.
+        case: client_does_not_accept_focus_different => (
+            20, false, Some(10), 3, false,
+            Some(20), vec![
+                _border(10, false), _id(Atom::WmTakeFocus), _take_focus(20)
+            ]
+        );
+
+        // If the client doesn't accept focus, and it is the current focus then we should just
+        // set the border and send the TakeFocus event
+        case: client_does_not_accept_focus_same => (
+            20, false, Some(20), 3, false,
+            Some(20), vec![_id(Atom::WmTakeFocus), _take_focus(20)]
+        );
+
+        // TODO: add test cases for follow_focus layout triggering
+
+        body: {
+            let conn = RecordingXConn::init();
+            let conf = Config {
+                layouts: focus_test_layouts(follow_focus),
+                focused_border: Color::try_from("#00ff00").unwrap(),
+                unfocused_border: Color::try_from("#ff0000").unwrap(),
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, n_clients, 0);
+            if let Some(id) = current {
+                wm.state.clients.set_focused(id, &wm.conn);
+            }
+            wm.clients.modify(target, |c| c.accepts_focus = accepts_focus);
+            wm.conn().clear();
+
+            wm.update_focus(target).unwrap();
+
+            assert_eq!(wm.clients.focused_client_id(), expected_focus);
+            assert_eq!(wm.conn().calls(), expected_calls);
+        }
+    }
+
+    #[test]
+    fn focused_clients_on_inactive_screens_use_the_inactive_screen_border() {
+        let accent = Color::try_from("#0000ff").unwrap();
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            focused_border: Color::try_from("#00ff00").unwrap(),
+            unfocused_border: Color::try_from("#ff0000").unwrap(),
+            inactive_screen_border: Some(accent),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        wm.move_client_to_workspace(20, 1).unwrap();
+        wm.conn().clear();
+
+        wm.update_focus(20).unwrap();
+        let borders: Vec<RecordedCall> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "set_client_border_color")
+            .collect();
+
+        assert_eq!(
+            borders,
+            vec![
+                _border(20, true),
+                ("set_client_border_color".into(), strings!(10, accent)),
+            ]
+        );
+    }
+
+    macro_rules! changing_screen_focus_test {
+        ($method:ident, $start_screen:expr, $test:expr, $expected:expr) => {
+            #[test]
+            fn $method() {
+                let conn = RecordingXConn::init();
+                let conf = Default::default();
+                let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+                wm.init().unwrap();
+
+                wm.focus_screen(&Selector::Index($start_screen));
+
+                let expected = $expected
+                    .into_iter()
+                    .map(|id| {
+                        (
+                            "change_prop".into(),
+                            strings!(42, "_NET_CURRENT_DESKTOP", Prop::Cardinal(id)),
+                        )
+                    })
+                    .collect::<Vec<RecordedCall>>();
+                wm.conn().clear();
+
+                // cycle the screen
+                $test(&mut wm);
+
+                // check that the recorded calls are the expected values
+                assert_eq!(wm.conn().calls(), expected);
+            }
+        };
+    }
+
+    changing_screen_focus_test!(
+        cycle_screen_backwards_on_first_screen,
+        0,
+        |wm: &mut WindowManager<_>| { wm.cycle_screen(Backward).unwrap() },
+        vec![]
+    );
+    changing_screen_focus_test!(
+        cycle_screen_backwards_on_second_screen,
+        1,
+        |wm: &mut WindowManager<_>| { wm.cycle_screen(Backward).unwrap() },
+        vec![0]
+    );
+    changing_screen_focus_test!(
+        cycle_screen_forwards_on_first_screen,
+        0,
+        |wm: &mut WindowManager<_>| { wm.cycle_screen(Forward).unwrap() },
+        vec![1]
+    );
+    changing_screen_focus_test!(
+        cycle_screen_forwards_on_last_screen,
+        1, // the mock setup apparently only has two screens
+        |wm: &mut WindowManager<_>| { wm.cycle_screen(Forward).unwrap() },
+        vec![]
+    );
+
+    changing_screen_focus_test!(
+        focus_screen_same,
+        0, // the mock setup apparently only has two screens
+        |wm: &mut WindowManager<_>| {
+            wm.focus_screen(&Selector::Index(0));
+        },
+        vec![]
+    );
+    changing_screen_focus_test!(
+        focus_screen_different,
+        0, // the mock setup apparently only has two screens
+        |wm: &mut WindowManager<_>| {
+            wm.focus_screen(&Selector::Index(1));
+        },
+        vec![1]
+    );
+
+    test_cases! {
+        focus_visible_workspace;
+        args: (
+            policy: VisibleWorkspacePolicy,
+            target: usize,
+            expected_visible: Vec<usize>,
+            expected_screen: usize
+        );
+
+        case: swap => (VisibleWorkspacePolicy::Swap, 1, vec![1, 0], 0);
+        case: focus_screen => (VisibleWorkspacePolicy::FocusScreen, 1, vec![0, 1], 1);
+        case: swap_same_screen => (VisibleWorkspacePolicy::Swap, 0, vec![0, 1], 0);
+        case: focus_screen_same_screen => (VisibleWorkspacePolicy::FocusScreen, 0, vec![0, 1], 0);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.visible_workspace_policy = policy;
+            wm.focus_workspace(&Selector::Index(target)).unwrap();
+
+            assert_eq!(wm.focused_workspaces(), expected_visible);
+            assert_eq!(wm.active_screen_index(), expected_screen);
+            assert_eq!(wm.screens.active_ws_index(), target);
+        }
+    }
+
+    #[test]
+    fn cycle_screen_updates_active() {
+        let mut wm = test_windowmanager(2, vec![]);
+
+        assert_eq!(wm.active_screen_index(), 0);
+        wm.cycle_screen(Forward).unwrap();
+        assert_eq!(wm.active_screen_index(), 1);
+        wm.cycle_screen(Forward).unwrap();
+        assert_eq!(wm.active_screen_index(), 1);
+    }
+
+    #[test]
+    fn cycle_workspace_updates_focused() {
+        let mut wm = test_windowmanager(1, vec![]);
+
+        assert_eq!(wm.focused_workspaces(), vec![0]);
+        wm.cycle_workspace(Forward).unwrap();
+        assert_eq!(wm.focused_workspaces(), vec![1]);
+        wm.cycle_workspace(Backward).unwrap();
+        wm.cycle_workspace(Backward).unwrap();
+        assert_eq!(wm.focused_workspaces(), vec![8]);
+    }
+
+    test_cases! {
+        cycle_overflow;
+        args: (overflow: Overflow, workspaces: Vec<usize>, client: Option<Xid>);
+
+        case: wrap => (Overflow::Wrap, vec![8], Some(2));
+        case: clamp => (Overflow::Clamp, vec![0], Some(0));
+
+        body: {
+            let mut wm = test_windowmanager(1, n_clients(3));
+            wm.init().unwrap();
+            wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+                .unwrap();
+            wm.state.config.cycle_overflow = overflow;
+            wm.focus_client(&Selector::WinId(0)).unwrap();
+
+            wm.cycle_client(Forward).unwrap();
+            assert_eq!(wm.focused_client_id(), client);
+            wm.cycle_workspace(Backward).unwrap();
+            assert_eq!(wm.focused_workspaces(), workspaces);
+        }
+    }
+
+    #[test]
+    fn drag_workspace_move_focused_workspaces_between_screens() {
+        let mut wm = test_windowmanager(2, vec![]);
+
+        assert_eq!(wm.focused_workspaces(), vec![0, 1]);
+        wm.drag_workspace(Forward).unwrap();
+        assert_eq!(wm.focused_workspaces(), vec![1, 0]);
+    }
+
+    test_cases! {
+        visible_workspace_policy_does_not_apply_to;
+        args: (
+            policy: VisibleWorkspacePolicy,
+            action: fn(&mut WindowManager<MockXConn>),
+            expected_screen: usize
+        );
+
+        case: drag_workspace_swap => (
+            VisibleWorkspacePolicy::Swap,
+            |wm| wm.drag_workspace(Forward).unwrap(),
+            1
+        );
+        case: drag_workspace_focus_screen => (
+            VisibleWorkspacePolicy::FocusScreen,
+            |wm| wm.drag_workspace(Forward).unwrap(),
+            1
+        );
+        case: cycle_workspace_swap => (
+            VisibleWorkspacePolicy::Swap,
+            |wm| wm.cycle_workspace(Forward).unwrap(),
+            0
+        );
+        case: cycle_workspace_focus_screen => (
+            VisibleWorkspacePolicy::FocusScreen,
+            |wm| wm.cycle_workspace(Forward).unwrap(),
+            0
+        );
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.visible_workspace_policy = policy;
+            action(&mut wm);
+
+            // the two screens swap workspaces rather than focus moving to the other screen
+            assert_eq!(wm.focused_workspaces(), vec![1, 0]);
+            assert_eq!(wm.active_screen_index(), expected_screen);
+        }
+    }
+
+    #[test]
+    fn cycle_client_updates_focus() {
+        let mut wm = test_windowmanager(1, n_clients(3));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+            .unwrap();
+        wm.focus_client(&Selector::WinId(0)).unwrap();
+
+        assert_eq!(wm.focused_client_id(), Some(0));
+        wm.cycle_client(Backward).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(1));
+        wm.cycle_client(Backward).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(2));
+        wm.cycle_client(Backward).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(0));
+    }
+
+    #[test]
+    fn focus_client() {
+        let mut wm = test_windowmanager(1, n_clients(3));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+            .unwrap();
+
+        let focused = wm.focus_client(&Selector::WinId(0));
+        assert_eq!(focused.unwrap(), 0);
+        let focused = wm.focus_client(&Selector::WinId(42));
+        assert!(focused.is_err());
+        let focused = wm.focus_client(&Selector::WinId(1));
+        assert_eq!(focused.unwrap(), 1);
+        let focused = wm.focus_client(&Selector::WinId(42));
+        assert!(focused.is_err());
+    }
+
+    #[test]
+    fn allowed_actions_follow_client_state() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        let allowed_actions = |wm: &WindowManager<RecordingXConn>| -> Vec<RecordedCall> {
+            wm.conn
+                .calls()
+                .into_iter()
+                .filter(|(m, args)| m == "change_prop" && args[1].contains("ALLOWED_ACTIONS"))
+                .collect()
+        };
+        let expected = |actions: &[&str]| -> Vec<RecordedCall> {
+            let names = actions.iter().map(|a| a.to_string()).collect();
+            vec![(
+                "change_prop".to_string(),
+                strings!(10, "_NET_WM_ALLOWED_ACTIONS", Prop::Atom(names)),
+            )]
+        };
+        let tiled = [
+            "_NET_WM_ACTION_CHANGE_DESKTOP",
+            "_NET_WM_ACTION_CLOSE",
+            "_NET_WM_ACTION_FULLSCREEN",
+            "_NET_WM_ACTION_MINIMIZE",
+        ];
+        let floating = [
+            &tiled[..],
+            &["_NET_WM_ACTION_MOVE", "_NET_WM_ACTION_RESIZE"],
+        ]
+        .concat();
+
+        add_n_clients(&mut wm, 1, 0);
+        assert_eq!(allowed_actions(&wm), expected(&tiled));
+
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        assert_eq!(allowed_actions(&wm), expected(&floating));
+
+        wm.toggle_client_fullscreen(&Selector::WinId(10)).unwrap();
+        assert_eq!(allowed_actions(&wm), expected(&tiled));
+
+        wm.toggle_client_fullscreen(&Selector::WinId(10)).unwrap();
+        assert_eq!(allowed_actions(&wm), expected(&floating));
+
+        wm.toggle_float(&Selector::WinId(10)).unwrap();
+        assert_eq!(allowed_actions(&wm), expected(&tiled));
+
+        // Tiled clients can be moved and resized while their workspace has a floating layout
+        wm.toggle_workspace_floating().unwrap();
+        assert_eq!(allowed_actions(&wm), expected(&floating));
+
+        wm.toggle_workspace_floating().unwrap();
+        assert_eq!(allowed_actions(&wm), expected(&tiled));
+    }
+
+    #[test]
+    fn unfocus_all_focuses_the_root_window() {
+        let conn = RecordingXConn::init();
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        wm.conn.clear();
+
+        wm.unfocus_all().unwrap();
+        assert_eq!(wm.focused_client_id(), None);
+        let calls = wm.conn.calls();
+        assert!(calls.contains(&("focus_root".to_string(), vec![])));
+        assert!(calls.contains(&_remove_active()));
+
+        // The previously focused client can be focused again directly
+        wm.focus_client(&Selector::WinId(20)).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(20));
+    }
+
+    #[test]
+    fn focus_client_no_clients() {
+        let mut wm = test_windowmanager(1, vec![]);
+        let focused = wm.focus_client(&Selector::WinId(0));
+        assert!(focused.is_err());
+    }
+
+    #[test]
+    fn rotate_clients() {
+        let mut wm = test_windowmanager(1, n_clients(3));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
+        wm.rotate_clients(Forward).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn drag_client() {
+        let mut wm = test_windowmanager(1, n_clients(4));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![3, 2, 1, 0]);
+        wm.drag_client(Forward).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![2, 3, 1, 0]);
+        wm.drag_client(Forward).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn cycle_layout() {
+        let mut wm = test_windowmanager(1, vec![]);
+
+        assert_eq!(wm.current_layout_symbol(), "first");
+        wm.cycle_layout(Forward).unwrap();
+        assert_eq!(wm.current_layout_symbol(), "second");
+        wm.cycle_layout(Forward).unwrap();
+        assert_eq!(wm.current_layout_symbol(), "first");
+    }
+
+    struct RecordLayoutChanges(Rc<RefCell<Vec<String>>>);
+
+    impl<X: XConn> crate::core::hooks::Hook<X> for RecordLayoutChanges {
+        fn layout_change(&mut self, wm: &mut WindowManager<X>, _: usize, _: usize) -> Result<()> {
+            self.0.borrow_mut().push(wm.current_layout_symbol().into());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn next_and_prev_layout_wrap_and_run_layout_change_hooks() {
+        let symbols = Rc::new(RefCell::new(vec![]));
+        let conf = Config {
+            layouts: ["first", "second", "third"]
+                .iter()
+                .map(|s| Layout::new(*s, LayoutConf::default(), mock_layout, 1, 0.6))
+                .collect(),
+            ..Default::default()
+        };
+        let hooks: Hooks<_> = vec![Box::new(RecordLayoutChanges(Rc::clone(&symbols)))];
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let mut wm = WindowManager::new(conf, conn, hooks, logging_error_handler());
+        wm.init().unwrap();
+
+        wm.prev_layout().unwrap();
+        wm.prev_layout().unwrap();
+        wm.next_layout().unwrap();
+        wm.next_layout().unwrap();
+        wm.next_layout().unwrap();
+
+        assert_eq!(
+            *symbols.borrow(),
+            vec!["third", "second", "third", "first", "second"]
+        );
+    }
+
+    #[test]
+    fn focus_workspace() {
+        let mut wm = test_windowmanager(1, vec![]);
+
+        assert_eq!(wm.active_workspace().name(), "1");
         wm.focus_workspace(&Selector::Index(3)).unwrap();
-        wm.set_num_screens(1);
-        wm.detect_screens().unwrap();
-        assert_eq!(wm.screens.n_screens(), 1);
-        assert_eq!(wm.screens.get(0).unwrap().wix, 3);
+        assert_eq!(wm.active_workspace().name(), "4");
+        wm.focus_workspace(&Selector::Condition(&|ws| ws.name() == "9"))
+            .unwrap();
+        assert_eq!(wm.active_workspace().name(), "9");
     }
 
-    // Check that workspace layout is triggered correctly from public methods
+    #[test]
+    fn toggle_workspace() {
+        let mut wm = test_windowmanager(1, vec![]);
 
-    macro_rules! layout_trigger_test {
-        { $method:ident; $should_layout:expr; $($arg:expr),* } => {
-            paste::paste! {
-                #[test]
-                fn [<layout_trigger_test _ $method>]() {
-                    let conn = RecordingXConn::init();
-                    let conf = Config {
-                        layouts: focus_test_layouts(false),
-                        ..Default::default()
-                    };
-                    let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
-                    wm.init().unwrap();
-                    add_n_clients(&mut wm, 3, 0);
-                    wm.focus_workspace(&Selector::Index(1)).unwrap();
-                    add_n_clients(&mut wm, 3, 30);
-                    wm.focus_workspace(&Selector::Index(0)).unwrap();
-                    wm.conn.clear();
-                    wm.$method($($arg),*).unwrap();
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.active_workspace().name(), "2");
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(wm.active_workspace().name(), "1");
+        wm.toggle_workspace().unwrap();
+        assert_eq!(wm.active_workspace().name(), "2");
+    }
 
-                    // Defining "we applied layout" as "position_client" was called
-                    // at least once. Tests around layout application itself being
-                    // correct are handled separately
-                    if $should_layout {
-                        assert!(wm.conn.was_called("position_client"));
-                    } else {
-                        assert!(wm.conn.was_not_called("position_client"));
-                    }
-                }
+    #[test]
+    fn client_to_workspace() {
+        let mut wm = test_windowmanager(1, n_clients(3));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
+        (0..3).for_each(|_| wm.client_to_workspace(&Selector::Index(1)).unwrap());
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn client_to_screen() {
+        let mut wm = test_windowmanager(2, n_clients(3));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert_eq!(wm.focused_workspaces(), vec![0, 1]);
+        assert_eq!(wm.active_screen_index(), 0);
+        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
+        wm.client_to_screen(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![1, 0]);
+        wm.cycle_screen(Forward).unwrap();
+        assert_eq!(wm.active_screen_index(), 1);
+        assert_eq!(wm.active_workspace().client_ids(), vec![2]);
+    }
+
+    #[test]
+    fn toggle_client_fullscreen() {
+        let mut wm = test_windowmanager(1, n_clients(1));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert!(!wm.client(&Selector::Focused).unwrap().is_fullscreen(),);
+        wm.toggle_client_fullscreen(&Selector::Focused).unwrap();
+        assert!(wm.client(&Selector::Focused).unwrap().is_fullscreen());
+        wm.toggle_client_fullscreen(&Selector::Focused).unwrap();
+        assert!(!wm.client(&Selector::Focused).unwrap().is_fullscreen(),);
+    }
+
+    #[test]
+    fn screen() {
+        let mut wm = test_windowmanager(2, n_clients(3));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
+        assert_eq!(wm.focused_workspaces(), vec![0, 1]);
+
+        assert_eq!(
+            wm.screen(&Selector::Index(0)),
+            wm.screen(&Selector::WinId(0)),
+        );
+
+        wm.client_to_screen(&Selector::Index(1)).unwrap();
+
+        assert_eq!(
+            wm.screen(&Selector::WinId(2)),
+            wm.screen(&Selector::Index(1)),
+        );
+    }
+
+    test_cases! {
+        move_client_to_adjacent_screen;
+        args: (n_screens: u32, forward: bool, follow: bool, screen: usize, focused_screen: usize);
+
+        case: next_no_follow => (3, true, false, 1, 0);
+        case: next_follow => (3, true, true, 1, 1);
+        case: prev_wraps => (3, false, false, 2, 0);
+        case: prev_wraps_follow => (3, false, true, 2, 2);
+        case: single_screen => (1, true, true, 0, 0);
+
+        body: {
+            let mut wm = test_windowmanager(n_screens, n_clients(3));
+            wm.init().unwrap();
+            wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+                .unwrap();
+
+            if forward {
+                wm.move_client_to_next_screen(follow).unwrap();
+            } else {
+                wm.move_client_to_prev_screen(follow).unwrap();
+            }
+
+            assert_eq!(
+                wm.screen(&Selector::WinId(2)),
+                wm.screen(&Selector::Index(screen)),
+            );
+            assert_eq!(wm.screens.focused_index(), focused_screen);
+            if follow {
+                assert_eq!(wm.focused_client_id(), Some(2));
             }
         }
     }
 
-    layout_trigger_test!(cycle_workspace; true; Forward);
-    layout_trigger_test!(drag_workspace; true; Forward);
-    layout_trigger_test!(cycle_client; false; Forward);
-    layout_trigger_test!(focus_client; false; &Selector::Any);
-    layout_trigger_test!(rotate_clients; true; Forward);
-    layout_trigger_test!(drag_client; true; Forward);
-    layout_trigger_test!(cycle_layout; true; Forward);
-    layout_trigger_test!(update_max_main; true; Change::More);
-    layout_trigger_test!(update_main_ratio; true; Change::More);
-    layout_trigger_test!(exit; false;);
-    layout_trigger_test!(set_root_window_name; false; "test");
-    layout_trigger_test!(set_client_insert_point; false; InsertPoint::First);
-    layout_trigger_test!(focus_workspace; true; &Selector::Index(1));
-    layout_trigger_test!(toggle_workspace; true;);
-    layout_trigger_test!(client_to_workspace; true; &Selector::Index(1));
-    layout_trigger_test!(client_to_screen; true; &Selector::Index(1));
-    layout_trigger_test!(toggle_client_fullscreen; true; &Selector::WinId(10));
-    layout_trigger_test!(kill_client; false;);
-    layout_trigger_test!(remove_workspace; true; &Selector::Index(0));
-    layout_trigger_test!(position_client; true; 10, Region::default(), true);
-    layout_trigger_test!(layout_screen; true; 0);
+    #[test]
+    fn screen_padding_is_applied_to_tiled_clients() {
+        let conn = PointerXConn::new();
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+        let (x, y, w, h) = wm.conn.client_geometry(10).unwrap().values();
+
+        wm.set_screen_padding(0, 20).unwrap();
+
+        assert_eq!(
+            wm.conn.client_geometry(10).unwrap(),
+            Region::new(x + 20, y + 20, w - 40, h - 40)
+        );
+        assert!(wm.set_screen_padding(1, 20).is_err());
+    }
 
     #[test]
-    fn layout_trigger_test_cycle_client_follow_focus() {
-        let conn = RecordingXConn::init();
+    fn hiding_the_bar_gives_its_space_to_tiled_clients() {
+        let conn = PointerXConn::new();
         let conf = Config {
-            layouts: focus_test_layouts(true),
+            layouts: focus_test_layouts(false),
             ..Default::default()
         };
+        let bar_height = conf.bar_height;
         let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
         wm.init().unwrap();
-        add_n_clients(&mut wm, 3, 0);
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        add_n_clients(&mut wm, 3, 30);
+        add_n_clients(&mut wm, 1, 0);
+        let (x, y, w, h) = wm.conn.client_geometry(10).unwrap().values();
+
+        wm.toggle_bar().unwrap();
+        assert!(!wm.focused_screen().bar_visible());
+        assert_eq!(
+            wm.conn.client_geometry(10).unwrap(),
+            Region::new(x, y - bar_height, w, h + bar_height)
+        );
+
+        wm.toggle_bar().unwrap();
+        assert!(wm.focused_screen().bar_visible());
+        assert_eq!(
+            wm.conn.client_geometry(10).unwrap(),
+            Region::new(x, y, w, h)
+        );
+    }
+
+    #[test]
+    fn bar_gap_is_reserved_along_with_the_bar() {
+        let conn = PointerXConn::new();
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            bar_gap: 5,
+            ..Default::default()
+        };
+        let bar_height = conf.bar_height;
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        assert_eq!(
+            wm.focused_screen().region(true),
+            Region::new(0, bar_height + 5, 1000, 600 - bar_height - 5)
+        );
+
+        wm.toggle_bar().unwrap();
+        assert_eq!(
+            wm.focused_screen().usable_region(true),
+            Region::new(0, 0, 1000, 600)
+        );
+    }
+
+    struct RecordBarVisibility(Rc<RefCell<Vec<(usize, bool)>>>);
+
+    impl<X: XConn> crate::core::hooks::Hook<X> for RecordBarVisibility {
+        fn bar_visibility_changed(
+            &mut self,
+            _: &mut WindowManager<X>,
+            screen_index: usize,
+            visible: bool,
+        ) -> Result<()> {
+            self.0.borrow_mut().push((screen_index, visible));
+            Ok(())
+        }
+    }
+
+    test_cases! {
+        toggle_bar;
+        args: (all_screens: bool, expected: Vec<(usize, bool)>);
+
+        case: all_screens => (true, vec![(0, false), (1, false), (0, true), (1, true)]);
+        case: focused_screen => (false, vec![(0, false), (0, true)]);
+
+        body: {
+            let calls = Rc::new(RefCell::new(vec![]));
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.state.config.toggle_bar_on_all_screens = all_screens;
+            wm.hooks.replace(vec![Box::new(RecordBarVisibility(Rc::clone(&calls)))]);
+
+            wm.toggle_bar().unwrap();
+            assert_eq!(wm.screens.get(1).unwrap().bar_visible(), !all_screens);
+            wm.toggle_bar().unwrap();
+
+            assert_eq!(*calls.borrow(), expected);
+            assert!(wm.set_bar_visible(5, false).is_err());
+        }
+    }
+
+    #[test]
+    fn workspaces_remember_if_the_bar_is_shown() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.state.config.toggle_bar_on_all_screens = false;
+        wm.set_workspace_bar_visible(&Selector::Index(2), Some(false))
+            .unwrap();
+        assert!(wm.focused_screen().bar_visible());
+
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert!(!wm.focused_screen().bar_visible());
+
+        wm.focus_workspace(&Selector::Index(3)).unwrap();
+        assert!(wm.focused_screen().bar_visible());
+        wm.toggle_bar().unwrap();
+        assert_eq!(wm.workspaces[3].show_bar(), Some(false));
+
         wm.focus_workspace(&Selector::Index(0)).unwrap();
-        wm.conn.clear();
-        wm.cycle_client(Direction::Forward).unwrap();
+        assert!(wm.focused_screen().bar_visible());
+        wm.focus_workspace(&Selector::Index(3)).unwrap();
+        assert!(!wm.focused_screen().bar_visible());
+    }
+
+    #[test]
+    fn hidden_bar_workspaces_are_set_from_config() {
+        let conf = Config {
+            hidden_bar_workspaces: vec!["2".into()],
+            ..Default::default()
+        };
+        let wm = WindowManager::new(
+            conf,
+            MockXConn::new(vec![], vec![], vec![]),
+            vec![],
+            logging_error_handler(),
+        );
+
+        assert_eq!(wm.workspaces[0].show_bar(), None);
+        assert_eq!(wm.workspaces[1].show_bar(), Some(false));
+    }
+
+    #[test]
+    fn sort_workspace_clients_keeps_focus() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 4, 0);
+        wm.focus_client(&Selector::WinId(20)).unwrap();
+
+        wm.sort_workspace_clients_by(&Selector::Focused, |a, b| a.id().cmp(&b.id()))
+            .unwrap();
+
+        assert_eq!(wm.workspaces[0].client_ids(), vec![10, 20, 30, 40]);
+        assert_eq!(wm.focused_client_id(), Some(20));
+        assert_eq!(wm.workspaces[0].focused_client(), Some(20));
+    }
+
+    #[test]
+    fn swap_focused_with_a_relative_selector() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 4, 0);
+        wm.focus_client(&Selector::WinId(30)).unwrap();
+        assert_eq!(wm.workspaces[0].client_ids(), vec![40, 30, 20, 10]);
+
+        assert_eq!(
+            wm.client(&Selector::FromFocused(2)).map(|c| c.id()),
+            Some(10)
+        );
+        assert_eq!(
+            wm.client(&Selector::FromFocused(-2)).map(|c| c.id()),
+            Some(10)
+        );
+        wm.swap_focused_with(&Selector::FromFocused(-1)).unwrap();
 
-        assert!(wm.conn.was_called("position_client"));
+        assert_eq!(wm.workspaces[0].client_ids(), vec![30, 40, 20, 10]);
+        assert_eq!(wm.focused_client_id(), Some(30));
+
+        wm.focus_client(&Selector::FromFocused(3)).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
     }
 
     #[test]
-    fn layout_trigger_test_focus_client_follow_focus() {
-        let conn = RecordingXConn::init();
+    fn a_single_client_is_auto_fullscreened_until_a_second_is_added() {
+        let conn = PointerXConn::new();
         let conf = Config {
-            layouts: focus_test_layouts(true),
+            layouts: focus_test_layouts(false),
+            auto_fullscreen_workspaces: vec!["1".into()],
             ..Default::default()
         };
         let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
         wm.init().unwrap();
-        wm.handle_map_request(0).unwrap();
-        wm.handle_map_request(1).unwrap();
-        wm.focus_client(&Selector::WinId(0)).unwrap();
-        wm.conn.clear();
+        let full = wm.focused_screen().region(false);
 
-        // shouldn't trigger layout when re-focusing same client
-        wm.focus_client(&Selector::WinId(0)).unwrap();
-        assert!(wm.conn.was_not_called("position_client"));
+        add_n_clients(&mut wm, 1, 0);
+        assert_eq!(wm.conn.client_geometry(10).unwrap(), full);
 
-        wm.conn.clear();
+        add_n_clients(&mut wm, 1, 1);
+        assert_ne!(wm.conn.client_geometry(10).unwrap(), full);
+        assert_ne!(wm.conn.client_geometry(20).unwrap(), full);
 
-        // focusing any other client should trigger layout stuff
-        wm.focus_client(&Selector::WinId(1)).unwrap();
-        assert!(wm.conn.was_called("position_client"));
+        wm.remove_client(20).unwrap();
+        assert_eq!(wm.conn.client_geometry(10).unwrap(), full);
+
+        wm.set_workspace_auto_fullscreen(&Selector::Index(0), false)
+            .unwrap();
+        assert_ne!(wm.conn.client_geometry(10).unwrap(), full);
     }
 
-    /*
-     * Helpers for specifying expected events with RecordingXConn
-     */
+    type WorkspaceChanges = Rc<RefCell<Vec<(bool, usize, String, usize)>>>;
 
-    fn _focus(id: Xid) -> RecordedCall {
-        ("focus_client".into(), strings!(id))
+    struct RecordWorkspaceChanges(WorkspaceChanges);
+
+    impl<X: XConn> crate::core::hooks::Hook<X> for RecordWorkspaceChanges {
+        fn workspace_added(
+            &mut self,
+            _: &mut WindowManager<X>,
+            index: usize,
+            name: &str,
+            n: usize,
+        ) -> Result<()> {
+            self.0.borrow_mut().push((true, index, name.into(), n));
+            Ok(())
+        }
+
+        fn workspace_removed(
+            &mut self,
+            _: &mut WindowManager<X>,
+            index: usize,
+            name: &str,
+            n: usize,
+        ) -> Result<()> {
+            self.0.borrow_mut().push((false, index, name.into(), n));
+            Ok(())
+        }
     }
 
-    fn _take_focus(id: Xid) -> RecordedCall {
-        let conn = RecordingXConn::init();
-        let evt = ClientMessageKind::TakeFocus(id).as_message(&conn).unwrap();
-        ("send_client_event".into(), strings!(evt))
+    #[test]
+    fn workspace_hooks_run_when_workspaces_are_added_and_removed() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        let changes = Rc::new(RefCell::new(vec![]));
+        wm.hooks
+            .replace(vec![Box::new(RecordWorkspaceChanges(changes.clone()))]);
+        let (n, layouts) = (wm.workspaces.len(), wm.state.config.layouts.clone());
+
+        wm.push_workspace(Workspace::new("pushed", layouts.clone()))
+            .unwrap();
+        wm.add_workspace(1, Workspace::new("added", layouts))
+            .unwrap();
+        wm.remove_workspace(&Selector::Index(1)).unwrap();
+
+        assert_eq!(
+            *changes.borrow(),
+            vec![
+                (true, n, "pushed".to_string(), n + 1),
+                (true, 1, "added".to_string(), n + 2),
+                (false, 1, "added".to_string(), n + 1),
+            ]
+        );
     }
 
-    fn _id(a: Atom) -> RecordedCall {
-        ("atom_id".into(), strings!(a.as_ref()))
+    struct RecordSettled(Rc<RefCell<Vec<usize>>>);
+
+    impl<X: XConn> crate::core::hooks::Hook<X> for RecordSettled {
+        fn startup_settled(&mut self, _: &mut WindowManager<X>, n_mapped: usize) -> Result<()> {
+            self.0.borrow_mut().push(n_mapped);
+            Ok(())
+        }
     }
 
-    fn _border(id: Xid, focused: bool) -> RecordedCall {
-        let color = if focused {
-            Color::try_from("#00ff00").unwrap()
-        } else {
-            Color::try_from("#ff0000").unwrap()
+    #[test]
+    fn startup_settled_runs_once_events_stop_arriving() {
+        let conf = Config {
+            startup_settle_ms: 1,
+            ..Default::default()
         };
-        ("set_client_border_color".into(), strings!(id, color))
+        let conn = MockXConn::new(test_screens(), vec![XEvent::MappingNotify], vec![]);
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let settled = Rc::new(RefCell::new(vec![]));
+        wm.hooks
+            .replace(vec![Box::new(RecordSettled(settled.clone()))]);
+        wm.settling = Some(0);
+
+        add_n_clients(&mut wm, 2, 0);
+        assert_eq!(wm.settling_map_requests(), Some(2));
+
+        assert!(wm.poll_until_settled().unwrap().is_some());
+        assert!(wm.is_settling());
+        assert!(wm.poll_until_settled().unwrap().is_none());
+        assert!(!wm.is_settling());
+        assert_eq!(*settled.borrow(), vec![2]);
+
+        assert!(wm.poll_until_settled().unwrap().is_none());
+        assert_eq!(*settled.borrow(), vec![2]);
     }
 
-    fn _active(id: Xid) -> RecordedCall {
-        let args = strings!(42, "_NET_ACTIVE_WINDOW", Prop::Window(vec![id]));
-        ("change_prop".into(), args)
-    }
+    struct CountTicks(Rc<RefCell<usize>>);
 
-    fn _remove_active() -> RecordedCall {
-        ("delete_prop".into(), strings!(42, "_NET_ACTIVE_WINDOW"))
+    impl<X: XConn> crate::core::hooks::Hook<X> for CountTicks {
+        fn tick(&mut self, _: &mut WindowManager<X>) -> Result<()> {
+            *self.0.borrow_mut() += 1;
+            Ok(())
+        }
     }
 
-    test_cases! {
-        update_focus;
-        args: (
-            target: Xid,
-            accepts_focus: bool,
-            current: Option<Xid>,
-            n_clients: usize,
-            follow_focus: bool,
-            expected_focus: Option<Xid>,
-            expected_calls: Vec<RecordedCall>
-        );
-
-        // We should still run focusing logic when the requested target is our current focus
-        case: client_is_current_focus => (
-            10, true, Some(10), 3, false,
-            Some(10), vec![_focus(10), _active(10), _border(10, true)]
-        );
+    #[test]
+    fn ticks_run_when_no_events_arrive() {
+        let conf = Config {
+            tick_interval_ms: 1,
+            ..Default::default()
+        };
+        let conn = MockXConn::new(test_screens(), vec![XEvent::MappingNotify], vec![]);
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let ticks = Rc::new(RefCell::new(0));
+        wm.hooks.replace(vec![Box::new(CountTicks(ticks.clone()))]);
 
-        // We should remove the focused border from the current client first
-        case: client_is_not_current_focus => (
-            20, true, Some(10), 3, false,
-            Some(20), vec![_border(10, false), _focus(20), _active(20), _border(20, true)]
-        );
+        assert_eq!(wm.next_event().unwrap(), XEvent::MappingNotify);
+        assert_eq!(*ticks.borrow(), 0);
 
-        // Focus should default to the focused client on the active workspace if the given client
-        // is not in the client_map
-        case: client_is_unknown_workspace_populated => (
-            999, true, Some(10), 3, false,
-            Some(30), vec![_border(10, false), _focus(30), _active(30), _border(30, true)]
-        );
+        assert!(wm.poll_until_tick().unwrap().is_none());
+        assert!(wm.poll_until_tick().unwrap().is_none());
+        assert_eq!(*ticks.borrow(), 2);
+    }
 
-        // If the client is unknown and the workspace is empty, focus should revert to root
-        case: client_is_unknown_workspace_empty => (
-            999, true, None, 0, false,
-            None, vec![_focus(42), _remove_active()]
-        );
+    #[test]
+    fn due_ticks_run_before_polling_for_events() {
+        let conf = Config {
+            tick_interval_ms: 1,
+            ..Default::default()
+        };
+        let conn = MockXConn::new(test_screens(), vec![XEvent::MappingNotify], vec![]);
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let ticks = Rc::new(RefCell::new(0));
+        wm.hooks.replace(vec![Box::new(CountTicks(ticks.clone()))]);
+        wm.next_tick = Some(Instant::now());
+
+        assert!(wm.poll_until_tick().unwrap().is_none());
+        assert_eq!(*ticks.borrow(), 1);
+        assert_eq!(wm.poll_until_tick().unwrap(), Some(XEvent::MappingNotify));
+        assert_eq!(*ticks.borrow(), 1);
+    }
 
-        // If the client doesn't accept focus then we should still mark it as focused in the
-        // internal state, but a TakeFocus client message should be sent instead of forcing
-        // focus.
-        case: client_does_not_accept_focus_different => (
-            20, false, Some(10), 3, false,
-            Some(20), vec![
-                _border(10, false), _id(Atom::WmTakeFocus), _take_focus(20)
-            ]
-        );
+    #[test]
+    fn ticks_wait_on_the_connection_until_they_are_due() {
+        let conf = Config {
+            tick_interval_ms: 20,
+            ..Default::default()
+        };
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let ticks = Rc::new(RefCell::new(0));
+        wm.hooks.replace(vec![Box::new(CountTicks(ticks.clone()))]);
 
-        // If the client doesn't accept focus, and it is the current focus then we should just
-        // set the border and send the TakeFocus event
-        case: client_does_not_accept_focus_same => (
-            20, false, Some(20), 3, false,
-            Some(20), vec![_id(Atom::WmTakeFocus), _take_focus(20)]
-        );
+        assert!(wm.poll_until_tick().unwrap().is_none());
+        assert_eq!(*ticks.borrow(), 1);
 
-        // TODO: add test cases for follow_focus layout triggering
+        // A single wait for the remainder of the interval rather than repeatedly polling
+        let timeouts = wm.conn.timeouts.take();
+        assert_eq!(timeouts.len(), 1);
+        assert!(timeouts[0] > Duration::from_millis(0));
+        assert!(timeouts[0] <= Duration::from_millis(20));
+    }
 
-        body: {
-            let conn = RecordingXConn::init();
-            let conf = Config {
-                layouts: focus_test_layouts(follow_focus),
-                focused_border: Color::try_from("#00ff00").unwrap(),
-                unfocused_border: Color::try_from("#ff0000").unwrap(),
-                ..Default::default()
-            };
-            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
-            wm.init().unwrap();
-            add_n_clients(&mut wm, n_clients, 0);
-            if let Some(id) = current {
-                wm.state.clients.set_focused(id, &wm.conn);
-            }
-            wm.clients.modify(target, |c| c.accepts_focus = accepts_focus);
-            wm.conn().clear();
+    type GeometryChanges = Rc<RefCell<Vec<(Xid, Region, Region)>>>;
 
-            wm.update_focus(target).unwrap();
+    struct RecordGeometryChanges(GeometryChanges);
 
-            assert_eq!(wm.clients.focused_client_id(), expected_focus);
-            assert_eq!(wm.conn().calls(), expected_calls);
+    impl<X: XConn> crate::core::hooks::Hook<X> for RecordGeometryChanges {
+        fn client_geometry_changed(
+            &mut self,
+            _: &mut WindowManager<X>,
+            id: Xid,
+            from: Region,
+            to: Region,
+        ) -> Result<()> {
+            self.0.borrow_mut().push((id, from, to));
+            Ok(())
         }
     }
 
-    macro_rules! changing_screen_focus_test {
-        ($method:ident, $start_screen:expr, $test:expr, $expected:expr) => {
-            #[test]
-            fn $method() {
-                let conn = RecordingXConn::init();
-                let conf = Default::default();
-                let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
-                wm.init().unwrap();
+    #[test]
+    fn client_geometry_changes_are_reported_with_the_previous_region() {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.hooks
+            .replace(vec![Box::new(RecordGeometryChanges(Rc::clone(&calls)))]);
 
-                wm.focus_screen(&Selector::Index($start_screen));
+        add_n_clients(&mut wm, 1, 0);
+        assert!(calls.borrow().is_empty());
+        let before = wm.client(&Selector::WinId(10)).unwrap().tiled_region();
 
-                let expected = $expected
-                    .into_iter()
-                    .map(|id| {
-                        (
-                            "change_prop".into(),
-                            strings!(42, "_NET_CURRENT_DESKTOP", Prop::Cardinal(id)),
-                        )
-                    })
-                    .collect::<Vec<RecordedCall>>();
-                wm.conn().clear();
+        add_n_clients(&mut wm, 1, 1);
+        let after = wm.client(&Selector::WinId(10)).unwrap().tiled_region();
+        assert_ne!(before, after);
+        assert_eq!(*calls.borrow(), vec![(10, before.unwrap(), after.unwrap())]);
 
-                // cycle the screen
-                $test(&mut wm);
+        // Re-applying an unchanged layout does not report anything
+        wm.layout_visible().unwrap();
+        assert_eq!(calls.borrow().len(), 1);
+    }
 
-                // check that the recorded calls are the expected values
-                assert_eq!(wm.conn().calls(), expected);
-            }
+    #[test]
+    fn undersized_clients_are_stacked_and_revealed_on_focus() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: crate::__test_helpers::test_layouts(),
+            min_tile_size: (0, 300),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let mapped = |wm: &WindowManager<MockXConn>| -> Vec<Xid> {
+            vec![10, 20, 30]
+                .into_iter()
+                .filter(|&id| matches!(wm.clients.get(id), Some(c) if c.mapped))
+                .collect()
         };
-    }
 
-    changing_screen_focus_test!(
-        cycle_screen_backwards_on_first_screen,
-        0,
-        |wm: &mut WindowManager<_>| { wm.cycle_screen(Backward).unwrap() },
-        vec![]
-    );
-    changing_screen_focus_test!(
-        cycle_screen_backwards_on_second_screen,
-        1,
-        |wm: &mut WindowManager<_>| { wm.cycle_screen(Backward).unwrap() },
-        vec![0]
-    );
-    changing_screen_focus_test!(
-        cycle_screen_forwards_on_first_screen,
-        0,
-        |wm: &mut WindowManager<_>| { wm.cycle_screen(Forward).unwrap() },
-        vec![1]
-    );
-    changing_screen_focus_test!(
-        cycle_screen_forwards_on_last_screen,
-        1, // the mock setup apparently only has two screens
-        |wm: &mut WindowManager<_>| { wm.cycle_screen(Forward).unwrap() },
-        vec![]
-    );
+        add_n_clients(&mut wm, 2, 0);
+        assert_eq!(mapped(&wm), vec![10, 20]);
 
-    changing_screen_focus_test!(
-        focus_screen_same,
-        0, // the mock setup apparently only has two screens
-        |wm: &mut WindowManager<_>| {
-            wm.focus_screen(&Selector::Index(0));
-        },
-        vec![]
-    );
-    changing_screen_focus_test!(
-        focus_screen_different,
-        0, // the mock setup apparently only has two screens
-        |wm: &mut WindowManager<_>| {
-            wm.focus_screen(&Selector::Index(1));
-        },
-        vec![1]
-    );
+        add_n_clients(&mut wm, 1, 2);
+        assert_eq!(wm.focused_client_id(), Some(30));
+        assert_eq!(mapped(&wm), vec![30]);
+        let r = wm.client(&Selector::WinId(30)).unwrap().tiled_region();
+        assert!(matches!(r, Some(r) if r.h >= 300));
+
+        wm.update_focus(10).unwrap();
+        assert_eq!(mapped(&wm), vec![10]);
+    }
 
     #[test]
-    fn cycle_screen_updates_active() {
-        let mut wm = test_windowmanager(2, vec![]);
+    fn overview_shows_clients_in_a_grid_and_focuses_the_selected_client() {
+        let conn = PointerXConn::new();
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            overview_scale: 0.5,
+            border_px: 0,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 4, 0);
+        let tiled = wm.conn.client_geometry(30).unwrap();
+
+        wm.start_overview().unwrap();
+        assert!(wm.overview_active());
+        let (x, y, w, h) = wm.focused_screen().usable_region(true).values();
+        let cells: Vec<Region> = [10, 20, 30, 40]
+            .iter()
+            .map(|&id| wm.conn.client_geometry(id).unwrap())
+            .collect();
+        assert!(cells.iter().all(|r| r.w == w / 4 && r.h == h / 4));
+        let first_cell = Region::new(x, y, w / 2, h / 2);
+        let expected = first_cell
+            .scale_w(0.5)
+            .scale_h(0.5)
+            .centered_in(&first_cell)
+            .unwrap();
+        assert!(cells.contains(&expected));
 
-        assert_eq!(wm.active_screen_index(), 0);
-        wm.cycle_screen(Forward).unwrap();
-        assert_eq!(wm.active_screen_index(), 1);
-        wm.cycle_screen(Forward).unwrap();
-        assert_eq!(wm.active_screen_index(), 1);
+        let root = wm.conn.root();
+        let state = MouseState::new(MouseButton::Left, vec![]);
+        let press = MouseEvent::new(root, 0, 0, 0, 0, state, MouseEventKind::Press).with_child(30);
+        wm.run_mouse_binding(press, &mut HashMap::new());
+
+        assert!(!wm.overview_active());
+        assert_eq!(wm.focused_client_id(), Some(30));
+        assert_eq!(wm.conn.client_geometry(30).unwrap(), tiled);
     }
 
     #[test]
-    fn cycle_workspace_updates_focused() {
-        let mut wm = test_windowmanager(1, vec![]);
+    fn clicking_outside_of_a_client_ends_the_overview_without_changing_focus() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        let focused = wm.focused_client_id();
 
-        assert_eq!(wm.focused_workspaces(), vec![0]);
-        wm.cycle_workspace(Forward).unwrap();
-        assert_eq!(wm.focused_workspaces(), vec![1]);
-        wm.cycle_workspace(Backward).unwrap();
-        wm.cycle_workspace(Backward).unwrap();
-        assert_eq!(wm.focused_workspaces(), vec![8]);
+        wm.toggle_overview().unwrap();
+        assert!(wm.overview_active());
+
+        let root = wm.conn.root();
+        let state = MouseState::new(MouseButton::Left, vec![]);
+        let press = MouseEvent::new(root, 0, 0, 0, 0, state, MouseEventKind::Press);
+        wm.run_mouse_binding(press, &mut HashMap::new());
+
+        assert!(!wm.overview_active());
+        assert_eq!(wm.focused_client_id(), focused);
     }
 
     #[test]
-    fn drag_workspace_move_focused_workspaces_between_screens() {
-        let mut wm = test_windowmanager(2, vec![]);
+    fn layouts_are_not_reapplied_to_a_workspace_in_overview() {
+        let conn = PointerXConn::new();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        let tiled = wm.conn.client_geometry(10).unwrap();
 
-        assert_eq!(wm.focused_workspaces(), vec![0, 1]);
-        wm.drag_workspace(Forward).unwrap();
-        assert_eq!(wm.focused_workspaces(), vec![1, 0]);
+        wm.start_overview().unwrap();
+        let in_overview = wm.conn.client_geometry(10).unwrap();
+        assert_ne!(in_overview, tiled);
+
+        wm.update_max_main(Change::More).unwrap();
+        wm.layout_visible().unwrap();
+        assert_eq!(wm.conn.client_geometry(10).unwrap(), in_overview);
+
+        wm.end_overview(None).unwrap();
+        assert_ne!(wm.conn.client_geometry(10).unwrap(), in_overview);
     }
 
     #[test]
-    fn cycle_client_updates_focus() {
-        let mut wm = test_windowmanager(1, n_clients(3));
+    fn screen_accessors() {
+        let mut wm = test_windowmanager(2, vec![]);
         wm.init().unwrap();
-        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
-            .unwrap();
-        wm.focus_client(&Selector::WinId(0)).unwrap();
+        wm.focus_workspace(&Selector::Index(4)).unwrap();
 
-        assert_eq!(wm.focused_client_id(), Some(0));
-        wm.cycle_client(Backward).unwrap();
-        assert_eq!(wm.focused_client_id(), Some(1));
-        wm.cycle_client(Backward).unwrap();
-        assert_eq!(wm.focused_client_id(), Some(2));
-        wm.cycle_client(Backward).unwrap();
-        assert_eq!(wm.focused_client_id(), Some(0));
+        let regions: Vec<Region> = wm.screens().iter().map(|s| s.region(false)).collect();
+        assert_eq!(
+            regions,
+            vec![Region::new(0, 0, 800, 600), Region::new(800, 600, 800, 600)]
+        );
+        assert_eq!(wm.focused_screen().wix, 4);
+        assert_eq!(wm.workspace_on_screen(0).map(|ws| ws.name()), Some("5"));
+        assert_eq!(wm.workspace_on_screen(1).map(|ws| ws.name()), Some("2"));
+        assert!(wm.workspace_on_screen(2).is_none());
     }
 
     #[test]
-    fn focus_client() {
+    fn client() {
         let mut wm = test_windowmanager(1, n_clients(3));
         wm.init().unwrap();
-        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
 
-        let focused = wm.focus_client(&Selector::WinId(0));
-        assert_eq!(focused.unwrap(), 0);
-        let focused = wm.focus_client(&Selector::WinId(42));
-        assert!(focused.is_err());
-        let focused = wm.focus_client(&Selector::WinId(1));
-        assert_eq!(focused.unwrap(), 1);
-        let focused = wm.focus_client(&Selector::WinId(42));
-        assert!(focused.is_err());
-    }
-
-    #[test]
-    fn focus_client_no_clients() {
-        let mut wm = test_windowmanager(1, vec![]);
-        let focused = wm.focus_client(&Selector::WinId(0));
-        assert!(focused.is_err());
+        assert_eq!(wm.client(&Selector::Focused).unwrap().id(), 2);
+        assert_eq!(wm.client(&Selector::Index(2)).unwrap().id(), 0);
     }
 
     #[test]
-    fn rotate_clients() {
+    fn client_mut() {
         let mut wm = test_windowmanager(1, n_clients(3));
         wm.init().unwrap();
         wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
 
-        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
-        wm.rotate_clients(Forward).unwrap();
-        assert_eq!(wm.active_workspace().client_ids(), vec![0, 2, 1]);
+        assert_eq!(
+            wm.client(&Selector::Focused).map(|c| c.workspace()),
+            Some(0)
+        );
+        wm.client_mut(&Selector::Focused).unwrap().set_workspace(5);
+        assert_eq!(
+            wm.client(&Selector::Focused).map(|c| c.workspace()),
+            Some(5)
+        );
     }
 
     #[test]
-    fn drag_client() {
-        let mut wm = test_windowmanager(1, n_clients(4));
+    fn mapping_notify_clears_key_codes() {
+        let mut wm = test_windowmanager(1, vec![XEvent::MappingNotify]);
         wm.init().unwrap();
+        wm.set_key_codes(vec![("q".to_string(), 24)].into_iter().collect());
+
+        assert_eq!(
+            wm.parse_key_binding("Mod4+q").unwrap(),
+            KeyCode { mask: 64, code: 24 }
+        );
+
         wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
 
-        assert_eq!(wm.active_workspace().client_ids(), vec![3, 2, 1, 0]);
-        wm.drag_client(Forward).unwrap();
-        assert_eq!(wm.active_workspace().client_ids(), vec![2, 3, 1, 0]);
-        wm.drag_client(Forward).unwrap();
-        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 3, 0]);
+        assert!(wm.key_codes.is_none());
     }
 
     #[test]
-    fn cycle_layout() {
-        let mut wm = test_windowmanager(1, vec![]);
+    fn mapping_notify_regrabs_bindings_when_num_lock_moves() {
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let mut key_bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        key_bindings.insert(EXIT_CODE, Box::new(|wm: &mut WindowManager<_>| wm.exit()));
+        let mut mouse_bindings = HashMap::new();
+        wm.conn.clear();
 
-        assert_eq!(wm.current_layout_symbol(), "first");
-        wm.cycle_layout(Forward).unwrap();
-        assert_eq!(wm.current_layout_symbol(), "second");
-        wm.cycle_layout(Forward).unwrap();
-        assert_eq!(wm.current_layout_symbol(), "first");
+        wm.handle_event_action(
+            EventAction::RefreshKeyCodes,
+            Some(&mut key_bindings),
+            Some(&mut mouse_bindings),
+        )
+        .unwrap();
+
+        let calls: Vec<_> = wm.conn.calls().into_iter().map(|(m, _)| m).collect();
+        assert_eq!(calls, vec!["refresh_num_lock_mask", "grab_keys"]);
+    }
+
+    fn failing_key_codes() -> Result<CodeMap> {
+        Err(perror!("xmodmap not found"))
     }
 
     #[test]
-    fn focus_workspace() {
-        let mut wm = test_windowmanager(1, vec![]);
+    fn failing_key_code_loader_is_logged_not_fatal() {
+        let mut wm = test_windowmanager(1, vec![XEvent::MappingNotify]);
+        wm.init().unwrap();
+        wm.state.config.key_binding_mode = KeyBindingMode::KeySym;
+        wm.load_key_codes = failing_key_codes;
 
-        assert_eq!(wm.active_workspace().name(), "1");
-        wm.focus_workspace(&Selector::Index(3)).unwrap();
-        assert_eq!(wm.active_workspace().name(), "4");
-        wm.focus_workspace(&Selector::Condition(&|ws| ws.name() == "9"))
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
-        assert_eq!(wm.active_workspace().name(), "9");
+
+        assert!(wm.key_codes.is_none());
+        assert!(wm.parse_key_binding("Mod4+q").is_err());
     }
 
     #[test]
-    fn toggle_workspace() {
-        let mut wm = test_windowmanager(1, vec![]);
+    fn rebind_and_unbind_while_running() {
+        let (a, b) = (KeyCode { mask: 0, code: 24 }, KeyCode { mask: 0, code: 25 });
+        let events = vec![a, b, a, b, b]
+            .into_iter()
+            .map(XEvent::KeyPress)
+            .collect();
+        let mut wm = test_windowmanager(1, events);
+        wm.set_key_codes(
+            vec![("a".into(), 24), ("b".into(), 25)]
+                .into_iter()
+                .collect(),
+        );
 
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        assert_eq!(wm.active_workspace().name(), "2");
-        wm.focus_workspace(&Selector::Index(0)).unwrap();
-        assert_eq!(wm.active_workspace().name(), "1");
-        wm.toggle_workspace().unwrap();
-        assert_eq!(wm.active_workspace().name(), "2");
+        let count = Rc::new(Cell::new(0));
+        let c = Rc::clone(&count);
+        let mut bindings = test_key_bindings();
+        bindings.insert(
+            a,
+            Box::new(move |wm: &mut TestWM| {
+                let c = Rc::clone(&c);
+                wm.rebind(
+                    "b",
+                    Box::new(move |wm: &mut TestWM| {
+                        c.set(c.get() + 1);
+                        wm.unbind("b")
+                    }),
+                )
+            }),
+        );
+
+        wm.grab_keys_and_run(bindings, test_mouse_bindings())
+            .unwrap();
+
+        // each press of 'a' rebinds 'b' once, which then unbinds itself on the first press
+        assert_eq!(count.get(), 2);
     }
 
     #[test]
-    fn client_to_workspace() {
-        let mut wm = test_windowmanager(1, n_clients(3));
-        wm.init().unwrap();
+    fn bare_keys_are_dispatched() {
+        let volume_up = KeyCode { mask: 0, code: 123 };
+        // NumLock (Mod2) and CapsLock are held: the backend strips them before dispatching
+        let pressed = KeyCode {
+            mask: 0x10 | 0x02,
+            code: 123,
+        }
+        .normalized(0x10 | 0x02);
+        let mut wm = test_windowmanager(1, vec![XEvent::KeyPress(pressed)]);
+        wm.set_key_codes(
+            vec![("XF86AudioRaiseVolume".into(), 123)]
+                .into_iter()
+                .collect(),
+        );
+
+        let count = Rc::new(Cell::new(0));
+        let c = Rc::clone(&count);
+        wm.rebind(
+            "XF86AudioRaiseVolume",
+            Box::new(move |_: &mut TestWM| {
+                c.set(c.get() + 1);
+                Ok(())
+            }),
+        )
+        .unwrap();
+
         wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
 
-        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
-        (0..3).for_each(|_| wm.client_to_workspace(&Selector::Index(1)).unwrap());
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        assert_eq!(wm.active_workspace().client_ids(), vec![0, 1, 2]);
+        assert_eq!(pressed, volume_up);
+        assert_eq!(count.get(), 1);
     }
 
     #[test]
-    fn client_to_screen() {
-        let mut wm = test_windowmanager(2, n_clients(3));
-        wm.init().unwrap();
+    fn duplicate_binds_replace_the_existing_binding() {
+        let a = KeyCode { mask: 0, code: 24 };
+        let mut wm = test_windowmanager(1, vec![XEvent::KeyPress(a)]);
+        wm.set_key_codes(vec![("a".into(), 24)].into_iter().collect());
+
+        let count = Rc::new(Cell::new(0));
+        for n in 1..=2 {
+            let c = Rc::clone(&count);
+            wm.rebind(
+                "a",
+                Box::new(move |_: &mut TestWM| {
+                    c.set(c.get() + n);
+                    Ok(())
+                }),
+            )
+            .unwrap();
+        }
+
         wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
 
-        assert_eq!(wm.focused_workspaces(), vec![0, 1]);
-        assert_eq!(wm.active_screen_index(), 0);
-        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
-        wm.client_to_screen(&Selector::Index(1)).unwrap();
-        assert_eq!(wm.active_workspace().client_ids(), vec![1, 0]);
-        wm.cycle_screen(Forward).unwrap();
-        assert_eq!(wm.active_screen_index(), 1);
-        assert_eq!(wm.active_workspace().client_ids(), vec![2]);
+        assert_eq!(count.get(), 2);
     }
 
     #[test]
-    fn toggle_client_fullscreen() {
-        let mut wm = test_windowmanager(1, n_clients(1));
-        wm.init().unwrap();
-        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+    fn key_binding_descriptions() {
+        let mut wm = test_windowmanager(1, vec![]);
+        wm.set_key_codes(
+            vec![
+                ("a".into(), 24),
+                ("b".into(), 25),
+                ("c".into(), 26),
+                ("d".into(), 27),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let noop = || Box::new(|_: &mut TestWM| Ok(())) as KeyEventHandler<_>;
+        let describe = |d: &KeyBindingDescription| (d.spec.clone(), d.description.clone());
+
+        // Keys can be described before they are bound but are only listed once bound
+        wm.describe_key_binding("M-b", Some("focus the next client"))
             .unwrap();
+        wm.rebind("M-a", noop()).unwrap();
+        wm.rebind("M-c", noop()).unwrap();
+        wm.describe_key_binding("M-c", Some("close")).unwrap();
+        assert!(wm.describe_key_binding("M-z", Some("unknown")).is_err());
+
+        let descriptions: Vec<_> = wm
+            .key_binding_descriptions()
+            .into_iter()
+            .map(describe)
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                ("M-a".to_string(), None),
+                ("M-c".to_string(), Some("close".to_string())),
+            ]
+        );
 
-        assert!(!wm.client(&Selector::Focused).unwrap().is_fullscreen(),);
-        wm.toggle_client_fullscreen(&Selector::Focused).unwrap();
-        assert!(wm.client(&Selector::Focused).unwrap().is_fullscreen());
-        wm.toggle_client_fullscreen(&Selector::Focused).unwrap();
-        assert!(!wm.client(&Selector::Focused).unwrap().is_fullscreen(),);
+        // Rebinding keeps the description, describing with None keeps the binding listed
+        // and unbinding removes it
+        wm.rebind("M-b", noop()).unwrap();
+        wm.describe_key_binding("Mod4+c", None).unwrap();
+        wm.unbind("M-a").unwrap();
+
+        let descriptions: Vec<_> = wm
+            .key_binding_descriptions()
+            .into_iter()
+            .map(describe)
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                ("M-b".to_string(), Some("focus the next client".to_string())),
+                ("Mod4+c".to_string(), None)
+            ]
+        );
+
+        // Rebinding under a different spec lists the new spec with the existing description
+        wm.describe_key_binding("M-d", Some("delete")).unwrap();
+        wm.rebind("M-d", noop()).unwrap();
+        wm.rebind("Mod4+d", noop()).unwrap();
+        let d = wm
+            .key_binding_descriptions()
+            .into_iter()
+            .map(describe)
+            .find(|(_, d)| d.as_deref() == Some("delete"));
+        assert_eq!(d, Some(("Mod4+d".to_string(), Some("delete".to_string()))));
     }
 
     #[test]
-    fn screen() {
-        let mut wm = test_windowmanager(2, n_clients(3));
-        wm.init().unwrap();
+    fn initial_key_bindings_are_listed_in_key_binding_descriptions() {
+        let mut wm = test_windowmanager(1, vec![]);
+        let names = ["q", "l", "w", "s", "j", "x", "n", "m"];
+        wm.set_key_codes(
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, &n)| (n.to_string(), i as u8))
+                .chain(Some(("d".to_string(), 42)))
+                .collect(),
+        );
+
+        wm.describe_key_binding("q", Some("exit")).unwrap();
+        wm.describe_key_binding("d", Some("not bound")).unwrap();
         wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
             .unwrap();
 
-        assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 0]);
-        assert_eq!(wm.focused_workspaces(), vec![0, 1]);
+        let descriptions: Vec<_> = wm
+            .key_binding_descriptions()
+            .into_iter()
+            .map(|d| (d.spec.as_str(), d.description.as_deref()))
+            .collect();
+        let mut expected: Vec<_> = names
+            .iter()
+            .map(|&n| (n, if n == "q" { Some("exit") } else { None }))
+            .collect();
+        expected.sort();
+
+        assert_eq!(descriptions, expected);
+    }
 
-        assert_eq!(
-            wm.screen(&Selector::Index(0)),
-            wm.screen(&Selector::WinId(0)),
+    const TAKEN: KeyCode = KeyCode { mask: 0, code: 42 };
+
+    #[test]
+    fn failed_grabs_are_reported() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init().with_taken_keys(vec![TAKEN]),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.set_key_codes(
+            vec![("a".into(), 24), ("b".into(), TAKEN.code)]
+                .into_iter()
+                .collect(),
         );
 
-        wm.client_to_screen(&Selector::Index(1)).unwrap();
+        let mut bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        bindings.insert(EXIT_CODE, Box::new(|wm: &mut WindowManager<_>| wm.exit()));
+        bindings.insert(TAKEN, Box::new(|_: &mut WindowManager<_>| Ok(())));
+        wm.rebind("M-b", Box::new(|_: &mut WindowManager<_>| Ok(())))
+            .unwrap();
+
+        wm.grab_keys_and_run(bindings, HashMap::new()).unwrap();
 
+        let report = wm.grab_report();
+        let mut grabbed = report.grabbed.clone();
+        grabbed.sort_by_key(|k| (k.mask, k.code));
+
+        assert!(!report.is_ok());
         assert_eq!(
-            wm.screen(&Selector::WinId(2)),
-            wm.screen(&Selector::Index(1)),
+            grabbed,
+            vec![
+                EXIT_CODE,
+                KeyCode {
+                    mask: 64,
+                    code: TAKEN.code
+                }
+            ]
+        );
+        assert_eq!(
+            report.failed,
+            vec![(TAKEN, "Unhandled error: BadAccess".to_string())]
         );
     }
 
     #[test]
-    fn client() {
-        let mut wm = test_windowmanager(1, n_clients(3));
+    fn bare_keys_are_grabbed_without_modifiers() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init().with_taken_keys(vec![TAKEN]),
+            vec![],
+            logging_error_handler(),
+        );
         wm.init().unwrap();
-        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+        wm.set_key_codes(vec![("XF86AudioMute".into(), 121)].into_iter().collect());
+
+        let mut bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        bindings.insert(EXIT_CODE, Box::new(|wm: &mut WindowManager<_>| wm.exit()));
+        wm.rebind("XF86AudioMute", Box::new(|_: &mut WindowManager<_>| Ok(())))
             .unwrap();
 
-        assert_eq!(wm.client(&Selector::Focused).unwrap().id(), 2);
-        assert_eq!(wm.client(&Selector::Index(2)).unwrap().id(), 0);
+        wm.grab_keys_and_run(bindings, HashMap::new()).unwrap();
+
+        let report = wm.grab_report();
+        assert!(report.is_ok());
+        assert!(report.grabbed.contains(&KeyCode { mask: 0, code: 121 }));
+    }
+
+    fn swapped_key_codes() -> Result<CodeMap> {
+        Ok(vec![("j".into(), 45), ("k".into(), 44)]
+            .into_iter()
+            .collect())
+    }
+
+    test_cases! {
+        key_binding_mode;
+        args: (mode: KeyBindingMode, expected: Vec<&'static str>);
+
+        case: key_code => (KeyBindingMode::KeyCode, vec!["k", "j"]);
+        case: key_sym => (KeyBindingMode::KeySym, vec!["j", "k"]);
+
+        body: {
+            let (j, k) = (KeyCode { mask: 0, code: 44 }, KeyCode { mask: 0, code: 45 });
+            let events = vec![XEvent::MappingNotify, XEvent::KeyPress(k), XEvent::KeyPress(j)];
+            let mut wm = test_windowmanager(1, events);
+            wm.state.config.key_binding_mode = mode;
+            wm.set_key_codes(vec![("j".into(), 44), ("k".into(), 45)].into_iter().collect());
+            wm.load_key_codes = swapped_key_codes;
+
+            let pressed = Rc::new(RefCell::new(vec![]));
+            let mut bindings = test_key_bindings();
+            for (code, name) in [(j, "j"), (k, "k")] {
+                let pressed = Rc::clone(&pressed);
+                bindings.insert(
+                    code,
+                    Box::new(move |_: &mut TestWM| {
+                        pressed.borrow_mut().push(name);
+                        Ok(())
+                    }),
+                );
+            }
+
+            wm.grab_keys_and_run(bindings, test_mouse_bindings())
+                .unwrap();
+
+            assert_eq!(*pressed.borrow(), expected);
+        }
     }
 
     #[test]
-    fn client_mut() {
-        let mut wm = test_windowmanager(1, n_clients(3));
-        wm.init().unwrap();
-        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
-            .unwrap();
+    fn spawn_on_empty_workspace_only_runs_once_until_the_workspace_is_emptied() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.state
+            .config
+            .workspace_spawns
+            .insert("3".into(), "true".into());
 
-        assert_eq!(
-            wm.client(&Selector::Focused).map(|c| c.workspace()),
-            Some(0)
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert!(wm.auto_spawned.contains("3"));
+
+        add_n_clients(&mut wm, 1, 0);
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert!(wm.auto_spawned.contains("3"));
+
+        wm.remove_client(10).unwrap();
+        assert!(!wm.auto_spawned.contains("3"));
+    }
+
+    #[test]
+    fn failed_spawns_on_empty_workspaces_are_retried() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.state
+            .config
+            .workspace_spawns
+            .insert("3".into(), "penrose-test-not-a-real-program".into());
+
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert!(wm.auto_spawned.is_empty());
+    }
+
+    test_cases! {
+        spawn_limits;
+        args: (min_interval_ms: u64, dedup_ms: u64, requests: Vec<(&str, u64)>, expected: Vec<bool>);
+
+        case: unlimited => (0, 0, vec![("a", 0), ("a", 0), ("b", 0)], vec![true, true, true]);
+        case: min_interval => (
+            100, 0, vec![("a", 0), ("b", 50), ("b", 100)], vec![true, false, true]
         );
-        wm.client_mut(&Selector::Focused).unwrap().set_workspace(5);
-        assert_eq!(
-            wm.client(&Selector::Focused).map(|c| c.workspace()),
-            Some(5)
+        case: dedup => (
+            0, 100, vec![("a", 0), ("a", 50), ("b", 60), ("a", 100)], vec![true, false, true, true]
+        );
+        case: dropped_requests_do_not_reset_the_interval => (
+            100, 0, vec![("a", 0), ("a", 90), ("a", 110)], vec![true, false, true]
         );
+        case: both => (
+            50, 200, vec![("a", 0), ("b", 60), ("a", 120), ("a", 200)], vec![true, true, false, true]
+        );
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            wm.config.spawn_min_interval_ms = min_interval_ms;
+            wm.config.spawn_dedup_ms = dedup_ms;
+            let start = Instant::now();
+
+            let allowed: Vec<bool> = requests
+                .into_iter()
+                .map(|(cmd, ms)| wm.spawn_allowed(cmd, start + Duration::from_millis(ms)))
+                .collect();
+
+            assert_eq!(allowed, expected);
+        }
+    }
+
+    #[test]
+    fn run_external_bindings_are_rate_limited() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.config.spawn_dedup_ms = 60_000;
+        let mut binding = run_external!("true");
+        binding(&mut wm).unwrap();
+
+        assert!(wm.recent_spawns.contains_key("true"));
+        assert!(!wm.spawn_allowed("true", Instant::now()));
     }
 }
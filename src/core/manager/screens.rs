@@ -12,12 +12,28 @@ use crate::{
 };
 use tracing::{debug, info, trace};
 
+use std::collections::HashMap;
+
+/// What was last shown on a named RandR output so that it can be restored if the output is
+/// disconnected and later reconnected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(super) struct OutputState {
+    pub(super) wix: usize,
+    pub(super) layout: String,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(super) struct Screens {
     pub(super) inner: Ring<Screen>,
     bar_height: u32,
     top_bar: bool,
+    // The name of the RandR output displaying each screen in inner (if known)
+    #[cfg_attr(feature = "serde", serde(default))]
+    output_names: Vec<Option<String>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    outputs: HashMap<String, OutputState>,
 }
 
 impl Screens {
@@ -26,9 +42,47 @@ impl Screens {
             inner: Ring::default(),
             bar_height,
             top_bar,
+            output_names: vec![],
+            outputs: HashMap::new(),
+        }
+    }
+
+    // Each screen paired with the name of the output displaying it, skipping unnamed outputs
+    fn named_screens(&self) -> impl Iterator<Item = (&str, &Screen)> {
+        self.output_names
+            .iter()
+            .zip(self.inner.iter())
+            .flat_map(|(name, s)| name.as_deref().map(|name| (name, s)))
+    }
+
+    /// Record the workspace and layout currently shown on each named output. A workspace is only
+    /// remembered for a single output: outputs that are no longer connected lose any claim on a
+    /// workspace that is now being shown elsewhere.
+    pub fn remember_outputs(&mut self, layout_for: impl Fn(usize) -> Option<String>) {
+        let shown: Vec<(String, usize)> = self
+            .named_screens()
+            .map(|(name, s)| (name.to_string(), s.wix))
+            .collect();
+
+        for (name, wix) in shown {
+            let layout = match layout_for(wix) {
+                Some(layout) => layout,
+                None => continue,
+            };
+
+            self.outputs.retain(|n, o| *n == name || o.wix != wix);
+            self.outputs.insert(name, OutputState { wix, layout });
         }
     }
 
+    /// The remembered state of each screen that is currently showing the workspace that was last
+    /// shown on its output.
+    pub fn restored_outputs(&self) -> Vec<&OutputState> {
+        self.named_screens()
+            .flat_map(|(name, s)| self.outputs.get(name).filter(|o| o.wix == s.wix))
+            .collect()
+    }
+
     pub fn indexed_screen_for_workspace(&self, wix: usize) -> Option<(usize, &Screen)> {
         self.inner
             .indexed_element(&Selector::Condition(&|s| s.wix == wix))
@@ -52,6 +106,10 @@ impl Screens {
         self.inner.focused_index()
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Screen> {
+        self.inner.iter()
+    }
+
     pub fn visible_workspaces(&self) -> Vec<usize> {
         self.inner.vec_map(|s| s.wix)
     }
@@ -64,8 +122,22 @@ impl Screens {
         self.inner.len()
     }
 
+    pub fn set_bar_visible(&mut self, index: usize, visible: bool) -> Option<usize> {
+        self.inner.get_mut(index).map(|s| {
+            s.bar_hidden = !visible;
+            s.wix
+        })
+    }
+
+    pub fn set_padding(&mut self, index: usize, px: u32) -> Option<usize> {
+        self.inner.get_mut(index).map(|s| {
+            s.padding = px;
+            s.wix
+        })
+    }
+
     pub fn screen_size(&self, index: usize, bar_visible: bool) -> Option<Region> {
-        self.inner.get(index).map(|s| s.region(bar_visible))
+        self.inner.get(index).map(|s| s.usable_region(bar_visible))
     }
 
     pub fn active_screen_index(&self) -> usize {
@@ -93,26 +165,77 @@ impl Screens {
 
         debug!(?workspace_ordering, "current workspace ordering");
 
-        let mut detected: Vec<Screen> = state.current_screens()?;
+        let mut detected: Vec<(Screen, Option<String>)> = state.current_outputs()?;
+
+        // Outputs can briefly report a zero sized region while monitors are being reconfigured
+        // so we leave the current screens in place until the next update.
+        if detected
+            .iter()
+            .any(|(s, _)| s.true_region.w == 0 || s.true_region.h == 0)
+        {
+            debug!(?detected, "ignoring transient zero sized screen state");
+            return Ok(vec![]);
+        }
 
         // sort the screens to be in the correct order
-        detected.sort_by_key(|s| s.true_region.x);
+        detected.sort_by_key(|(s, _)| s.true_region.values());
+
+        // Outputs that are mirroring one another report identical regions and should be treated
+        // as a single logical screen.
+        let n_outputs = detected.len();
+        detected.dedup_by_key(|(s, _)| s.true_region);
+        if detected.len() < n_outputs {
+            debug!(
+                n_outputs,
+                n_screens = detected.len(),
+                "collapsing mirrored outputs"
+            );
+        }
 
-        let detected = detected
+        // Outputs that have been seen before get back the workspace that they were last showing
+        // (if it is not already claimed by an earlier screen) while new outputs take the next
+        // workspace in the existing ordering.
+        let mut claimed = vec![];
+        let remembered: Vec<Option<usize>> = detected
+            .iter()
+            .map(|(_, name)| {
+                let wix = name
+                    .as_ref()
+                    .and_then(|name| self.outputs.get(name))
+                    .map(|o| o.wix)
+                    .filter(|&wix| wix < n_workspaces && !claimed.contains(&wix));
+                claimed.extend(wix);
+                wix
+            })
+            .collect();
+
+        let mut unclaimed = workspace_ordering
+            .into_iter()
+            .filter(|wix| !claimed.contains(wix));
+        let workspace_ordering: Vec<usize> = remembered
+            .into_iter()
+            .map_while(|wix| wix.or_else(|| unclaimed.next()))
+            .collect();
+
+        let (detected, names): (Vec<Screen>, Vec<Option<String>>) = detected
             .into_iter()
             .zip(workspace_ordering)
             .enumerate()
-            .map(|(ix, (mut s, wix))| {
+            .map(|(ix, ((mut s, name), wix))| {
                 s.update_effective_region(self.bar_height, self.top_bar);
                 trace!(screen = ix, workspace = wix, "setting workspace for screen");
                 s.wix = wix;
+                if let Some(prev) = self.inner.get(ix) {
+                    s.padding = prev.padding;
+                }
 
                 let r = s.region(false);
-                info!(index = ix, w = r.w, h = r.h, "screen detected");
-                s
+                info!(index = ix, w = r.w, h = r.h, ?name, "screen detected");
+                (s, name)
             })
-            .collect();
+            .unzip();
 
+        self.output_names = names;
         Ok(if self.inner.as_vec() != detected {
             self.inner = Ring::new(detected);
             vec![
@@ -190,6 +313,60 @@ mod tests {
         assert!(events.is_empty());
     }
 
+    #[test]
+    fn update_known_screens_ignores_zero_sized_screens() {
+        let mut s = Screens::new(10, true);
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        let transient = vec![
+            Screen::new(Region::new(0, 0, 1366, 768), 0),
+            Screen::new(Region::new(1366, 0, 0, 0), 1),
+        ];
+        let conn = MockXConn::new(transient, vec![], vec![]);
+        let events = s.update_known_screens(&conn, 10).unwrap();
+
+        assert!(events.is_empty());
+        assert_eq!(
+            s.get(1).unwrap().region(false),
+            Region::new(1366, 0, 1366, 768)
+        );
+    }
+
+    #[test]
+    fn update_known_screens_collapses_mirrored_outputs() {
+        let mut s = Screens::new(10, true);
+        let mirrored = vec![
+            Screen::new(Region::new(0, 0, 1366, 768), 0),
+            Screen::new(Region::new(0, 0, 1366, 768), 1),
+        ];
+        let conn = MockXConn::new(mirrored, vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        assert_eq!(s.n_screens(), 1);
+        assert_eq!(s.get(0).unwrap().wix, 0);
+
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        let events = s.update_known_screens(&conn, 10).unwrap();
+
+        assert!(!events.is_empty());
+        assert_eq!(s.n_screens(), 2);
+        assert_eq!(s.get(1).unwrap().wix, 1);
+    }
+
+    #[test]
+    fn update_known_screens_preserves_padding() {
+        let mut s = Screens::new(10, true);
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+        assert_eq!(s.set_padding(1, 30), Some(1));
+        let events = s.update_known_screens(&conn, 10).unwrap();
+
+        assert!(events.is_empty());
+        assert_eq!(s.get(0).unwrap().padding(), 0);
+        assert_eq!(s.get(1).unwrap().padding(), 30);
+    }
+
     #[test]
     fn changing_focus_generates_event_actions() {
         let mut s = Screens::new(10, true);
@@ -242,6 +419,95 @@ mod tests {
         assert!(events.unwrap().is_empty())
     }
 
+    fn named_screens(names: &[&str]) -> Vec<(Screen, Option<String>)> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let s = Screen::new(Region::new(1000 * i as u32, 0, 1000, 800), i);
+                (s, Some(name.to_string()))
+            })
+            .collect()
+    }
+
+    fn remember(s: &mut Screens) {
+        s.remember_outputs(|wix| Some(format!("layout-{}", wix)));
+    }
+
+    fn show(s: &mut Screens, ix: usize, wix: usize) {
+        s.inner.get_mut(ix).unwrap().wix = wix;
+    }
+
+    #[test]
+    fn reconnected_outputs_restore_their_workspace() {
+        let mut s = Screens::new(10, true);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1", "DP-2"])), 10)
+            .unwrap();
+        show(&mut s, 1, 4);
+
+        // undock
+        remember(&mut s);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1"])), 10)
+            .unwrap();
+        show(&mut s, 0, 2);
+
+        // redock
+        remember(&mut s);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1", "DP-2"])), 10)
+            .unwrap();
+
+        assert_eq!(s.visible_workspaces(), vec![2, 4]);
+        assert_eq!(
+            s.restored_outputs(),
+            vec![
+                &OutputState {
+                    wix: 2,
+                    layout: "layout-2".into()
+                },
+                &OutputState {
+                    wix: 4,
+                    layout: "layout-4".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn outputs_lose_workspaces_that_are_shown_elsewhere() {
+        let mut s = Screens::new(10, true);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1", "DP-2"])), 10)
+            .unwrap();
+        show(&mut s, 1, 4);
+
+        remember(&mut s);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1"])), 10)
+            .unwrap();
+        show(&mut s, 0, 4);
+
+        remember(&mut s);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1", "DP-2"])), 10)
+            .unwrap();
+
+        assert_eq!(s.visible_workspaces(), vec![4, 0]);
+        assert_eq!(s.restored_outputs().len(), 1);
+    }
+
+    #[test]
+    fn unknown_outputs_take_the_next_free_workspace() {
+        let mut s = Screens::new(10, true);
+        s.update_known_screens(&OutputsXConn(named_screens(&["eDP-1", "DP-2"])), 10)
+            .unwrap();
+        show(&mut s, 0, 3);
+        show(&mut s, 1, 1);
+
+        remember(&mut s);
+        s.update_known_screens(&OutputsXConn(named_screens(&["HDMI-1", "DP-2"])), 10)
+            .unwrap();
+
+        assert_eq!(s.visible_workspaces(), vec![3, 1]);
+        assert_eq!(s.restored_outputs().len(), 1);
+    }
+
     fn test_screens(h: u32, top_bar: bool) -> Vec<Screen> {
         let regions = &[
             Region::new(0, 0, 1000, 800),
@@ -258,11 +524,13 @@ mod tests {
             .collect()
     }
 
-    struct OutputsXConn(Vec<Screen>);
+    struct OutputsXConn(Vec<(Screen, Option<String>)>);
 
     impl StubXAtomQuerier for OutputsXConn {}
     impl StubXState for OutputsXConn {
-        fn mock_current_screens(&self) -> crate::core::xconnection::Result<Vec<Screen>> {
+        fn mock_current_outputs(
+            &self,
+        ) -> crate::core::xconnection::Result<Vec<(Screen, Option<String>)>> {
             Ok(self.0.clone())
         }
     }
@@ -281,7 +549,7 @@ mod tests {
         body: {
             let (bar_height, top_bar) = (10, true);
             let screens = test_screens(bar_height, top_bar);
-            let conn = OutputsXConn(screens);
+            let conn = OutputsXConn(screens.into_iter().map(|s| (s, None)).collect());
             let mut s = Screens {
                 inner: Ring::new(
                     current.into_iter().map(|wix|
@@ -289,7 +557,9 @@ mod tests {
                     ).collect()
                 ),
                 bar_height,
-                top_bar
+                top_bar,
+                output_names: vec![],
+                outputs: HashMap::new(),
             };
 
             s.update_known_screens(&conn, n_workspaces).unwrap();
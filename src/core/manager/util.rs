@@ -1,6 +1,9 @@
 use crate::{
     core::{
-        data_types::Region,
+        client::Client,
+        config::Config,
+        data_types::{Region, RelativePosition, ResizeAction},
+        screen::Screen,
         xconnection::{XClientConfig, XState, Xid},
     },
     Result,
@@ -12,6 +15,30 @@ use crate::{
     PenroseError,
 };
 
+// Convert a pixel value from the user config to raw pixels for the given screen
+pub(super) fn scaled_px(config: &Config, screen: &Screen, px: u32) -> u32 {
+    if config.dpi_scaling {
+        screen.scale_px(px)
+    } else {
+        px
+    }
+}
+
+// The border and gap sizes in raw pixels to use for clients on the given screen
+pub(super) fn scaled_border_and_gap(config: &Config, screen: &Screen) -> (u32, u32) {
+    (
+        scaled_px(config, screen, config.border_px),
+        scaled_px(config, screen, config.gap_px),
+    )
+}
+
+// The border width in raw pixels for a client on the given screen, taking into account any per
+// client override that has been set.
+pub(super) fn client_border_px(config: &Config, screen: &Screen, client: Option<&Client>) -> u32 {
+    let px = client.and_then(|c| c.border_px).unwrap_or(config.border_px);
+    scaled_px(config, screen, px)
+}
+
 pub(super) fn pad_region(region: &Region, gapless: bool, gap_px: u32, border_px: u32) -> Region {
     let gpx = if gapless { 0 } else { gap_px };
     let padding = 2 * (border_px + gpx);
@@ -27,6 +54,285 @@ pub(super) fn pad_region(region: &Region, gapless: bool, gap_px: u32, border_px:
     Region::new(x + gpx, y + gpx, w - padding, h - padding)
 }
 
+// Stack all clients that have been given a region smaller than `min` (w, h) in a single region
+// covering all of them. Only `focused` (or the first of the undersized clients if it is not one
+// of them) is shown in that region and the rest are hidden. Undersized clients are only added to
+// the stack if doing so does not cause it to overlap any of the clients that are large enough:
+// those that can not be added keep their original region.
+pub(super) fn stack_undersized(
+    actions: Vec<ResizeAction>,
+    focused: Option<Xid>,
+    min: (u32, u32),
+) -> Vec<ResizeAction> {
+    let (min_w, min_h) = min;
+    let undersized = |r: &Option<Region>| matches!(r, Some(r) if r.w < min_w || r.h < min_h);
+    let stacked: Vec<(Xid, Region)> = actions
+        .iter()
+        .filter(|(_, r)| undersized(r))
+        .flat_map(|&(id, r)| r.map(|r| (id, r)))
+        .collect();
+
+    let (first, first_region) = match stacked.first() {
+        Some(&first) => first,
+        None => return actions,
+    };
+
+    let (shown, mut region) = match focused.and_then(|f| stacked.iter().find(|&&(s, _)| s == f)) {
+        Some(&(id, r)) => (id, r),
+        None => (first, first_region),
+    };
+
+    let kept: Vec<Region> = actions
+        .iter()
+        .filter(|(_, r)| !undersized(r))
+        .flat_map(|&(_, r)| r)
+        .collect();
+    let overlaps = |a: &Region, b: &Region| {
+        a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+    };
+    let bounding = |a: &Region, b: &Region| {
+        let (x1, y1) = (a.x.min(b.x), a.y.min(b.y));
+        let (x2, y2) = ((a.x + a.w).max(b.x + b.w), (a.y + a.h).max(b.y + b.h));
+        Region::new(x1, y1, x2 - x1, y2 - y1)
+    };
+
+    // Keep growing the stack until none of the remaining undersized clients can be added to it
+    let mut merged = vec![shown];
+    loop {
+        let next = stacked.iter().find(|&&(id, r)| {
+            let candidate = bounding(&region, &r);
+            !merged.contains(&id) && !kept.iter().any(|k| overlaps(&candidate, k))
+        });
+        match next {
+            Some(&(id, r)) => {
+                region = bounding(&region, &r);
+                merged.push(id);
+            }
+            None => break,
+        }
+    }
+
+    actions
+        .into_iter()
+        .map(|(id, r)| match r {
+            _ if id == shown => (id, Some(region)),
+            _ if merged.contains(&id) => (id, None),
+            Some(r) if undersized(&Some(r)) && overlaps(&region, &r) => (id, None),
+            r => (id, r),
+        })
+        .collect()
+}
+
+// The edges of `r` that lie along an edge of at least one of the `others` regions, such as the
+// shared edge between two adjacent screens.
+pub(super) fn shared_edges(r: &Region, others: &[Region]) -> Vec<RelativePosition> {
+    let overlaps_x = |o: &Region| o.x < r.x + r.w && r.x < o.x + o.w;
+    let overlaps_y = |o: &Region| o.y < r.y + r.h && r.y < o.y + o.h;
+
+    [
+        RelativePosition::Left,
+        RelativePosition::Right,
+        RelativePosition::Above,
+        RelativePosition::Below,
+    ]
+    .iter()
+    .copied()
+    .filter(|edge| {
+        others.iter().any(|o| match edge {
+            RelativePosition::Left => o.x + o.w == r.x && overlaps_y(o),
+            RelativePosition::Right => r.x + r.w == o.x && overlaps_y(o),
+            RelativePosition::Above => o.y + o.h == r.y && overlaps_x(o),
+            RelativePosition::Below => r.y + r.h == o.y && overlaps_x(o),
+        })
+    })
+    .collect()
+}
+
+// Remove the gap that pad_region added to `padded` on each side where the unpadded client region
+// sits on one of the given `edges` of `area`.
+pub(super) fn drop_edge_gaps(
+    padded: Region,
+    unpadded: &Region,
+    area: &Region,
+    edges: &[RelativePosition],
+    gap_px: u32,
+) -> Region {
+    // pad_region leaves regions that are too small to pad untouched
+    if padded == *unpadded {
+        return padded;
+    }
+
+    let (mut x, mut y, mut w, mut h) = padded.values();
+    for edge in edges {
+        match edge {
+            RelativePosition::Left if unpadded.x == area.x => {
+                x -= gap_px;
+                w += gap_px;
+            }
+            RelativePosition::Right if unpadded.x + unpadded.w == area.x + area.w => w += gap_px,
+            RelativePosition::Above if unpadded.y == area.y => {
+                y -= gap_px;
+                h += gap_px;
+            }
+            RelativePosition::Below if unpadded.y + unpadded.h == area.y + area.h => h += gap_px,
+            _ => (),
+        }
+    }
+
+    Region::new(x, y, w, h)
+}
+
+// Shift a span so that whichever of its ends is closest to one of the given lines (and within
+// `distance` pixels of it) sits on that line.
+fn snap_axis(pos: u32, len: u32, lines: &[u32], distance: u32) -> u32 {
+    let (start, end) = (pos as i64, (pos + len) as i64);
+
+    lines
+        .iter()
+        .flat_map(|&l| [l as i64 - start, l as i64 - end])
+        .filter(|offset| offset.abs() <= distance as i64)
+        .min_by_key(|offset| offset.abs())
+        .map(|offset| (start + offset).max(0) as u32)
+        .unwrap_or(pos)
+}
+
+// Snap the edges of a region to the nearest vertical (xs) and horizontal (ys) lines that are
+// within `distance` pixels. A distance of 0 disables snapping.
+pub(super) fn snap_region(r: Region, xs: &[u32], ys: &[u32], distance: u32) -> Region {
+    if distance == 0 {
+        return r;
+    }
+
+    let (x, y, w, h) = r.values();
+    Region::new(
+        snap_axis(x, w, xs, distance),
+        snap_axis(y, h, ys, distance),
+        w,
+        h,
+    )
+}
+
+// Make the geometry requested by a floating client in a configure request safe to pass on to
+// the X server. Positions are sent as 16 bit signed values so negative positions arrive as very
+// large u32s: these are moved back to 0. Sizes are grown to at least `min` (and never zero) and,
+// if `bounds` are given, the region and its border are kept within them. Values that could not
+// have come from a valid request are rejected.
+pub(super) fn sanitize_configure_region(
+    r: Region,
+    bounds: Option<&Region>,
+    min: (u32, u32),
+    border_px: u32,
+) -> Option<Region> {
+    let position = |v: u32| {
+        let v = v as i32;
+        if (i16::MIN as i32..=i16::MAX as i32).contains(&v) {
+            Some(v.max(0) as u32)
+        } else {
+            None
+        }
+    };
+
+    let (x, y, w, h) = r.values();
+    let (mut x, mut y) = (position(x)?, position(y)?);
+    if w > u16::MAX as u32 || h > u16::MAX as u32 {
+        return None;
+    }
+    let (mut w, mut h) = (w.max(min.0).max(1), h.max(min.1).max(1));
+
+    if let Some(b) = bounds {
+        let (bx, by, bw, bh) = b.values();
+        let (max_w, max_h) = (
+            bw.saturating_sub(2 * border_px),
+            bh.saturating_sub(2 * border_px),
+        );
+        w = w.min(max_w).max(1);
+        h = h.min(max_h).max(1);
+        x = x.clamp(bx, (bx + bw).saturating_sub(w + 2 * border_px).max(bx));
+        y = y.clamp(by, (by + bh).saturating_sub(h + 2 * border_px).max(by));
+    }
+
+    Some(Region::new(x, y, w, h))
+}
+
+// Convert an opacity between 0.0 and 1.0 to a value for _NET_WM_WINDOW_OPACITY
+pub(super) fn opacity_cardinal(opacity: f64) -> u32 {
+    (opacity.clamp(0.0, 1.0) * u32::MAX as f64).round() as u32
+}
+
+// Find the region that lies entirely past the edge of `focused` in the given direction,
+// preferring regions that overlap with it along the other axis and then those whose centre is
+// closest.
+pub(super) fn neighbour_in_direction<T: Copy>(
+    focused: &Region,
+    candidates: &[(T, Region)],
+    direction: RelativePosition,
+) -> Option<T> {
+    let (fx, fy, fw, fh) = focused.values();
+    nearest_in_direction(focused, candidates, direction, |r| match direction {
+        RelativePosition::Left => r.x + r.w <= fx,
+        RelativePosition::Right => r.x >= fx + fw,
+        RelativePosition::Above => r.y + r.h <= fy,
+        RelativePosition::Below => r.y >= fy + fh,
+    })
+}
+
+// Find the region whose centre lies in the given direction from the centre of `focused`, using
+// the same tie breaks as [neighbour_in_direction].
+//
+// Comparing centres rather than requiring candidates to lie entirely past the edge of `focused`
+// lets floating clients that overlap a tiled client be reached in both directions. It also means
+// that a region straddling the edge of `focused` counts as being beyond it: from a full height
+// main column the top of the stack is "above".
+pub(super) fn focus_target_in_direction<T: Copy>(
+    focused: &Region,
+    candidates: &[(T, Region)],
+    direction: RelativePosition,
+) -> Option<T> {
+    let (fcx, fcy) = doubled_centre(focused);
+    nearest_in_direction(focused, candidates, direction, |r| {
+        let (cx, cy) = doubled_centre(r);
+        match direction {
+            RelativePosition::Left => cx < fcx,
+            RelativePosition::Right => cx > fcx,
+            RelativePosition::Above => cy < fcy,
+            RelativePosition::Below => cy > fcy,
+        }
+    })
+}
+
+// doubled to keep the centre of odd sized regions exact
+fn doubled_centre(r: &Region) -> (i64, i64) {
+    (r.x as i64 * 2 + r.w as i64, r.y as i64 * 2 + r.h as i64)
+}
+
+// Pick the best of the candidates accepted by `in_direction`. Ties are broken by preferring
+// regions that overlap with `focused` along the other axis, then those whose centre is closest
+// and finally by the order of `candidates`.
+fn nearest_in_direction<T: Copy>(
+    focused: &Region,
+    candidates: &[(T, Region)],
+    direction: RelativePosition,
+    in_direction: impl Fn(&Region) -> bool,
+) -> Option<T> {
+    let (fx, fy, fw, fh) = focused.values();
+    let (fcx, fcy) = doubled_centre(focused);
+
+    candidates
+        .iter()
+        .filter(|(_, r)| in_direction(r))
+        .min_by_key(|(_, r)| {
+            let overlaps = match direction {
+                RelativePosition::Left | RelativePosition::Right => r.y < fy + fh && fy < r.y + r.h,
+                RelativePosition::Above | RelativePosition::Below => {
+                    r.x < fx + fw && fx < r.x + r.w
+                }
+            };
+            let (cx, cy) = doubled_centre(r);
+            (!overlaps, (cx - fcx).pow(2) + (cy - fcy).pow(2))
+        })
+        .map(|&(id, _)| id)
+}
+
 pub(super) fn position_floating_client<X>(
     conn: &X,
     id: Xid,
@@ -107,6 +413,240 @@ mod tests {
     use super::*;
     use crate::__test_helpers::*;
 
+    test_cases! {
+        snap_region;
+        args: (r: Region, distance: u32, expected: Region);
+
+        case: disabled => (Region::new(12, 8, 100, 100), 0, Region::new(12, 8, 100, 100));
+        case: out_of_range => (Region::new(30, 30, 100, 100), 10, Region::new(30, 30, 100, 100));
+        case: start_edges => (Region::new(8, 5, 100, 100), 10, Region::new(0, 0, 100, 100));
+        case: end_edges => (Region::new(295, 205, 100, 100), 10, Region::new(300, 200, 100, 100));
+        case: nearest_line_wins => (Region::new(196, 103, 100, 100), 10, Region::new(200, 100, 100, 100));
+
+        body: {
+            let (xs, ys) = ([0, 200, 400], [0, 100, 300]);
+            assert_eq!(snap_region(r, &xs, &ys, distance), expected);
+        }
+    }
+
+    test_cases! {
+        sanitize_configure_region;
+        args: (r: Region, bounded: bool, expected: Option<Region>);
+
+        case: valid => (Region::new(100, 100, 300, 200), true, Some(Region::new(100, 100, 300, 200)));
+        case: zero_size => (Region::new(100, 100, 0, 0), true, Some(Region::new(100, 100, 10, 5)));
+        case: below_min => (Region::new(100, 100, 3, 300), true, Some(Region::new(100, 100, 10, 300)));
+        case: negative_position => (Region::new(-50i16 as u32, -1i16 as u32, 300, 200), true, Some(Region::new(0, 0, 300, 200)));
+        case: negative_position_unbounded => (Region::new(-50i16 as u32, 20, 300, 200), false, Some(Region::new(0, 20, 300, 200)));
+        case: larger_than_screen => (Region::new(100, 100, 5000, 5000), true, Some(Region::new(0, 0, 996, 596)));
+        case: larger_than_screen_unbounded => (Region::new(100, 100, 5000, 5000), false, Some(Region::new(100, 100, 5000, 5000)));
+        case: off_the_end_of_the_screen => (Region::new(900, 550, 300, 200), true, Some(Region::new(696, 396, 300, 200)));
+        case: absurd_position => (Region::new(1 << 20, 100, 300, 200), true, None);
+        case: absurd_size => (Region::new(100, 100, 300, u32::MAX), true, None);
+
+        body: {
+            let screen = Region::new(0, 0, 1000, 600);
+            let bounds = if bounded { Some(&screen) } else { None };
+            assert_eq!(sanitize_configure_region(r, bounds, (10, 5), 2), expected);
+        }
+    }
+
+    test_cases! {
+        stack_undersized;
+        args: (focused: Option<Xid>, min: (u32, u32), expected: Vec<ResizeAction>);
+
+        case: disabled => (None, (0, 0), vec![
+            (1, Some(Region::new(0, 0, 500, 800))),
+            (2, Some(Region::new(500, 0, 500, 790))),
+            (3, Some(Region::new(500, 790, 500, 5))),
+            (4, Some(Region::new(500, 795, 500, 5))),
+        ]);
+        case: all_large_enough => (None, (100, 5), vec![
+            (1, Some(Region::new(0, 0, 500, 800))),
+            (2, Some(Region::new(500, 0, 500, 790))),
+            (3, Some(Region::new(500, 790, 500, 5))),
+            (4, Some(Region::new(500, 795, 500, 5))),
+        ]);
+        case: first_undersized_shown => (Some(1), (100, 100), vec![
+            (1, Some(Region::new(0, 0, 500, 800))),
+            (2, Some(Region::new(500, 0, 500, 790))),
+            (3, Some(Region::new(500, 790, 500, 10))),
+            (4, None),
+        ]);
+        case: focused_undersized_shown => (Some(4), (100, 100), vec![
+            (1, Some(Region::new(0, 0, 500, 800))),
+            (2, Some(Region::new(500, 0, 500, 790))),
+            (3, None),
+            (4, Some(Region::new(500, 790, 500, 10))),
+        ]);
+
+        body: {
+            let actions = vec![
+                (1, Some(Region::new(0, 0, 500, 800))),
+                (2, Some(Region::new(500, 0, 500, 790))),
+                (3, Some(Region::new(500, 790, 500, 5))),
+                (4, Some(Region::new(500, 795, 500, 5))),
+            ];
+            assert_eq!(stack_undersized(actions, focused, min), expected);
+        }
+    }
+
+    test_cases! {
+        stack_undersized_without_overlapping;
+        args: (focused: Option<Xid>, expected: Vec<ResizeAction>);
+
+        case: first_undersized_shown => (None, vec![
+            (1, Some(Region::new(0, 0, 500, 10))),
+            (2, None),
+            (3, Some(Region::new(0, 10, 1000, 785))),
+            (4, Some(Region::new(500, 795, 500, 5))),
+        ]);
+        case: focused_undersized_shown => (Some(2), vec![
+            (1, None),
+            (2, Some(Region::new(0, 0, 500, 10))),
+            (3, Some(Region::new(0, 10, 1000, 785))),
+            (4, Some(Region::new(500, 795, 500, 5))),
+        ]);
+
+        body: {
+            // 1 and 2 can be stacked together but doing the same with 4 would cover 3
+            let actions = vec![
+                (1, Some(Region::new(0, 0, 500, 5))),
+                (2, Some(Region::new(0, 5, 500, 5))),
+                (3, Some(Region::new(0, 10, 1000, 785))),
+                (4, Some(Region::new(500, 795, 500, 5))),
+            ];
+            assert_eq!(stack_undersized(actions, focused, (100, 100)), expected);
+        }
+    }
+
+    test_cases! {
+        shared_edges;
+        args: (others: Vec<Region>, expected: Vec<RelativePosition>);
+
+        case: no_other_screens => (vec![], vec![]);
+        case: screen_to_the_right => (
+            vec![Region::new(2000, 0, 1000, 800)],
+            vec![RelativePosition::Right]
+        );
+        case: offset_screen_below => (
+            vec![Region::new(1500, 800, 1000, 800)],
+            vec![RelativePosition::Below]
+        );
+        case: diagonal_screen_is_ignored => (vec![Region::new(2000, 800, 1000, 800)], vec![]);
+        case: gap_between_screens => (vec![Region::new(2010, 0, 1000, 800)], vec![]);
+        case: screens_either_side => (
+            vec![Region::new(2000, 0, 1000, 800), Region::new(0, 0, 1000, 800)],
+            vec![RelativePosition::Left, RelativePosition::Right]
+        );
+
+        body: {
+            let r = Region::new(1000, 0, 1000, 800);
+            assert_eq!(shared_edges(&r, &others), expected);
+        }
+    }
+
+    test_cases! {
+        drop_edge_gaps;
+        args: (unpadded: Region, edges: Vec<RelativePosition>, expected: Region);
+
+        case: no_shared_edges => (
+            Region::new(0, 0, 500, 800), vec![], Region::new(5, 5, 490, 790)
+        );
+        case: shared_edge_not_touched => (
+            Region::new(0, 0, 500, 800), vec![RelativePosition::Right], Region::new(5, 5, 490, 790)
+        );
+        case: shared_right_edge => (
+            Region::new(500, 0, 500, 800), vec![RelativePosition::Right], Region::new(505, 5, 495, 790)
+        );
+        case: shared_left_and_top_edges => (
+            Region::new(0, 0, 500, 400),
+            vec![RelativePosition::Left, RelativePosition::Above],
+            Region::new(0, 0, 495, 395)
+        );
+
+        body: {
+            let area = Region::new(0, 0, 1000, 800);
+            let padded = pad_region(&unpadded, false, 5, 0);
+            assert_eq!(drop_edge_gaps(padded, &unpadded, &area, &edges, 5), expected);
+        }
+    }
+
+    test_cases! {
+        opacity_cardinal;
+        args: (opacity: f64, expected: u32);
+
+        case: transparent => (0.0, 0);
+        case: opaque => (1.0, u32::MAX);
+        case: half => (0.5, 0x8000_0000);
+        case: clamped_low => (-1.0, 0);
+        case: clamped_high => (2.0, u32::MAX);
+
+        body: {
+            assert_eq!(opacity_cardinal(opacity), expected);
+        }
+    }
+
+    test_cases! {
+        neighbour_in_direction;
+        args: (focused: Xid, direction: RelativePosition, expected: Option<Xid>);
+
+        // 1 | 2
+        // --+---
+        // 1 | 3
+        case: main_to_right => (1, RelativePosition::Right, Some(2));
+        case: main_to_left => (1, RelativePosition::Left, None);
+        case: stack_to_left => (3, RelativePosition::Left, Some(1));
+        case: stack_up => (3, RelativePosition::Above, Some(2));
+        case: stack_down => (2, RelativePosition::Below, Some(3));
+        case: top_edge => (2, RelativePosition::Above, None);
+        case: straddling_edge => (1, RelativePosition::Above, None);
+
+        body: {
+            let regions = [
+                (1, Region::new(0, 0, 600, 800)),
+                (2, Region::new(600, 0, 400, 400)),
+                (3, Region::new(600, 400, 400, 400)),
+            ];
+            let r = regions.iter().find(|&&(id, _)| id == focused).unwrap().1;
+            let others: Vec<_> = regions.iter().filter(|&&(id, _)| id != focused).copied().collect();
+
+            assert_eq!(neighbour_in_direction(&r, &others, direction), expected);
+        }
+    }
+
+    test_cases! {
+        focus_target_in_direction;
+        args: (focused: Xid, direction: RelativePosition, expected: Option<Xid>);
+
+        // 1 | 2 (with 4 floating in the top right corner of 2)
+        // --+---
+        // 1 | 3
+        case: main_to_right => (1, RelativePosition::Right, Some(2));
+        case: main_to_left => (1, RelativePosition::Left, None);
+        case: stack_to_left => (3, RelativePosition::Left, Some(1));
+        case: stack_up => (3, RelativePosition::Above, Some(2));
+        case: stack_down => (2, RelativePosition::Below, Some(3));
+        case: top_edge => (4, RelativePosition::Above, None);
+        case: bottom_edge => (3, RelativePosition::Below, None);
+        case: straddling_edge => (1, RelativePosition::Above, Some(2));
+        case: to_overlapping_float => (2, RelativePosition::Right, Some(4));
+        case: from_overlapping_float => (4, RelativePosition::Left, Some(2));
+
+        body: {
+            let regions = [
+                (1, Region::new(0, 0, 600, 800)),
+                (2, Region::new(600, 0, 400, 400)),
+                (3, Region::new(600, 400, 400, 400)),
+                (4, Region::new(900, 0, 100, 100)),
+            ];
+            let r = regions.iter().find(|&&(id, _)| id == focused).unwrap().1;
+            let others: Vec<_> = regions.iter().filter(|&&(id, _)| id != focused).copied().collect();
+
+            assert_eq!(focus_target_in_direction(&r, &others, direction), expected);
+        }
+    }
+
     #[test]
     fn pad_region_centered() {
         let r = Region::new(0, 0, 200, 100);
@@ -125,6 +665,50 @@ mod tests {
         assert_eq!(pad_region(&r, true, g, b), r);
     }
 
+    test_cases! {
+        scaled_border_and_gap;
+        args: (dpi_scaling: bool, mm_width: u32, expected: (u32, u32));
+
+        case: scaling_disabled => (false, 254, (2, 5));
+        case: unknown_dpi => (true, 0, (2, 5));
+        case: hidpi => (true, 254, (4, 10));
+        case: base_dpi => (true, 508, (2, 5));
+
+        body: {
+            let config = Config::default()
+                .builder()
+                .dpi_scaling(dpi_scaling)
+                .build()
+                .unwrap();
+            let s = Screen::new(Region::new(0, 0, 1920, 1080), 0).with_physical_width(mm_width);
+
+            assert_eq!(scaled_border_and_gap(&config, &s), expected);
+        }
+    }
+
+    test_cases! {
+        client_border_px;
+        args: (dpi_scaling: bool, override_px: Option<u32>, expected: u32);
+
+        case: no_override => (false, None, 2);
+        case: with_override => (false, Some(7), 7);
+        case: override_is_scaled => (true, Some(7), 14);
+        case: zero_override => (false, Some(0), 0);
+
+        body: {
+            let config = Config::default()
+                .builder()
+                .dpi_scaling(dpi_scaling)
+                .build()
+                .unwrap();
+            let s = Screen::new(Region::new(0, 0, 1920, 1080), 0).with_physical_width(254);
+            let mut client = Client::new(&TestXConn::new(1, vec![], vec![]), 1, 0, &[]);
+            client.border_px = override_px;
+
+            assert_eq!(client_border_px(&config, &s, Some(&client)), expected);
+        }
+    }
+
     #[test]
     fn position_floating() {
         let conn = TestXConn::new(1, vec![], vec![]);
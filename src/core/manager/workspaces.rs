@@ -2,9 +2,9 @@
 use crate::{
     core::{
         client::Client,
-        data_types::{Change, Region},
+        data_types::{Change, Overflow, Region},
         hooks::HookName,
-        layout::LayoutConf,
+        layout::{Layout, LayoutConf},
         manager::EventAction,
         ring::{Direction, InsertPoint, Ring, Selector},
         workspace::{ArrangeActions, Workspace},
@@ -73,6 +73,20 @@ impl Workspaces {
         }
     }
 
+    pub fn indexed_workspace(
+        &self,
+        selector: &Selector<'_, Workspace>,
+    ) -> Option<(usize, &Workspace)> {
+        if let Selector::WinId(id) = selector {
+            self.inner
+                .iter()
+                .enumerate()
+                .find(|(_, ws)| ws.client_ids().contains(id))
+        } else {
+            self.inner.indexed_element(selector)
+        }
+    }
+
     pub fn workspace_mut(&mut self, selector: &Selector<'_, Workspace>) -> Option<&mut Workspace> {
         if let Selector::WinId(id) = selector {
             self.inner
@@ -165,7 +179,7 @@ impl Workspaces {
     }
 
     pub fn get_arrange_actions(
-        &mut self,
+        &self,
         wix: usize,
         region: Region,
         clients: &[&Client],
@@ -189,15 +203,26 @@ impl Workspaces {
         }
     }
 
-    pub fn cycle_workspace(&mut self, direction: Direction) -> usize {
-        self.inner.cycle_focus(direction);
+    pub fn cycle_workspace(&mut self, direction: Direction, overflow: Overflow) -> usize {
+        if overflow == Overflow::Wrap || !self.inner.would_wrap(direction) {
+            self.inner.cycle_focus(direction);
+        }
         self.inner.focused_index()
     }
 
-    pub fn cycle_client(&mut self, wix: usize, direction: Direction) -> Option<(Xid, Xid)> {
-        self.inner
-            .get_mut(wix)
-            .and_then(|ws| ws.cycle_client(direction))
+    pub fn cycle_client(
+        &mut self,
+        wix: usize,
+        direction: Direction,
+        overflow: Overflow,
+    ) -> Option<(Xid, Xid)> {
+        self.inner.get_mut(wix).and_then(|ws| {
+            if overflow == Overflow::Clamp && ws.cycle_would_wrap(direction) {
+                None
+            } else {
+                ws.cycle_client(direction)
+            }
+        })
     }
 
     pub fn drag_client(&mut self, wix: usize, direction: Direction) {
@@ -206,6 +231,12 @@ impl Workspaces {
         });
     }
 
+    pub fn swap_focused_with(&mut self, wix: usize, id: Xid) {
+        self.inner.apply_to(&Selector::Index(wix), |ws| {
+            ws.swap_focused_with(id);
+        });
+    }
+
     pub fn rotate_clients(&mut self, wix: usize, direction: Direction) {
         self.inner.apply_to(&Selector::Index(wix), |ws| {
             ws.rotate_clients(direction);
@@ -218,9 +249,38 @@ impl Workspaces {
         });
     }
 
-    pub fn update_max_main(&mut self, wix: usize, change: Change) {
+    pub fn set_layout(&mut self, wix: usize, symbol: &str) -> Result<()> {
+        self.inner
+            .get_mut(wix)
+            .ok_or_else(|| perror!("unknown workspace: {}", wix))?
+            .try_set_layout(symbol)
+            .map(|_| ())
+            .ok_or_else(|| perror!("unknown layout: {}", symbol))
+    }
+
+    pub fn is_floating(&self, wix: usize) -> bool {
+        self.inner
+            .get(wix)
+            .map(|ws| ws.is_floating())
+            .unwrap_or(false)
+    }
+
+    pub fn toggle_floating(&mut self, wix: usize) {
+        self.inner.apply_to(&Selector::Index(wix), |ws| {
+            ws.toggle_floating();
+        });
+    }
+
+    pub fn update_max_main(
+        &mut self,
+        wix: usize,
+        change: Change,
+        limit: Option<u32>,
+        limit_to_clients: bool,
+        overflow: Overflow,
+    ) {
         self.inner.apply_to(&Selector::Index(wix), |ws| {
-            ws.update_max_main(change);
+            ws.update_max_main_bounded(change, limit, limit_to_clients, overflow);
         });
     }
 
@@ -231,6 +291,25 @@ impl Workspaces {
         });
     }
 
+    pub fn set_max_main(&mut self, wix: usize, max_main: u32) {
+        self.inner.apply_to(&Selector::Index(wix), |ws| {
+            ws.set_max_main(max_main);
+        });
+    }
+
+    pub fn set_main_ratio(&mut self, wix: usize, ratio: f32) {
+        self.inner.apply_to(&Selector::Index(wix), |ws| {
+            ws.set_main_ratio(ratio);
+        });
+    }
+
+    pub fn reset_layout(&mut self, wix: usize, defaults: &[Layout]) -> bool {
+        match self.inner.get_mut(wix) {
+            Some(ws) => ws.reset_layout(defaults),
+            None => false,
+        }
+    }
+
     pub fn current_layout_symbol(&self, wix: usize) -> &str {
         match self.inner.get(wix) {
             Some(ws) => ws.layout_symbol(),
@@ -45,6 +45,8 @@ pub mod layout;
 pub mod manager;
 pub mod ring;
 pub mod screen;
+#[cfg(feature = "startup_notification")]
+pub mod startup;
 pub mod workspace;
 pub mod xconnection;
 
@@ -65,4 +67,4 @@ pub use ring::Selector;
 #[doc(inline)]
 pub use screen::Screen;
 #[doc(inline)]
-pub use workspace::Workspace;
+pub use workspace::{Workspace, WorkspaceView};
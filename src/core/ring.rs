@@ -4,6 +4,7 @@
 use crate::core::xconnection::Xid;
 
 use std::{
+    cmp::Ordering,
     collections::VecDeque,
     fmt,
     iter::{FromIterator, IntoIterator},
@@ -63,6 +64,16 @@ pub enum Selector<'a, T> {
     WinId(Xid),
     /// The first element satisfying this condition.
     Condition(&'a dyn Fn(&T) -> bool),
+    /// The element this many positions after the focused element, wrapping around at either end
+    /// of the collection. Negative offsets count backwards from the focused element and an offset
+    /// of 0 is equivalent to `Focused`.
+    ///
+    /// Selects nothing if the collection is empty. For [Client][1]s the offset is relative to the
+    /// focused client in the client order of the active [Workspace][2].
+    ///
+    /// [1]: crate::core::client::Client
+    /// [2]: crate::core::workspace::Workspace
+    FromFocused(i32),
 }
 
 impl<'a, T> From<Xid> for Selector<'a, T> {
@@ -84,6 +95,10 @@ impl<'a, T> fmt::Debug for Selector<'a, T> {
             Self::Focused => f.debug_struct("Selector::Focused").finish(),
             Self::Index(i) => f.debug_struct("Selector::Index").field("index", i).finish(),
             Self::WinId(i) => f.debug_struct("Selector::WinId").field("id", i).finish(),
+            Self::FromFocused(n) => f
+                .debug_struct("Selector::FromFocused")
+                .field("offset", n)
+                .finish(),
             Self::Condition(_func) => f
                 .debug_struct("Selector::Condition")
                 .field("condition", &stringify!(_func))
@@ -195,6 +210,15 @@ impl<T> Ring<T> {
         self.cycle_focus(direction)
     }
 
+    pub fn swap_focused(&mut self, index: usize) -> Option<&T> {
+        if index >= self.elements.len() {
+            return None;
+        }
+        self.elements.swap(self.focused, index);
+        self.focused = index;
+        self.focused()
+    }
+
     pub fn len(&self) -> usize {
         self.elements.len()
     }
@@ -220,6 +244,67 @@ impl<T> Ring<T> {
         self.elements.insert(index, element);
     }
 
+    // Unlike insert and insert_at, the focused element remains the same after inserting.
+    // Indices past the end of the ring insert as the last element.
+    pub fn insert_keeping_focus(&mut self, index: usize, element: T) {
+        let index = index.min(self.elements.len());
+        if !self.elements.is_empty() && index <= self.focused {
+            self.focused += 1;
+        }
+        self.elements.insert(index, element);
+    }
+
+    // Insert after the last element that is not greater than `element`, keeping focus on the
+    // current focused element. If the ring is already sorted by `cmp` it remains sorted.
+    pub fn insert_sorted_by<F>(&mut self, element: T, mut cmp: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let index = self
+            .elements
+            .iter()
+            .position(|e| cmp(e, &element) == Ordering::Greater)
+            .unwrap_or(self.elements.len());
+
+        self.insert_keeping_focus(index, element);
+    }
+
+    // Move the element at `from` so that it ends up at index `to`, shifting the elements in
+    // between. Focus follows the element that was focused before the move. Returns false without
+    // modifying the ring if either index is out of bounds.
+    pub fn move_element(&mut self, from: usize, to: usize) -> bool {
+        let len = self.elements.len();
+        if from >= len || to >= len {
+            return false;
+        }
+
+        if let Some(element) = self.elements.remove(from) {
+            self.elements.insert(to, element);
+        }
+
+        self.focused = match self.focused {
+            f if f == from => to,
+            f if from < f && f <= to => f - 1,
+            f if to <= f && f < from => f + 1,
+            f => f,
+        };
+
+        true
+    }
+
+    // A stable sort of the elements that keeps focus on the current focused element
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut indexed: Vec<(usize, T)> = self.elements.drain(..).enumerate().collect();
+        indexed.sort_by(|(_, a), (_, b)| cmp(a, b));
+
+        let focused = self.focused;
+        self.focused = indexed.iter().position(|&(i, _)| i == focused).unwrap_or(0);
+        self.elements = indexed.into_iter().map(|(_, e)| e).collect();
+    }
+
     pub fn push(&mut self, element: T) {
         self.elements.push_back(element);
     }
@@ -232,6 +317,25 @@ impl<T> Ring<T> {
         self.elements.iter_mut()
     }
 
+    // Each element once, starting with the focused element and wrapping around to the start of
+    // the ring. Yields nothing for an empty ring.
+    pub fn iter_from_focused(&self) -> impl Iterator<Item = &T> + '_ {
+        let split = self.focused.min(self.elements.len());
+        self.elements
+            .range(split..)
+            .chain(self.elements.range(..split))
+    }
+
+    // Each element once, starting with the focused element and moving backwards, wrapping
+    // around to the end of the ring. Yields nothing for an empty ring.
+    pub fn iter_from_focused_rev(&self) -> impl Iterator<Item = &T> + '_ {
+        let split = (self.focused + 1).min(self.elements.len());
+        self.elements
+            .range(..split)
+            .rev()
+            .chain(self.elements.range(split..).rev())
+    }
+
     pub fn get(&self, index: usize) -> Option<&T> {
         self.elements.get(index)
     }
@@ -256,6 +360,16 @@ impl<T> Ring<T> {
         }
     }
 
+    // The index `offset` positions away from the focused element, wrapping at either end
+    fn relative_index(&self, offset: i32) -> Option<usize> {
+        if self.elements.is_empty() {
+            return None;
+        }
+
+        let len = self.elements.len() as i64;
+        Some((self.focused as i64 + offset as i64).rem_euclid(len) as usize)
+    }
+
     fn element_by(&self, cond: impl Fn(&T) -> bool) -> Option<(usize, &T)> {
         self.elements.iter().enumerate().find(|(_, e)| cond(*e))
     }
@@ -276,6 +390,7 @@ impl<T> Ring<T> {
                 }
             }
             Selector::Condition(f) => self.element_by(f).map(|(i, _)| i),
+            Selector::FromFocused(n) => self.relative_index(*n),
         }
     }
 
@@ -289,6 +404,7 @@ impl<T> Ring<T> {
             Selector::Index(i) => self.elements.get(*i),
             Selector::WinId(_) => None, // ignored
             Selector::Condition(f) => self.element_by(f).map(|(_, e)| e),
+            Selector::FromFocused(n) => self.relative_index(*n).map(|i| &self.elements[i]),
         }
     }
 
@@ -298,6 +414,10 @@ impl<T> Ring<T> {
             Selector::Index(i) => self.elements.get_mut(*i),
             Selector::WinId(_) => None, // ignored
             Selector::Condition(f) => self.element_by_mut(f).map(|(_, e)| e),
+            Selector::FromFocused(n) => match self.relative_index(*n) {
+                Some(i) => self.elements.get_mut(i),
+                None => None,
+            },
         }
     }
 
@@ -308,6 +428,7 @@ impl<T> Ring<T> {
             Selector::Index(i) => self.elements.get(*i).into_iter().collect(),
             Selector::WinId(_) => vec![], // ignored
             Selector::Condition(f) => self.elements.iter().filter(|e| f(*e)).collect(),
+            Selector::FromFocused(_) => self.element(s).into_iter().collect(),
         }
     }
 
@@ -318,6 +439,7 @@ impl<T> Ring<T> {
             Selector::Index(i) => self.elements.get_mut(*i).into_iter().collect(),
             Selector::WinId(_) => vec![], // ignored
             Selector::Condition(f) => self.elements.iter_mut().filter(|e| f(*e)).collect(),
+            Selector::FromFocused(_) => self.element_mut(s).into_iter().collect(),
         }
     }
 
@@ -341,6 +463,10 @@ impl<T> Ring<T> {
                     None
                 }
             }
+            Selector::FromFocused(n) => {
+                self.focused = self.relative_index(*n)?;
+                Some((true, &self.elements[self.focused]))
+            }
         }
     }
 
@@ -366,6 +492,11 @@ impl<T> Ring<T> {
                     None
                 }
             }
+            Selector::FromFocused(n) => {
+                let c = self.elements.remove(self.relative_index(*n)?);
+                self.clamp_focus();
+                c
+            }
         }
     }
 }
@@ -457,6 +588,16 @@ mod tests {
         assert_eq!(r.focused(), Some(&1));
     }
 
+    #[test]
+    fn swap_focused_retains_focus() {
+        let mut r = Ring::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(r.swap_focused(2), Some(&1));
+        assert_eq!(r.elements, vec![3, 2, 1, 4]);
+        assert_eq!(r.swap_focused(4), None);
+        assert_eq!(r.elements, vec![3, 2, 1, 4]);
+    }
+
     #[test]
     fn dragging_an_element_forward() {
         let mut r = Ring::new(vec![1, 2, 3, 4]);
@@ -519,6 +660,42 @@ mod tests {
         assert_eq!(r.index(&Selector::Index(42)), None);
     }
 
+    test_cases! {
+        from_focused;
+        args: (focused: usize, offset: i32, expected: Option<usize>);
+
+        case: zero_is_focused => (2, 0, Some(2));
+        case: forward => (2, 2, Some(4));
+        case: wraps_forward => (4, 3, Some(1));
+        case: backward => (2, -1, Some(1));
+        case: wraps_backward => (0, -1, Some(5));
+        case: multiple_laps => (1, 15, Some(4));
+        case: multiple_laps_backward => (1, -14, Some(5));
+
+        body: {
+            let mut r = Ring::new(vec![0, 1, 2, 3, 4, 5]);
+            r.focused = focused;
+            assert_eq!(r.index(&Selector::FromFocused(offset)), expected);
+            assert_eq!(r.element(&Selector::FromFocused(offset)).copied(), expected);
+        }
+    }
+
+    #[test]
+    fn from_focused_on_an_empty_ring_selects_nothing() {
+        let mut r: Ring<u32> = Ring::new(vec![]);
+        assert_eq!(r.index(&Selector::FromFocused(1)), None);
+        assert_eq!(r.focus(&Selector::FromFocused(-1)), None);
+        assert_eq!(r.remove(&Selector::FromFocused(0)), None);
+    }
+
+    #[test]
+    fn focus_and_remove_from_focused() {
+        let mut r = Ring::new(vec![1, 2, 3, 4]);
+        assert_eq!(r.focus(&Selector::FromFocused(-1)), Some((true, &4)));
+        assert_eq!(r.remove(&Selector::FromFocused(2)), Some(2));
+        assert_eq!(r.as_vec(), vec![1, 3, 4]);
+    }
+
     #[test]
     fn remove() {
         let mut r = Ring::new(vec![1, 2, 3, 4, 5, 6]);
@@ -633,6 +810,115 @@ mod tests {
         assert_eq!(r.as_vec(), vec![1, 4, 5, 0, 0, 3, 2, 6]);
     }
 
+    test_cases! {
+        iter_from_focused;
+        args: (focused: usize, expected: Vec<u8>, expected_rev: Vec<u8>);
+
+        case: first => (0, vec![1, 2, 3, 4], vec![1, 4, 3, 2]);
+        case: middle => (2, vec![3, 4, 1, 2], vec![3, 2, 1, 4]);
+        case: last => (3, vec![4, 1, 2, 3], vec![4, 3, 2, 1]);
+
+        body: {
+            let mut r = Ring::new(vec![1, 2, 3, 4]);
+            r.focus(&Selector::Index(focused));
+            assert_eq!(r.iter_from_focused().copied().collect::<Vec<_>>(), expected);
+            assert_eq!(r.iter_from_focused_rev().copied().collect::<Vec<_>>(), expected_rev);
+        }
+    }
+
+    #[test]
+    fn iter_from_focused_on_an_empty_ring_is_empty() {
+        let r: Ring<u8> = Ring::new(vec![]);
+        assert_eq!(r.iter_from_focused().next(), None);
+        assert_eq!(r.iter_from_focused_rev().next(), None);
+    }
+
+    #[test]
+    fn insert_keeping_focus() {
+        let mut r = Ring::new(vec![1, 2, 3]);
+        r.focus(&Selector::Index(1));
+        r.insert_keeping_focus(0, 4);
+        assert_eq!(r.as_vec(), vec![4, 1, 2, 3]);
+        assert_eq!(r.focused(), Some(&2));
+        r.insert_keeping_focus(2, 5);
+        assert_eq!(r.as_vec(), vec![4, 1, 5, 2, 3]);
+        assert_eq!(r.focused(), Some(&2));
+        r.insert_keeping_focus(42, 6);
+        assert_eq!(r.as_vec(), vec![4, 1, 5, 2, 3, 6]);
+        assert_eq!(r.focused(), Some(&2));
+    }
+
+    #[test]
+    fn insert_keeping_focus_into_an_empty_ring_focuses_the_element() {
+        let mut r = Ring::new(vec![]);
+        r.insert_keeping_focus(3, 1);
+        assert_eq!(r.focused(), Some(&1));
+    }
+
+    #[test]
+    fn insert_sorted_by() {
+        let mut r = Ring::new(vec![1, 3, 5]);
+        r.focus(&Selector::Index(2));
+        r.insert_sorted_by(4, |a, b| a.cmp(b));
+        r.insert_sorted_by(0, |a, b| a.cmp(b));
+        r.insert_sorted_by(9, |a, b| a.cmp(b));
+        assert_eq!(r.as_vec(), vec![0, 1, 3, 4, 5, 9]);
+        assert_eq!(r.focused(), Some(&5));
+    }
+
+    #[test]
+    fn insert_sorted_by_goes_after_equal_elements() {
+        let mut r = Ring::new(vec![(1, "a"), (2, "b")]);
+        r.insert_sorted_by((1, "c"), |a, b| a.0.cmp(&b.0));
+        assert_eq!(r.as_vec(), vec![(1, "a"), (1, "c"), (2, "b")]);
+    }
+
+    test_cases! {
+        move_element;
+        args: (from: usize, to: usize, focused: usize, expected: Vec<u8>, expected_focus: u8);
+
+        case: moving_the_focused_element_forward => (1, 3, 1, vec![1, 3, 4, 2, 5], 2);
+        case: moving_the_focused_element_backward => (3, 0, 3, vec![4, 1, 2, 3, 5], 4);
+        case: moving_past_the_focused_element_forward => (0, 2, 1, vec![2, 3, 1, 4, 5], 2);
+        case: moving_past_the_focused_element_backward => (4, 1, 2, vec![1, 5, 2, 3, 4], 3);
+        case: moving_onto_the_focused_element_forward => (0, 2, 2, vec![2, 3, 1, 4, 5], 3);
+        case: moving_onto_the_focused_element_backward => (4, 2, 2, vec![1, 2, 5, 3, 4], 3);
+        case: not_passing_the_focused_element => (3, 4, 1, vec![1, 2, 3, 5, 4], 2);
+        case: to_the_same_index => (2, 2, 2, vec![1, 2, 3, 4, 5], 3);
+
+        body: {
+            let mut r = Ring::new(vec![1, 2, 3, 4, 5]);
+            r.focus(&Selector::Index(focused));
+            assert!(r.move_element(from, to));
+            assert_eq!(r.as_vec(), expected);
+            assert_eq!(r.focused(), Some(&expected_focus));
+        }
+    }
+
+    #[test]
+    fn move_element_out_of_bounds_is_a_no_op() {
+        let mut r = Ring::new(vec![1, 2, 3]);
+        r.focus(&Selector::Index(1));
+        assert!(!r.move_element(0, 3));
+        assert!(!r.move_element(5, 0));
+        assert_eq!(r.as_vec(), vec![1, 2, 3]);
+        assert_eq!(r.focused(), Some(&2));
+    }
+
+    #[test]
+    fn sort_by_is_stable_and_preserves_focus() {
+        let mut r = Ring::new(vec![(3, "a"), (1, "b"), (2, "c"), (1, "d")]);
+        r.focus(&Selector::Index(0));
+        r.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(r.as_vec(), vec![(1, "b"), (1, "d"), (2, "c"), (3, "a")]);
+        assert_eq!(r.focused(), Some(&(3, "a")));
+
+        r.focus(&Selector::Index(1));
+        r.sort_by(|a, b| b.1.cmp(a.1));
+        assert_eq!(r.as_vec(), vec![(1, "d"), (2, "c"), (1, "b"), (3, "a")]);
+        assert_eq!(r.focused(), Some(&(1, "d")));
+    }
+
     #[test]
     fn vec_map() {
         let contents = vec!["this", "is", "a", "lot", "nicer"];
@@ -9,8 +9,17 @@ pub struct Screen {
     pub wix: usize,
     pub(crate) true_region: Region,
     effective_region: Region,
+    dpi: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) padding: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) bar_hidden: bool,
 }
 
+/// The DPI that unscaled pixel values in the user [Config][crate::core::config::Config] are
+/// assumed to be relative to.
+pub const BASE_DPI: u32 = 96;
+
 impl Screen {
     /// Create a new screen instance directly
     pub fn new(region: Region, wix: usize) -> Screen {
@@ -18,9 +27,48 @@ impl Screen {
             true_region: region,
             effective_region: region,
             wix,
+            dpi: None,
+            padding: 0,
+            bar_hidden: false,
+        }
+    }
+
+    /// Set the DPI of this screen based on its physical width in millimeters (as reported by
+    /// RandR). A width of zero is treated as the DPI being unknown.
+    pub fn with_physical_width(mut self, mm_width: u32) -> Screen {
+        self.dpi = if mm_width > 0 {
+            Some((self.true_region.w as f32 * 25.4 / mm_width as f32).round() as u32)
+        } else {
+            None
+        };
+        self
+    }
+
+    /// The DPI of this screen if it is known
+    pub fn dpi(&self) -> Option<u32> {
+        self.dpi
+    }
+
+    /// The scale factor to apply to DPI relative pixel values for this screen. If the DPI of the
+    /// screen is not known then this is always 1.0
+    pub fn scale_factor(&self) -> f32 {
+        match self.dpi {
+            Some(dpi) => dpi as f32 / BASE_DPI as f32,
+            None => 1.0,
         }
     }
 
+    /// Scale a DPI relative pixel value to raw pixels for this screen.
+    pub fn scale_px(&self, px: u32) -> u32 {
+        (px as f32 * self.scale_factor()).round() as u32
+    }
+
+    /// Extra padding in pixels applied around the edge of this screen when laying out tiled
+    /// clients, on top of any gaps. Useful for outputs that suffer from overscan.
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
     /// Cache the current effective region of this screen based on whether or not a bar is
     /// displayed and if that bar is positioned at the top or bottom of the screen.
     pub fn update_effective_region(&mut self, bar_height: u32, top_bar: bool) {
@@ -42,9 +90,51 @@ impl Screen {
         }
     }
 
+    /// Whether or not the status bar for this screen is currently shown. See
+    /// [toggle_bar][crate::core::manager::WindowManager::toggle_bar] for details.
+    pub fn bar_visible(&self) -> bool {
+        !self.bar_hidden
+    }
+
+    /// The available space for displaying clients on this screen, accounting for space taken up
+    /// by a bar if `show_bar` is true and the bar for this screen has not been hidden.
+    pub fn usable_region(&self, show_bar: bool) -> Region {
+        self.region(show_bar && !self.bar_hidden)
+    }
+
     /// Determine whether or not an absolute coordinate Point (relative to the root window) is
     /// located on this screen.
     pub fn contains(&self, p: Point) -> bool {
         self.true_region.contains_point(&p)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen_with_width(mm_width: u32) -> Screen {
+        Screen::new(Region::new(0, 0, 1920, 1080), 0).with_physical_width(mm_width)
+    }
+
+    test_cases! {
+        scale_px;
+        args: (mm_width: u32, px: u32, expected: u32);
+
+        case: unknown_dpi => (0, 3, 3);
+        case: base_dpi => (508, 3, 3);
+        case: double_dpi => (254, 3, 6);
+        case: rounds_to_nearest => (381, 3, 4);
+
+        body: {
+            assert_eq!(screen_with_width(mm_width).scale_px(px), expected);
+        }
+    }
+
+    #[test]
+    fn unknown_dpi_is_unscaled() {
+        let s = screen_with_width(0);
+        assert_eq!(s.dpi(), None);
+        assert_eq!(s.scale_factor(), 1.0);
+    }
+}
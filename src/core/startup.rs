@@ -0,0 +1,139 @@
+//! Tracking of in progress application launches using the startup notification protocol
+//!
+//! Processes spawned via
+//! [spawn_with_startup_notification][crate::core::manager::WindowManager::spawn_with_startup_notification]
+//! are started with `DESKTOP_STARTUP_ID` set in their environment. Clients that support the
+//! protocol copy this ID to the `_NET_STARTUP_ID` property of the windows that they map, allowing
+//! the launch to be marked as complete once the matching window appears.
+use crate::core::helpers::new_startup_id;
+
+use std::time::{Duration, Instant};
+
+/// The default length of time to wait for a window to be mapped for a launch before giving up
+pub const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// An application launch that is waiting for a matching window to be mapped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupSequence {
+    /// The startup ID that was passed to the spawned process
+    pub id: String,
+    /// The command that was spawned
+    pub command: String,
+    started: Instant,
+}
+
+impl StartupSequence {
+    /// How long it has been since this launch was started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// The set of currently pending [StartupSequence]s
+#[derive(Debug, Clone)]
+pub struct StartupTracker {
+    pending: Vec<StartupSequence>,
+    timeout: Duration,
+}
+
+impl Default for StartupTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_STARTUP_TIMEOUT)
+    }
+}
+
+impl StartupTracker {
+    /// Create a new tracker that expires pending launches after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: vec![],
+            timeout,
+        }
+    }
+
+    /// Begin tracking a new launch of `command`, returning the startup ID that should be set as
+    /// the `DESKTOP_STARTUP_ID` of the spawned process.
+    pub fn begin(&mut self, command: impl Into<String>) -> String {
+        let id = new_startup_id();
+        self.pending.push(StartupSequence {
+            id: id.clone(),
+            command: command.into(),
+            started: Instant::now(),
+        });
+
+        id
+    }
+
+    /// Stop tracking the launch with the given startup ID, returning it if it was pending
+    pub fn complete(&mut self, id: &str) -> Option<StartupSequence> {
+        let ix = self.pending.iter().position(|s| s.id == id)?;
+        Some(self.pending.remove(ix))
+    }
+
+    /// Stop tracking any launches that have exceeded the timeout, returning them.
+    pub fn remove_expired(&mut self) -> Vec<StartupSequence> {
+        let timeout = self.timeout;
+        let (expired, pending) = self.pending.drain(..).partition(|s| s.elapsed() >= timeout);
+        self.pending = pending;
+
+        expired
+    }
+
+    /// The launches that are still waiting for a matching window and have not timed out
+    pub fn pending(&self) -> Vec<&StartupSequence> {
+        self.pending
+            .iter()
+            .filter(|s| s.elapsed() < self.timeout)
+            .collect()
+    }
+
+    /// Whether or not there are any launches still waiting for a window
+    pub fn is_launching(&self) -> bool {
+        !self.pending().is_empty()
+    }
+
+    /// Set the timeout used for future checks of pending launches
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn begin_generates_unique_ids() {
+        let mut t = StartupTracker::default();
+        let a = t.begin("a");
+        let b = t.begin("b");
+
+        assert_ne!(a, b);
+        assert_eq!(t.pending().len(), 2);
+        assert!(t.is_launching());
+    }
+
+    #[test]
+    fn complete_removes_the_matching_sequence() {
+        let mut t = StartupTracker::default();
+        let a = t.begin("a");
+        let b = t.begin("b");
+
+        assert_eq!(t.complete(&a).map(|s| s.command), Some("a".into()));
+        assert_eq!(t.complete(&a), None);
+        assert_eq!(
+            t.pending().iter().map(|s| &s.id).collect::<Vec<_>>(),
+            vec![&b]
+        );
+    }
+
+    #[test]
+    fn expired_sequences_are_not_pending() {
+        let mut t = StartupTracker::new(Duration::ZERO);
+        t.begin("a");
+
+        assert!(!t.is_launching());
+        assert_eq!(t.remove_expired().len(), 1);
+        assert!(t.remove_expired().is_empty());
+    }
+}
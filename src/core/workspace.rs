@@ -9,8 +9,8 @@
 //! [2]: crate::core::layout::Layout
 use crate::{
     core::{
-        client::Client,
-        data_types::{Change, Region, ResizeAction},
+        client::{Client, Tag},
+        data_types::{Change, Overflow, Region, ResizeAction},
         layout::{Layout, LayoutConf},
         ring::{Direction, InsertPoint, Ring, Selector},
         xconnection::Xid,
@@ -21,6 +21,8 @@ use crate::{
 #[cfg(feature = "serde")]
 use crate::{core::layout::LayoutFunc, PenroseError};
 
+use std::{cmp::Ordering, collections::BTreeSet};
+
 #[cfg(feature = "serde")]
 use std::collections::HashMap;
 
@@ -29,6 +31,35 @@ pub(crate) struct ArrangeActions {
     pub(crate) floating: Vec<Xid>,
 }
 
+/// A point in time snapshot of the state of a [Workspace].
+///
+/// Unlike a `&Workspace` this does not borrow from the [WindowManager][1], allowing it to be
+/// cheaply cloned and passed to widgets or other threads. It will not be updated as the state of
+/// the underlying workspace changes.
+///
+/// [1]: crate::core::manager::WindowManager
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceView {
+    /// The index of the workspace
+    pub index: usize,
+    /// The name of the workspace
+    pub name: String,
+    /// The symbol of the active layout for the workspace
+    pub layout_symbol: String,
+    /// The IDs of the clients on the workspace in stack order
+    pub client_ids: Vec<Xid>,
+    /// The ID of the focused client on the workspace if there is one
+    pub focused_client: Option<Xid>,
+}
+
+impl WorkspaceView {
+    /// The number of clients on the workspace
+    pub fn n_clients(&self) -> usize {
+        self.client_ids.len()
+    }
+}
+
 /// A Workspace represents a named set of clients that are tiled according
 /// to a specific layout. Layout properties are tracked per workspace and
 /// clients are referenced by ID. Workspaces are independent of monitors and
@@ -43,6 +74,14 @@ pub struct Workspace {
     name: String,
     clients: Ring<Xid>,
     layouts: Ring<Layout>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    floating: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    viewed_tags: Option<BTreeSet<Tag>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    show_bar: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    auto_fullscreen_single: bool,
 }
 
 impl Workspace {
@@ -58,6 +97,10 @@ impl Workspace {
             name: name.into(),
             clients: Ring::new(Vec::new()),
             layouts: Ring::new(layouts),
+            floating: false,
+            viewed_tags: None,
+            show_bar: None,
+            auto_fullscreen_single: false,
         }
     }
 
@@ -91,6 +134,80 @@ impl Workspace {
         })
     }
 
+    /// Whether or not this workspace is in floating mode. When in floating mode, the active layout
+    /// is not applied and all clients are left at their requested positions.
+    pub fn is_floating(&self) -> bool {
+        self.floating
+    }
+
+    /// Enable or disable floating mode for this workspace
+    pub fn set_floating(&mut self, floating: bool) {
+        self.floating = floating;
+    }
+
+    /// Whether or not the status bar should be shown while this workspace is on screen. `None`
+    /// means that the bar is shown as normal.
+    pub fn show_bar(&self) -> Option<bool> {
+        self.show_bar
+    }
+
+    /// Set whether or not the status bar should be shown while this workspace is on screen,
+    /// with `None` showing the bar as normal.
+    pub fn set_show_bar(&mut self, show_bar: Option<bool>) {
+        self.show_bar = show_bar;
+    }
+
+    /// Whether or not a lone client on this workspace is shown borderless and covering the
+    /// entire screen rather than being tiled by the current layout.
+    pub fn auto_fullscreen_single(&self) -> bool {
+        self.auto_fullscreen_single
+    }
+
+    /// Set whether or not a lone client on this workspace should be shown borderless and
+    /// covering the entire screen. The client returns to being tiled as normal as soon as a
+    /// second client is added to the workspace.
+    pub fn set_auto_fullscreen_single(&mut self, auto_fullscreen_single: bool) {
+        self.auto_fullscreen_single = auto_fullscreen_single;
+    }
+
+    /// Toggle floating mode for this workspace, returning the new state
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert!(!workspace.is_floating());
+    /// assert!(workspace.toggle_floating());
+    /// assert!(workspace.layout_conf().floating);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 2)).unwrap();
+    /// ```
+    pub fn toggle_floating(&mut self) -> bool {
+        self.floating = !self.floating;
+        self.floating
+    }
+
+    /// The [tags][Tag] currently being viewed on this workspace, or None if tag views are not in
+    /// use. When viewing tags, only clients with at least one of the viewed tags are shown.
+    pub fn viewed_tags(&self) -> Option<&BTreeSet<Tag>> {
+        self.viewed_tags.as_ref()
+    }
+
+    pub(crate) fn set_viewed_tags(&mut self, tags: Option<BTreeSet<Tag>>) {
+        self.viewed_tags = tags;
+    }
+
+    /// Whether or not the given client should be shown on this workspace based on the current
+    /// tag view. All clients are shown if tag views are not in use.
+    pub fn shows_client(&self, client: &Client) -> bool {
+        match self.viewed_tags {
+            Some(ref tags) => !client.tags.is_disjoint(tags),
+            None => true,
+        }
+    }
+
     /// The number of clients currently on this workspace
     pub fn len(&self) -> usize {
         self.clients.len()
@@ -119,6 +236,48 @@ impl Workspace {
         self.clients.iter()
     }
 
+    /// Iterate over the [Client] IDs contained in this workspace, starting with the focused
+    /// client and wrapping around to the start of the client order. Empty if the workspace has no
+    /// clients.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// workspace.focus_client(2);
+    /// let ids: Vec<Xid> = workspace.iter_from_focused().copied().collect();
+    ///
+    /// assert_eq!(ids, vec![2, 3, 0, 1]);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn iter_from_focused(&self) -> impl Iterator<Item = &Xid> + '_ {
+        self.clients.iter_from_focused()
+    }
+
+    /// Iterate over the [Client] IDs contained in this workspace, starting with the focused
+    /// client and moving backwards through the client order, wrapping around to the end. Empty
+    /// if the workspace has no clients.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// workspace.focus_client(2);
+    /// let ids: Vec<Xid> = workspace.iter_from_focused_rev().copied().collect();
+    ///
+    /// assert_eq!(ids, vec![2, 1, 0, 3]);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn iter_from_focused_rev(&self) -> impl Iterator<Item = &Xid> + '_ {
+        self.clients.iter_from_focused_rev()
+    }
+
     /// The ordered list of [Client] IDs currently contained in this workspace
     ///
     /// # Example
@@ -135,6 +294,28 @@ impl Workspace {
         self.clients.as_vec()
     }
 
+    /// The ID of the client `offset` positions after the focused client, wrapping around at
+    /// either end of the client order. Negative offsets count backwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// assert_eq!(workspace.client_from_focused(2), Some(2));
+    /// assert_eq!(workspace.client_from_focused(-1), Some(3));
+    /// assert_eq!(workspace.client_from_focused(9), Some(1));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn client_from_focused(&self, offset: i32) -> Option<Xid> {
+        self.clients
+            .element(&Selector::FromFocused(offset))
+            .copied()
+    }
+
     /// A reference to the currently focused client if there is one
     ///
     /// # Example
@@ -182,6 +363,85 @@ impl Workspace {
         Ok(())
     }
 
+    /// Add a new client to this workspace after the last client that is not greater than it
+    /// according to `cmp`, without changing which client is focused. If the clients are already
+    /// sorted by `cmp` then they remain sorted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> penrose::Result<()> {
+    /// workspace.add_client(4, &InsertPoint::Last)?;
+    /// assert_eq!(workspace.client_ids(), vec![0, 2, 4]);
+    ///
+    /// workspace.insert_client_sorted_by(3, |a, b| a.cmp(b))?;
+    /// assert_eq!(workspace.client_ids(), vec![0, 2, 3, 4]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// # Ok(())
+    /// # }
+    /// # let mut ws = test_workspace("example", 1);
+    /// # ws.add_client(2, &InsertPoint::Last).unwrap();
+    /// # example(ws).unwrap();
+    /// ```
+    pub fn insert_client_sorted_by<F>(&mut self, id: Xid, cmp: F) -> Result<()>
+    where
+        F: FnMut(&Xid, &Xid) -> Ordering,
+    {
+        let existing = self.clients.element(&Selector::Condition(&|c| *c == id));
+        if existing.is_some() {
+            return Err(perror!("{} is already in this workspace", id));
+        }
+        self.clients.insert_sorted_by(id, cmp);
+
+        Ok(())
+    }
+
+    /// Move the client at index `from` in the client order so that it ends up at index `to`,
+    /// keeping focus on the currently focused client. Returns `false` if either index is out of
+    /// bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> penrose::Result<()> {
+    /// assert_eq!(workspace.client_ids(), vec![0, 1, 2, 3]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    ///
+    /// assert!(workspace.move_client(0, 2));
+    /// assert_eq!(workspace.client_ids(), vec![1, 2, 0, 3]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn move_client(&mut self, from: usize, to: usize) -> bool {
+        self.clients.move_element(from, to)
+    }
+
+    /// Stable sort the clients of this workspace using `cmp`, keeping focus on the currently
+    /// focused client.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> penrose::Result<()> {
+    /// workspace.sort_clients_by(|a, b| b.cmp(a));
+    /// assert_eq!(workspace.client_ids(), vec![3, 2, 1, 0]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn sort_clients_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&Xid, &Xid) -> Ordering,
+    {
+        self.clients.sort_by(cmp);
+    }
+
     /// Focus the client with the given id, returns an option of the previously focused
     /// client if there was one
     ///
@@ -342,8 +602,45 @@ impl Workspace {
         &self.layouts.focused_unchecked().symbol
     }
 
+    /// Take a [WorkspaceView] snapshot of the current state of this workspace, recording it as
+    /// having the given index.
+    pub fn view(&self, index: usize) -> WorkspaceView {
+        WorkspaceView {
+            index,
+            name: self.name.clone(),
+            layout_symbol: self.layout_symbol().to_string(),
+            client_ids: self.client_ids(),
+            focused_client: self.focused_client(),
+        }
+    }
+
+    /// The maximum number of clients in the main area of the currently active Layout
+    pub fn max_main(&self) -> u32 {
+        self.layouts.focused_unchecked().max_main()
+    }
+
+    /// The ratio of the main area to the secondary area of the currently active Layout
+    pub fn main_ratio(&self) -> f32 {
+        self.layouts.focused_unchecked().ratio()
+    }
+
+    /// Set the maximum number of clients in the main area of the currently active Layout
+    pub fn set_max_main(&mut self, max_main: u32) {
+        if let Some(layout) = self.layouts.focused_mut() {
+            layout.set_max_main(max_main);
+        }
+    }
+
+    /// Set the ratio of the main area to the secondary area of the currently active Layout
+    pub fn set_main_ratio(&mut self, ratio: f32) {
+        if let Some(layout) = self.layouts.focused_mut() {
+            layout.set_ratio(ratio);
+        }
+    }
+
     /// The LayoutConf of the currently active Layout. Used by the WindowManager to
-    /// determine when and how the layout function should be applied.
+    /// determine when and how the layout function should be applied. If this workspace is in
+    /// floating mode then the returned LayoutConf will always be floating.
     ///
     /// # Example
     ///
@@ -356,7 +653,15 @@ impl Workspace {
     /// # example(test_workspace("example", 2)).unwrap();
     /// ```
     pub fn layout_conf(&self) -> LayoutConf {
-        self.layouts.focused_unchecked().conf
+        let conf = self.layouts.focused_unchecked().conf;
+        if self.floating {
+            LayoutConf {
+                floating: true,
+                ..conf
+            }
+        } else {
+            conf
+        }
     }
 
     /// Cycle focus through the clients on this workspace, returning the previous and new focused
@@ -394,6 +699,24 @@ impl Workspace {
         }
     }
 
+    /// Whether cycling client focus in `direction` would wrap around the ends of the stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// assert!(workspace.cycle_would_wrap(Backward));
+    /// assert!(!workspace.cycle_would_wrap(Forward));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 3)).unwrap();
+    /// ```
+    pub fn cycle_would_wrap(&self, direction: Direction) -> bool {
+        self.clients.len() > 0 && self.clients.would_wrap(direction)
+    }
+
     /// Drag the focused client through the stack, retaining focus
     ///
     /// # Example
@@ -418,6 +741,28 @@ impl Workspace {
         self.clients.drag_focused(direction).copied()
     }
 
+    /// Swap the focused client with the client with the given ID, retaining focus
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.client_ids(), vec![0, 1, 2]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    ///
+    /// assert_eq!(workspace.swap_focused_with(2), Some(0));
+    /// assert_eq!(workspace.client_ids(), vec![2, 1, 0]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 3)).unwrap();
+    /// ```
+    pub fn swap_focused_with(&mut self, id: Xid) -> Option<Xid> {
+        let index = self.clients.index(&Selector::Condition(&|c| *c == id))?;
+        self.clients.swap_focused(index).copied()
+    }
+
     /// Rotate the client stack in the given direction
     ///
     /// # Example
@@ -444,14 +789,62 @@ impl Workspace {
     }
 
     /// Increase or decrease the number of possible clients in the main area of the current Layout
+    ///
+    /// This is a no-op if the workspace is in floating mode.
     pub fn update_max_main(&mut self, change: Change) {
+        if self.floating {
+            return;
+        }
         if let Some(layout) = self.layouts.focused_mut() {
             layout.update_max_main(change);
         }
     }
 
+    /// Increase or decrease the number of possible clients in the main area of the current Layout,
+    /// keeping it between 1 and `limit`. If `limit_to_clients` is true then the number of clients
+    /// on this workspace is also used as an upper limit. Changes past either end of that range
+    /// are handled according to `overflow`.
+    ///
+    /// This is a no-op if the workspace is in floating mode.
+    pub fn update_max_main_bounded(
+        &mut self,
+        change: Change,
+        limit: Option<u32>,
+        limit_to_clients: bool,
+        overflow: Overflow,
+    ) {
+        if self.floating {
+            return;
+        }
+        let mut upper = limit.unwrap_or(u32::MAX);
+        if limit_to_clients {
+            upper = upper.min(self.clients.len() as u32);
+        }
+        if let Some(layout) = self.layouts.focused_mut() {
+            layout.update_max_main_bounded(change, upper, overflow);
+        }
+    }
+
+    /// Reset max_main and ratio for the current Layout to those of the Layout in `defaults` with
+    /// the same symbol. Returns false if there was no matching Layout in `defaults`.
+    pub fn reset_layout(&mut self, defaults: &[Layout]) -> bool {
+        let layout = self.layouts.focused_mut_unchecked();
+        match defaults.iter().find(|l| l.symbol == layout.symbol) {
+            Some(d) => {
+                layout.reset_to(d);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Increase or decrease the size of the main area for the current Layout
+    ///
+    /// This is a no-op if the workspace is in floating mode.
     pub fn update_main_ratio(&mut self, change: Change, step: f32) {
+        if self.floating {
+            return;
+        }
         if let Some(layout) = self.layouts.focused_mut() {
             layout.update_main_ratio(change, step);
         }
@@ -474,6 +867,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn view_reflects_current_state() {
+        let mut ws = Workspace::new("test", test_layouts());
+        add_n_clients(&mut ws, 3);
+        ws.focus_client(20);
+        let view = ws.view(4);
+
+        ws.remove_client(20);
+
+        assert_eq!(
+            view,
+            WorkspaceView {
+                index: 4,
+                name: "test".into(),
+                layout_symbol: "t".into(),
+                client_ids: vec![30, 20, 10],
+                focused_client: Some(20),
+            }
+        );
+        assert_eq!(view.n_clients(), 3);
+    }
+
     #[test]
     fn ref_to_focused_client_when_empty() {
         let ws = Workspace::new("test", test_layouts());
@@ -578,4 +993,26 @@ mod tests {
 
         assert_eq!(ws.focused_client(), Some(3));
     }
+
+    #[test]
+    fn reset_layout_restores_default_params() {
+        let mut ws = Workspace::new("test", test_layouts());
+        add_n_clients(&mut ws, 3);
+        ws.update_max_main(Change::More);
+        ws.update_main_ratio(Change::More, 0.1);
+
+        assert!(ws.reset_layout(&test_layouts()));
+        assert_eq!(ws.max_main(), 1);
+        assert_eq!(ws.main_ratio(), 0.6);
+    }
+
+    #[test]
+    fn reset_layout_requires_a_matching_symbol() {
+        let mut ws = Workspace::new("test", test_layouts());
+        ws.update_max_main(Change::More);
+        let other = vec![Layout::new("o", LayoutConf::default(), mock_layout, 3, 0.2)];
+
+        assert!(!ws.reset_layout(&other));
+        assert_eq!(ws.max_main(), 2);
+    }
 }
@@ -68,9 +68,18 @@ pub enum Atom {
     /// _NET_DESKTOP_NAMES
     #[strum(serialize = "_NET_DESKTOP_NAMES")]
     NetDesktopNames,
+    /// _NET_FRAME_EXTENTS
+    #[strum(serialize = "_NET_FRAME_EXTENTS")]
+    NetFrameExtents,
     /// _NET_NUMBER_OF_DESKTOPS
     #[strum(serialize = "_NET_NUMBER_OF_DESKTOPS")]
     NetNumberOfDesktops,
+    /// _NET_REQUEST_FRAME_EXTENTS
+    #[strum(serialize = "_NET_REQUEST_FRAME_EXTENTS")]
+    NetRequestFrameExtents,
+    /// _NET_STARTUP_ID
+    #[strum(serialize = "_NET_STARTUP_ID")]
+    NetStartupId,
     /// _NET_SUPPORTED
     #[strum(serialize = "_NET_SUPPORTED")]
     NetSupported,
@@ -89,18 +98,51 @@ pub enum Atom {
     /// _NET_SYSTEM_TRAY_S0
     #[strum(serialize = "_NET_SYSTEM_TRAY_S0")]
     NetSystemTrayS0,
+    /// _NET_WM_ALLOWED_ACTIONS
+    #[strum(serialize = "_NET_WM_ALLOWED_ACTIONS")]
+    NetWmAllowedActions,
     /// _NET_WM_DESKTOP
     #[strum(serialize = "_NET_WM_DESKTOP")]
     NetWmDesktop,
     /// _NET_WM_NAME
     #[strum(serialize = "_NET_WM_NAME")]
     NetWmName,
+    /// _NET_WM_PID
+    #[strum(serialize = "_NET_WM_PID")]
+    NetWmPid,
+    /// _NET_WM_STARTUP_ID
+    #[strum(serialize = "_NET_WM_STARTUP_ID")]
+    NetWmStartupId,
     /// _NET_WM_STATE
     #[strum(serialize = "_NET_WM_STATE")]
     NetWmState,
+    /// _NET_WM_STATE_ABOVE
+    #[strum(serialize = "_NET_WM_STATE_ABOVE")]
+    NetWmStateAbove,
     /// _NET_WM_STATE_FULLSCREEN
     #[strum(serialize = "_NET_WM_STATE_FULLSCREEN")]
     NetWmStateFullscreen,
+    /// _NET_WM_STATE_HIDDEN
+    #[strum(serialize = "_NET_WM_STATE_HIDDEN")]
+    NetWmStateHidden,
+    /// _NET_WM_STATE_MAXIMIZED_HORZ
+    #[strum(serialize = "_NET_WM_STATE_MAXIMIZED_HORZ")]
+    NetWmStateMaximizedHorz,
+    /// _NET_WM_STATE_MAXIMIZED_VERT
+    #[strum(serialize = "_NET_WM_STATE_MAXIMIZED_VERT")]
+    NetWmStateMaximizedVert,
+    /// _NET_WM_STATE_SKIP_PAGER
+    #[strum(serialize = "_NET_WM_STATE_SKIP_PAGER")]
+    NetWmStateSkipPager,
+    /// _NET_WM_STATE_SKIP_TASKBAR
+    #[strum(serialize = "_NET_WM_STATE_SKIP_TASKBAR")]
+    NetWmStateSkipTaskbar,
+    /// _NET_WM_STATE_STICKY
+    #[strum(serialize = "_NET_WM_STATE_STICKY")]
+    NetWmStateSticky,
+    /// _NET_WM_WINDOW_OPACITY
+    #[strum(serialize = "_NET_WM_WINDOW_OPACITY")]
+    NetWmWindowOpacity,
     /// _NET_WM_WINDOW_TYPE
     #[strum(serialize = "_NET_WM_WINDOW_TYPE")]
     NetWmWindowType,
@@ -111,6 +153,26 @@ pub enum Atom {
     #[strum(serialize = "_XEMBED_INFO")]
     XEmbedInfo,
 
+    // Allowed actions
+    /// _NET_WM_ACTION_MOVE
+    #[strum(serialize = "_NET_WM_ACTION_MOVE")]
+    NetWmActionMove,
+    /// _NET_WM_ACTION_RESIZE
+    #[strum(serialize = "_NET_WM_ACTION_RESIZE")]
+    NetWmActionResize,
+    /// _NET_WM_ACTION_MINIMIZE
+    #[strum(serialize = "_NET_WM_ACTION_MINIMIZE")]
+    NetWmActionMinimize,
+    /// _NET_WM_ACTION_FULLSCREEN
+    #[strum(serialize = "_NET_WM_ACTION_FULLSCREEN")]
+    NetWmActionFullscreen,
+    /// _NET_WM_ACTION_CHANGE_DESKTOP
+    #[strum(serialize = "_NET_WM_ACTION_CHANGE_DESKTOP")]
+    NetWmActionChangeDesktop,
+    /// _NET_WM_ACTION_CLOSE
+    #[strum(serialize = "_NET_WM_ACTION_CLOSE")]
+    NetWmActionClose,
+
     // Window Types
     /// _NET_WM_WINDOW_TYPE_DESKTOP
     #[strum(serialize = "_NET_WM_WINDOW_TYPE_DESKTOP")]
@@ -183,14 +245,27 @@ pub const EWMH_SUPPORTED_ATOMS: &[Atom] = &[
     Atom::NetClientListStacking,
     Atom::NetCurrentDesktop,
     Atom::NetDesktopNames,
+    Atom::NetFrameExtents,
     Atom::NetNumberOfDesktops,
+    Atom::NetRequestFrameExtents,
     Atom::NetSupported,
     Atom::NetSupportingWmCheck,
     // Atom::NetSystemTrayS0,
     // Atom::NetSystemTrayOpcode,
     // Atom::NetSystemTrayOrientationHorz,
+    Atom::NetWmActionChangeDesktop,
+    Atom::NetWmActionClose,
+    Atom::NetWmActionFullscreen,
+    Atom::NetWmActionMinimize,
+    Atom::NetWmActionMove,
+    Atom::NetWmActionResize,
+    Atom::NetWmAllowedActions,
     Atom::NetWmName,
     Atom::NetWmState,
+    Atom::NetWmStateAbove,
     Atom::NetWmStateFullscreen,
+    Atom::NetWmStateHidden,
+    Atom::NetWmStateSkipPager,
+    Atom::NetWmStateSkipTaskbar,
     Atom::NetWmWindowType,
 ];
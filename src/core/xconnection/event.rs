@@ -2,7 +2,7 @@
 use crate::core::{
     bindings::{KeyCode, MouseEvent},
     data_types::{Point, Region},
-    xconnection::{Atom, Result, XAtomQuerier, Xid},
+    xconnection::{Atom, Result, XAtomQuerier, XError, Xid},
 };
 
 use std::convert::TryFrom;
@@ -33,6 +33,8 @@ pub enum XEvent {
     KeyPress(KeyCode),
     /// The mouse pointer has left the current client window
     Leave(PointerChange),
+    /// The keyboard mapping has changed so key codes need to be re-resolved
+    MappingNotify,
     /// A client window is requesting to be positioned and rendered on the screen.
     MapRequest(Xid, bool),
     /// The mouse has moved or a mouse button has been pressed
@@ -40,7 +42,7 @@ pub enum XEvent {
     /// A client property has changed in some way
     PropertyNotify(PropertyEvent),
     /// A randr action has occured (new outputs, resolution change etc)
-    RandrNotify,
+    RandrNotify(RandrChange),
     /// Focus has moved to a different screen
     ScreenChange,
     /// A client is being unmapped
@@ -59,16 +61,58 @@ impl std::fmt::Display for XEvent {
             XEvent::Destroy(_) => write!(f, "Destroy"),
             XEvent::KeyPress(_) => write!(f, "KeyPress"),
             XEvent::Leave(_) => write!(f, "Leave"),
+            XEvent::MappingNotify => write!(f, "MappingNotify"),
             XEvent::MapRequest(_, _) => write!(f, "MapRequest"),
             XEvent::MouseEvent(_) => write!(f, "MouseEvent"),
             XEvent::PropertyNotify(_) => write!(f, "PropertyNotify"),
-            XEvent::RandrNotify => write!(f, "RandrNotify"),
+            XEvent::RandrNotify(_) => write!(f, "RandrNotify"),
             XEvent::ScreenChange => write!(f, "ScreenChange"),
             XEvent::UnmapNotify(_) => write!(f, "UnmapNotify"),
         }
     }
 }
 
+/// The details of what changed in a [XEvent::RandrNotify] event.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RandrChange {
+    /// An output was connected or disconnected
+    Output {
+        /// The name of the output (e.g. "DP-2")
+        name: String,
+        /// Whether or not the output is now connected
+        connected: bool,
+        /// The CRTC driving this output, if it has been assigned one
+        crtc: Option<Xid>,
+    },
+    /// The geometry of a CRTC changed. A CRTC that has been disabled will have a zero sized region.
+    Crtc {
+        /// The ID of the CRTC that changed
+        crtc: Xid,
+        /// The new position and size of the CRTC
+        region: Region,
+    },
+    /// The event did not carry details of what changed so the current state needs to be queried
+    Unknown,
+}
+
+impl RandrChange {
+    /// Whether or not this change may have altered the connected screens.
+    ///
+    /// Newly connected outputs that have not yet been assigned a CRTC are not visible as screens
+    /// until a subsequent CRTC change is received.
+    pub fn affects_screens(&self) -> bool {
+        !matches!(
+            self,
+            RandrChange::Output {
+                connected: true,
+                crtc: None,
+                ..
+            }
+        )
+    }
+}
+
 /// Known common client message formats.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ClientMessageKind {
@@ -88,6 +132,49 @@ pub enum ClientMessageKind {
     XEmbedNotify(Xid, Xid),
     /// Inform an embedded window that it is now active
     XEmbedWindowActivate(Xid, Xid),
+    /// Request a change to one of the states in the `_NET_WM_STATE` property of a window
+    ///
+    /// Args are the id of the root window, the id of the window whose state is changing, the
+    /// change to make and the state [Atom] being changed.
+    WmState(Xid, Xid, WmStateAction, Atom),
+}
+
+/// The change to make to a state in the `_NET_WM_STATE` property of a window.
+///
+/// The discriminants match the values used in the first data field of `_NET_WM_STATE` client
+/// messages.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WmStateAction {
+    /// _NET_WM_STATE_REMOVE: unset the state
+    Remove = 0,
+    /// _NET_WM_STATE_ADD: set the state
+    Add = 1,
+    /// _NET_WM_STATE_TOGGLE: set the state if it is currently unset and vice versa
+    Toggle = 2,
+}
+
+impl WmStateAction {
+    /// Parse the action from the first data field of a `_NET_WM_STATE` client message,
+    /// returning `None` for unknown values.
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Remove),
+            1 => Some(Self::Add),
+            2 => Some(Self::Toggle),
+            _ => None,
+        }
+    }
+
+    /// Whether or not the state should be set after applying this action to a state whose
+    /// current presence is `present`.
+    pub fn apply(&self, present: bool) -> bool {
+        match self {
+            Self::Remove => false,
+            Self::Add => true,
+            Self::Toggle => !present,
+        }
+    }
 }
 
 impl ClientMessageKind {
@@ -137,6 +224,15 @@ impl ClientMessageKind {
             ClientMessageKind::XEmbedModalityOn(id, other) => xembed_msg(*id, *other, modality_on),
             ClientMessageKind::XEmbedNotify(id, other) => xembed_msg(*id, *other, notify),
             ClientMessageKind::XEmbedWindowActivate(id, other) => xembed_msg(*id, *other, activate),
+
+            // https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407959456
+            ClientMessageKind::WmState(root_id, id, action, state) => {
+                let atom = Atom::NetWmState.as_ref();
+                // The final 1 marks the source of the request as a normal application
+                let data = &[*action as u32, q.atom_id(state.as_ref())?, 0, 1, 0];
+                let mask = ClientEventMask::SubstructureNotify;
+                Ok(ClientMessage::new(*id, mask, atom, data.into()).with_destination(*root_id))
+            }
         }
     }
 }
@@ -164,6 +260,39 @@ pub enum ClientMessageData {
 }
 
 impl ClientMessageData {
+    /// Build client message data in the given format (8, 16 or 32 bits per value) from raw
+    /// values.
+    ///
+    /// Fails if the format is not one of 8, 16 or 32, if the number of values does not match the
+    /// format (20, 10 or 5 values respectively) or if any value is too large for the format.
+    pub fn try_from_format(format: u8, data: &[u32]) -> Result<Self> {
+        let expected = match format {
+            8 => 20,
+            16 => 10,
+            32 => 5,
+            _ => return Err(XError::InvalidClientMessageData(format)),
+        };
+        if data.len() != expected {
+            return Err(XError::InvalidClientMessageLength(
+                format,
+                expected,
+                data.len(),
+            ));
+        }
+
+        let max = u32::MAX >> (32 - format as u32);
+        if let Some(&v) = data.iter().find(|&&v| v > max) {
+            return Err(XError::InvalidClientMessageValue(format, v));
+        }
+
+        let err = |_| XError::InvalidClientMessageData(format);
+        match format {
+            8 => Self::try_from(cast_slice!(data, u8).as_slice()).map_err(err),
+            16 => Self::try_from(cast_slice!(data, u16).as_slice()).map_err(err),
+            _ => Self::try_from(data).map_err(err),
+        }
+    }
+
     /// Convert this client message into a single data format
     ///
     /// The number of raw values will be maintained but this allows you to have a consistant
@@ -227,6 +356,8 @@ pub struct ClientMessage {
     /// The data type being set
     pub dtype: String,
     data: ClientMessageData,
+    #[cfg_attr(feature = "serde", serde(default))]
+    destination: Option<Xid>,
 }
 
 impl ClientMessage {
@@ -242,13 +373,41 @@ impl ClientMessage {
             mask,
             dtype: dtype.into(),
             data,
+            destination: None,
         }
     }
 
+    /// Try to build a new ClientMessage from raw data values in the given format. See
+    /// [ClientMessageData::try_from_format] for the validation that is applied to `data`.
+    pub fn try_from_data(
+        id: Xid,
+        mask: ClientEventMask,
+        dtype: impl Into<String>,
+        format: u8,
+        data: &[u32],
+    ) -> Result<Self> {
+        let data = ClientMessageData::try_from_format(format, data)?;
+        Ok(Self::new(id, mask, dtype, data))
+    }
+
+    /// Send this message to `destination` rather than to the window it is about. This is needed
+    /// for messages such as `_NET_WM_STATE` requests that are sent to the root window.
+    pub fn with_destination(mut self, destination: Xid) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
     /// The raw data being sent in this message
     pub fn data(&self) -> &ClientMessageData {
         &self.data
     }
+
+    /// The ID of the window that this message should be sent to. Unless set using
+    /// [with_destination][ClientMessage::with_destination] this is the window the message is
+    /// about.
+    pub fn destination(&self) -> Xid {
+        self.destination.unwrap_or(self.id)
+    }
 }
 
 /// A configure request or notification when a client changes position or size
@@ -261,6 +420,39 @@ pub struct ConfigureEvent {
     pub r: Region,
     /// Is this window the root window?
     pub is_root: bool,
+    /// The new border width if it is being changed.
+    ///
+    /// For configure requests this is reported for completeness only: the [WindowManager][1]
+    /// does not allow clients to set their own border width.
+    ///
+    /// [1]: crate::core::manager::WindowManager
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub border_width: Option<u32>,
+    /// The sibling window that `stack_mode` is relative to (if any)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sibling: Option<Xid>,
+    /// The requested position of the window in the stacking order (if any)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stack_mode: Option<StackMode>,
+}
+
+/// The position of a window in the stacking order relative to its siblings.
+///
+/// See the X11 protocol documentation for ConfigureWindow for details of how each mode behaves
+/// with and without a sibling window being specified.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StackMode {
+    /// Place the window above the sibling (or all siblings)
+    Above,
+    /// Place the window below the sibling (or all siblings)
+    Below,
+    /// Raise the window if the sibling (or any sibling) occludes it
+    TopIf,
+    /// Lower the window if it occludes the sibling (or any sibling)
+    BottomIf,
+    /// Raise the window if it is occluded, otherwise lower it if it occludes others
+    Opposite,
 }
 
 /// A notification that a window has become visible
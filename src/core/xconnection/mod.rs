@@ -9,7 +9,7 @@
 //! [1]: crate::core::manager::WindowManager
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyPress, MouseBindings},
+        bindings::{KeyBindings, KeyCode, KeyGrabReport, KeyPress, MouseBindings},
         client::Client,
         data_types::{Point, Region},
         screen::Screen,
@@ -19,6 +19,13 @@ use crate::{
 
 use penrose_proc::stubbed_companion_trait;
 
+#[cfg(any(feature = "xcb", feature = "x11rb"))]
+use std::os::unix::io::RawFd;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
 pub mod atom;
 pub mod event;
 pub mod property;
@@ -28,7 +35,7 @@ pub use atom::{
 };
 pub use event::{
     ClientEventMask, ClientMessage, ClientMessageData, ClientMessageKind, ConfigureEvent,
-    ExposeEvent, PointerChange, PropertyEvent, XEvent,
+    ExposeEvent, PointerChange, PropertyEvent, RandrChange, StackMode, WmStateAction, XEvent,
 };
 pub use property::{
     MapState, Prop, WindowAttributes, WindowClass, WindowState, WmHints, WmNormalHints,
@@ -40,6 +47,9 @@ pub type Xid = u32;
 
 const WM_NAME: &str = "penrose";
 
+// How often to poll for new events when waiting for an event without a file descriptor to wait on
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
 /// Enum to store the various ways that operations can fail in X traits
 #[derive(thiserror::Error, Debug)]
 pub enum XError {
@@ -51,10 +61,22 @@ pub enum XError {
     #[error("Invalid client message format: {0} (expected 8, 16 or 32)")]
     InvalidClientMessageData(u8),
 
+    /// Client data had the wrong number of values for its format
+    #[error("Format {0} client messages require {1} data values, got {2}")]
+    InvalidClientMessageLength(u8, usize, usize),
+
+    /// A client data value was too large for its format
+    #[error("{1} does not fit in a format {0} client message value")]
+    InvalidClientMessageValue(u8, u32),
+
     /// The requested property is not set for the given client
     #[error("The {0} property is not set for client {1}")]
     MissingProperty(String, Xid),
 
+    /// A property was set but did not have the expected type
+    #[error("Expected {0} to be a {1} property, got {2}")]
+    UnexpectedPropertyType(String, String, String),
+
     /// A generic error type for use in user code when needing to construct
     /// a simple [XError].
     #[error("Unhandled error: {0}")]
@@ -92,6 +114,65 @@ pub enum XError {
     X11rb(#[from] crate::x11rb::X11rbError),
 }
 
+impl XError {
+    /// Whether or not this error means that the connection to the X server can no longer be
+    /// used. Other errors (such as requests referencing a window that has already been
+    /// destroyed) only affect the request that raised them.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::ConnectionClosed => true,
+            #[cfg(feature = "xcb")]
+            Self::Xcb(e) => e.is_fatal(),
+            #[cfg(feature = "x11rb")]
+            Self::X11rb(e) => e.is_fatal(),
+            _ => false,
+        }
+    }
+}
+
+fn unexpected_prop_type(name: &str, expected: &str, got: Prop) -> XError {
+    XError::UnexpectedPropertyType(name.into(), expected.into(), format!("{:?}", got))
+}
+
+// Repeatedly poll for an event until one arrives or the timeout expires. This is only used when
+// there is no connection file descriptor to wait on.
+pub(crate) fn poll_for_event_with_timeout<F>(timeout: Duration, poll: F) -> Result<Option<XEvent>>
+where
+    F: Fn() -> Result<Option<XEvent>>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(event) = poll()? {
+            return Ok(Some(event));
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(EVENT_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+// Block until `fd` is readable or the timeout expires, returning whether or not it is readable.
+// Being interrupted by a signal is treated as the timeout expiring.
+#[cfg(any(feature = "xcb", feature = "x11rb"))]
+pub(crate) fn wait_for_readable(fd: RawFd, timeout: Duration) -> std::io::Result<bool> {
+    use nix::{
+        errno::Errno,
+        poll::{poll, PollFd, PollFlags},
+    };
+
+    let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+    let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+    match poll(&mut fds, timeout_ms) {
+        Ok(n) => Ok(n > 0),
+        Err(Errno::EINTR) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
 /// Result type for errors raised by X traits
 pub type Result<T> = std::result::Result<T, XError>;
 
@@ -105,12 +186,16 @@ pub enum ClientConfig {
     Position(Region),
     /// Mark this window as stacking on top of its peers
     StackAbove,
+    /// Restack this window relative to an optional sibling window
+    Stack(StackMode, Option<Xid>),
 }
 
 /// Attributes for an X11 client window (not all are curently implemented)
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ClientAttr {
+    /// Background color as an rgb hex value
+    BackgroundColor(u32),
     /// Border color as an argb hex value
     BorderColor(u32),
     /// Set the pre-defined client event mask
@@ -136,6 +221,10 @@ pub trait XAtomQuerier {
     fn atom_name(&self, atom: Xid) -> Result<String>;
 
     /// Fetch or intern an atom by name
+    ///
+    /// Names are not restricted to the known [Atom] variants: any other name will be interned
+    /// with the X server the first time that it is requested, allowing custom properties to be
+    /// used.
     #[stub(Err(XError::Raw("mocked".into())))]
     fn atom_id(&self, name: &str) -> Result<Xid>;
 }
@@ -151,6 +240,16 @@ pub trait XState: XAtomQuerier {
     #[stub(Ok(vec![]))]
     fn current_screens(&self) -> Result<Vec<Screen>>;
 
+    /// Determine the currently connected [screens][Screen] along with the name of the RandR
+    /// output (e.g. "DP-2") displaying each of them, if known.
+    ///
+    /// The default implementation does not know any output names.
+    fn current_outputs(&self) -> Result<Vec<(Screen, Option<String>)>> {
+        let screens = self.current_screens()?;
+
+        Ok(screens.into_iter().map(|s| (s, None)).collect())
+    }
+
     /// Determine the current (x,y) position of the cursor relative to the root window.
     #[stub(Ok(Point::default()))]
     fn cursor_position(&self) -> Result<Point>;
@@ -184,6 +283,21 @@ pub trait XEventHandler {
     #[stub(Err(XError::Raw("mocked".into())))]
     fn wait_for_event(&self) -> Result<XEvent>;
 
+    /// Return the next event from the X server as an [XEvent] if there is one, without blocking
+    #[stub(Ok(None))]
+    fn poll_for_event(&self) -> Result<Option<XEvent>>;
+
+    /// Wait for up to `timeout` for the next event from the X server, returning `None` if no
+    /// event arrives in time.
+    ///
+    /// This may return `None` before `timeout` has elapsed if the only events that arrived were
+    /// ones that are not converted to an [XEvent]. The default implementation polls for events
+    /// every few milliseconds: implementations with access to the file descriptor of their
+    /// connection should wait on that instead.
+    fn wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>> {
+        poll_for_event_with_timeout(timeout, || self.poll_for_event())
+    }
+
     /// Send an X event to the target client
     ///
     /// The `msg` being sent can be composed by hand or, for known common message types, generated
@@ -221,6 +335,13 @@ pub trait XClientHandler {
     #[stub(Ok(()))]
     fn focus_client(&self, id: Xid) -> Result<()>;
 
+    /// Move input focus to the root window so that no client has focus.
+    ///
+    /// This uses the time of the last event received from the X server rather than the current
+    /// time so that the change is ordered correctly against focus changes made by clients.
+    #[stub(Ok(()))]
+    fn focus_root(&self) -> Result<()>;
+
     /// Map a known penrose [Client] if it is not currently visible
     fn map_client_if_needed(&self, win: Option<&mut Client>) -> Result<()> {
         if let Some(c) = win {
@@ -283,16 +404,90 @@ pub trait XClientProperties {
      *  equivaled to those implemented here.
      */
 
-    /// Check to see if a given client window supports a particular protocol or not
-    fn client_supports_protocol(&self, id: Xid, proto: &str) -> Result<bool> {
-        match self.get_prop(id, Atom::WmProtocols.as_ref()) {
-            Ok(Prop::Atom(protocols)) => Ok(protocols.iter().any(|p| p == proto)),
-            Ok(p) => Err(XError::Raw(format!("Expected atoms, got {:?}", p))),
-            Err(XError::MissingProperty(_, _)) => Ok(false),
+    /// Fetch a string property for a client, where `name` is either a known [Atom] or the name of
+    /// a custom property.
+    fn get_string_prop(&self, id: Xid, name: &str) -> Result<Vec<String>> {
+        match self.get_prop(id, name)? {
+            Prop::UTF8String(strs) => Ok(strs),
+            p => Err(unexpected_prop_type(name, "string", p)),
+        }
+    }
+
+    /// Fetch the first string of a string property for a client, returning an empty string if the
+    /// property is set but contains no values.
+    ///
+    /// Both `STRING` (Latin-1) and `UTF8_STRING` properties are converted to UTF-8.
+    fn get_utf8_prop(&self, id: Xid, name: &str) -> Result<String> {
+        let strs = self.get_string_prop(id, name)?;
+        Ok(strs.into_iter().next().unwrap_or_default())
+    }
+
+    /// Fetch the `WM_CLASS` property of a client as an `(instance, class)` pair.
+    ///
+    /// Missing components are returned as empty strings.
+    fn get_wm_class(&self, id: Xid) -> Result<(String, String)> {
+        let strs = self.get_string_prop(id, Atom::WmClass.as_ref())?;
+        let mut strs = strs.into_iter();
+        let instance = strs.next().unwrap_or_default();
+        let class = strs.next().unwrap_or_default();
+
+        Ok((instance, class))
+    }
+
+    /// Set a string property for a client, where `name` is either a known [Atom] or the name of
+    /// a custom property.
+    fn set_string_prop(&self, id: Xid, name: &str, val: &[&str]) -> Result<()> {
+        let strs = val.iter().map(|s| s.to_string()).collect();
+        self.change_prop(id, name, Prop::UTF8String(strs))
+    }
+
+    /// Fetch a cardinal property for a client, where `name` is either a known [Atom] or the name
+    /// of a custom property.
+    fn get_cardinal(&self, id: Xid, name: &str) -> Result<u32> {
+        match self.get_prop(id, name)? {
+            Prop::Cardinal(val) => Ok(val),
+            p => Err(unexpected_prop_type(name, "cardinal", p)),
+        }
+    }
+
+    /// Set a cardinal property for a client, where `name` is either a known [Atom] or the name of
+    /// a custom property.
+    fn set_cardinal(&self, id: Xid, name: &str, val: u32) -> Result<()> {
+        self.change_prop(id, name, Prop::Cardinal(val))
+    }
+
+    /// Fetch an atom list property for a client, where `name` is either a known [Atom] or the
+    /// name of a custom property.
+    fn get_atom_list_prop(&self, id: Xid, name: &str) -> Result<Vec<String>> {
+        match self.get_prop(id, name)? {
+            Prop::Atom(atoms) => Ok(atoms),
+            p => Err(unexpected_prop_type(name, "atom", p)),
+        }
+    }
+
+    /// Set an atom list property for a client, where `name` is either a known [Atom] or the name
+    /// of a custom property. The atoms themselves may also be custom.
+    fn set_atom_list_prop(&self, id: Xid, name: &str, atoms: &[&str]) -> Result<()> {
+        let atoms = atoms.iter().map(|s| s.to_string()).collect();
+        self.change_prop(id, name, Prop::Atom(atoms))
+    }
+
+    /// The names of the protocols listed in the `WM_PROTOCOLS` property of a given client window.
+    /// Clients without the property set support no protocols.
+    fn client_protocols(&self, id: Xid) -> Result<Vec<String>> {
+        match self.get_atom_list_prop(id, Atom::WmProtocols.as_ref()) {
+            Ok(protocols) => Ok(protocols),
+            Err(XError::MissingProperty(_, _)) => Ok(vec![]),
             Err(e) => Err(e),
         }
     }
 
+    /// Check to see if a given client window supports a particular protocol or not
+    fn client_supports_protocol(&self, id: Xid, proto: &str) -> Result<bool> {
+        let protocols = self.client_protocols(id)?;
+        Ok(protocols.iter().any(|p| p == proto))
+    }
+
     /// Check to see if a given client accepts input focus
     fn client_accepts_focus(&self, id: Xid) -> bool {
         match self.get_prop(id, Atom::WmHints.as_ref()) {
@@ -301,30 +496,53 @@ pub trait XClientProperties {
         }
     }
 
+    /// Add or remove a single state from the `_NET_WM_STATE` property of the given client ID,
+    /// leaving any other states that are currently set in place.
+    fn set_client_wm_state(&self, id: Xid, state: Atom, present: bool) -> Result<()> {
+        let current = self.get_atom_list_prop(id, Atom::NetWmState.as_ref());
+        let mut data = current.unwrap_or_default();
+        data.retain(|s| s != state.as_ref());
+        if present {
+            data.push(state.as_ref().to_string());
+        }
+
+        self.change_prop(id, Atom::NetWmState.as_ref(), Prop::Atom(data))
+    }
+
+    /// Apply a [WmStateAction] to a single state in the `_NET_WM_STATE` property of the given
+    /// client ID, returning whether or not the state is now set.
+    fn update_client_wm_state(&self, id: Xid, state: Atom, action: WmStateAction) -> Result<bool> {
+        let current = self.get_atom_list_prop(id, Atom::NetWmState.as_ref());
+        let present = current
+            .unwrap_or_default()
+            .iter()
+            .any(|s| s == state.as_ref());
+        let wanted = action.apply(present);
+        self.set_client_wm_state(id, state, wanted)?;
+
+        Ok(wanted)
+    }
+
     /// Toggle the fullscreen state of the given client ID with the X server
     fn toggle_client_fullscreen(&self, id: Xid, client_is_fullscreen: bool) -> Result<()> {
-        let data = if client_is_fullscreen {
-            vec![]
-        } else {
-            vec![Atom::NetWmStateFullscreen.as_ref().to_string()]
-        };
+        let fullscreen = !client_is_fullscreen;
+        self.set_client_wm_state(id, Atom::NetWmStateFullscreen, fullscreen)
+    }
 
-        self.change_prop(id, Atom::NetWmState.as_ref(), Prop::Atom(data))
+    /// Set the hidden (minimized) state of the given client ID with the X server
+    fn set_client_hidden(&self, id: Xid, hidden: bool) -> Result<()> {
+        self.set_client_wm_state(id, Atom::NetWmStateHidden, hidden)
     }
 
     /// Fetch a [client's][1] name proprty following ICCCM / EWMH standards
     ///
     /// [1]: crate::core::client::Client
     fn client_name(&self, id: Xid) -> Result<String> {
-        match self.get_prop(id, Atom::NetWmName.as_ref()) {
-            Ok(Prop::UTF8String(strs)) if !strs.is_empty() && !strs[0].is_empty() => {
-                Ok(strs[0].clone())
-            }
-
-            _ => match self.get_prop(id, Atom::WmName.as_ref()) {
-                Ok(Prop::UTF8String(strs)) if !strs.is_empty() => Ok(strs[0].clone()),
-                Err(e) => Err(e),
-                _ => Ok(String::new()),
+        match self.get_utf8_prop(id, Atom::NetWmName.as_ref()) {
+            Ok(name) if !name.is_empty() => Ok(name),
+            _ => match self.get_utf8_prop(id, Atom::WmName.as_ref()) {
+                Err(XError::UnexpectedPropertyType(_, _, _)) => Ok(String::new()),
+                res => res,
             },
         }
     }
@@ -336,14 +554,17 @@ pub trait XClientProperties {
             return true;
         }
 
-        if let Ok(Prop::UTF8String(strs)) = self.get_prop(id, Atom::WmClass.as_ref()) {
-            if strs.iter().any(|c| floating_classes.contains(&c.as_ref())) {
+        if let Ok((instance, class)) = self.get_wm_class(id) {
+            if [instance, class]
+                .iter()
+                .any(|c| floating_classes.contains(&c.as_ref()))
+            {
                 return true;
             }
         }
 
         let float_types: Vec<&str> = AUTO_FLOAT_WINDOW_TYPES.iter().map(|a| a.as_ref()).collect();
-        if let Ok(Prop::Atom(atoms)) = self.get_prop(id, Atom::NetWmWindowType.as_ref()) {
+        if let Ok(atoms) = self.get_atom_list_prop(id, Atom::NetWmWindowType.as_ref()) {
             atoms.iter().any(|a| float_types.contains(&a.as_ref()))
         } else {
             false
@@ -366,6 +587,16 @@ pub trait XClientConfig {
     #[stub(Err(XError::Raw("mocked".into())))]
     fn get_window_attributes(&self, id: Xid) -> Result<WindowAttributes>;
 
+    /// Send a synthetic ConfigureNotify event to a client informing it of its actual geometry.
+    ///
+    /// Required by ICCCM section 4.1.5 when the window manager constrains or moves a client.
+    #[stub(Ok(()))]
+    fn send_configure_notify(&self, id: Xid, r: Region, border: u32) -> Result<()>;
+
+    /// Repaint the whole of a window using its background
+    #[stub(Ok(()))]
+    fn clear_client(&self, id: Xid) -> Result<()>;
+
     /*
      *  The following default implementations should used if possible.
      *
@@ -455,17 +686,79 @@ pub trait XConn:
     #[stub(Ok(()))]
     fn cleanup(&self) -> Result<()>;
 
+    /// Replace the underlying connection to the X server with a freshly opened one following the
+    /// loss of the previous connection.
+    ///
+    /// Any state tied to the old connection (atoms, the check window etc) must be re-created but
+    /// [init][XConn::init] and [grab_keys][XConn::grab_keys] will be called again by the
+    /// [WindowManager][1] once this returns successfully. Implementations that are unable to
+    /// re-open their connection should return an error, making connection loss fatal: this is
+    /// what the default implementation does.
+    ///
+    /// [1]: crate::core::manager::WindowManager
+    fn reconnect(&mut self) -> Result<()> {
+        Err(XError::Raw("reconnecting is not supported".into()))
+    }
+
     /// Notify the X server that we are intercepting the user specified key bindings and prevent
     /// them being passed through to the underlying applications.
     ///
     /// This is what determines which key press events end up being sent through in the main event
-    /// loop for the WindowManager.
-    #[stub(Ok(()))]
+    /// loop for the WindowManager. Failing to grab an individual key binding should not prevent
+    /// the remaining bindings from being grabbed: the outcome for each key binding is returned in
+    /// the [KeyGrabReport].
+    #[stub(Ok(KeyGrabReport::default()))]
     fn grab_keys(
         &self,
         key_bindings: &KeyBindings<Self>,
         mouse_bindings: &MouseBindings<Self>,
-    ) -> Result<()>;
+    ) -> Result<KeyGrabReport>;
+
+    /// Intercept a single additional key binding without modifying any existing grabs.
+    #[stub(Ok(()))]
+    fn grab_key(&self, key: KeyCode) -> Result<()>;
+
+    /// Release a single previously grabbed key binding without modifying any other grabs.
+    #[stub(Ok(()))]
+    fn ungrab_key(&self, key: KeyCode) -> Result<()>;
+
+    /// Re-query the modifier that NumLock is bound to after the X server reports that the
+    /// keyboard mapping has changed, returning `true` if it is now bound to a different modifier.
+    ///
+    /// Key and mouse bindings are grabbed for each combination of the lock modifiers so when the
+    /// modifier has changed all existing key and mouse grabs must be released before returning,
+    /// leaving them to be grabbed again using [grab_keys][XConn::grab_keys].
+    #[stub(Ok(false))]
+    fn refresh_num_lock_mask(&self) -> Result<bool>;
+
+    /// Actively grab the pointer so that all button presses and releases are reported to the
+    /// window manager rather than the clients under the cursor, until
+    /// [ungrab_pointer][XConn::ungrab_pointer] is called.
+    ///
+    /// The default implementation does nothing, leaving button events to be reported only for
+    /// the grabbed mouse bindings.
+    fn grab_pointer(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Release an active pointer grab taken using [grab_pointer][XConn::grab_pointer].
+    ///
+    /// The default implementation does nothing.
+    fn ungrab_pointer(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Prevent the user from moving the pointer outside of `region`, replacing any previous
+    /// confinement. Passing `None` releases the pointer.
+    ///
+    /// Unlike [grab_pointer][XConn::grab_pointer], confining the pointer must not intercept
+    /// pointer events: clients inside of the region should continue to receive them as normal.
+    /// Implementations should also release any confinement as part of [cleanup][XConn::cleanup].
+    ///
+    /// The default implementation does nothing, leaving the pointer free to move between screens.
+    fn confine_pointer(&self, _region: Option<Region>) -> Result<()> {
+        Ok(())
+    }
 
     /*
      *  The following default implementations should used if possible.
@@ -529,15 +822,22 @@ pub trait XConn:
     }
 
     /// Update the root window properties with the current client details
+    ///
+    /// The stacking order of clients is set separately using [update_client_stacking][1].
+    ///
+    /// [1]: XConn::update_client_stacking
     fn update_known_clients(&self, clients: &[Xid]) -> Result<()> {
-        let root = self.root();
         self.change_prop(
-            root,
+            self.root(),
             Atom::NetClientList.as_ref(),
             Prop::Window(clients.to_vec()),
-        )?;
+        )
+    }
+
+    /// Update the root window property listing clients in their stacking order (bottom to top)
+    fn update_client_stacking(&self, clients: &[Xid]) -> Result<()> {
         self.change_prop(
-            root,
+            self.root(),
             Atom::NetClientListStacking.as_ref(),
             Prop::Window(clients.to_vec()),
         )
@@ -566,6 +866,24 @@ pub trait XConn:
         self.change_prop(id, Atom::NetWmDesktop.as_ref(), Prop::Cardinal(wix as u32))
     }
 
+    /// Set the _NET_FRAME_EXTENTS of a client to `border_px` on all four sides
+    fn set_frame_extents(&self, id: Xid, border_px: u32) -> Result<()> {
+        self.change_prop(
+            id,
+            Atom::NetFrameExtents.as_ref(),
+            Prop::CardinalList(vec![border_px; 4]),
+        )
+    }
+
+    /// Set the _NET_WM_ALLOWED_ACTIONS of a client
+    fn set_allowed_actions(&self, id: Xid, actions: &[Atom]) -> Result<()> {
+        self.change_prop(
+            id,
+            Atom::NetWmAllowedActions.as_ref(),
+            Prop::Atom(actions.iter().map(|a| a.as_ref().to_string()).collect()),
+        )
+    }
+
     /// Check to see if this client is one that we should be handling or not
     #[tracing::instrument(level = "trace", skip(self))]
     fn is_managed_client(&self, c: &Client) -> bool {
@@ -614,6 +932,7 @@ mod mock_conn {
     use super::*;
     use std::{cell::Cell, fmt};
 
+    /// A stub [XConn] for tests with fixed screens that pulls events from a queue
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct MockXConn {
         screens: Vec<Screen>,
@@ -645,7 +964,8 @@ mod mock_conn {
             }
         }
 
-        fn remaining_events(&self) -> Vec<XEvent> {
+        /// The events that have not yet been pulled from this [MockXConn]
+        pub fn remaining_events(&self) -> Vec<XEvent> {
             let remaining = self.events.replace(vec![]);
             self.events.set(remaining.clone());
             remaining
@@ -687,6 +1007,13 @@ mod mock_conn {
                 Ok(next)
             }
 
+            fn mock_poll_for_event(&self) -> Result<Option<XEvent>> {
+                let mut remaining = self.events.replace(vec![]);
+                let next = if remaining.is_empty() { None } else { Some(remaining.remove(0)) };
+                self.events.set(remaining);
+                Ok(next)
+            }
+
             fn mock_send_client_event(&self, _: ClientMessage) -> Result<()> {
                 Ok(())
             }
@@ -711,6 +1038,7 @@ mod mock_conn {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::__test_helpers::RecordingXConn;
 
     use std::str::FromStr;
 
@@ -754,4 +1082,143 @@ mod tests {
             assert_eq!(&conn.client_name(42).unwrap(), expected);
         }
     }
+
+    #[test]
+    fn custom_props_round_trip() {
+        let conn = RecordingXConn::default();
+
+        conn.set_string_prop(1, "_CUSTOM_STR", &["a", "b"]).unwrap();
+        conn.set_cardinal(1, "_CUSTOM_CARD", 42).unwrap();
+        conn.set_atom_list_prop(1, "_CUSTOM_ATOMS", &["_CUSTOM_ATOM", "WM_NAME"])
+            .unwrap();
+
+        assert_eq!(
+            conn.get_string_prop(1, "_CUSTOM_STR").unwrap(),
+            vec!["a", "b"]
+        );
+        assert_eq!(conn.get_cardinal(1, "_CUSTOM_CARD").unwrap(), 42);
+        assert_eq!(
+            conn.get_atom_list_prop(1, "_CUSTOM_ATOMS").unwrap(),
+            vec!["_CUSTOM_ATOM", "WM_NAME"]
+        );
+    }
+
+    test_cases! {
+        get_wm_class;
+        args: (strs: Vec<&str>, expected: (&str, &str));
+
+        case: instance_and_class => (vec!["xterm", "XTerm"], ("xterm", "XTerm"));
+        case: instance_only => (vec!["xterm"], ("xterm", ""));
+        case: empty => (vec![], ("", ""));
+
+        body: {
+            let conn = RecordingXConn::default();
+            conn.set_string_prop(1, Atom::WmClass.as_ref(), &strs).unwrap();
+            let (instance, class) = conn.get_wm_class(1).unwrap();
+
+            assert_eq!((instance.as_ref(), class.as_ref()), expected);
+        }
+    }
+
+    #[test]
+    fn set_client_wm_state_preserves_other_states() {
+        let conn = RecordingXConn::default();
+        let skip = Atom::NetWmStateSkipTaskbar.as_ref();
+        conn.set_atom_list_prop(1, Atom::NetWmState.as_ref(), &[skip])
+            .unwrap();
+
+        conn.toggle_client_fullscreen(1, false).unwrap();
+        assert_eq!(
+            conn.get_atom_list_prop(1, Atom::NetWmState.as_ref())
+                .unwrap(),
+            vec![skip, Atom::NetWmStateFullscreen.as_ref()]
+        );
+
+        conn.toggle_client_fullscreen(1, true).unwrap();
+        assert_eq!(
+            conn.get_atom_list_prop(1, Atom::NetWmState.as_ref())
+                .unwrap(),
+            vec![skip]
+        );
+    }
+
+    test_cases! {
+        update_client_wm_state;
+        args: (initial: bool, action: WmStateAction, expected: bool);
+
+        case: add_unset => (false, WmStateAction::Add, true);
+        case: add_set => (true, WmStateAction::Add, true);
+        case: remove_unset => (false, WmStateAction::Remove, false);
+        case: remove_set => (true, WmStateAction::Remove, false);
+        case: toggle_unset => (false, WmStateAction::Toggle, true);
+        case: toggle_set => (true, WmStateAction::Toggle, false);
+
+        body: {
+            let conn = RecordingXConn::default();
+            let skip = Atom::NetWmStateSkipTaskbar.as_ref();
+            let sticky = Atom::NetWmStateSticky.as_ref();
+            let initial_states = if initial { vec![skip, sticky] } else { vec![skip] };
+            conn.set_atom_list_prop(1, Atom::NetWmState.as_ref(), &initial_states)
+                .unwrap();
+
+            let res = conn.update_client_wm_state(1, Atom::NetWmStateSticky, action);
+            let expected_states = if expected { vec![skip, sticky] } else { vec![skip] };
+
+            assert_eq!(res.unwrap(), expected);
+            assert_eq!(
+                conn.get_atom_list_prop(1, Atom::NetWmState.as_ref()).unwrap(),
+                expected_states
+            );
+        }
+    }
+
+    test_cases! {
+        client_message_data_from_format;
+        args: (format: u8, data: Vec<u32>, expected: Result<ClientMessageData>);
+
+        case: format_8 => (8, vec![255; 20], Ok(ClientMessageData::U8([255; 20])));
+        case: format_16 => (16, vec![7; 10], Ok(ClientMessageData::U16([7; 10])));
+        case: format_32 => (32, vec![u32::MAX; 5], Ok(ClientMessageData::U32([u32::MAX; 5])));
+        case: unknown_format => (24, vec![0; 5], Err(XError::InvalidClientMessageData(24)));
+        case: too_short => (8, vec![0; 5], Err(XError::InvalidClientMessageLength(8, 20, 5)));
+        case: too_long => (32, vec![0; 6], Err(XError::InvalidClientMessageLength(32, 5, 6)));
+        case: value_too_large => (
+            16,
+            vec![0, 0, 0, 70000, 0, 0, 0, 0, 0, 0],
+            Err(XError::InvalidClientMessageValue(16, 70000))
+        );
+
+        body: {
+            let res = ClientMessageData::try_from_format(format, &data);
+            assert_eq!(res.map_err(|e| e.to_string()), expected.map_err(|e| e.to_string()));
+        }
+    }
+
+    #[test]
+    fn wm_state_messages_are_sent_to_the_root_window() {
+        let conn = MockXConn::new(vec![], vec![], vec![]);
+        let kind = ClientMessageKind::WmState(1, 42, WmStateAction::Toggle, Atom::NetWmStateSticky);
+        let msg = kind.as_message(&conn).unwrap();
+        let sticky = conn.atom_id(Atom::NetWmStateSticky.as_ref()).unwrap();
+
+        assert_eq!(msg.id, 42);
+        assert_eq!(msg.destination(), 1);
+        assert_eq!(msg.dtype, Atom::NetWmState.as_ref());
+        assert_eq!(msg.data().as_u32(), vec![2, sticky, 0, 1, 0]);
+    }
+
+    #[test]
+    fn typed_props_reject_the_wrong_type() {
+        let conn = RecordingXConn::default();
+        conn.set_cardinal(1, "_CUSTOM", 42).unwrap();
+
+        assert!(matches!(
+            conn.get_string_prop(1, "_CUSTOM"),
+            Err(XError::UnexpectedPropertyType(_, _, _))
+        ));
+        assert!(matches!(
+            conn.get_cardinal(1, "_MISSING"),
+            Err(XError::MissingProperty(_, 1))
+        ));
+    }
 }
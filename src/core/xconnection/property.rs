@@ -7,6 +7,26 @@ use crate::{
     PenroseError, Result,
 };
 
+/// Decode the raw bytes of a `STRING` or `UTF8_STRING` property into its null separated
+/// component strings.
+///
+/// `STRING` properties are Latin-1 encoded (ICCCM section 2.7.1) so each byte maps directly to a
+/// char. `UTF8_STRING` properties containing invalid UTF-8 have the invalid sequences replaced
+/// rather than discarding the entire value.
+#[cfg(any(feature = "xcb", feature = "x11rb", test))]
+pub(crate) fn decode_string_prop(bytes: &[u8], utf8: bool) -> Vec<String> {
+    let s: String = if utf8 {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    };
+
+    s.trim_matches('\0')
+        .split('\0')
+        .map(|s| s.to_string())
+        .collect()
+}
+
 /// Know property types that should be returnable by XConn impls when they check
 /// window properties.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -18,6 +38,8 @@ pub enum Prop {
     Bytes(Vec<u32>),
     /// A cardinal number
     Cardinal(u32),
+    /// A list of cardinal numbers
+    CardinalList(Vec<u32>),
     /// UTF-8 encoded string data
     UTF8String(Vec<String>),
     /// An X window IDs
@@ -222,7 +244,9 @@ impl WmHints {
 /// data fromat but note that Penrose does not honour the following hints:
 ///   - gravity
 ///   - increment
-///   - aspect ratio
+///
+/// Aspect ratios are only honoured for clients that have been marked as preserving their
+/// aspect ratio.
 ///
 /// [1]: https://www.x.org/releases/X11R7.6/doc/xorg-docs/specs/ICCCM/icccm.html#wm_normal_hints_property
 /// [2]: https://tronche.com/gui/x/xlib/ICC/client-to-window-manager/wm-normal-hints.html
@@ -234,6 +258,10 @@ pub struct WmNormalHints {
     pub(crate) min: Option<Region>,
     pub(crate) max: Option<Region>,
     pub(crate) user_specified: Option<Region>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) min_aspect: Option<(u32, u32)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) max_aspect: Option<(u32, u32)>,
 }
 
 impl WmNormalHints {
@@ -251,9 +279,53 @@ impl WmNormalHints {
             min,
             max,
             user_specified,
+            min_aspect: None,
+            max_aspect: None,
         }
     }
 
+    /// Set the min and max aspect ratios (as width:height pairs) requested by the client
+    pub fn with_aspect(mut self, min: Option<(u32, u32)>, max: Option<(u32, u32)>) -> Self {
+        self.min_aspect = min;
+        self.max_aspect = max;
+        self
+    }
+
+    /// The minimum width:height ratio requested by the client
+    pub fn min_aspect(&self) -> Option<(u32, u32)> {
+        self.min_aspect
+    }
+
+    /// The maximum width:height ratio requested by the client
+    pub fn max_aspect(&self) -> Option<(u32, u32)> {
+        self.max_aspect
+    }
+
+    /// The flags marking which of the hints have been set by the client
+    pub fn flags(&self) -> WmNormalHintsFlags {
+        self.flags
+    }
+
+    /// The base size requested by the client
+    pub fn base(&self) -> Option<Region> {
+        self.base
+    }
+
+    /// The minimum size requested by the client
+    pub fn min(&self) -> Option<Region> {
+        self.min
+    }
+
+    /// The maximum size requested by the client
+    pub fn max(&self) -> Option<Region> {
+        self.max
+    }
+
+    /// The user specified position and size of the client
+    pub fn user_specified(&self) -> Option<Region> {
+        self.user_specified
+    }
+
     /// Try to construct a [WmNormalHints] instance from raw bytes.
     ///
     /// This method expects a slice of 18 u32s corresponding to the C struct layout shown below.
@@ -294,8 +366,14 @@ impl WmNormalHints {
         let (max_w, max_h) = (raw[7], raw[8]);
         let (base_w, base_h) = (raw[15], raw[16]);
 
-        // ignoring increment, aspect ratio, gravity as they are not used in
-        // the main WindowManager logic
+        // ignoring increment and gravity as they are not used in the main WindowManager logic
+        let aspect = |n: u32, d: u32| {
+            if flags.contains(WmNormalHintsFlags::P_ASPECT) && n > 0 && d > 0 {
+                Some((n, d))
+            } else {
+                None
+            }
+        };
 
         let if_set = |x, y, w, h| {
             if w > 0 && h > 0 {
@@ -311,6 +389,8 @@ impl WmNormalHints {
             min: if_set(x, y, min_w, min_h),
             max: if_set(x, y, max_w, max_h),
             user_specified: if_set(x, y, user_w, user_h),
+            min_aspect: aspect(raw[9], raw[10]),
+            max_aspect: aspect(raw[11], raw[12]),
         })
     }
 }
@@ -361,3 +441,40 @@ impl WindowAttributes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        decode_string_prop;
+        args: (bytes: &[u8], utf8: bool, expected: Vec<&str>);
+
+        case: latin1 => (b"caf\xe9", false, vec!["café"]);
+        case: utf8 => ("café".as_bytes(), true, vec!["café"]);
+        case: invalid_utf8_is_replaced => (b"caf\xe9", true, vec!["caf\u{FFFD}"]);
+        case: null_separated => (b"xterm\0XTerm\0", false, vec!["xterm", "XTerm"]);
+
+        body: {
+            assert_eq!(decode_string_prop(bytes, utf8), expected);
+        }
+    }
+
+    test_cases! {
+        wm_normal_hints_aspect;
+        args: (flags: u32, min: Option<(u32, u32)>, max: Option<(u32, u32)>);
+
+        case: flag_set => (WmNormalHintsFlags::P_ASPECT.bits(), Some((4, 3)), Some((16, 9)));
+        case: flag_not_set => (0, None, None);
+
+        body: {
+            let mut raw = [0; 18];
+            raw[0] = flags;
+            raw[9..13].copy_from_slice(&[4, 3, 16, 9]);
+            let hints = WmNormalHints::try_from_bytes(&raw).unwrap();
+
+            assert_eq!(hints.min_aspect(), min);
+            assert_eq!(hints.max_aspect(), max);
+        }
+    }
+}
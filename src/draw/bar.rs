@@ -307,7 +307,7 @@ macro_rules! __impl_status_bar_as_hook {
 
             fn screens_updated(&mut self, wm: &mut WindowManager<X>, dimensions: &[Region]) -> crate::Result<()> {
                 for (id, _) in self.screens.iter() {
-                    self.drw.destroy_client(*id)?;
+                    self.drw.destroy_window(*id)?;
                 }
 
                 if let Err(e) = self.init_for_screens() {
@@ -321,11 +321,31 @@ macro_rules! __impl_status_bar_as_hook {
                 Ok(self.redraw()?)
             }
 
+            fn bar_visibility_changed(&mut self, wm: &mut WindowManager<X>, ix: usize, visible: bool) -> crate::Result<()> {
+                if let Some(&(id, _)) = self.screens.get(ix) {
+                    if visible {
+                        self.drw.map_client(id)?;
+                        self.redraw()?;
+                    } else {
+                        self.drw.unmap_client(id)?;
+                    }
+                }
+
+                self.widgets
+                    .iter_mut()
+                    .try_for_each(|w| w.bar_visibility_changed(wm, ix, visible))
+            }
+
             fn event_handled(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
                 self.widgets.iter_mut().try_for_each(|w| w.event_handled(wm))?;
                 Ok(self.redraw_if_needed()?)
             }
 
+            fn tick(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+                self.widgets.iter_mut().try_for_each(|w| w.tick(wm))?;
+                Ok(self.redraw_if_needed()?)
+            }
+
             fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()>  {
                 self.widgets.iter_mut().try_for_each(|w| w.startup(wm))?;
                 Ok(self.redraw()?)
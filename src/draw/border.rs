@@ -0,0 +1,690 @@
+//! Client borders rendered using a [Draw] backend
+//!
+//! By default penrose indicates focus using the border of each client window that is provided by
+//! the X server: this is cheap but limited to a single solid color. [DrawnBorders] is a [Hook]
+//! that instead paints the border area of each visible client into a set of thin override
+//! redirect windows placed on top of it, allowing for effects such as gradients that can not be
+//! achieved with plain X borders.
+//!
+//! The drawn border occupies the space reserved by the X border of each client so `border_px` in
+//! your [Config][crate::core::config::Config] should be set to the width that you want the drawn
+//! border to be. Clients with a border width of zero (and fullscreen clients) are not framed.
+//!
+//! Example
+//! ```no_run
+//! # use penrose::__test_helpers::*;
+//! use penrose::core::hooks::Hooks;
+//! use penrose::draw::{Color, DrawContext, DrawnBorders};
+//! use penrose::xcb::{XcbDraw, new_xcb_backed_window_manager};
+//!
+//! use std::convert::TryFrom;
+//!
+//! # fn example() -> penrose::Result<()> {
+//! let config = Config::default();
+//! let mut borders = DrawnBorders::new(
+//!     XcbDraw::new()?,
+//!     *config.border_px(),
+//!     Color::try_from("#cc241d")?,
+//!     Color::try_from("#3c3836")?,
+//! );
+//!
+//! // Fade the focused border from red to orange across each edge
+//! borders.set_painter(Box::new(|ctx: &mut dyn DrawContext, _edge, w, h, focused| {
+//!     let steps = 8.0;
+//!     for i in 0..steps as usize {
+//!         let t = i as f64 / steps;
+//!         let color = if focused { (0.8, 0.14 + 0.4 * t, 0.11) } else { (0.24, 0.22, 0.21) };
+//!         ctx.color(&color.into());
+//!         ctx.rectangle(w * t, 0.0, w / steps + 1.0, h)?;
+//!     }
+//!     Ok(())
+//! }));
+//!
+//! let hooks: Hooks<_> = vec![Box::new(borders)];
+//! let mut wm = new_xcb_backed_window_manager(config, hooks, logging_error_handler())?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    core::{
+        data_types::{Region, WinType},
+        hooks::Hook,
+        manager::WindowManager,
+        ring::Selector,
+        xconnection::{Atom, ClientConfig, StackMode, XClientConfig, XConn, Xid},
+    },
+    draw::{Color, Draw, DrawContext, Result},
+};
+
+use std::{collections::HashMap, fmt};
+
+/// One of the four edges making up the border of a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BorderEdge {
+    /// The top edge, spanning the full width of the client including the corners
+    Top,
+    /// The bottom edge, spanning the full width of the client including the corners
+    Bottom,
+    /// The left edge, between the top and bottom edges
+    Left,
+    /// The right edge, between the top and bottom edges
+    Right,
+}
+
+/// A function used to paint a single edge of a client border.
+///
+/// Arguments are the context for the edge window, which [BorderEdge] is being painted, the width
+/// and height of the edge window in pixels and whether or not the client currently has focus.
+pub type BorderPainter =
+    Box<dyn FnMut(&mut dyn DrawContext, BorderEdge, f64, f64, bool) -> Result<()>>;
+
+/// The regions covered by each edge of the X border of a client placed at `r` with a border
+/// width of `bpx`.
+///
+/// As with X borders, `r` is the position of the outer corner of the border and the size of the
+/// client window that it surrounds.
+///
+/// ```
+/// # use penrose::core::data_types::Region;
+/// # use penrose::draw::border::{border_edges, BorderEdge};
+/// let edges = border_edges(Region::new(10, 20, 100, 50), 2);
+///
+/// assert_eq!(edges[0], (BorderEdge::Top, Region::new(10, 20, 104, 2)));
+/// assert_eq!(edges[3], (BorderEdge::Right, Region::new(112, 22, 2, 50)));
+/// ```
+pub fn border_edges(r: Region, bpx: u32) -> [(BorderEdge, Region); 4] {
+    let (x, y, w, h) = r.values();
+    let outer_w = w + 2 * bpx;
+
+    [
+        (BorderEdge::Top, Region::new(x, y, outer_w, bpx)),
+        (
+            BorderEdge::Bottom,
+            Region::new(x, y + h + bpx, outer_w, bpx),
+        ),
+        (BorderEdge::Left, Region::new(x, y + bpx, bpx, h)),
+        (BorderEdge::Right, Region::new(x + w + bpx, y + bpx, bpx, h)),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameSpec {
+    region: Region,
+    bpx: u32,
+    focused: bool,
+}
+
+impl FrameSpec {
+    fn same_size(&self, other: &FrameSpec) -> bool {
+        let (_, _, w, h) = self.region.values();
+        let (_, _, ow, oh) = other.region.values();
+
+        self.bpx == other.bpx && (w, h) == (ow, oh)
+    }
+}
+
+#[derive(Debug)]
+struct Frame {
+    spec: FrameSpec,
+    windows: Vec<(BorderEdge, Xid, Region)>,
+}
+
+/// A [Hook] that draws the borders of visible clients using a [Draw] backend.
+///
+/// Frames are created, repositioned and repainted after each event has been handled so that they
+/// follow their clients as they are moved, resized, hidden and focused. Tiled clients are framed
+/// using the region assigned to them by their layout while floating clients are framed using
+/// their current X geometry. Moving a client moves its existing frame while resizing it recreates
+/// the windows used for the frame.
+pub struct DrawnBorders<D>
+where
+    D: Draw + XClientConfig,
+{
+    drw: D,
+    bpx: u32,
+    focused: Color,
+    unfocused: Color,
+    painter: Option<BorderPainter>,
+    frames: HashMap<Xid, Frame>,
+}
+
+impl<D> fmt::Debug for DrawnBorders<D>
+where
+    D: Draw + XClientConfig,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DrawnBorders")
+            .field("drw", &stringify!(self.drw))
+            .field("bpx", &self.bpx)
+            .field("focused", &self.focused)
+            .field("unfocused", &self.unfocused)
+            .field("painter", &self.painter.as_ref().map(|_| "<painter>"))
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
+
+impl<D> DrawnBorders<D>
+where
+    D: Draw + XClientConfig,
+{
+    /// Create a new set of drawn borders that fill the border of each client with a solid color.
+    ///
+    /// `bpx` is the border width used for clients that do not have their own border width set
+    /// and should match the `border_px` of your [Config][crate::core::config::Config].
+    pub fn new(drw: D, bpx: u32, focused: impl Into<Color>, unfocused: impl Into<Color>) -> Self {
+        Self {
+            drw,
+            bpx,
+            focused: focused.into(),
+            unfocused: unfocused.into(),
+            painter: None,
+            frames: HashMap::new(),
+        }
+    }
+
+    /// Replace the solid colors used for each edge with a custom [BorderPainter].
+    ///
+    /// Existing frames are repainted the next time that their client changes.
+    pub fn set_painter(&mut self, painter: BorderPainter) {
+        self.painter = Some(painter);
+    }
+
+    fn desired_frames<X: XConn>(&self, wm: &WindowManager<X>) -> HashMap<Xid, FrameSpec> {
+        let focused = wm.focused_client_id();
+
+        wm.all_clients(&Selector::Any)
+            .into_iter()
+            .filter(|c| !c.is_fullscreen() && wm.client_is_visible(c.id()))
+            .flat_map(|c| {
+                let floating_layout = matches!(
+                    wm.workspace(&Selector::Index(c.workspace())),
+                    Some(ws) if ws.layout_conf().floating
+                );
+                // Only floating clients need a round trip to the X server: the region of a tiled
+                // client is the one that it was last placed in by its layout.
+                let region = match c.tiled_region() {
+                    Some(r) if !c.floating && !floating_layout => r,
+                    _ => wm.conn().client_geometry(c.id()).ok()?,
+                };
+                let bpx = c.border_px().unwrap_or(self.bpx);
+                let spec = FrameSpec {
+                    region,
+                    bpx,
+                    focused: Some(c.id()) == focused,
+                };
+
+                if bpx > 0 {
+                    Some((c.id(), spec))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn destroy_frame(&mut self, id: Xid) -> Result<()> {
+        match self.frames.remove(&id) {
+            Some(frame) => self.destroy_windows(&frame),
+            None => Ok(()),
+        }
+    }
+
+    fn destroy_windows(&mut self, frame: &Frame) -> Result<()> {
+        for &(_, win, _) in frame.windows.iter() {
+            self.drw.destroy_window(win)?;
+        }
+
+        Ok(())
+    }
+
+    // The drawing surface of each window is fixed when it is created so frames can only be moved
+    // in place if the size of their client is unchanged.
+    fn move_frame(&self, frame: Frame, spec: FrameSpec) -> Result<Frame> {
+        let windows = frame
+            .windows
+            .iter()
+            .zip(border_edges(spec.region, spec.bpx).iter())
+            .map(|(&(edge, win, _), &(_, r))| {
+                self.drw
+                    .configure_client(win, &[ClientConfig::Position(r)])?;
+                Ok((edge, win, r))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Frame { spec, windows })
+    }
+
+    fn create_frame(&mut self, spec: FrameSpec) -> Result<Frame> {
+        let windows = border_edges(spec.region, spec.bpx)
+            .iter()
+            .map(|&(edge, r)| {
+                let win =
+                    self.drw
+                        .new_window(WinType::InputOutput(Atom::NetWindowTypeDock), r, false)?;
+                Ok((edge, win, r))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Frame { spec, windows })
+    }
+
+    fn paint_frame(&mut self, client: Xid, frame: &Frame) -> Result<()> {
+        let focused = frame.spec.focused;
+        let color = if focused {
+            self.focused
+        } else {
+            self.unfocused
+        };
+
+        for &(edge, win, r) in frame.windows.iter() {
+            let (_, _, w, h) = r.values();
+            let (w, h) = (w as f64, h as f64);
+            let mut ctx = self.drw.context_for(win)?;
+
+            match self.painter {
+                Some(ref mut painter) => painter(&mut ctx, edge, w, h, focused)?,
+                None => {
+                    ctx.color(&color);
+                    ctx.rectangle(0.0, 0.0, w, h)?;
+                }
+            }
+
+            ctx.flush();
+            self.drw
+                .configure_client(win, &[ClientConfig::Stack(StackMode::Above, Some(client))])?;
+            self.drw.flush(win)?;
+        }
+
+        Ok(())
+    }
+
+    /// Bring the frames of all visible clients in line with the current state of the
+    /// [WindowManager], creating, moving and repainting them as needed.
+    pub fn sync<X: XConn>(&mut self, wm: &WindowManager<X>) -> Result<()> {
+        let desired = self.desired_frames(wm);
+
+        let hidden: Vec<Xid> = self
+            .frames
+            .keys()
+            .filter(|id| !desired.contains_key(id))
+            .copied()
+            .collect();
+
+        for id in hidden {
+            self.destroy_frame(id)?;
+        }
+
+        for (id, spec) in desired {
+            let frame = match self.frames.remove(&id) {
+                Some(frame) if frame.spec == spec => {
+                    self.frames.insert(id, frame);
+                    continue;
+                }
+                Some(frame) if frame.spec.same_size(&spec) => self.move_frame(frame, spec)?,
+                Some(frame) => {
+                    self.destroy_windows(&frame)?;
+                    self.create_frame(spec)?
+                }
+                None => self.create_frame(spec)?,
+            };
+
+            self.paint_frame(id, &frame)?;
+            self.frames.insert(id, frame);
+        }
+
+        Ok(())
+    }
+}
+
+impl<D, X> Hook<X> for DrawnBorders<D>
+where
+    D: Draw + XClientConfig,
+    X: XConn,
+{
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        Ok(self.sync(wm)?)
+    }
+
+    fn remove_client(&mut self, _: &mut WindowManager<X>, id: Xid) -> crate::Result<()> {
+        Ok(self.destroy_frame(id)?)
+    }
+
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        Ok(self.sync(wm)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        __test_helpers::PointerXConn,
+        core::{
+            bindings::{KeyBindings, MouseBindings},
+            config::Config,
+            xconnection::{
+                ConfigureEvent, StubXClientConfig, StubXClientHandler, StubXClientProperties,
+                XEvent, XState,
+            },
+        },
+        draw::DrawError,
+        logging_error_handler,
+    };
+
+    use std::cell::Cell;
+
+    struct NoopContext;
+
+    impl DrawContext for NoopContext {
+        fn font(&mut self, _: &str, _: i32) -> Result<()> {
+            Ok(())
+        }
+        fn color(&mut self, _: &Color) {}
+        fn clear(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn translate(&self, _: f64, _: f64) {}
+        fn set_x_offset(&self, _: f64) {}
+        fn set_y_offset(&self, _: f64) {}
+        fn rectangle(&self, _: f64, _: f64, _: f64, _: f64) -> Result<()> {
+            Ok(())
+        }
+        fn text(&self, _: &str, _: f64, _: (f64, f64)) -> Result<(f64, f64)> {
+            Ok((0.0, 0.0))
+        }
+        fn text_extent(&self, _: &str) -> Result<(f64, f64)> {
+            Ok((0.0, 0.0))
+        }
+        fn flush(&self) {}
+    }
+
+    // Records the windows created, destroyed and moved while syncing frames
+    #[derive(Default)]
+    struct RecordingDraw {
+        next_id: Xid,
+        created: Vec<Region>,
+        destroyed: Vec<Xid>,
+        moved: Cell<Vec<(Xid, Region)>>,
+    }
+
+    impl RecordingDraw {
+        fn take_calls(&mut self) -> (Vec<Region>, Vec<Xid>, Vec<(Xid, Region)>) {
+            (
+                std::mem::take(&mut self.created),
+                std::mem::take(&mut self.destroyed),
+                self.moved.take(),
+            )
+        }
+    }
+
+    impl StubXClientProperties for RecordingDraw {}
+
+    impl StubXClientHandler for RecordingDraw {}
+
+    impl StubXClientConfig for RecordingDraw {
+        fn mock_configure_client(
+            &self,
+            id: Xid,
+            data: &[ClientConfig],
+        ) -> crate::core::xconnection::Result<()> {
+            let mut moved = self.moved.take();
+            for d in data {
+                if let ClientConfig::Position(r) = d {
+                    moved.push((id, *r));
+                }
+            }
+            self.moved.set(moved);
+            Ok(())
+        }
+    }
+
+    impl Draw for RecordingDraw {
+        type Ctx = NoopContext;
+
+        fn new_window(&mut self, _: WinType, r: Region, _: bool) -> Result<Xid> {
+            self.next_id += 1;
+            self.created.push(r);
+            Ok(100 + self.next_id)
+        }
+        fn destroy_window(&mut self, id: Xid) -> Result<()> {
+            self.destroyed.push(id);
+            Ok(())
+        }
+        fn screen_sizes(&self) -> Result<Vec<Region>> {
+            Ok(vec![])
+        }
+        fn register_font(&mut self, _: &str) {}
+        fn context_for(&self, _: Xid) -> Result<Self::Ctx> {
+            Ok(NoopContext)
+        }
+        fn temp_context(&self, _: u32, _: u32) -> Result<Self::Ctx> {
+            Err(DrawError::Raw("not supported".into()))
+        }
+        fn flush(&self, _: Xid) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    type Wm = WindowManager<PointerXConn>;
+
+    fn handle(wm: &mut Wm, event: XEvent) {
+        let mut kb: KeyBindings<PointerXConn> = HashMap::new();
+        let mut mb: MouseBindings<PointerXConn> = HashMap::new();
+        wm.handle_event(event, &mut kb, &mut mb).unwrap();
+    }
+
+    fn configure_request(id: Xid, r: Region) -> XEvent {
+        XEvent::ConfigureRequest(ConfigureEvent {
+            id,
+            r,
+            is_root: false,
+            border_width: None,
+            sibling: None,
+            stack_mode: None,
+        })
+    }
+
+    fn edge_regions(r: Region) -> Vec<Region> {
+        border_edges(r, 2).iter().map(|&(_, r)| r).collect()
+    }
+
+    fn setup() -> (Wm, DrawnBorders<RecordingDraw>) {
+        let wm = WindowManager::new_headless(
+            Config::default(),
+            PointerXConn::new(),
+            vec![],
+            logging_error_handler(),
+        )
+        .unwrap();
+        let borders = DrawnBorders::new(RecordingDraw::default(), 2, 0xffffffff, 0x000000ff);
+
+        (wm, borders)
+    }
+
+    test_cases! {
+        border_edges;
+        args: (r: Region, bpx: u32, expected: [Region; 4]);
+
+        case: single_pixel => (
+            Region::new(0, 0, 10, 10),
+            1,
+            [
+                Region::new(0, 0, 12, 1),
+                Region::new(0, 11, 12, 1),
+                Region::new(0, 1, 1, 10),
+                Region::new(11, 1, 1, 10),
+            ]
+        );
+        case: offset_client => (
+            Region::new(100, 50, 30, 20),
+            3,
+            [
+                Region::new(100, 50, 36, 3),
+                Region::new(100, 73, 36, 3),
+                Region::new(100, 53, 3, 20),
+                Region::new(133, 53, 3, 20),
+            ]
+        );
+
+        body: {
+            let edges = border_edges(r, bpx);
+            let edge_names: Vec<BorderEdge> = edges.iter().map(|&(e, _)| e).collect();
+            let regions: Vec<Region> = edges.iter().map(|&(_, r)| r).collect();
+
+            assert_eq!(
+                edge_names,
+                vec![BorderEdge::Top, BorderEdge::Bottom, BorderEdge::Left, BorderEdge::Right]
+            );
+            assert_eq!(regions, expected.to_vec());
+        }
+    }
+
+    #[test]
+    fn edges_tile_the_border_without_overlapping() {
+        let (r, bpx) = (Region::new(5, 5, 40, 30), 4);
+        let area: u32 = border_edges(r, bpx)
+            .iter()
+            .map(|(_, e)| e.values())
+            .map(|(_, _, w, h)| w * h)
+            .sum();
+
+        assert_eq!(area, (40 + 8) * (30 + 8) - 40 * 30);
+    }
+
+    #[test]
+    fn sync_creates_frames_for_new_clients() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        borders.sync(&wm).unwrap();
+
+        let r = wm.conn().client_geometry(1).unwrap();
+        let (created, destroyed, _) = borders.drw.take_calls();
+
+        assert_eq!(created, edge_regions(r));
+        assert!(destroyed.is_empty());
+    }
+
+    #[test]
+    fn tiled_clients_are_framed_using_their_layout_region() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        let tiled = wm
+            .client(&Selector::WinId(1))
+            .unwrap()
+            .tiled_region()
+            .unwrap();
+
+        // the X geometry of tiled clients is not re-queried when syncing
+        wm.conn().positions.set(HashMap::new());
+        borders.sync(&wm).unwrap();
+        let (created, _, _) = borders.drw.take_calls();
+
+        assert_eq!(created, edge_regions(tiled));
+    }
+
+    #[test]
+    fn sync_is_a_noop_when_nothing_has_changed() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        borders.sync(&wm).unwrap();
+        borders.drw.take_calls();
+        borders.sync(&wm).unwrap();
+
+        assert_eq!(borders.drw.take_calls(), (vec![], vec![], vec![]));
+    }
+
+    #[test]
+    fn sync_moves_frames_when_floating_clients_move() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        wm.toggle_float(&Selector::Focused).unwrap();
+        borders.sync(&wm).unwrap();
+        borders.drw.take_calls();
+
+        let (x, y, w, h) = wm.conn().client_geometry(1).unwrap().values();
+        handle(
+            &mut wm,
+            configure_request(1, Region::new(x + 10, y + 20, w, h)),
+        );
+        borders.sync(&wm).unwrap();
+
+        let r = wm.conn().client_geometry(1).unwrap();
+        let (created, destroyed, moved) = borders.drw.take_calls();
+        let moved: Vec<Region> = moved.into_iter().map(|(_, r)| r).collect();
+
+        assert_eq!(r, Region::new(x + 10, y + 20, w, h));
+        assert!(created.is_empty());
+        assert!(destroyed.is_empty());
+        assert_eq!(moved, edge_regions(r));
+    }
+
+    #[test]
+    fn sync_recreates_frames_when_clients_are_resized() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        wm.toggle_float(&Selector::Focused).unwrap();
+        borders.sync(&wm).unwrap();
+        let original: Vec<Xid> = borders.frames[&1].windows.iter().map(|w| w.1).collect();
+        borders.drw.take_calls();
+
+        handle(&mut wm, configure_request(1, Region::new(10, 10, 200, 100)));
+        borders.sync(&wm).unwrap();
+
+        let (created, destroyed, _) = borders.drw.take_calls();
+
+        assert_eq!(destroyed, original);
+        assert_eq!(created, edge_regions(Region::new(10, 10, 200, 100)));
+    }
+
+    #[test]
+    fn sync_follows_picture_in_picture_clients() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        borders.sync(&wm).unwrap();
+        borders.drw.take_calls();
+
+        wm.toggle_pip().unwrap();
+        borders.sync(&wm).unwrap();
+
+        let r = wm.conn().client_geometry(1).unwrap();
+        let (created, destroyed, _) = borders.drw.take_calls();
+
+        assert_eq!(destroyed.len(), 4);
+        assert_eq!(created, edge_regions(r));
+    }
+
+    #[test]
+    fn sync_destroys_frames_for_hidden_clients() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        handle(&mut wm, XEvent::MapRequest(2, false));
+        borders.sync(&wm).unwrap();
+        let framed: Vec<Xid> = borders.frames[&2].windows.iter().map(|w| w.1).collect();
+        borders.drw.take_calls();
+
+        wm.minimize_client(&Selector::WinId(2)).unwrap();
+        borders.sync(&wm).unwrap();
+
+        // The remaining client is resized to fill the screen so its frame is recreated as well
+        let (_, destroyed, _) = borders.drw.take_calls();
+
+        assert!(framed.iter().all(|id| destroyed.contains(id)));
+        assert!(!borders.frames.contains_key(&2));
+        assert!(borders.frames.contains_key(&1));
+    }
+
+    #[test]
+    fn removed_clients_have_their_frames_destroyed() {
+        let (mut wm, mut borders) = setup();
+        handle(&mut wm, XEvent::MapRequest(1, false));
+        borders.sync(&wm).unwrap();
+        let framed: Vec<Xid> = borders.frames[&1].windows.iter().map(|w| w.1).collect();
+        borders.drw.take_calls();
+
+        Hook::remove_client(&mut borders, &mut wm, 1).unwrap();
+
+        assert_eq!(borders.drw.take_calls().1, framed);
+        assert!(borders.frames.is_empty());
+    }
+}
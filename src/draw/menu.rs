@@ -0,0 +1,399 @@
+//! A simple built in menu for selecting from a list of lines using the keyboard
+//!
+//! [PMenu] is a minimal alternative to external tools such as dmenu that renders using a
+//! [KeyPressDraw] implementation. While the menu is open the keyboard is grabbed: typed characters
+//! filter the available lines by prefix, Up / Down move the selection, Return accepts the selected
+//! line and Escape closes the menu without making a selection.
+use crate::{
+    core::{
+        bindings::KeyPress,
+        data_types::{Region, WinType},
+        xconnection::{Atom, KeyPressParseAttempt, Prop, Xid},
+    },
+    draw::{Color, DrawContext, DrawError, KeyPressDraw, Result},
+};
+
+/// The result of attempting to match against user input in a [PMenu]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PMenuMatch {
+    /// The selected line along its line number (0 indexed)
+    Line(usize, String),
+    /// Nothing matched and this was the user's input when they hit Return
+    UserInput(String),
+    /// The user exited out of matching or had nothing typed
+    NoMatch,
+}
+
+/// Config for running a [PMenu] selection
+#[derive(Debug, Clone, PartialEq)]
+pub struct PMenuConfig {
+    /// Background color for the rendered window
+    ///
+    /// Default: #282828
+    pub bg_color: Color,
+    /// Foreground color for text
+    ///
+    /// Default: #ebdbb2
+    pub fg_color: Color,
+    /// Selected line background color
+    ///
+    /// Default #458588
+    pub selected_color: Color,
+    /// Font to render text with
+    ///
+    /// Default: "monospace"
+    pub font: String,
+    /// Point size to render the font at
+    ///
+    /// Default: 12
+    pub point_size: i32,
+    /// Pixel padding around each rendered line
+    ///
+    /// Default: 4.0
+    pub padding: f64,
+    /// Number of lines to display at a time
+    ///
+    /// Default: 10
+    pub n_lines: usize,
+}
+
+impl Default for PMenuConfig {
+    fn default() -> Self {
+        Self {
+            bg_color: 0x282828ff.into(),
+            fg_color: 0xebdbb2ff.into(),
+            selected_color: 0x458588ff.into(),
+            font: "monospace".into(),
+            point_size: 12,
+            padding: 4.0,
+            n_lines: 10,
+        }
+    }
+}
+
+/// The filtering and selection state of an open menu, independent of rendering
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MenuState {
+    lines: Vec<String>,
+    input: String,
+    matches: Vec<usize>,
+    selected: usize,
+    n_lines: usize,
+}
+
+impl MenuState {
+    fn new(lines: Vec<String>, n_lines: usize) -> Self {
+        let matches = (0..lines.len()).collect();
+        Self {
+            lines,
+            input: String::new(),
+            matches,
+            selected: 0,
+            n_lines: n_lines.max(1),
+        }
+    }
+
+    fn refilter(&mut self) {
+        self.matches = filter_lines(&self.lines, &self.input);
+        self.selected = 0;
+    }
+
+    // The lines that should currently be rendered, along with whether or not they are selected
+    fn visible_lines(&self) -> Vec<(&str, bool)> {
+        let start = (self.selected / self.n_lines) * self.n_lines;
+        self.matches
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(self.n_lines)
+            .map(|(i, &ix)| (self.lines[ix].as_ref(), i == self.selected))
+            .collect()
+    }
+
+    // Returns Some when the menu should close with the given result
+    fn handle_keypress(&mut self, k: KeyPress) -> Option<PMenuMatch> {
+        let last = self.matches.len().saturating_sub(1);
+
+        match k {
+            KeyPress::Escape => return Some(PMenuMatch::NoMatch),
+            KeyPress::Return => {
+                return Some(match self.matches.get(self.selected) {
+                    Some(&ix) => PMenuMatch::Line(ix, self.lines[ix].clone()),
+                    None if !self.input.is_empty() => PMenuMatch::UserInput(self.input.clone()),
+                    None => PMenuMatch::NoMatch,
+                })
+            }
+            KeyPress::Up => self.selected = self.selected.saturating_sub(1),
+            KeyPress::Down | KeyPress::Tab => self.selected = (self.selected + 1).min(last),
+            KeyPress::PageUp => self.selected = self.selected.saturating_sub(self.n_lines),
+            KeyPress::PageDown => self.selected = (self.selected + self.n_lines).min(last),
+            KeyPress::Backspace => {
+                self.input.pop();
+                self.refilter();
+            }
+            KeyPress::Utf8(s) => {
+                self.input.push_str(&s);
+                self.refilter();
+            }
+            _ => (),
+        }
+
+        None
+    }
+}
+
+/// The indices of each line in `lines` that starts with `input`, ignoring case.
+pub fn filter_lines(lines: &[String], input: &str) -> Vec<usize> {
+    let input = input.to_lowercase();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.to_lowercase().starts_with(&input))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// A simple keyboard driven menu rendered using a [KeyPressDraw]
+///
+/// ```no_run
+/// # use penrose::draw::{KeyPressDraw, menu::{PMenu, PMenuConfig, PMenuMatch}};
+/// # fn example<D: KeyPressDraw>(drw: D) -> penrose::draw::Result<()> {
+/// let mut menu = PMenu::new(drw, PMenuConfig::default());
+///
+/// match menu.get_selection_from_input(">>> ", vec!["firefox", "alacritty"], 0)? {
+///     PMenuMatch::Line(_, choice) => println!("selected {}", choice),
+///     _ => println!("no selection made"),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PMenu<D: KeyPressDraw> {
+    drw: D,
+    config: PMenuConfig,
+}
+
+impl<D: KeyPressDraw> PMenu<D> {
+    /// Construct a new [PMenu] using the given [KeyPressDraw] for rendering
+    pub fn new(mut drw: D, config: PMenuConfig) -> Self {
+        drw.register_font(&config.font);
+        Self { drw, config }
+    }
+
+    /// The current [PMenuConfig] for this menu
+    pub fn config(&self) -> &PMenuConfig {
+        &self.config
+    }
+
+    /// Open the menu on the screen with the given index and block until the user either selects
+    /// one of the provided lines or closes the menu.
+    ///
+    /// The keyboard is grabbed for as long as the menu is open.
+    pub fn get_selection_from_input(
+        &mut self,
+        prompt: impl Into<String>,
+        input: Vec<impl Into<String>>,
+        screen_index: usize,
+    ) -> Result<PMenuMatch> {
+        let prompt = prompt.into();
+        let lines = input.into_iter().map(|s| s.into()).collect();
+        let mut state = MenuState::new(lines, self.config.n_lines);
+
+        let screen = self
+            .drw
+            .screen_sizes()?
+            .get(screen_index)
+            .copied()
+            .ok_or_else(|| DrawError::Raw(format!("unknown screen index {}", screen_index)))?;
+
+        let line_h = self.line_height()?;
+        let (sx, sy, sw, _) = screen.values();
+        let h = line_h * (self.config.n_lines as u32 + 1);
+        let id = self.drw.new_window(
+            WinType::InputOutput(Atom::NetWindowTypeMenu),
+            Region::new(sx, sy, sw, h),
+            false,
+        )?;
+
+        let p = Prop::UTF8String(vec!["penrose-menu".to_string()]);
+        for atom in &[Atom::NetWmName, Atom::WmName, Atom::WmClass] {
+            self.drw.change_prop(id, atom.as_ref(), p.clone())?;
+        }
+
+        self.drw.grab_keyboard()?;
+        let res = self.run(id, &prompt, &mut state, sw as f64, line_h as f64);
+        self.drw.ungrab_keyboard()?;
+        self.drw.destroy_window(id)?;
+
+        res
+    }
+
+    fn line_height(&self) -> Result<u32> {
+        let mut ctx = self.drw.temp_context(0, 0)?;
+        ctx.font(&self.config.font, self.config.point_size)?;
+        let (_, h) = ctx.text_extent("Ag")?;
+
+        Ok((h + 2.0 * self.config.padding).ceil() as u32)
+    }
+
+    fn run(
+        &self,
+        id: Xid,
+        prompt: &str,
+        state: &mut MenuState,
+        w: f64,
+        line_h: f64,
+    ) -> Result<PMenuMatch> {
+        loop {
+            self.redraw(id, prompt, state, w, line_h)?;
+
+            if let KeyPressParseAttempt::KeyPress(k) = self.drw.next_keypress_blocking()? {
+                if let Some(m) = state.handle_keypress(k) {
+                    return Ok(m);
+                }
+            }
+        }
+    }
+
+    fn redraw(&self, id: Xid, prompt: &str, state: &MenuState, w: f64, line_h: f64) -> Result<()> {
+        let PMenuConfig {
+            bg_color,
+            fg_color,
+            selected_color,
+            font,
+            point_size,
+            padding,
+            n_lines,
+        } = &self.config;
+
+        let mut ctx = self.drw.context_for(id)?;
+        ctx.clear()?;
+        ctx.font(font, *point_size)?;
+
+        ctx.color(bg_color);
+        ctx.rectangle(0.0, 0.0, w, line_h * (*n_lines as f64 + 1.0))?;
+        ctx.color(fg_color);
+        ctx.text(
+            &format!("{}{}", prompt, state.input),
+            0.0,
+            (*padding, *padding),
+        )?;
+
+        for (i, (line, selected)) in state.visible_lines().into_iter().enumerate() {
+            ctx.set_y_offset(line_h * (i as f64 + 1.0));
+            if selected {
+                ctx.color(selected_color);
+                ctx.rectangle(0.0, 0.0, w, line_h)?;
+            }
+            ctx.color(fg_color);
+            ctx.text(line, 0.0, (*padding, *padding))?;
+        }
+
+        ctx.flush();
+        self.drw.flush(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines() -> Vec<String> {
+        vec!["firefox", "Files", "alacritty", "feh"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    fn utf8(s: &str) -> KeyPress {
+        KeyPress::Utf8(s.into())
+    }
+
+    test_cases! {
+        filter_lines_by_prefix;
+        args: (input: &str, expected: Vec<usize>);
+
+        case: empty_input_matches_all => ("", vec![0, 1, 2, 3]);
+        case: prefix_is_case_insensitive => ("FI", vec![0, 1]);
+        case: single_match => ("al", vec![2]);
+        case: no_match => ("x", vec![]);
+
+        body: {
+            assert_eq!(filter_lines(&lines(), input), expected);
+        }
+    }
+
+    test_cases! {
+        menu_state_keypresses;
+        args: (keys: Vec<KeyPress>, expected: Option<PMenuMatch>);
+
+        case: escape_closes_without_a_match => (vec![KeyPress::Escape], Some(PMenuMatch::NoMatch));
+        case: return_selects_the_first_line => (
+            vec![KeyPress::Return], Some(PMenuMatch::Line(0, "firefox".into()))
+        );
+        case: down_moves_the_selection => (
+            vec![KeyPress::Down, KeyPress::Down, KeyPress::Return],
+            Some(PMenuMatch::Line(2, "alacritty".into()))
+        );
+        case: up_stops_at_the_first_line => (
+            vec![KeyPress::Down, KeyPress::Up, KeyPress::Up, KeyPress::Return],
+            Some(PMenuMatch::Line(0, "firefox".into()))
+        );
+        case: down_stops_at_the_last_line => (
+            vec![KeyPress::PageDown, KeyPress::Down, KeyPress::Return],
+            Some(PMenuMatch::Line(3, "feh".into()))
+        );
+        case: typing_filters_the_lines => (
+            vec![utf8("f"), KeyPress::Down, KeyPress::Down, KeyPress::Return],
+            Some(PMenuMatch::Line(3, "feh".into()))
+        );
+        case: typing_resets_the_selection => (
+            vec![KeyPress::Down, utf8("a"), KeyPress::Return],
+            Some(PMenuMatch::Line(2, "alacritty".into()))
+        );
+        case: backspace_widens_the_filter => (
+            vec![utf8("a"), KeyPress::Backspace, KeyPress::Return],
+            Some(PMenuMatch::Line(0, "firefox".into()))
+        );
+        case: no_matching_lines_returns_user_input => (
+            vec![utf8("x"), utf8("y"), KeyPress::Return],
+            Some(PMenuMatch::UserInput("xy".into()))
+        );
+        case: other_keys_keep_the_menu_open => (vec![KeyPress::Left, utf8("f")], None);
+
+        body: {
+            let mut state = MenuState::new(lines(), 10);
+            let mut res = None;
+            for k in keys {
+                res = state.handle_keypress(k);
+                if res.is_some() {
+                    break;
+                }
+            }
+            assert_eq!(res, expected);
+        }
+    }
+
+    #[test]
+    fn visible_lines_are_paged_around_the_selection() {
+        let mut state = MenuState::new(lines(), 3);
+        let visible = |s: &MenuState| {
+            s.visible_lines()
+                .into_iter()
+                .map(|(l, sel)| (l.to_string(), sel))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            visible(&state),
+            vec![
+                ("firefox".to_string(), true),
+                ("Files".to_string(), false),
+                ("alacritty".to_string(), false)
+            ]
+        );
+
+        state.handle_keypress(KeyPress::PageDown);
+        assert_eq!(visible(&state), vec![("feh".to_string(), true)]);
+    }
+}
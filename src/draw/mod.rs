@@ -5,11 +5,19 @@
 //! for writing a full GUI application, the [Draw] and [DrawContext] traits are enough for setting
 //! up simple text based UI elements such as status bars and menus.
 pub mod bar;
+pub mod border;
+pub mod menu;
 pub mod widget;
 
 #[doc(inline)]
 pub use bar::*;
 
+#[doc(inline)]
+pub use border::{BorderEdge, BorderPainter, DrawnBorders};
+
+#[doc(inline)]
+pub use menu::{PMenu, PMenuConfig, PMenuMatch};
+
 #[doc(inline)]
 pub use widget::{HookableWidget, KeyboardControlled, Widget};
 
@@ -189,6 +197,10 @@ pub trait Draw: XClientHandler + XClientProperties {
 
     /// Create a new client window with a canvas for drawing
     fn new_window(&mut self, ty: WinType, r: Region, managed: bool) -> Result<Xid>;
+    /// Destroy a window created using [new_window][Draw::new_window] along with its canvas
+    fn destroy_window(&mut self, id: Xid) -> Result<()> {
+        Ok(self.destroy_client(id)?)
+    }
     /// Get the size of the target screen in pixels
     fn screen_sizes(&self) -> Result<Vec<Region>>;
     /// Register a font by name for later use
@@ -383,8 +383,9 @@ where
     }
 
     fn focus_change(&mut self, wm: &mut WindowManager<X>, id: Xid) -> crate::Result<()> {
-        if let Some(client) = wm.client(&Selector::WinId(id)) {
-            self.set_text(client.wm_name());
+        match wm.client(&Selector::WinId(id)) {
+            Some(client) => self.set_text(client.wm_name()),
+            None => self.txt.set_text(""), // focus moved to the root window
         }
 
         Ok(())
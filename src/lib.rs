@@ -236,6 +236,10 @@ pub enum PenroseError {
     #[error("{0} is not a known client")]
     UnknownClient(Xid),
 
+    /// A user specified key binding contained a key name that is not in the current keymap
+    #[error("Unknown key name: {0}")]
+    UnknownKeyName(String),
+
     /// A user specified key binding contained an invalid modifier key
     #[error("Unknown modifier key: {0}")]
     UnknownModifier(String),
@@ -259,6 +263,22 @@ pub enum PenroseError {
     X(#[from] crate::core::xconnection::XError),
 }
 
+impl PenroseError {
+    /// Whether or not this error means that penrose is no longer able to communicate with the X
+    /// server. See [ErrorPolicy][crate::core::data_types::ErrorPolicy] for how this is used when
+    /// handling errors raised in the main event loop.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::X(e) => e.is_fatal(),
+            #[cfg(feature = "xcb")]
+            Self::Xcb(e) => e.is_fatal(),
+            #[cfg(feature = "x11rb")]
+            Self::X11rb(e) => e.is_fatal(),
+            _ => false,
+        }
+    }
+}
+
 /// Top level penrose Result type
 pub type Result<T> = std::result::Result<T, PenroseError>;
 
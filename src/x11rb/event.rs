@@ -1,10 +1,14 @@
 use crate::{
     core::{
-        bindings::{KeyCode, ModifierKey, MouseButton, MouseEvent, MouseEventKind, MouseState},
+        bindings::{
+            KeyCode, ModifierKey, MouseButton, MouseEvent, MouseEventKind, MouseState,
+            CAPS_LOCK_MASK,
+        },
         data_types::{Point, Region},
         xconnection::{
             event::ClientEventMask, ClientMessage, ClientMessageData, ConfigureEvent, ExposeEvent,
-            PointerChange, PropertyEvent, Result, XAtomQuerier, XError, XEvent,
+            PointerChange, PropertyEvent, RandrChange, Result, StackMode, XAtomQuerier, XError,
+            XEvent,
         },
     },
     x11rb::{xconn::X11rbConnection, X11rbError},
@@ -15,61 +19,90 @@ use strum::IntoEnumIterator;
 use x11rb::{
     connection::Connection,
     protocol::{
-        xproto::{ClientMessageEvent, ConnectionExt as _, ModMask},
+        randr,
+        xproto::{
+            ClientMessageEvent, ConfigWindow, ConnectionExt as _, Mapping, StackMode as XStackMode,
+        },
         Event,
     },
 };
 
+// The timestamp carried by the events that we use for ordering focus changes
+fn event_time(event: &Event) -> Option<u32> {
+    match event {
+        Event::KeyPress(e) => Some(e.time),
+        Event::ButtonPress(e) | Event::ButtonRelease(e) => Some(e.time),
+        Event::MotionNotify(e) => Some(e.time),
+        Event::EnterNotify(e) | Event::LeaveNotify(e) => Some(e.time),
+        Event::PropertyNotify(e) => Some(e.time),
+        _ => None,
+    }
+}
+
 pub(crate) fn convert_event<C: Connection>(
     conn: &X11rbConnection<C>,
     event: Event,
 ) -> Result<Option<XEvent>> {
+    if let Some(time) = event_time(&event) {
+        conn.set_last_event_time(time);
+    }
+
     match event {
-        Event::RandrNotify(_) => Ok(Some(XEvent::RandrNotify)),
+        Event::RandrNotify(event) => Ok(Some(XEvent::RandrNotify(to_randr_change(conn, event)))),
         Event::RandrScreenChangeNotify(_) => Ok(Some(XEvent::ScreenChange)),
         Event::ButtonPress(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
-            XEvent::MouseEvent(MouseEvent::new(
-                event.event,
-                event.root_x,
-                event.root_y,
-                event.event_x,
-                event.event_y,
-                state,
-                MouseEventKind::Press,
-            ))
+            XEvent::MouseEvent(
+                MouseEvent::new(
+                    event.event,
+                    event.root_x,
+                    event.root_y,
+                    event.event_x,
+                    event.event_y,
+                    state,
+                    MouseEventKind::Press,
+                )
+                .with_child(event.child)
+                .with_time(event.time),
+            )
         })),
         Event::ButtonRelease(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
-            XEvent::MouseEvent(MouseEvent::new(
-                event.event,
-                event.root_x,
-                event.root_y,
-                event.event_x,
-                event.event_y,
-                state,
-                MouseEventKind::Release,
-            ))
+            XEvent::MouseEvent(
+                MouseEvent::new(
+                    event.event,
+                    event.root_x,
+                    event.root_y,
+                    event.event_x,
+                    event.event_y,
+                    state,
+                    MouseEventKind::Release,
+                )
+                .with_child(event.child)
+                .with_time(event.time),
+            )
         })),
         // FIXME: The 5 is due to https://github.com/sminez/penrose/issues/113
         Event::MotionNotify(event) => Ok(to_mouse_state(5, event.state).map(|state| {
-            XEvent::MouseEvent(MouseEvent::new(
-                event.event,
-                event.root_x,
-                event.root_y,
-                event.event_x,
-                event.event_y,
-                state,
-                MouseEventKind::Motion,
-            ))
+            XEvent::MouseEvent(
+                MouseEvent::new(
+                    event.event,
+                    event.root_x,
+                    event.root_y,
+                    event.event_x,
+                    event.event_y,
+                    state,
+                    MouseEventKind::Motion,
+                )
+                .with_child(event.child)
+                .with_time(event.time),
+            )
         })),
         Event::KeyPress(event) => {
             let code = KeyCode {
                 mask: event.state,
                 code: event.detail,
             };
-            let numlock = ModMask::M2;
-            Ok(Some(XEvent::KeyPress(
-                code.ignoring_modifier(numlock.into()),
-            )))
+            let locks = conn.num_lock_mask() | CAPS_LOCK_MASK;
+            Ok(Some(XEvent::KeyPress(code.normalized(locks))))
         }
         Event::MapRequest(event) => {
             let attr = conn
@@ -101,17 +134,28 @@ pub(crate) fn convert_event<C: Connection>(
                 event.height as u32,
             ),
             is_root: event.window == conn.root(),
+            border_width: Some(event.border_width as u32),
+            sibling: Some(event.above_sibling).filter(|&id| id != x11rb::NONE),
+            stack_mode: None,
         }))),
-        Event::ConfigureRequest(event) => Ok(Some(XEvent::ConfigureRequest(ConfigureEvent {
-            id: event.window,
-            r: Region::new(
-                event.x as u32,
-                event.y as u32,
-                event.width as u32,
-                event.height as u32,
-            ),
-            is_root: event.window == conn.root(),
-        }))),
+        Event::ConfigureRequest(event) => {
+            let is_set = |flag: ConfigWindow| u16::from(event.value_mask) & u16::from(flag) != 0;
+            Ok(Some(XEvent::ConfigureRequest(ConfigureEvent {
+                id: event.window,
+                r: Region::new(
+                    event.x as u32,
+                    event.y as u32,
+                    event.width as u32,
+                    event.height as u32,
+                ),
+                is_root: event.window == conn.root(),
+                border_width: Some(event.border_width as u32)
+                    .filter(|_| is_set(ConfigWindow::BORDER_WIDTH)),
+                sibling: Some(event.sibling).filter(|_| is_set(ConfigWindow::SIBLING)),
+                stack_mode: to_stack_mode(event.stack_mode)
+                    .filter(|_| is_set(ConfigWindow::STACK_MODE)),
+            })))
+        }
         Event::Expose(event) => Ok(Some(XEvent::Expose(ExposeEvent {
             id: event.window,
             r: Region::new(
@@ -128,6 +172,9 @@ pub(crate) fn convert_event<C: Connection>(
             atom: conn.atom_name(event.atom)?,
             is_root: event.window == conn.root(),
         }))),
+        Event::MappingNotify(event) if event.request == Mapping::KEYBOARD => {
+            Ok(Some(XEvent::MappingNotify))
+        }
         Event::Error(err) => Err(X11rbError::X11Error(err).into()),
 
         // NOTE: Ignoring other event types
@@ -172,3 +219,45 @@ fn to_client_message<C: Connection>(
         data,
     )))
 }
+
+fn to_stack_mode(mode: XStackMode) -> Option<StackMode> {
+    match mode {
+        XStackMode::ABOVE => Some(StackMode::Above),
+        XStackMode::BELOW => Some(StackMode::Below),
+        XStackMode::TOP_IF => Some(StackMode::TopIf),
+        XStackMode::BOTTOM_IF => Some(StackMode::BottomIf),
+        XStackMode::OPPOSITE => Some(StackMode::Opposite),
+        _ => None,
+    }
+}
+
+// Extract the details of a randr notify event, falling back to RandrChange::Unknown for events
+// that we do not handle or if we are unable to query the output name.
+fn to_randr_change<C: Connection>(
+    conn: &X11rbConnection<C>,
+    event: randr::NotifyEvent,
+) -> RandrChange {
+    match event.sub_code {
+        randr::Notify::CRTC_CHANGE => {
+            let cc = event.u.as_cc();
+            RandrChange::Crtc {
+                crtc: cc.crtc,
+                region: Region::new(cc.x as u32, cc.y as u32, cc.width as u32, cc.height as u32),
+            }
+        }
+
+        randr::Notify::OUTPUT_CHANGE => {
+            let oc = event.u.as_oc();
+            match conn.output_name(oc.output) {
+                Ok(name) => RandrChange::Output {
+                    name,
+                    connected: oc.connection == randr::Connection::CONNECTED,
+                    crtc: Some(oc.crtc).filter(|&c| c != x11rb::NONE),
+                },
+                Err(_) => RandrChange::Unknown,
+            }
+        }
+
+        _ => RandrChange::Unknown,
+    }
+}
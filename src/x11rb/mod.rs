@@ -26,6 +26,8 @@ use x11rb::{
     x11_utils::X11Error,
 };
 
+use std::os::unix::io::AsRawFd;
+
 pub(crate) mod atom;
 pub(crate) mod event;
 pub mod xconn;
@@ -48,7 +50,9 @@ pub fn new_x11rb_rust_backed_window_manager(
     error_handler: ErrorHandler,
 ) -> crate::Result<WindowManager<X11rbConnection<RustConnection>>> {
     let (conn, _) = RustConnection::connect(None).map_err(X11rbError::from)?;
-    new_x11rb_backed_window_manager(conn, config, hooks, error_handler)
+    let fd = conn.stream().as_raw_fd();
+    let conn = X11rbConnection::new_for_connection(conn)?.with_event_fd(fd);
+    window_manager_for(conn, config, hooks, error_handler)
 }
 
 /// Construct a penrose [WindowManager] backed by the [x11rb][crate::x11rb] backend using
@@ -60,11 +64,17 @@ pub fn new_x11rb_xcb_backed_window_manager(
     error_handler: ErrorHandler,
 ) -> crate::Result<WindowManager<X11rbConnection<XCBConnection>>> {
     let (conn, _) = XCBConnection::connect(None).map_err(X11rbError::from)?;
-    new_x11rb_backed_window_manager(conn, config, hooks, error_handler)
+    let fd = conn.as_raw_fd();
+    let conn = X11rbConnection::new_for_connection(conn)?.with_event_fd(fd);
+    window_manager_for(conn, config, hooks, error_handler)
 }
 
 /// Construct a penrose [WindowManager] backed by the [x11rb][crate::x11rb] backend using
 /// the given connection.
+///
+/// The file descriptor of `connection` is not known so waiting for events with a timeout falls
+/// back to polling: use [X11rbConnection::with_event_fd] along with [WindowManager::new] to avoid
+/// this.
 pub fn new_x11rb_backed_window_manager<C: Connection>(
     connection: C,
     config: Config,
@@ -72,6 +82,15 @@ pub fn new_x11rb_backed_window_manager<C: Connection>(
     error_handler: ErrorHandler,
 ) -> crate::Result<WindowManager<X11rbConnection<C>>> {
     let conn = X11rbConnection::new_for_connection(connection)?;
+    window_manager_for(conn, config, hooks, error_handler)
+}
+
+fn window_manager_for<C: Connection>(
+    conn: X11rbConnection<C>,
+    config: Config,
+    hooks: Vec<Box<dyn Hook<X11rbConnection<C>>>>,
+    error_handler: ErrorHandler,
+) -> crate::Result<WindowManager<X11rbConnection<C>>> {
     let mut wm = WindowManager::new(config, conn, hooks, error_handler);
     wm.init()?;
 
@@ -119,6 +138,20 @@ pub enum X11rbError {
     X11Error(X11Error),
 }
 
+impl X11rbError {
+    /// Whether or not this error means that the connection to the X server can no longer be
+    /// used.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::Connect(_)
+                | Self::Connection(_)
+                | Self::ReplyError(ReplyError::ConnectionError(_))
+                | Self::ReplyOrIdError(ReplyOrIdError::ConnectionError(_))
+        )
+    }
+}
+
 macro_rules! from_error {
     ($type:ident) => {
         impl From<$type> for XError {
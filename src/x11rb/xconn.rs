@@ -9,30 +9,41 @@
 
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, MouseBindings, MouseState},
+        bindings::{
+            lock_modifier_combinations, num_lock_mask, KeyBindings, KeyCode, KeyGrabReport,
+            MouseBindings, MouseState,
+        },
         data_types::{Point, Region},
         screen::Screen,
         xconnection::{
-            self, Atom, ClientAttr, ClientConfig, ClientEventMask, ClientMessage,
-            ClientMessageKind, Prop, Result, WindowAttributes, WindowState, WmHints, WmNormalHints,
-            XAtomQuerier, XClientConfig, XClientHandler, XClientProperties, XConn, XError, XEvent,
-            XEventHandler, XState, Xid,
+            self, property::decode_string_prop, Atom, ClientAttr, ClientConfig, ClientEventMask,
+            ClientMessage, ClientMessageKind, Prop, Result, WindowAttributes, WindowState, WmHints,
+            WmNormalHints, XAtomQuerier, XClientConfig, XClientHandler, XClientProperties, XConn,
+            XError, XEvent, XEventHandler, XState, Xid,
         },
     },
     x11rb::{atom::Atoms, X11rbError},
 };
 
-use std::{convert::TryFrom, str::FromStr};
+use std::{
+    cell::{Cell, RefCell},
+    convert::TryFrom,
+    os::unix::io::RawFd,
+    str::FromStr,
+    time::Duration,
+};
 
 use x11rb::{
     connection::Connection,
+    errors::ConnectionError,
     protocol::{
         randr::{self, ConnectionExt as _},
+        xfixes::ConnectionExt as _,
         xproto::{
             AtomEnum, ButtonIndex, ChangeWindowAttributesAux, ClientMessageData,
-            ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, EventMask,
-            Grab, GrabMode, InputFocus, MapState, ModMask, PropMode, StackMode, WindowClass,
-            CLIENT_MESSAGE_EVENT,
+            ClientMessageEvent, ConfigureNotifyEvent, ConfigureWindowAux, ConnectionExt as _,
+            CreateWindowAux, EventMask, Grab, GrabMode, InputFocus, MapState, ModMask, PropMode,
+            StackMode, WindowClass, CLIENT_MESSAGE_EVENT, CONFIGURE_NOTIFY_EVENT,
         },
     },
     wrapper::ConnectionExt as _,
@@ -48,6 +59,10 @@ pub struct X11rbConnection<C: Connection> {
     root: Xid,
     check_win: Xid,
     atoms: Atoms,
+    num_lock_mask: Cell<u16>,
+    pointer_barriers: RefCell<Vec<Xid>>,
+    event_fd: Option<RawFd>,
+    last_event_time: Cell<u32>,
 }
 
 impl<C: Connection> X11rbConnection<C> {
@@ -56,6 +71,7 @@ impl<C: Connection> X11rbConnection<C> {
         let root = conn.setup().roots[0].root;
         conn.prefetch_extension_information(randr::X11_EXTENSION_NAME)?;
         let atoms = Atoms::new(&conn)?;
+        let num_lock_mask = Self::query_num_lock_mask(&conn)?;
 
         if conn
             .extension_information(randr::X11_EXTENSION_NAME)?
@@ -98,9 +114,21 @@ impl<C: Connection> X11rbConnection<C> {
             root,
             check_win,
             atoms,
+            num_lock_mask: Cell::new(num_lock_mask),
+            pointer_barriers: RefCell::new(vec![]),
+            event_fd: None,
+            last_event_time: Cell::new(CURRENT_TIME),
         })
     }
 
+    /// Set the file descriptor of the underlying connection so that
+    /// [wait_for_event_timeout][XEventHandler::wait_for_event_timeout] can wait on it rather
+    /// than polling for events.
+    pub fn with_event_fd(mut self, fd: RawFd) -> Self {
+        self.event_fd = Some(fd);
+        self
+    }
+
     /// The root window ID
     pub fn root(&self) -> Xid {
         self.root
@@ -145,6 +173,18 @@ impl<C: Connection> XClientConfig for X11rbConnection<C> {
                     aux = aux.x(x as i32).y(y as i32).width(w).height(h);
                 }
                 ClientConfig::StackAbove => aux = aux.stack_mode(StackMode::ABOVE),
+                ClientConfig::Stack(mode, sibling) => {
+                    aux = aux.stack_mode(match mode {
+                        xconnection::StackMode::Above => StackMode::ABOVE,
+                        xconnection::StackMode::Below => StackMode::BELOW,
+                        xconnection::StackMode::TopIf => StackMode::TOP_IF,
+                        xconnection::StackMode::BottomIf => StackMode::BOTTOM_IF,
+                        xconnection::StackMode::Opposite => StackMode::OPPOSITE,
+                    });
+                    if let Some(id) = sibling {
+                        aux = aux.sibling(*id);
+                    }
+                }
             }
         }
         self.conn.configure_window(id, &aux)?;
@@ -165,6 +205,7 @@ impl<C: Connection> XClientConfig for X11rbConnection<C> {
         let mut aux = ChangeWindowAttributesAux::new();
         for conf in data.iter() {
             match conf {
+                ClientAttr::BackgroundColor(c) => aux = aux.background_pixel(*c),
                 ClientAttr::BorderColor(c) => aux = aux.border_pixel(*c),
                 ClientAttr::ClientEventMask => aux = aux.event_mask(client_event_mask),
                 ClientAttr::RootEventMask => aux = aux.event_mask(root_event_mask),
@@ -206,6 +247,32 @@ impl<C: Connection> XClientConfig for X11rbConnection<C> {
             window_class,
         ))
     }
+
+    fn send_configure_notify(&self, id: Xid, r: Region, border: u32) -> Result<()> {
+        let (x, y, w, h) = r.values();
+        let event = ConfigureNotifyEvent {
+            response_type: CONFIGURE_NOTIFY_EVENT,
+            sequence: 0,
+            event: id,
+            window: id,
+            above_sibling: x11rb::NONE,
+            x: x as i16,
+            y: y as i16,
+            width: w as u16,
+            height: h as u16,
+            border_width: border as u16,
+            override_redirect: false,
+        };
+
+        self.conn
+            .send_event(false, id, EventMask::STRUCTURE_NOTIFY, event)?;
+        Ok(())
+    }
+
+    fn clear_client(&self, id: Xid) -> Result<()> {
+        self.conn.clear_area(false, id, 0, 0, 0, 0)?;
+        Ok(())
+    }
 }
 
 impl<C: Connection> XClientHandler for X11rbConnection<C> {
@@ -230,6 +297,15 @@ impl<C: Connection> XClientHandler for X11rbConnection<C> {
         )
     }
 
+    fn focus_root(&self) -> Result<()> {
+        self.conn.set_input_focus(
+            InputFocus::POINTER_ROOT,
+            self.root,
+            self.last_event_time.get(),
+        )?;
+        Ok(())
+    }
+
     fn destroy_client(&self, id: Xid) -> Result<()> {
         self.conn.destroy_window(id)?;
         Ok(())
@@ -261,15 +337,10 @@ impl<C: Connection> XClientProperties for X11rbConnection<C> {
             // This uses unwrap() for symmetry with penrose::xcb (which does value()[0] to "panic")
             "CARDINAL" => Prop::Cardinal(r.value32().unwrap().next().unwrap()),
 
-            "STRING" | "UTF8_STRING" => Prop::UTF8String(
-                // FIXME: I think this should check prop.format == 8, but penrose::xcb does not
-                String::from_utf8(r.value)
-                    .map_err(X11rbError::from)?
-                    .trim_matches('\0')
-                    .split('\0')
-                    .map(|s| s.to_string())
-                    .collect(),
-            ),
+            // FIXME: I think this should check prop.format == 8, but penrose::xcb does not
+            "STRING" => Prop::UTF8String(decode_string_prop(&r.value, false)),
+
+            "UTF8_STRING" => Prop::UTF8String(decode_string_prop(&r.value, true)),
 
             "WINDOW" => Prop::Window(
                 r.value32()
@@ -358,6 +429,8 @@ impl<C: Connection> XClientProperties for X11rbConnection<C> {
 
             Prop::Cardinal(val) => (AtomEnum::CARDINAL, vec![val]),
 
+            Prop::CardinalList(vals) => (AtomEnum::CARDINAL, vals),
+
             Prop::Window(ids) => (AtomEnum::WINDOW, ids),
 
             // FIXME: handle changing WmHints and WmNormalHints correctly in change_prop
@@ -402,6 +475,37 @@ impl<C: Connection> XEventHandler for X11rbConnection<C> {
         }
     }
 
+    fn poll_for_event(&self) -> Result<Option<XEvent>> {
+        while let Some(event) = self.conn.poll_for_event()? {
+            if let Some(event) = super::event::convert_event(self, event)? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>> {
+        let fd = match self.event_fd {
+            Some(fd) => fd,
+            None => {
+                return xconnection::poll_for_event_with_timeout(timeout, || self.poll_for_event())
+            }
+        };
+
+        if let Some(event) = self.poll_for_event()? {
+            return Ok(Some(event));
+        }
+
+        let readable = xconnection::wait_for_readable(fd, timeout)
+            .map_err(|e| X11rbError::from(ConnectionError::IoError(e)))?;
+        if readable {
+            self.poll_for_event()
+        } else {
+            Ok(None)
+        }
+    }
+
     fn send_client_event(&self, msg: ClientMessage) -> Result<()> {
         let type_ = self.atom_id(&msg.dtype)?;
         let data = match msg.data() {
@@ -422,7 +526,8 @@ impl<C: Connection> XEventHandler for X11rbConnection<C> {
             ClientEventMask::SubstructureNotify => EventMask::SUBSTRUCTURE_NOTIFY,
         };
 
-        self.conn.send_event(false, msg.id, mask, event)?;
+        self.conn
+            .send_event(false, msg.destination(), mask, event)?;
         Ok(())
     }
 
@@ -437,6 +542,14 @@ impl<C: Connection> XState for X11rbConnection<C> {
     }
 
     fn current_screens(&self) -> Result<Vec<Screen>> {
+        Ok(self
+            .current_outputs()?
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect())
+    }
+
+    fn current_outputs(&self) -> Result<Vec<(Screen, Option<String>)>> {
         let resources = self.conn.randr_get_screen_resources(self.root)?.reply()?;
 
         // Send queries for all CRTCs
@@ -463,7 +576,14 @@ impl<C: Connection> XState for X11rbConnection<C> {
                     reply.width as u32,
                     reply.height as u32,
                 );
-                Screen::new(region, i)
+                let info = reply
+                    .outputs
+                    .first()
+                    .and_then(|o| self.conn.randr_get_output_info(*o, 0).ok())
+                    .and_then(|cookie| cookie.reply().ok());
+                let mm_width = info.as_ref().map(|info| info.mm_width).unwrap_or(0);
+                let name = info.map(|info| String::from_utf8_lossy(&info.name).into_owned());
+                (Screen::new(region, i).with_physical_width(mm_width), name)
             })
             .collect();
         Ok(screens)
@@ -526,6 +646,7 @@ impl<C: Connection> XConn for X11rbConnection<C> {
     }
 
     fn cleanup(&self) -> Result<()> {
+        self.confine_pointer(None)?;
         self.conn.ungrab_keyboard(x11rb::CURRENT_TIME)?;
         self.conn.ungrab_key(Grab::ANY, self.root, ModMask::ANY)?;
         self.conn
@@ -539,12 +660,21 @@ impl<C: Connection> XConn for X11rbConnection<C> {
         Ok(())
     }
 
+    fn reconnect(&mut self) -> Result<()> {
+        Err(XError::Raw(
+            "reconnecting is not supported for X11rbConnection".into(),
+        ))
+    }
+
     fn grab_keys(
         &self,
         key_bindings: &KeyBindings<Self>,
         mouse_bindings: &MouseBindings<Self>,
-    ) -> Result<()> {
-        self.grab_key_bindings(&key_bindings.keys().collect::<Vec<_>>())?;
+    ) -> Result<KeyGrabReport> {
+        let mut report = KeyGrabReport::default();
+        for &k in key_bindings.keys() {
+            report.record(k, self.grab_key_bindings(&[&k]));
+        }
         self.grab_mouse_buttons(
             &mouse_bindings
                 .keys()
@@ -553,28 +683,154 @@ impl<C: Connection> XConn for X11rbConnection<C> {
         )?;
         self.flush();
 
+        Ok(report)
+    }
+
+    fn grab_key(&self, key: KeyCode) -> Result<()> {
+        self.grab_key_bindings(&[&key])
+    }
+
+    fn ungrab_key(&self, key: KeyCode) -> Result<()> {
+        // The lock modifiers were grabbed as additional modifiers so they need to be released as
+        // well.
+        let modifiers = lock_modifier_combinations(self.num_lock_mask.get());
+
+        for m in modifiers.iter() {
+            self.conn.ungrab_key(key.code, self.root, key.mask | m)?;
+        }
+
+        self.flush();
+        Ok(())
+    }
+
+    fn refresh_num_lock_mask(&self) -> Result<bool> {
+        let mask = Self::query_num_lock_mask(&self.conn)?;
+        if mask == self.num_lock_mask.get() {
+            return Ok(false);
+        }
+
+        self.conn.ungrab_key(Grab::ANY, self.root, ModMask::ANY)?;
+        self.conn
+            .ungrab_button(ButtonIndex::ANY, self.root, ModMask::ANY)?;
+        self.num_lock_mask.set(mask);
+        self.flush();
+
+        Ok(true)
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE;
+        let mask = u16::try_from(u32::from(mask)).unwrap();
+        self.conn
+            .grab_pointer(
+                false,
+                self.root,
+                mask,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                CURRENT_TIME,
+            )?
+            .reply()?;
+
+        Ok(())
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        self.conn.ungrab_pointer(CURRENT_TIME)?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn confine_pointer(&self, region: Option<Region>) -> Result<()> {
+        for id in self.pointer_barriers.replace(vec![]) {
+            self.conn.xfixes_delete_pointer_barrier(id)?;
+        }
+
+        if let Some(r) = region {
+            // Pointer barriers require XFixes >= 5.0
+            self.conn.xfixes_query_version(5, 0)?.reply()?;
+
+            let (x, y, w, h) = r.values();
+            let (x1, y1, x2, y2) = (x as u16, y as u16, (x + w) as u16, (y + h) as u16);
+            let edges = [
+                (x1, y1, x1, y2),
+                (x2, y1, x2, y2),
+                (x1, y1, x2, y1),
+                (x1, y2, x2, y2),
+            ];
+
+            let mut barriers = self.pointer_barriers.borrow_mut();
+            for &(ax, ay, bx, by) in edges.iter() {
+                let id = self.conn.generate_id()?;
+                self.conn
+                    .xfixes_create_pointer_barrier(id, self.root, ax, ay, bx, by, 0u32, &[])?
+                    .check()?;
+                barriers.push(id);
+            }
+        }
+
+        self.flush();
         Ok(())
     }
 }
 
 impl<C: Connection> X11rbConnection<C> {
+    /// The modifier mask that NumLock is bound to
+    pub fn num_lock_mask(&self) -> u16 {
+        self.num_lock_mask.get()
+    }
+
+    // Remember the time of the latest event for use when changing input focus
+    pub(crate) fn set_last_event_time(&self, time: u32) {
+        self.last_event_time.set(time);
+    }
+
+    // Look up which modifier NumLock is currently bound to
+    fn query_num_lock_mask(conn: &C) -> Result<u16> {
+        let (min, max) = (conn.setup().min_keycode, conn.setup().max_keycode);
+        let keyboard = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let modifiers = conn.get_modifier_mapping()?.reply()?;
+
+        Ok(num_lock_mask(
+            min,
+            keyboard.keysyms_per_keycode as usize,
+            &keyboard.keysyms,
+            modifiers.keycodes_per_modifier() as usize,
+            &modifiers.keycodes,
+        ))
+    }
+
+    /// The name of the given randr output (e.g. "DP-2")
+    pub(crate) fn output_name(&self, output: Xid) -> Result<String> {
+        let info = self
+            .conn
+            .randr_get_output_info(output, CURRENT_TIME)?
+            .reply()?;
+        Ok(String::from_utf8_lossy(&info.name).into_owned())
+    }
+
     fn grab_key_bindings(&self, keys: &[&KeyCode]) -> Result<()> {
-        // We need to explicitly grab NumLock as an additional modifier and then drop it later on
-        // when we are passing events through to the WindowManager as NumLock alters the modifier
-        // mask when it is active.
-        let modifiers = &[0, u16::from(ModMask::M2)];
+        // We need to explicitly grab each combination of the lock modifiers (NumLock and CapsLock)
+        // and then drop them later on when we are passing events through to the WindowManager as
+        // they alter the modifier mask when they are active.
+        let modifiers = lock_modifier_combinations(self.num_lock_mask.get());
         let mode = GrabMode::ASYNC;
 
         for m in modifiers.iter() {
             for k in keys.iter() {
-                self.conn.grab_key(
-                    false,      // don't pass grabbed events through to the client
-                    self.root,  // the window to grab: in this case the root window
-                    k.mask | m, // modifiers to grab
-                    k.code,     // keycode to grab
-                    mode,       // don't lock pointer input while grabbing
-                    mode,       // don't lock keyboard input while grabbing
-                )?;
+                self.conn
+                    .grab_key(
+                        false,      // don't pass grabbed events through to the client
+                        self.root,  // the window to grab: in this case the root window
+                        k.mask | m, // modifiers to grab
+                        k.code,     // keycode to grab
+                        mode,       // don't lock pointer input while grabbing
+                        mode,       // don't lock keyboard input while grabbing
+                    )?
+                    .check()?;
             }
         }
 
@@ -583,10 +839,9 @@ impl<C: Connection> X11rbConnection<C> {
     }
 
     fn grab_mouse_buttons(&self, states: &[&MouseState]) -> Result<()> {
-        // We need to explicitly grab NumLock as an additional modifier and then drop it later on
-        // when we are passing events through to the WindowManager as NumLock alters the modifier
-        // mask when it is active.
-        let modifiers = &[0, u16::from(ModMask::M2)];
+        // We need to explicitly grab each combination of the lock modifiers (NumLock and CapsLock)
+        // as they alter the modifier mask when they are active.
+        let modifiers = lock_modifier_combinations(self.num_lock_mask.get());
         let mode = GrabMode::ASYNC;
         let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::BUTTON_MOTION;
         let mask = u16::try_from(u32::from(mask)).unwrap();
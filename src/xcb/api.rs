@@ -1,22 +1,29 @@
 //! A wrapper around the underlying xcb api layer that only exposes Penrose types
 use crate::{
     core::{
-        bindings::{KeyCode, KeyCodeMask, KeyCodeValue, MouseEvent, MouseState},
+        bindings::{
+            lock_modifier_combinations, num_lock_mask, KeyCode, KeyCodeMask, KeyCodeValue,
+            MouseEvent, MouseState, CAPS_LOCK_MASK, DEFAULT_NUM_LOCK_MASK,
+        },
         data_types::{Point, Region, WinType},
         helpers::spawn_for_output,
         screen::Screen,
         xconnection::{
-            Atom, ClientAttr, ClientConfig, ClientEventMask, ClientMessage, ClientMessageData,
-            ClientMessageKind, ConfigureEvent, ExposeEvent, MapState, PointerChange, Prop,
-            PropertyEvent, WindowAttributes, WindowClass, WindowState, WmHints, WmNormalHints,
-            XAtomQuerier, XEvent, Xid,
+            property::decode_string_prop, wait_for_readable, Atom, ClientAttr, ClientConfig,
+            ClientEventMask, ClientMessage, ClientMessageData, ClientMessageKind, ConfigureEvent,
+            ExposeEvent, MapState, PointerChange, Prop, PropertyEvent, RandrChange,
+            WindowAttributes, WindowClass, WindowState, WmHints, WmNormalHints, XAtomQuerier,
+            XEvent, Xid,
         },
     },
-    xcb::{Result, XErrorCode, XcbError, XcbGenericEvent},
+    xcb::{conversions::stack_mode_from_xcb, Result, XErrorCode, XcbError, XcbGenericEvent},
 };
 use strum::*;
 
-use std::{collections::HashMap, convert::TryFrom, fmt, str::FromStr};
+use std::{
+    cell::Cell, collections::HashMap, convert::TryFrom, fmt, os::unix::io::AsRawFd, str::FromStr,
+    time::Duration,
+};
 
 #[cfg(feature = "keysyms")]
 use crate::core::{bindings::KeyPress, xconnection::KeyPressParseAttempt};
@@ -77,6 +84,10 @@ pub struct Api {
     root: Xid,
     randr_base: u8,
     atoms: HashMap<Atom, u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    num_lock_mask: Cell<u16>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last_event_time: Cell<u32>,
     #[cfg(feature = "keysyms")]
     code_map: ReverseCodeMap,
 }
@@ -101,6 +112,24 @@ impl XAtomQuerier for Api {
     }
 }
 
+// The timestamp carried by the events that we use for ordering focus changes
+fn event_time(event: &XcbGenericEvent, etype: u8) -> Option<u32> {
+    Some(match etype {
+        xcb::KEY_PRESS => unsafe { xcb::cast_event::<xcb::KeyPressEvent>(event) }.time(),
+        xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
+            unsafe { xcb::cast_event::<xcb::ButtonPressEvent>(event) }.time()
+        }
+        xcb::MOTION_NOTIFY => unsafe { xcb::cast_event::<xcb::MotionNotifyEvent>(event) }.time(),
+        xcb::ENTER_NOTIFY | xcb::LEAVE_NOTIFY => {
+            unsafe { xcb::cast_event::<xcb::EnterNotifyEvent>(event) }.time()
+        }
+        xcb::PROPERTY_NOTIFY => {
+            unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(event) }.time()
+        }
+        _ => return None,
+    })
+}
+
 impl Api {
     /// Connect to the X server using the [XCB API][1]
     ///
@@ -121,6 +150,8 @@ impl Api {
             root: 0,
             randr_base: 0,
             atoms: HashMap::new(),
+            num_lock_mask: Cell::new(DEFAULT_NUM_LOCK_MASK),
+            last_event_time: Cell::new(xcb::CURRENT_TIME),
             #[cfg(feature = "keysyms")]
             code_map: code_map_from_xmodmap()?,
         };
@@ -159,9 +190,49 @@ impl Api {
             })
             .collect::<Result<HashMap<_, _>>>()?;
 
+        self.num_lock_mask.set(self.query_num_lock_mask()?);
+
         Ok(())
     }
 
+    // Look up which modifier NumLock is currently bound to
+    fn query_num_lock_mask(&self) -> Result<u16> {
+        let setup = self.conn.get_setup();
+        let (min, max) = (setup.min_keycode(), setup.max_keycode());
+        let keyboard = xcb::get_keyboard_mapping(&self.conn, min, max - min + 1).get_reply()?;
+        let modifiers = xcb::get_modifier_mapping(&self.conn).get_reply()?;
+
+        Ok(num_lock_mask(
+            min,
+            keyboard.keysyms_per_keycode() as usize,
+            keyboard.keysyms(),
+            modifiers.keycodes_per_modifier() as usize,
+            modifiers.keycodes(),
+        ))
+    }
+
+    /// The modifier mask that NumLock is bound to
+    pub fn num_lock_mask(&self) -> u16 {
+        self.num_lock_mask.get()
+    }
+
+    /// Re-query the modifier that NumLock is bound to, releasing all key and mouse grabs if it
+    /// has changed so that they can be re-grabbed using the new modifier.
+    ///
+    /// Returns `true` if the modifier has changed.
+    pub fn refresh_num_lock_mask(&self) -> Result<bool> {
+        let mask = self.query_num_lock_mask()?;
+        if mask == self.num_lock_mask.get() {
+            return Ok(false);
+        }
+
+        self.ungrab_keys()?;
+        self.ungrab_mouse_buttons()?;
+        self.num_lock_mask.set(mask);
+
+        Ok(true)
+    }
+
     pub(crate) fn known_atoms(&self) -> &HashMap<Atom, u32> {
         &self.atoms
     }
@@ -230,21 +301,9 @@ impl Api {
 
             "CARDINAL" => Prop::Cardinal(r.value()[0]),
 
-            "STRING" => Prop::UTF8String(
-                String::from_utf8_lossy(r.value())
-                    .trim_matches('\0')
-                    .split('\0')
-                    .map(|s| s.to_string())
-                    .collect(),
-            ),
+            "STRING" => Prop::UTF8String(decode_string_prop(r.value(), false)),
 
-            "UTF8_STRING" => Prop::UTF8String(
-                String::from_utf8(r.value().to_vec())?
-                    .trim_matches('\0')
-                    .split('\0')
-                    .map(|s| s.to_string())
-                    .collect(),
-            ),
+            "UTF8_STRING" => Prop::UTF8String(decode_string_prop(r.value(), true)),
 
             "WINDOW" => Prop::Window(r.value().to_vec()),
 
@@ -323,6 +382,80 @@ impl Api {
         Ok(())
     }
 
+    /// Grab control of all pointer button presses and releases
+    pub fn grab_pointer(&self) -> Result<()> {
+        let mask = (xcb::EVENT_MASK_BUTTON_PRESS | xcb::EVENT_MASK_BUTTON_RELEASE) as u16;
+        xcb::grab_pointer(
+            &self.conn,
+            false,
+            self.root(),
+            mask,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::NONE,
+            xcb::NONE,
+            xcb::CURRENT_TIME,
+        )
+        .get_reply()?;
+
+        Ok(())
+    }
+
+    /// Release pointer input
+    pub fn ungrab_pointer(&self) -> Result<()> {
+        xcb::ungrab_pointer_checked(&self.conn, xcb::CURRENT_TIME).request_check()?;
+
+        Ok(())
+    }
+
+    /// Create XFixes pointer barriers along each edge of `region`, preventing the pointer from
+    /// being moved out of it. Returns the ids of the barriers that were created.
+    ///
+    /// Barriers only restrict pointer motion: they do not grab the pointer and so clients
+    /// continue to receive pointer events as normal. They are destroyed by the X server when
+    /// this connection is closed.
+    pub fn create_pointer_barriers(&self, region: Region) -> Result<Vec<Xid>> {
+        xcb::xfixes::query_version(&self.conn, 5, 0).get_reply()?;
+
+        let (x, y, w, h) = region.values();
+        let (x1, y1, x2, y2) = (x as u16, y as u16, (x + w) as u16, (y + h) as u16);
+        let edges = [
+            (x1, y1, x1, y2),
+            (x2, y1, x2, y2),
+            (x1, y1, x2, y1),
+            (x1, y2, x2, y2),
+        ];
+
+        edges
+            .iter()
+            .map(|&(ax, ay, bx, by)| {
+                let id = self.conn.generate_id();
+                xcb::xfixes::create_pointer_barrier_checked(
+                    &self.conn,
+                    id,
+                    self.root,
+                    ax,
+                    ay,
+                    bx,
+                    by,
+                    0,
+                    &[],
+                )
+                .request_check()?;
+                Ok(id)
+            })
+            .collect()
+    }
+
+    /// Destroy XFixes pointer barriers previously created using [Api::create_pointer_barriers]
+    pub fn delete_pointer_barriers(&self, barriers: &[Xid]) -> Result<()> {
+        for &id in barriers {
+            xcb::xfixes::delete_pointer_barrier_checked(&self.conn, id).request_check()?;
+        }
+
+        Ok(())
+    }
+
     /// Poll for the next event from the underlying [XCB Connection][::xcb::Connection],
     /// returning it as an [XKeySym] if it was a user keypress, or an [XEvent] if not.
     ///
@@ -379,13 +512,17 @@ impl Api {
 
     fn generic_xcb_to_xevent(&self, event: XcbGenericEvent) -> Result<Option<XEvent>> {
         let xcb_response_type_mask: u8 = 0x7F;
-        let numlock = xcb::MOD_MASK_2 as u16;
+        let locks = self.num_lock_mask.get() | CAPS_LOCK_MASK;
 
         let etype = event.response_type() & xcb_response_type_mask;
+        if let Some(time) = event_time(&event, etype) {
+            self.last_event_time.set(time);
+        }
 
         // Need to apply the randr_base mask as well which doesn't seem to work in 'match'
         if etype == self.randr_base + xcb::randr::NOTIFY {
-            return Ok(Some(XEvent::RandrNotify));
+            let e: &xcb::randr::NotifyEvent = unsafe { xcb::cast_event(&event) };
+            return Ok(Some(XEvent::RandrNotify(self.randr_change(e))));
         } else if etype == self.randr_base + xcb::randr::SCREEN_CHANGE_NOTIFY {
             return Ok(Some(XEvent::ScreenChange));
         }
@@ -402,7 +539,7 @@ impl Api {
             }
 
             xcb::KEY_PRESS => Some(XEvent::KeyPress(
-                KeyCode::try_from(event)?.ignoring_modifier(numlock),
+                KeyCode::try_from(event)?.normalized(locks),
             )),
 
             xcb::MAP_REQUEST => {
@@ -453,11 +590,16 @@ impl Api {
                         e.height() as u32,
                     ),
                     is_root: e.window() == self.root,
+                    border_width: Some(e.border_width() as u32),
+                    sibling: Some(e.above_sibling()).filter(|&id| id != xcb::NONE),
+                    stack_mode: None,
                 }))
             }
 
             xcb::CONFIGURE_REQUEST => {
-                let e: &xcb::ConfigureNotifyEvent = unsafe { xcb::cast_event(&event) };
+                let e: &xcb::ConfigureRequestEvent = unsafe { xcb::cast_event(&event) };
+                let mask = e.value_mask();
+                let is_set = |flag: u32| mask & flag as u16 != 0;
                 Some(XEvent::ConfigureRequest(ConfigureEvent {
                     id: e.window(),
                     r: Region::new(
@@ -467,6 +609,11 @@ impl Api {
                         e.height() as u32,
                     ),
                     is_root: e.window() == self.root,
+                    border_width: Some(e.border_width() as u32)
+                        .filter(|_| is_set(xcb::CONFIG_WINDOW_BORDER_WIDTH)),
+                    sibling: Some(e.sibling()).filter(|_| is_set(xcb::CONFIG_WINDOW_SIBLING)),
+                    stack_mode: stack_mode_from_xcb(e.stack_mode())
+                        .filter(|_| is_set(xcb::CONFIG_WINDOW_STACK_MODE)),
                 }))
             }
 
@@ -528,6 +675,15 @@ impl Api {
                     })
             }
 
+            xcb::MAPPING_NOTIFY => {
+                let e: &xcb::MappingNotifyEvent = unsafe { xcb::cast_event(&event) };
+                if e.request() == xcb::MAPPING_KEYBOARD as u8 {
+                    Some(XEvent::MappingNotify)
+                } else {
+                    None
+                }
+            }
+
             0 => {
                 let e: &xcb::GenericError = unsafe { xcb::cast_event(&event) };
                 return Err(XcbError::from(e));
@@ -603,6 +759,8 @@ impl Api {
 
             Prop::Cardinal(val) => (xcb::xproto::ATOM_CARDINAL, vec![val]),
 
+            Prop::CardinalList(vals) => (xcb::xproto::ATOM_CARDINAL, vals),
+
             Prop::UTF8String(strs) => {
                 return Ok(xcb::change_property_checked(
                     &self.conn,
@@ -739,7 +897,9 @@ impl Api {
 
     /// Apply a set of config options to a window
     pub fn configure_client(&self, id: Xid, conf: &[ClientConfig]) -> Result<()> {
-        let data: Vec<(u16, u32)> = conf.iter().flat_map::<Vec<_>, _>(|c| c.into()).collect();
+        let mut data: Vec<(u16, u32)> = conf.iter().flat_map::<Vec<_>, _>(|c| c.into()).collect();
+        // X requires the values to be provided in the same order as the bits in the value mask
+        data.sort_by_key(|&(mask, _)| mask);
         Ok(xcb::configure_window_checked(&self.conn, id, &data).request_check()?)
     }
 
@@ -780,6 +940,18 @@ impl Api {
         )
     }
 
+    /// Move input focus to the root window using the time of the last event that we received
+    pub fn focus_root(&self) -> Result<()> {
+        xcb::set_input_focus(
+            &self.conn,
+            xcb::INPUT_FOCUS_POINTER_ROOT as u8,
+            self.root,
+            self.last_event_time.get(),
+        );
+
+        Ok(())
+    }
+
     /// Send an event to a client
     pub fn send_client_event(&self, msg: ClientMessage) -> Result<()> {
         let (dtype, d) = (self.atom(&msg.dtype)?, msg.data().as_u32());
@@ -790,7 +962,30 @@ impl Api {
             ClientEventMask::SubstructureNotify => xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
         };
 
-        Ok(xcb::send_event_checked(&self.conn, false, msg.id, mask, &event).request_check()?)
+        let destination = msg.destination();
+        Ok(
+            xcb::send_event_checked(&self.conn, false, destination, mask, &event)
+                .request_check()?,
+        )
+    }
+
+    /// Send a synthetic ConfigureNotify event to the target client with the given geometry
+    pub fn send_configure_notify(&self, id: Xid, r: Region, border: u32) -> Result<()> {
+        let (x, y, w, h) = r.values();
+        let event = xcb::ConfigureNotifyEvent::new(
+            id,
+            id,
+            xcb::NONE,
+            x as i16,
+            y as i16,
+            w as u16,
+            h as u16,
+            border as u16,
+            false,
+        );
+        let mask = xcb::EVENT_MASK_STRUCTURE_NOTIFY;
+
+        Ok(xcb::send_event_checked(&self.conn, false, id, mask, &event).request_check()?)
     }
 
     /// Build a new known client event
@@ -803,10 +998,17 @@ impl Api {
 
     /// Set attributes on the target client
     pub fn set_client_attributes(&self, id: Xid, attrs: &[ClientAttr]) -> Result<()> {
-        let data: Vec<(u32, u32)> = attrs.iter().flat_map::<Vec<_>, _>(|c| c.into()).collect();
+        let mut data: Vec<(u32, u32)> = attrs.iter().flat_map::<Vec<_>, _>(|c| c.into()).collect();
+        // values must be provided in the order of their mask bits
+        data.sort_by_key(|&(mask, _)| mask);
         Ok(xcb::change_window_attributes_checked(&self.conn, id, &data).request_check()?)
     }
 
+    /// Repaint the whole of the target window using its background
+    pub fn clear_window(&self, id: Xid) -> Result<()> {
+        Ok(xcb::clear_area_checked(&self.conn, false, id, 0, 0, 0, 0).request_check()?)
+    }
+
     /// Find the current size and position of the target window
     pub fn client_geometry(&self, id: Xid) -> Result<Region> {
         let res = xcb::get_geometry(&self.conn, id).get_reply()?;
@@ -822,6 +1024,16 @@ impl Api {
     /// Query the randr API for current outputs and return the details as penrose
     /// [Screen] structs.
     pub fn current_screens(&self) -> Result<Vec<Screen>> {
+        Ok(self
+            .current_outputs()?
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect())
+    }
+
+    /// Query the randr API for current outputs and return the details as penrose
+    /// [Screen] structs along with the name of each output.
+    pub fn current_outputs(&self) -> Result<Vec<(Screen, Option<String>)>> {
         // xcb docs: https://www.mankier.com/3/xcb_randr_get_screen_resources
         let check_win = self.check_window();
         let resources = xcb::randr::get_screen_resources(&self.conn, check_win);
@@ -840,9 +1052,17 @@ impl Api {
                     r.width() as u32,
                     r.height() as u32,
                 );
-                Screen::new(region, i)
+                // xcb docs: https://www.mankier.com/3/xcb_randr_get_output_info
+                let info = r.outputs().first().and_then(|o| {
+                    xcb::randr::get_output_info(&self.conn, *o, 0)
+                        .get_reply()
+                        .ok()
+                });
+                let mm_width = info.as_ref().map(|info| info.mm_width()).unwrap_or(0);
+                let name = info.map(|info| String::from_utf8_lossy(info.name()).into_owned());
+                (Screen::new(region, i).with_physical_width(mm_width), name)
             })
-            .filter(|s| {
+            .filter(|(s, _)| {
                 let (_, _, w, _) = s.region(false).values();
                 w > 0
             })
@@ -852,6 +1072,39 @@ impl Api {
         Ok(screens)
     }
 
+    // Extract the details of a randr notify event, falling back to RandrChange::Unknown for
+    // events that we do not handle or if we are unable to query the output name.
+    fn randr_change(&self, e: &xcb::randr::NotifyEvent) -> RandrChange {
+        match e.sub_code() as u32 {
+            xcb::randr::NOTIFY_CRTC_CHANGE => {
+                let cc = e.u().cc();
+                RandrChange::Crtc {
+                    crtc: cc.crtc(),
+                    region: Region::new(
+                        cc.x() as u32,
+                        cc.y() as u32,
+                        cc.width() as u32,
+                        cc.height() as u32,
+                    ),
+                }
+            }
+
+            xcb::randr::NOTIFY_OUTPUT_CHANGE => {
+                let oc = e.u().oc();
+                match xcb::randr::get_output_info(&self.conn, oc.output(), 0).get_reply() {
+                    Ok(info) => RandrChange::Output {
+                        name: String::from_utf8_lossy(info.name()).into_owned(),
+                        connected: oc.connection() as u32 == xcb::randr::CONNECTION_CONNECTED,
+                        crtc: Some(oc.crtc()).filter(|&c| c != xcb::NONE),
+                    },
+                    Err(_) => RandrChange::Unknown,
+                }
+            }
+
+            _ => RandrChange::Unknown,
+        }
+    }
+
     /// Query the randr API for current outputs and return the size of each screen
     pub fn screen_sizes(&self) -> Result<Vec<Region>> {
         self.current_screens()
@@ -885,10 +1138,10 @@ impl Api {
 
     /// Register intercepts for each given [KeyCode]
     pub fn grab_keys(&self, keys: &[&KeyCode]) -> Result<()> {
-        // We need to explicitly grab NumLock as an additional modifier and then drop it later on
-        // when we are passing events through to the WindowManager as NumLock alters the modifier
-        // mask when it is active.
-        let modifiers = &[0, xcb::MOD_MASK_2 as u16];
+        // We need to explicitly grab each combination of the lock modifiers (NumLock and CapsLock)
+        // and then drop them later on when we are passing events through to the WindowManager as
+        // they alter the modifier mask when they are active.
+        let modifiers = lock_modifier_combinations(self.num_lock_mask.get());
         let mode = xcb::GRAB_MODE_ASYNC as u8;
 
         for m in modifiers.iter() {
@@ -911,12 +1164,34 @@ impl Api {
         Ok(())
     }
 
+    /// Release the intercepts for each given [KeyCode], leaving all other grabs in place
+    pub fn ungrab_key_codes(&self, keys: &[&KeyCode]) -> Result<()> {
+        // The lock modifiers were grabbed as additional modifiers in grab_keys so they need to be
+        // released as well.
+        let modifiers = lock_modifier_combinations(self.num_lock_mask.get());
+
+        for m in modifiers.iter() {
+            for k in keys.iter() {
+                // xcb docs: https://www.mankier.com/3/xcb_ungrab_key
+                xcb::ungrab_key_checked(
+                    &self.conn, // xcb connection to X11
+                    k.code,     // keycode to release
+                    self.root,  // the window that was grabbed
+                    k.mask | m, // modifiers to release
+                )
+                .request_check()?;
+            }
+        }
+
+        self.flush();
+        Ok(())
+    }
+
     /// Register intercepts for each given [MouseState]
     pub fn grab_mouse_buttons(&self, states: &[&MouseState]) -> Result<()> {
-        // We need to explicitly grab NumLock as an additional modifier and then drop it later on
-        // when we are passing events through to the WindowManager as NumLock alters the modifier
-        // mask when it is active.
-        let modifiers = &[0, xcb::MOD_MASK_2 as u16];
+        // We need to explicitly grab each combination of the lock modifiers (NumLock and CapsLock)
+        // as they alter the modifier mask when they are active.
+        let modifiers = lock_modifier_combinations(self.num_lock_mask.get());
         let mode = xcb::GRAB_MODE_ASYNC as u8;
         let mask = (xcb::EVENT_MASK_BUTTON_PRESS
             | xcb::EVENT_MASK_BUTTON_RELEASE
@@ -1030,10 +1305,29 @@ impl Api {
     /// returning None if there is no pending event and an error if the connection to the X server
     /// is closed.
     pub fn poll_for_event(&self) -> Result<Option<XEvent>> {
-        if let Some(event) = self.conn.poll_for_event() {
-            self.generic_xcb_to_xevent(event)
+        while let Some(event) = self.conn.poll_for_event() {
+            // Skip over events that we don't care about / know how to handle
+            if let Some(e) = self.generic_xcb_to_xevent(event)? {
+                return Ok(Some(e));
+            }
+        }
+
+        Ok(self.conn.has_error().map(|_| None)?)
+    }
+
+    /// Wait for up to `timeout` for the next event from the X event loop.
+    ///
+    /// Returns None if no event arrives in time or if the only events that arrived are not
+    /// handled by penrose.
+    pub fn wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>> {
+        if let Some(event) = self.poll_for_event()? {
+            return Ok(Some(event));
+        }
+
+        if wait_for_readable(self.conn.as_raw_fd(), timeout)? {
+            self.poll_for_event()
         } else {
-            Ok(self.conn.has_error().map(|_| None)?)
+            Ok(None)
         }
     }
 
@@ -2,7 +2,7 @@
 use crate::{
     core::{
         bindings::{KeyCode, ModifierKey, MouseButton, MouseEvent, MouseEventKind, MouseState},
-        xconnection::{ClientAttr, ClientConfig},
+        xconnection::{ClientAttr, ClientConfig, StackMode},
     },
     xcb::{Result, XcbError, XcbGenericEvent},
 };
@@ -17,17 +17,6 @@ impl ModifierKey {
     }
 }
 
-impl From<ModifierKey> for u16 {
-    fn from(m: ModifierKey) -> u16 {
-        (match m {
-            ModifierKey::Ctrl => xcb::MOD_MASK_CONTROL,
-            ModifierKey::Alt => xcb::MOD_MASK_1,
-            ModifierKey::Shift => xcb::MOD_MASK_SHIFT,
-            ModifierKey::Meta => xcb::MOD_MASK_4,
-        }) as u16
-    }
-}
-
 impl From<xcb::KeyPressEvent> for KeyCode {
     fn from(e: xcb::KeyPressEvent) -> Self {
         Self {
@@ -114,16 +103,18 @@ impl TryFrom<XcbGenericEvent> for MouseEvent {
     type Error = XcbError;
 
     fn try_from(raw: XcbGenericEvent) -> Result<Self> {
-        let (detail, state, id, rx, ry, x, y, kind) = data_from_event(raw)?;
+        let (detail, state, id, child, time, rx, ry, x, y, kind) = data_from_event(raw)?;
         let state = MouseState::from_detail_and_state(detail, state)?;
-        Ok(MouseEvent::new(id, rx, ry, x, y, state, kind))
+        Ok(MouseEvent::new(id, rx, ry, x, y, state, kind)
+            .with_child(child)
+            .with_time(time))
     }
 }
 
 #[allow(clippy::type_complexity)]
 fn data_from_event(
     raw: XcbGenericEvent,
-) -> Result<(u8, u16, u32, i16, i16, i16, i16, MouseEventKind)> {
+) -> Result<(u8, u16, u32, u32, u32, i16, i16, i16, i16, MouseEventKind)> {
     Ok(match raw.response_type() {
         xcb::BUTTON_PRESS => {
             let e: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(&raw) };
@@ -131,6 +122,8 @@ fn data_from_event(
                 e.detail(),
                 e.state(),
                 e.event(),
+                e.child(),
+                e.time(),
                 e.root_x(),
                 e.root_y(),
                 e.event_x(),
@@ -145,6 +138,8 @@ fn data_from_event(
                 e.detail(),
                 e.state(),
                 e.event(),
+                e.child(),
+                e.time(),
                 e.root_x(),
                 e.root_y(),
                 e.event_x(),
@@ -159,6 +154,8 @@ fn data_from_event(
                 e.detail(),
                 e.state(),
                 e.event(),
+                e.child(),
+                e.time(),
                 e.root_x(),
                 e.root_y(),
                 e.event_x(),
@@ -190,10 +187,42 @@ impl From<&ClientConfig> for Vec<(u16, u32)> {
             ClientConfig::StackAbove => {
                 vec![(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)]
             }
+            ClientConfig::Stack(mode, sibling) => {
+                let mut data = vec![];
+                if let Some(id) = sibling {
+                    data.push((xcb::CONFIG_WINDOW_SIBLING as u16, *id));
+                }
+                data.push((xcb::CONFIG_WINDOW_STACK_MODE as u16, u32::from(*mode)));
+                data
+            }
         }
     }
 }
 
+impl From<StackMode> for u32 {
+    fn from(mode: StackMode) -> u32 {
+        match mode {
+            StackMode::Above => xcb::STACK_MODE_ABOVE,
+            StackMode::Below => xcb::STACK_MODE_BELOW,
+            StackMode::TopIf => xcb::STACK_MODE_TOP_IF,
+            StackMode::BottomIf => xcb::STACK_MODE_BOTTOM_IF,
+            StackMode::Opposite => xcb::STACK_MODE_OPPOSITE,
+        }
+    }
+}
+
+/// Convert a raw xcb stack mode into a [StackMode], returning None for unknown values.
+pub(crate) fn stack_mode_from_xcb(mode: u8) -> Option<StackMode> {
+    match mode as u32 {
+        xcb::STACK_MODE_ABOVE => Some(StackMode::Above),
+        xcb::STACK_MODE_BELOW => Some(StackMode::Below),
+        xcb::STACK_MODE_TOP_IF => Some(StackMode::TopIf),
+        xcb::STACK_MODE_BOTTOM_IF => Some(StackMode::BottomIf),
+        xcb::STACK_MODE_OPPOSITE => Some(StackMode::Opposite),
+        _ => None,
+    }
+}
+
 impl From<&ClientAttr> for Vec<(u32, u32)> {
     fn from(w: &ClientAttr) -> Vec<(u32, u32)> {
         let client_event_mask = xcb::EVENT_MASK_ENTER_WINDOW
@@ -207,6 +236,7 @@ impl From<&ClientAttr> for Vec<(u32, u32)> {
             | xcb::EVENT_MASK_BUTTON_MOTION;
 
         match w {
+            ClientAttr::BackgroundColor(c) => vec![(xcb::CW_BACK_PIXEL, *c)],
             ClientAttr::BorderColor(c) => vec![(xcb::CW_BORDER_PIXEL, *c)],
             ClientAttr::ClientEventMask => vec![(xcb::CW_EVENT_MASK, client_event_mask)],
             ClientAttr::RootEventMask => vec![(xcb::CW_EVENT_MASK, root_event_mask)],
@@ -8,7 +8,7 @@
 use crate::{
     core::{
         data_types::{Region, WinType},
-        xconnection::{Prop, WindowState, XClientHandler, Xid},
+        xconnection::{ClientAttr, ClientConfig, Prop, WindowState, XClientHandler, Xid},
     },
     draw::{Color, Draw, DrawContext, DrawError, Result},
     xcb::{Api, XcbError},
@@ -33,6 +33,7 @@ pub struct XcbDraw {
     surfaces: HashMap<Xid, cairo::XCBSurface>,
 }
 
+crate::__xcb_impl_xclientconfig!(XcbDraw);
 crate::__xcb_impl_xclienthandler!(XcbDraw);
 crate::__xcb_impl_xclientproperties!(XcbDraw);
 #[cfg(feature = "keysyms")]
@@ -96,6 +97,11 @@ impl Draw for XcbDraw {
         Ok(id)
     }
 
+    fn destroy_window(&mut self, id: Xid) -> Result<()> {
+        self.surfaces.remove(&id);
+        Ok(self.api.destroy_client(id)?)
+    }
+
     fn screen_sizes(&self) -> Result<Vec<Region>> {
         Ok(self.api.screen_sizes()?)
     }
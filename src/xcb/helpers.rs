@@ -14,6 +14,7 @@ use crate::core::bindings::{CodeMap, KeyCode};
  *   A - Alt
  *   C - Ctrl
  *   S - Shift
+ *   G - AltGr (for key names on the third and fourth levels of a key)
  *
  * The user friendly patterns are parsed into a modifier mask and X key code
  * pair that is then grabbed by penrose to trigger the bound action.
@@ -29,6 +30,7 @@ pub fn parse_key_binding(pattern: String, known_codes: &CodeMap) -> Option<KeyCo
                     "M" => xcb::MOD_MASK_4,
                     "S" => xcb::MOD_MASK_SHIFT,
                     "C" => xcb::MOD_MASK_CONTROL,
+                    "G" => xcb::MOD_MASK_5,
                     _ => panic!("invalid key binding prefix: {}", s),
                 })
                 .fold(0, |acc, v| acc | v);
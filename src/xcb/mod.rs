@@ -182,6 +182,14 @@ pub enum XcbError {
     XcbUnknown(u8, u8),
 }
 
+impl XcbError {
+    /// Whether or not this error means that the connection to the X server can no longer be
+    /// used.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Connection(_) | Self::Io(_))
+    }
+}
+
 fn from_error_code(code: u8, response_type: u8) -> XcbError {
     match code {
         1..=11 => XcbError::XcbKnown(unsafe { std::mem::transmute(code) }),
@@ -259,6 +267,10 @@ macro_rules! __xcb_impl_xstate {
                 Ok(self.api.current_screens()?)
             }
 
+            fn current_outputs(&self) -> $crate::core::xconnection::Result<Vec<(Screen, Option<String>)>> {
+                Ok(self.api.current_outputs()?)
+            }
+
             fn cursor_position(&self) -> $crate::core::xconnection::Result<Point> {
                 Ok(self.api.cursor_position()?)
             }
@@ -306,6 +318,17 @@ macro_rules! __xcb_impl_xeventhandler {
                 Ok(self.api.wait_for_event()?)
             }
 
+            fn poll_for_event(&self) -> $crate::core::xconnection::Result<Option<XEvent>> {
+                Ok(self.api.poll_for_event()?)
+            }
+
+            fn wait_for_event_timeout(
+                &self,
+                timeout: ::std::time::Duration,
+            ) -> $crate::core::xconnection::Result<Option<XEvent>> {
+                Ok(self.api.wait_for_event_timeout(timeout)?)
+            }
+
             fn send_client_event(&self, msg: ClientMessage) -> $crate::core::xconnection::Result<()> {
                 Ok(self.api.send_client_event(msg)?)
             }
@@ -334,6 +357,10 @@ macro_rules! __xcb_impl_xclienthandler {
                 Ok(self.api.focus_client(id)?)
             }
 
+            fn focus_root(&self) -> $crate::core::xconnection::Result<()> {
+                Ok(self.api.focus_root()?)
+            }
+
             fn destroy_client(&self, id: Xid) -> $crate::core::xconnection::Result<()> {
                 Ok(self.api.destroy_client(id)?)
             }
@@ -394,6 +421,14 @@ macro_rules! __xcb_impl_xclientconfig {
             fn get_window_attributes(&self, id: Xid) -> $crate::core::xconnection::Result<$crate::core::xconnection::WindowAttributes> {
                 Ok(self.api.get_window_attributes(id)?)
             }
+
+            fn send_configure_notify(&self, id: Xid, r: Region, border: u32) -> $crate::core::xconnection::Result<()> {
+                Ok(self.api.send_configure_notify(id, r, border)?)
+            }
+
+            fn clear_client(&self, id: Xid) -> $crate::core::xconnection::Result<()> {
+                Ok(self.api.clear_window(id)?)
+            }
         }
     }
 }
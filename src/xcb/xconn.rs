@@ -13,7 +13,7 @@
  */
 use crate::{
     core::{
-        bindings::{KeyBindings, MouseBindings},
+        bindings::{KeyBindings, KeyCode, KeyGrabReport, MouseBindings},
         data_types::{Point, Region},
         manager::WindowManager,
         screen::Screen,
@@ -25,7 +25,7 @@ use crate::{
     xcb::{Api, XcbError},
 };
 
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap};
 
 /**
  * Handles communication with an X server via the XCB library.
@@ -38,6 +38,8 @@ use std::collections::HashMap;
 pub struct XcbConnection {
     check_win: Xid,
     api: Api,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pointer_barriers: RefCell<Vec<Xid>>,
 }
 
 impl XcbConnection {
@@ -47,7 +49,11 @@ impl XcbConnection {
         let check_win = api.check_window();
         api.set_randr_notify_mask()?;
 
-        Ok(Self { check_win, api })
+        Ok(Self {
+            check_win,
+            api,
+            pointer_barriers: RefCell::new(vec![]),
+        })
     }
 
     /// Get a handle on the underlying [XCB Connection][::xcb::Connection] used by [Api]
@@ -110,6 +116,7 @@ impl XConn for XcbConnection {
     }
 
     fn cleanup(&self) -> Result<()> {
+        self.confine_pointer(None)?;
         self.api.ungrab_keys()?;
         self.api.ungrab_mouse_buttons()?;
         let net_name = Atom::NetActiveWindow.as_ref();
@@ -120,13 +127,20 @@ impl XConn for XcbConnection {
         Ok(())
     }
 
+    fn reconnect(&mut self) -> Result<()> {
+        *self = Self::new()?;
+        Ok(())
+    }
+
     fn grab_keys(
         &self,
         key_bindings: &KeyBindings<Self>,
         mouse_bindings: &MouseBindings<Self>,
-    ) -> Result<()> {
-        self.api
-            .grab_keys(&key_bindings.keys().collect::<Vec<_>>())?;
+    ) -> Result<KeyGrabReport> {
+        let mut report = KeyGrabReport::default();
+        for &k in key_bindings.keys() {
+            report.record(k, self.api.grab_keys(&[&k]));
+        }
         self.api.grab_mouse_buttons(
             &mouse_bindings
                 .keys()
@@ -135,6 +149,37 @@ impl XConn for XcbConnection {
         )?;
         self.flush();
 
+        Ok(report)
+    }
+
+    fn grab_key(&self, key: KeyCode) -> Result<()> {
+        Ok(self.api.grab_keys(&[&key])?)
+    }
+
+    fn ungrab_key(&self, key: KeyCode) -> Result<()> {
+        Ok(self.api.ungrab_key_codes(&[&key])?)
+    }
+
+    fn refresh_num_lock_mask(&self) -> Result<bool> {
+        Ok(self.api.refresh_num_lock_mask()?)
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        Ok(self.api.grab_pointer()?)
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        Ok(self.api.ungrab_pointer()?)
+    }
+
+    fn confine_pointer(&self, region: Option<Region>) -> Result<()> {
+        let previous = self.pointer_barriers.replace(vec![]);
+        self.api.delete_pointer_barriers(&previous)?;
+        if let Some(r) = region {
+            *self.pointer_barriers.borrow_mut() = self.api.create_pointer_barriers(r)?;
+        }
+        self.api.flush();
+
         Ok(())
     }
 }
@@ -24,6 +24,7 @@ pub const FOCUS_CHANGE_CODE: KeyCode = KeyCode { mask: 0, code: 4 };
 pub const KILL_CLIENT_CODE: KeyCode = KeyCode { mask: 0, code: 5 };
 pub const ADD_WORKSPACE_CODE: KeyCode = KeyCode { mask: 0, code: 6 };
 pub const CLIENT_TO_WORKSPACE_CODE: KeyCode = KeyCode { mask: 0, code: 7 };
+pub const REMOVE_WORKSPACE_CODE: KeyCode = KeyCode { mask: 0, code: 8 };
 
 pub fn simple_screen(n: usize) -> Screen {
     Screen::new(
@@ -78,6 +79,11 @@ pub fn test_bindings<X: XConn>() -> KeyBindings<X> {
         Box::new(|wm: &mut WindowManager<X>| wm.client_to_workspace(&Selector::Index(1)))
             as KeyEventHandler<X>,
     );
+    bindings.insert(
+        REMOVE_WORKSPACE_CODE,
+        Box::new(|wm: &mut WindowManager<X>| wm.remove_workspace(&Selector::Index(8)).map(|_| ()))
+            as KeyEventHandler<X>,
+    );
 
     bindings
 }
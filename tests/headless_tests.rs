@@ -0,0 +1,90 @@
+// Check that a headless WindowManager can be driven by injecting events directly
+#[macro_use]
+extern crate penrose;
+
+use penrose::{
+    __test_helpers::TestXConn,
+    core::{
+        config::Config,
+        manager::WindowManager,
+        ring::Selector,
+        xconnection::{XEvent, Xid},
+    },
+    logging_error_handler,
+};
+
+use std::collections::HashMap;
+
+// Only the key bindings are needed here
+#[allow(dead_code)]
+mod common;
+
+test_cases! {
+    injected_events;
+    args: (events: Vec<XEvent>, focused: Option<Xid>, n_on_first_workspace: usize);
+
+    case: no_events => (vec![], None, 0);
+    case: map_requests => (
+        vec![XEvent::MapRequest(1, false), XEvent::MapRequest(2, false)],
+        Some(2),
+        2
+    );
+    case: key_bindings => (
+        vec![
+            XEvent::MapRequest(1, false),
+            XEvent::MapRequest(2, false),
+            XEvent::KeyPress(common::FOCUS_CHANGE_CODE),
+        ],
+        Some(1),
+        2
+    );
+    case: client_to_workspace => (
+        vec![
+            XEvent::MapRequest(1, false),
+            XEvent::MapRequest(2, false),
+            XEvent::KeyPress(common::CLIENT_TO_WORKSPACE_CODE),
+        ],
+        Some(1),
+        1
+    );
+    case: destroy => (
+        vec![XEvent::MapRequest(1, false), XEvent::Destroy(1)],
+        None,
+        0
+    );
+
+    body: {
+        let conn = TestXConn::new(2, vec![], vec![]);
+        let mut wm =
+            WindowManager::new_headless(Config::default(), conn, vec![], logging_error_handler())
+                .unwrap();
+
+        let mut key_bindings = common::test_bindings();
+        let mut mouse_bindings = HashMap::new();
+        for event in events {
+            wm.handle_event(event, &mut key_bindings, &mut mouse_bindings).unwrap();
+        }
+
+        assert_eq!(wm.focused_client_id(), focused);
+        let ws = wm.workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(ws.len(), n_on_first_workspace);
+        assert_eq!(wm.conn().remaining_events(), vec![]);
+    }
+}
+
+#[test]
+fn queued_events_are_pulled_from_the_conn() {
+    let conn = TestXConn::new(1, vec![], vec![]);
+    let mut wm =
+        WindowManager::new_headless(Config::default(), conn, vec![], logging_error_handler())
+            .unwrap();
+    wm.conn().push_events(vec![
+        XEvent::MapRequest(1, false),
+        XEvent::KeyPress(common::EXIT_CODE),
+    ]);
+
+    wm.grab_keys_and_run(common::test_bindings(), HashMap::new())
+        .unwrap();
+
+    assert_eq!(wm.focused_client_id(), Some(1));
+}
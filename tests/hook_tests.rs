@@ -10,7 +10,10 @@ use penrose::{
         hooks::{Hook, Hooks},
         manager::WindowManager,
         screen::Screen,
-        xconnection::{Atom, Prop, PropertyEvent, Result, XConn, XError, XEvent, Xid},
+        xconnection::{
+            Atom, Prop, PropertyEvent, RandrChange, Result, WmNormalHints, WmNormalHintsFlags,
+            XConn, XError, XEvent, Xid,
+        },
     },
     logging_error_handler,
 };
@@ -50,6 +53,10 @@ __impl_stub_xcon! {
         fn mock_get_prop(&self, id: Xid, name: &str) -> Result<Prop> {
             if name == Atom::NetWmName.as_ref() {
                 Ok(Prop::UTF8String(vec!["mock name".into()]))
+            } else if name == Atom::WmNormalHints.as_ref() {
+                let min = Some(Region::new(0, 0, 100, 100));
+                let flags = WmNormalHintsFlags::P_MIN_SIZE;
+                Ok(Prop::WmNormalHints(WmNormalHints::new(flags, None, min, None, None)))
             } else {
                 Err(XError::MissingProperty(name.into(), id))
             }
@@ -122,18 +129,22 @@ macro_rules! __impl_test_hook {
 
 __impl_test_hook! {
     client_name_updated => Xid, &str, bool;
+    client_normal_hints_changed => Xid, &WmNormalHints;
     client_added_to_workspace => Xid, usize;
     event_handled => ;
     focus_change => Xid;
     layout_applied => usize, usize;
     layout_change => usize, usize;
     new_client => Xid;
+    randr_change => &RandrChange;
     randr_notify => ;
     remove_client => Xid;
     screen_change => usize;
     screens_updated => &[Region];
     startup => ;
+    workspace_added => usize, &str, usize;
     workspace_change => usize, usize;
+    workspace_removed => usize, &str, usize;
     workspaces_updated => &[&str], usize;
 }
 
@@ -145,6 +156,11 @@ test_cases! {
         XEvent::PropertyNotify(PropertyEvent { id: 1, atom: "WM_NAME".into(), is_root: false }),
         XEvent::PropertyNotify(PropertyEvent { id: 1, atom: "_NET_WM_NAME".into(), is_root: false }),
     ]);
+    case: client_normal_hints_changed => ("client_normal_hints_changed", 1, vec![
+        XEvent::MapRequest(1, false),
+        XEvent::PropertyNotify(PropertyEvent { id: 1, atom: "WM_NORMAL_HINTS".into(), is_root: false }),
+        XEvent::PropertyNotify(PropertyEvent { id: 2, atom: "WM_NORMAL_HINTS".into(), is_root: false }),
+    ]);
     case: client_added_to_workspace => ("client_added_to_workspace", 2, vec![
         XEvent::MapRequest(1, false),
         XEvent::KeyPress(common::CLIENT_TO_WORKSPACE_CODE)
@@ -158,16 +174,25 @@ test_cases! {
     case: layout_applied => ("layout_applied", 3, vec![XEvent::KeyPress(common::LAYOUT_CHANGE_CODE)]);
     case: layout_change => ("layout_change", 1, vec![XEvent::KeyPress(common::LAYOUT_CHANGE_CODE)]);
     case: new_client => ("new_client", 1, vec![XEvent::MapRequest(1, false)]);
-    case: randr_notify => ("randr_notify", 1, vec![XEvent::RandrNotify]);
+    case: randr_change => ("randr_change", 2, vec![
+        XEvent::RandrNotify(RandrChange::Unknown),
+        XEvent::RandrNotify(RandrChange::Output { name: "DP-2".into(), connected: true, crtc: None }),
+    ]);
+    case: randr_notify => ("randr_notify", 1, vec![XEvent::RandrNotify(RandrChange::Unknown)]);
+    case: randr_notify_skipped_for_outputs_without_a_crtc => ("randr_notify", 0, vec![
+        XEvent::RandrNotify(RandrChange::Output { name: "DP-2".into(), connected: true, crtc: None }),
+    ]);
     case: remove_client => ("remove_client", 1, vec![
         XEvent::MapRequest(1, false),
         XEvent::KeyPress(common::KILL_CLIENT_CODE),
         XEvent::Destroy(1),
     ]);
     case: screen_change => ("screen_change", 1, vec![XEvent::KeyPress(common::SCREEN_CHANGE_CODE)]);
-    case: screens_updated => ("screens_updated", 1, vec![XEvent::RandrNotify]);
+    case: screens_updated => ("screens_updated", 1, vec![XEvent::RandrNotify(RandrChange::Unknown)]);
     case: startup => ("startup", 1, vec![]);
+    case: workspace_added => ("workspace_added", 1, vec![XEvent::KeyPress(common::ADD_WORKSPACE_CODE)]);
     case: workspace_change => ("workspace_change", 1, vec![XEvent::KeyPress(common::WORKSPACE_CHANGE_CODE)]);
+    case: workspace_removed => ("workspace_removed", 1, vec![XEvent::KeyPress(common::REMOVE_WORKSPACE_CODE)]);
     case: workspaces_updated => ("workspaces_updated", 1, vec![XEvent::KeyPress(common::ADD_WORKSPACE_CODE)]);
 
     body: {